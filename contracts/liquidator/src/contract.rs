@@ -1,14 +1,31 @@
+use std::collections::BTreeMap;
+
 use cosmwasm_std::{
-    entry_point, to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError,
-    StdResult,
+    entry_point, to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response,
+    StdError, StdResult,
 };
 use cw2::set_contract_version;
 
 use crate::{
+    cw20_input::{
+        liquidate_cw20, receive_cw20, remove_cw20_input, reply_cw20_swap, set_cw20_input,
+        CW20_SWAP_REPLY_ID,
+    },
     error::ContractError,
-    handle::{ibc_transfer, liquidate, remove_route, set_route, update_config, update_owner},
-    msg::{ExecuteMsg, InstantiateMsg, QueryMsg},
-    query::{query_all_routes, query_config, query_owner, query_route},
+    handle::{
+        disburse, discover_and_set_route, ibc_transfer, liquidate, liquidate_and_send,
+        liquidate_denom, remove_route, reply_liquidate, send_proceeds, set_candidate_pools,
+        set_denom_config, set_destinations, set_keepers, set_paused, set_reserve, set_route,
+        set_route_config, update_config, update_owner,
+    },
+    ibc::{ibc_lifecycle_complete, reply_transfer, retry_transfer, TRANSFER_REPLY_ID_OFFSET},
+    msg::{ExecuteMsg, InstantiateMsg, QueryMsg, SudoMsg},
+    query::{
+        query_all_routes, query_candidate_pools, query_config, query_cw20_inputs,
+        query_denom_config, query_destinations, query_owner, query_route, query_route_config,
+        query_simulate_liquidation, query_simulate_route, query_swap_history, query_swap_totals,
+        query_transfer, query_transfers,
+    },
     state::{Config, CONFIG, OWNER},
 };
 
@@ -32,6 +49,9 @@ pub fn instantiate(
             ibc_channel_id: msg.ibc_channel_id,
             ibc_to_address: ibc_address,
             liquidation_target: msg.liquidation_target,
+            keepers: vec![],
+            paused: false,
+            reserves: BTreeMap::new(),
         },
     )?;
 
@@ -72,13 +92,88 @@ pub fn execute(
             input_denom,
             output_denom,
         } => remove_route(deps, info, &input_denom, &output_denom),
-        ExecuteMsg::Liquidate {} => liquidate(deps.as_ref(), env, info),
-        ExecuteMsg::IbcTransfer {} => ibc_transfer(deps.as_ref(), env, info),
+        ExecuteMsg::SetCandidatePools {
+            pool_ids,
+        } => set_candidate_pools(deps, info, pool_ids),
+        ExecuteMsg::DiscoverRoute {
+            input_denom,
+            output_denom,
+            max_hops,
+        } => discover_and_set_route(deps, info, input_denom, output_denom, max_hops),
+        ExecuteMsg::SetRouteConfig {
+            input_denom,
+            output_denom,
+            twap_window,
+            max_price_impact,
+        } => set_route_config(deps, info, input_denom, output_denom, twap_window, max_price_impact),
+        ExecuteMsg::Liquidate {} => liquidate(deps, env, info),
+        ExecuteMsg::IbcTransfer {} => ibc_transfer(deps, env, info),
+        ExecuteMsg::LiquidateAndSend {} => liquidate_and_send(deps, env, info),
+        ExecuteMsg::SendProceeds {} => send_proceeds(deps, env, info),
+        ExecuteMsg::LiquidateDenom {
+            denom,
+            amount,
+            min_out,
+        } => liquidate_denom(deps, env, info, denom, amount, min_out),
+        ExecuteMsg::SetDestinations {
+            destinations,
+        } => set_destinations(deps, info, destinations),
+        ExecuteMsg::Disburse {} => disburse(deps, env, info),
+        ExecuteMsg::RetryTransfer {
+            id,
+        } => retry_transfer(deps, env, info, id),
+        ExecuteMsg::SetDenomConfig {
+            denom,
+            max_chunk,
+            min_balance,
+        } => set_denom_config(deps, info, denom, max_chunk, min_balance),
+        ExecuteMsg::SetKeepers {
+            keepers,
+        } => set_keepers(deps, info, keepers),
+        ExecuteMsg::SetPaused {
+            paused,
+        } => set_paused(deps, info, paused),
+        ExecuteMsg::SetReserve {
+            denom,
+            reserve,
+        } => set_reserve(deps, info, denom, reserve),
+        ExecuteMsg::Receive(msg) => receive_cw20(deps, info, msg),
+        ExecuteMsg::SetCw20Input {
+            token,
+            router,
+            router_msg,
+            denom,
+            min_price,
+        } => set_cw20_input(deps, info, token, router, router_msg, denom, min_price),
+        ExecuteMsg::RemoveCw20Input {
+            token,
+        } => remove_cw20_input(deps, info, token),
+        ExecuteMsg::LiquidateCw20 {
+            token,
+        } => liquidate_cw20(deps, env, info, token),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, reply: Reply) -> Result<Response, ContractError> {
+    if reply.id >= TRANSFER_REPLY_ID_OFFSET {
+        reply_transfer(deps, reply)
+    } else if reply.id == CW20_SWAP_REPLY_ID {
+        reply_cw20_swap(deps, env, reply)
+    } else {
+        reply_liquidate(deps, env, reply)
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
+    match msg {
+        SudoMsg::IbcLifecycleComplete(msg) => ibc_lifecycle_complete(deps, msg),
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::GetOwner {} => {
             to_binary(&query_owner(deps).map_err(|err| StdError::generic_err(err.to_string()))?)
@@ -88,6 +183,35 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             input_denom,
             output_denom,
         } => to_binary(&query_route(deps, input_denom, output_denom)?),
+        QueryMsg::GetRouteConfig {
+            input_denom,
+            output_denom,
+        } => to_binary(&query_route_config(deps, input_denom, output_denom)?),
+        QueryMsg::SimulateLiquidation {} => to_binary(&query_simulate_liquidation(deps, env)?),
+        QueryMsg::SimulateRoute {
+            input,
+            output_denom,
+        } => to_binary(&query_simulate_route(deps, &env, input, output_denom)?),
+        QueryMsg::GetTransfer {
+            id,
+        } => to_binary(&query_transfer(deps, id)?),
+        QueryMsg::GetTransfers {
+            start_after,
+            limit,
+        } => to_binary(&query_transfers(deps, start_after, limit)?),
+        QueryMsg::GetDestinations {} => to_binary(&query_destinations(deps)?),
+        QueryMsg::GetCw20Inputs {} => to_binary(&query_cw20_inputs(deps)?),
+        QueryMsg::GetSwapHistory {
+            start_after,
+            limit,
+        } => to_binary(&query_swap_history(deps, start_after, limit)?),
+        QueryMsg::GetSwapTotals {
+            input_denom,
+        } => to_binary(&query_swap_totals(deps, input_denom)?),
+        QueryMsg::GetDenomConfig {
+            denom,
+        } => to_binary(&query_denom_config(deps, denom)?),
+        QueryMsg::GetCandidatePools {} => to_binary(&query_candidate_pools(deps)?),
         QueryMsg::GetAllRoutes {
             start_after,
             limit,
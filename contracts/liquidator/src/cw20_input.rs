@@ -0,0 +1,291 @@
+use std::ops::Mul;
+
+use cosmwasm_std::{
+    ensure, from_binary, to_binary, Binary, Coin, Decimal, Deps, DepsMut, Env, Event, MessageInfo,
+    Order, Reply, Response, StdResult, SubMsg, SubMsgResult, Uint128, WasmMsg,
+};
+use cw20::{BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg};
+
+use crate::{
+    error::ContractError,
+    handle::skip_liquidation_event,
+    helpers::{
+        available_balance, calculate_min_output, cancel_outflow, generate_swap_msg,
+        liquidation_amount, record_outflow, update_reserved,
+    },
+    msg::{Cw20HookMsg, ExecuteMsg},
+    state::{
+        Cw20Input, PendingCw20Swap, PendingSwap, CONFIG, CW20_INPUTS, OWNER, PENDING_CW20_SWAP,
+        PENDING_PROCEEDS, PENDING_SWAPS,
+    },
+};
+
+/// Reply id of the router swap of a cw20 input, apart from the reply ids of
+/// liquidation swaps and IBC transfers
+pub const CW20_SWAP_REPLY_ID: u64 = 1 << 31;
+
+pub fn set_cw20_input(
+    deps: DepsMut,
+    info: MessageInfo,
+    token: String,
+    router: String,
+    router_msg: Binary,
+    denom: String,
+    min_price: Decimal,
+) -> Result<Response, ContractError> {
+    ensure!(OWNER.is_admin(deps.as_ref(), &info.sender)?, ContractError::Unauthorized {});
+
+    let token = deps.api.addr_validate(&token)?;
+    let router = deps.api.addr_validate(&router)?;
+    ensure!(
+        !denom.is_empty(),
+        ContractError::CustomError {
+            val: "Cw20 input requires the denom returned by the router".to_string(),
+        }
+    );
+    ensure!(
+        !min_price.is_zero(),
+        ContractError::CustomError {
+            val: "Cw20 input requires a minimum price".to_string(),
+        }
+    );
+    router_msg_with_minimum(&router_msg, Uint128::zero())?;
+
+    CW20_INPUTS.save(
+        deps.storage,
+        &token,
+        &Cw20Input {
+            token: token.clone(),
+            router: router.clone(),
+            router_msg,
+            denom: denom.clone(),
+            min_price,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_cw20_input")
+        .add_attribute("token", token)
+        .add_attribute("router", router)
+        .add_attribute("denom", denom)
+        .add_attribute("min_price", min_price.to_string()))
+}
+
+// router_msg_with_minimum sets the minimum_receive of the single variant of
+// router_msg, so that the router fails swaps returning less than minimum.
+fn router_msg_with_minimum(router_msg: &Binary, minimum: Uint128) -> Result<Binary, ContractError> {
+    let invalid = || ContractError::CustomError {
+        val: "Cw20 router message must be a JSON object with a single variant".to_string(),
+    };
+
+    let mut msg: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_slice(router_msg.as_slice()).map_err(|_e| invalid())?;
+    if msg.len() != 1 {
+        return Err(invalid());
+    }
+    let variant = msg.values_mut().next().and_then(|v| v.as_object_mut()).ok_or_else(invalid)?;
+    variant.insert("minimum_receive".to_string(), minimum.to_string().into());
+
+    Ok(Binary::from(serde_json::to_vec(&msg).map_err(|_e| invalid())?))
+}
+
+pub fn remove_cw20_input(
+    deps: DepsMut,
+    info: MessageInfo,
+    token: String,
+) -> Result<Response, ContractError> {
+    ensure!(OWNER.is_admin(deps.as_ref(), &info.sender)?, ContractError::Unauthorized {});
+
+    let token = deps.api.addr_validate(&token)?;
+    CW20_INPUTS.remove(deps.storage, &token);
+
+    Ok(Response::new().add_attribute("action", "remove_cw20_input").add_attribute("token", token))
+}
+
+/// Accepts deposits of registered cw20 inputs
+pub fn receive_cw20(
+    deps: DepsMut,
+    info: MessageInfo,
+    cw20_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    ensure!(
+        CW20_INPUTS.has(deps.storage, &info.sender),
+        ContractError::CustomError {
+            val: format!("Unregistered cw20 token {}", info.sender),
+        }
+    );
+
+    match from_binary(&cw20_msg.msg)? {
+        Cw20HookMsg::Deposit {} => Ok(Response::new()
+            .add_attribute("action", "deposit_cw20")
+            .add_attribute("token", info.sender)
+            .add_attribute("sender", cw20_msg.sender)
+            .add_attribute("amount", cw20_msg.amount)),
+    }
+}
+
+// cw20_liquidation_msgs returns the callbacks liquidating a chunk of the
+// balance of every cw20 input. Each callback runs after the messages before
+// it, so that it sees the native balance left by them.
+pub fn cw20_liquidation_msgs(deps: Deps, env: &Env) -> Result<Vec<WasmMsg>, ContractError> {
+    let inputs = CW20_INPUTS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut msgs = vec![];
+    for (token, _) in inputs {
+        let balance = cw20_balance(deps, token.as_str(), env)?;
+        if liquidation_amount(deps, token.as_str(), balance)?.is_some() {
+            msgs.push(WasmMsg::Execute {
+                contract_addr: env.contract.address.to_string(),
+                msg: to_binary(&ExecuteMsg::LiquidateCw20 {
+                    token: token.to_string(),
+                })?,
+                funds: vec![],
+            });
+        }
+    }
+    Ok(msgs)
+}
+
+/// Sends a chunk of the balance of a cw20 input to its router. The native
+/// output of the router is swapped to the liquidation target in the reply.
+/// Can only be called by the contract itself.
+pub fn liquidate_cw20(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token: String,
+) -> Result<Response, ContractError> {
+    ensure!(info.sender == env.contract.address, ContractError::Unauthorized {});
+
+    let input = CW20_INPUTS.load(deps.storage, &deps.api.addr_validate(&token)?)?;
+    let balance = cw20_balance(deps.as_ref(), &token, &env)?;
+    update_reserved(deps.storage, &token, balance)?;
+    let amount = match liquidation_amount(deps.as_ref(), &token, balance)? {
+        Some(amount) => amount,
+        None => return Ok(Response::new().add_attribute("action", "liquidate_cw20")),
+    };
+
+    // The router fails the swap if it returns less than the minimum price
+    let min_output = amount.mul(input.min_price);
+    let router_msg = router_msg_with_minimum(&input.router_msg, min_output)?;
+
+    let balance_before = deps.querier.query_balance(&env.contract.address, &input.denom)?.amount;
+    PENDING_CW20_SWAP.save(
+        deps.storage,
+        &PendingCw20Swap {
+            token: input.token.clone(),
+            amount,
+            denom: input.denom,
+            balance_before,
+        },
+    )?;
+    record_outflow(deps.storage, &token, amount)?;
+
+    let msg = WasmMsg::Execute {
+        contract_addr: input.token.to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Send {
+            contract: input.router.to_string(),
+            amount,
+            msg: router_msg,
+        })?,
+        funds: vec![],
+    };
+
+    // A failed router swap skips the token instead of failing the liquidation
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_always(msg, CW20_SWAP_REPLY_ID))
+        .add_attribute("action", "liquidate_cw20")
+        .add_attribute("token", token)
+        .add_attribute("amount", amount)
+        .add_attribute("min_output", min_output))
+}
+
+/// Handles the result of the router swap of a cw20 input. The output of the
+/// router is swapped along the route to the liquidation target, or added to
+/// the pending proceeds if it is the liquidation target.
+pub fn reply_cw20_swap(deps: DepsMut, env: Env, reply: Reply) -> Result<Response, ContractError> {
+    let PendingCw20Swap {
+        token,
+        amount,
+        denom,
+        balance_before,
+    } = PENDING_CW20_SWAP.load(deps.storage)?;
+    PENDING_CW20_SWAP.remove(deps.storage);
+
+    if let SubMsgResult::Err(err) = reply.result {
+        cancel_outflow(deps.storage, token.as_str(), amount)?;
+        return Ok(Response::new().add_event(skip_liquidation_event(token.as_str(), err)));
+    }
+
+    let balance = deps.querier.query_balance(&env.contract.address, &denom)?.amount;
+    let output = Coin {
+        amount: balance.checked_sub(balance_before)?,
+        denom,
+    };
+
+    let event = Event::new("liquidate_cw20")
+        .add_attribute("token", token)
+        .add_attribute("input", amount)
+        .add_attribute("output", output.to_string());
+    let res = Response::new().add_event(event);
+
+    if output.amount.is_zero() {
+        return Ok(res);
+    }
+
+    let config = CONFIG.load(deps.storage)?;
+    if output.denom == config.liquidation_target {
+        if let Some(proceeds) = PENDING_PROCEEDS.may_load(deps.storage)? {
+            PENDING_PROCEEDS.save(deps.storage, &proceeds.checked_add(output.amount)?)?;
+        }
+        return Ok(res);
+    }
+
+    // The reserve of the output applies to it like to any other inflow
+    let balance = available_balance(deps.as_ref(), &env, &output.denom)?;
+    let reserved = update_reserved(deps.storage, &output.denom, balance)?;
+    let output = Coin {
+        amount: output.amount.min(balance - reserved),
+        denom: output.denom,
+    };
+    if output.amount.is_zero() {
+        return Ok(res);
+    }
+
+    let token_out = match calculate_min_output(
+        deps.as_ref(),
+        output.clone(),
+        config.liquidation_target,
+        env.block.time,
+    ) {
+        Ok(token_out) => token_out,
+        Err(err) => {
+            return Ok(res.add_event(skip_liquidation_event(&output.denom, err.to_string())))
+        }
+    };
+
+    let pending_swap = PendingSwap {
+        input: output.clone(),
+        output_denom: token_out.denom.clone(),
+    };
+    let msg = generate_swap_msg(deps.as_ref(), env.contract.address, output, token_out)?;
+
+    // The swaps of the liquidation have all replied before the callbacks of
+    // the cw20 inputs run, so the swap can take the first reply id
+    PENDING_SWAPS.save(deps.storage, 0, &pending_swap)?;
+    record_outflow(deps.storage, &pending_swap.input.denom, pending_swap.input.amount)?;
+    Ok(res.add_submessage(SubMsg::reply_always(msg, 0)))
+}
+
+// cw20_balance returns the balance of the contract of a cw20 token.
+fn cw20_balance(deps: Deps, token: &str, env: &Env) -> StdResult<Uint128> {
+    let res: BalanceResponse = deps.querier.query_wasm_smart(
+        token,
+        &Cw20QueryMsg::Balance {
+            address: env.contract.address.to_string(),
+        },
+    )?;
+    Ok(res.balance)
+}
@@ -1,4 +1,4 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{OverflowError, StdError};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -6,6 +6,9 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+
     #[error("Owner not set")]
     NoOwner {},
 
@@ -23,6 +26,9 @@ pub enum ContractError {
         reason: String,
     },
 
+    #[error("Paused")]
+    Paused {},
+
     #[error("Insufficient Funds")]
     InsufficientFunds {},
 
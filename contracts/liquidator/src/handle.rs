@@ -1,15 +1,29 @@
 use cosmwasm_std::{
-    ensure, Addr, BalanceResponse, BankQuery, Coin, Deps, DepsMut, Env, Event, IbcMsg, MessageInfo,
-    Order, QueryRequest, Response, Uint128,
+    ensure, to_binary, Addr, BankMsg, Coin, Decimal, DepsMut, Env, Event, MessageInfo, Order,
+    Reply, Response, StdError, StdResult, SubMsg, SubMsgResult, Uint128, Uint64, WasmMsg,
+};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{
+    MsgSwapExactAmountInResponse, SwapAmountInRoute,
 };
-use osmosis_std::types::osmosis::poolmanager::v1beta1::{MsgSwapExactAmountIn, SwapAmountInRoute};
 
 pub const PACKET_LIFETIME: u64 = 60 * 60; // One hour
 
 use crate::{
+    cw20_input::cw20_liquidation_msgs,
     error::ContractError,
-    helpers::{generate_swap_msg, validate_pool_route},
-    state::{CONFIG, OWNER, ROUTING_TABLE},
+    helpers::{
+        available_balance, calculate_min_output, cancel_outflow, discover_route, ensure_keeper,
+        generate_swap_msg, liquidation_amount, record_outflow, update_reserved,
+        validate_pool_route,
+    },
+    ibc::{callback_memo, transfer_submsg},
+    msg::ExecuteMsg,
+    state::{
+        DenomConfig, Destination, DestinationKind, PendingSwap, RouteConfig, SwapRecord,
+        CANDIDATE_POOLS, CONFIG, DENOM_CONFIGS, DESTINATIONS, MAX_DISCOVERY_HOPS, NEXT_SWAP_ID,
+        OWNER, PENDING_PROCEEDS, PENDING_SWAPS, RESERVED, ROUTE_CONFIGS, ROUTING_TABLE,
+        SWAP_HISTORY, SWAP_TOTALS,
+    },
 };
 
 pub fn update_owner(
@@ -55,67 +69,410 @@ pub fn update_config(
     Ok(Response::default().add_event(event))
 }
 
-pub fn ibc_transfer(deps: Deps, env: Env, _info: MessageInfo) -> Result<Response, ContractError> {
+pub fn ibc_transfer(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
-    let liquidation_target = config.liquidation_target.clone();
-
-    let res: BalanceResponse = deps.querier.query(&QueryRequest::Bank(BankQuery::Balance {
-        address: env.contract.address.to_string(),
-        denom: liquidation_target,
-    }))?;
+    ensure_keeper(deps.as_ref(), &config, &info.sender)?;
 
-    let balance = res.amount.amount;
+    let balance = available_balance(deps.as_ref(), &env, &config.liquidation_target)?;
+    let balance = balance - update_reserved(deps.storage, &config.liquidation_target, balance)?;
+    ensure!(!balance.is_zero(), ContractError::InsufficientFunds {});
+    record_outflow(deps.storage, &config.liquidation_target, balance)?;
 
-    let msg = IbcMsg::Transfer {
-        channel_id: config.ibc_channel_id,
-        to_address: config.ibc_to_address.to_string(),
-        amount: Coin {
+    let msg = transfer_submsg(
+        deps,
+        &env,
+        config.ibc_channel_id,
+        config.ibc_to_address.to_string(),
+        Coin {
             amount: balance,
             denom: config.liquidation_target,
         },
-        timeout: env.block.time.plus_seconds(PACKET_LIFETIME).into(),
-    };
+        None,
+    )?;
+
+    let res = Response::new().add_submessage(msg).add_attribute("action", "handle_send_funds");
+
+    Ok(res)
+}
+
+pub fn set_destinations(
+    deps: DepsMut,
+    info: MessageInfo,
+    destinations: Vec<Destination>,
+) -> Result<Response, ContractError> {
+    ensure!(OWNER.is_admin(deps.as_ref(), &info.sender)?, ContractError::Unauthorized {});
+
+    for destination in &destinations {
+        ensure!(destination.weight > 0, ContractError::InvalidTokenShare {});
+        match &destination.kind {
+            DestinationKind::Local {
+                address,
+            }
+            | DestinationKind::Contract {
+                address,
+                ..
+            } => {
+                deps.api.addr_validate(address)?;
+            }
+            DestinationKind::Ibc {
+                channel_id,
+                to_address,
+                memo,
+            } => {
+                ensure!(
+                    !channel_id.is_empty() && !to_address.is_empty(),
+                    ContractError::CustomError {
+                        val: "IBC destinations require a channel id and address".to_string(),
+                    }
+                );
+                callback_memo(memo.as_deref(), &Addr::unchecked(""))?;
+            }
+        }
+    }
+
+    DESTINATIONS.save(deps.storage, &destinations)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_destinations")
+        .add_attribute("destinations", destinations.len().to_string()))
+}
+
+pub fn disburse(mut deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    ensure_keeper(deps.as_ref(), &config, &info.sender)?;
+    let destinations = DESTINATIONS.may_load(deps.storage)?.unwrap_or_default();
+    ensure!(
+        !destinations.is_empty(),
+        ContractError::CustomError {
+            val: "No destinations set".to_string(),
+        }
+    );
+
+    let balance = available_balance(deps.as_ref(), &env, &config.liquidation_target)?;
+    let balance = balance - update_reserved(deps.storage, &config.liquidation_target, balance)?;
+    ensure!(!balance.is_zero(), ContractError::InsufficientFunds {});
+    record_outflow(deps.storage, &config.liquidation_target, balance)?;
 
-    let res = Response::new().add_message(msg).add_attribute("action", "handle_send_funds");
+    // Each destination receives its share of the balance, the last destination
+    // also receives the rounding remainder
+    let total_weight: u64 = destinations.iter().map(|destination| destination.weight).sum();
+    let last = destinations.len() - 1;
+    let mut remaining = balance;
+    let mut msgs: Vec<SubMsg> = Vec::new();
+    for (i, destination) in destinations.into_iter().enumerate() {
+        let amount = if i == last {
+            remaining
+        } else {
+            balance.multiply_ratio(destination.weight, total_weight)
+        };
+        remaining -= amount;
+        if amount.is_zero() {
+            continue;
+        }
+
+        let coin = Coin {
+            amount,
+            denom: config.liquidation_target.clone(),
+        };
+        let msg = match destination.kind {
+            DestinationKind::Local {
+                address,
+            } => SubMsg::new(BankMsg::Send {
+                to_address: address,
+                amount: vec![coin],
+            }),
+            DestinationKind::Contract {
+                address,
+                msg,
+            } => SubMsg::new(WasmMsg::Execute {
+                contract_addr: address,
+                msg,
+                funds: vec![coin],
+            }),
+            DestinationKind::Ibc {
+                channel_id,
+                to_address,
+                memo,
+            } => transfer_submsg(deps.branch(), &env, channel_id, to_address, coin, memo)?,
+        };
+        msgs.push(msg);
+    }
+
+    Ok(Response::new()
+        .add_submessages(msgs)
+        .add_attribute("action", "disburse")
+        .add_attribute("amount", balance))
+}
+
+pub fn liquidate(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    ensure_keeper(deps.as_ref(), &config, &info.sender)?;
 
+    let (swap_msgs, events) = liquidation_swaps(deps.branch(), &env, None)?;
+    let cw20_msgs = cw20_liquidation_msgs(deps.as_ref(), &env)?;
+
+    let res = Response::new()
+        .add_submessages(swap_msgs)
+        .add_messages(cw20_msgs)
+        .add_events(events)
+        .add_attribute("action", "liquidate");
     Ok(res)
 }
 
-pub fn liquidate(deps: Deps, env: Env, _info: MessageInfo) -> Result<Response, ContractError> {
+pub fn liquidate_and_send(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    ensure_keeper(deps.as_ref(), &config, &info.sender)?;
+
+    // The outputs of the swaps are added up in the replies, and sent once all
+    // swaps are done
+    PENDING_PROCEEDS.save(deps.storage, &Uint128::zero())?;
+    let (swap_msgs, events) =
+        liquidation_swaps(deps.branch(), &env, Some(&config.liquidation_target))?;
+    let cw20_msgs = cw20_liquidation_msgs(deps.as_ref(), &env)?;
+    let send_proceeds = WasmMsg::Execute {
+        contract_addr: env.contract.address.to_string(),
+        msg: to_binary(&ExecuteMsg::SendProceeds {})?,
+        funds: vec![],
+    };
+
+    let res = Response::new()
+        .add_submessages(swap_msgs)
+        .add_messages(cw20_msgs)
+        .add_message(send_proceeds)
+        .add_events(events)
+        .add_attribute("action", "liquidate_and_send");
+    Ok(res)
+}
+
+pub fn send_proceeds(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    ensure!(info.sender == env.contract.address, ContractError::Unauthorized {});
+
+    let config = CONFIG.load(deps.storage)?;
+    let proceeds = PENDING_PROCEEDS.may_load(deps.storage)?.unwrap_or_default();
+    PENDING_PROCEEDS.remove(deps.storage);
+
+    // The proceeds are an inflow of the liquidation target, so its reserve
+    // applies to them like to any other inflow
+    let balance = available_balance(deps.as_ref(), &env, &config.liquidation_target)?;
+    let reserved = update_reserved(deps.storage, &config.liquidation_target, balance)?;
+    let amount = proceeds.min(balance - reserved);
+
+    if amount.is_zero() {
+        return Ok(Response::new().add_attribute("action", "send_proceeds"));
+    }
+    record_outflow(deps.storage, &config.liquidation_target, amount)?;
+
+    let msg = transfer_submsg(
+        deps,
+        &env,
+        config.ibc_channel_id,
+        config.ibc_to_address.to_string(),
+        Coin {
+            amount,
+            denom: config.liquidation_target,
+        },
+        None,
+    )?;
+
+    Ok(Response::new()
+        .add_submessage(msg)
+        .add_attribute("action", "send_proceeds")
+        .add_attribute("amount", amount))
+}
+
+// liquidation_swaps returns the swaps liquidating a chunk of the balance of
+// every input denom of the routing table, optionally only of the routes to
+// output_denom.
+fn liquidation_swaps(
+    deps: DepsMut,
+    env: &Env,
+    output_denom: Option<&str>,
+) -> Result<(Vec<SubMsg>, Vec<Event>), ContractError> {
     let contract_address = env.contract.address.to_string();
-    let mut swap_msgs: Vec<MsgSwapExactAmountIn> = Vec::new();
+    let mut swap_msgs: Vec<SubMsg> = Vec::new();
+    let mut events: Vec<Event> = Vec::new();
+
+    let routes = ROUTING_TABLE
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
 
     // Loop through pairs in ROUTING_TABLE
     // If the contract has balance liquidate to target via the route
-    for item in ROUTING_TABLE.range(deps.storage, None, None, Order::Ascending) {
-        let ((token1, token2), _routes) = item?;
+    for (token1, token2) in routes {
+        if output_denom.map_or(false, |output_denom| output_denom != token2) {
+            continue;
+        }
 
         // Check if the contract has any balance of the token_in denom
-        let res: BalanceResponse = deps.querier.query(&QueryRequest::Bank(BankQuery::Balance {
-            address: contract_address.clone(),
-            denom: token1.clone(),
-        }))?;
-
-        let balance = res.amount.amount;
+        let balance = available_balance(deps.as_ref(), env, &token1)?;
+        update_reserved(deps.storage, &token1, balance)?;
 
-        // If there is some balance liquidate via the route
-        if !balance.is_zero() {
+        // If there is enough balance liquidate a chunk of it via the route
+        if let Some(amount) = liquidation_amount(deps.as_ref(), &token1, balance)? {
             let token_in = Coin {
-                amount: res.amount.amount,
-                denom: token1,
+                amount,
+                denom: token1.clone(),
             };
 
-            let token_out = Coin {
-                amount: Uint128::from(1u128),
-                denom: token2,
-            };
+            // Skip the denom if its route cannot be priced, instead of failing
+            // the liquidation of all other denoms
+            let token_out =
+                match calculate_min_output(deps.as_ref(), token_in.clone(), token2, env.block.time)
+                {
+                    Ok(token_out) => token_out,
+                    Err(err) => {
+                        events.push(skip_liquidation_event(&token1, err.to_string()));
+                        continue;
+                    }
+                };
+
             let address = Addr::unchecked(contract_address.clone());
-            let msg = generate_swap_msg(deps, address, token_in, token_out);
-            swap_msgs.push(msg.unwrap());
+            let pending_swap = PendingSwap {
+                input: token_in.clone(),
+                output_denom: token_out.denom.clone(),
+            };
+            let msg = generate_swap_msg(deps.as_ref(), address, token_in, token_out)?;
+
+            // Swaps are recorded in the reply, and swaps that fall short of the
+            // minimum output are skipped
+            let reply_id = swap_msgs.len() as u64;
+            PENDING_SWAPS.save(deps.storage, reply_id, &pending_swap)?;
+            record_outflow(deps.storage, &token1, amount)?;
+            swap_msgs.push(SubMsg::reply_always(msg, reply_id));
         }
     }
-    let res = Response::new().add_messages(swap_msgs).add_attribute("action", "liquidate");
-    Ok(res)
+
+    Ok((swap_msgs, events))
+}
+
+pub fn liquidate_denom(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    denom: String,
+    amount: Option<Uint128>,
+    min_out: Option<Uint128>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    ensure_keeper(deps.as_ref(), &config, &info.sender)?;
+    let balance = available_balance(deps.as_ref(), &env, &denom)?;
+    update_reserved(deps.storage, &denom, balance)?;
+
+    let max_amount = liquidation_amount(deps.as_ref(), &denom, balance)?.ok_or_else(|| {
+        ContractError::CustomError {
+            val: format!("Balance of {denom} is below the liquidation threshold"),
+        }
+    })?;
+    let amount = amount.unwrap_or(max_amount);
+    ensure!(amount <= max_amount, ContractError::InsufficientFunds {});
+
+    let token_in = Coin {
+        amount,
+        denom: denom.clone(),
+    };
+
+    // The given min_out can only tighten the TWAP-based minimum output
+    let mut token_out = calculate_min_output(
+        deps.as_ref(),
+        token_in.clone(),
+        config.liquidation_target,
+        env.block.time,
+    )?;
+    if let Some(min_out) = min_out {
+        token_out.amount = token_out.amount.max(min_out);
+    }
+
+    let pending_swap = PendingSwap {
+        input: token_in.clone(),
+        output_denom: token_out.denom.clone(),
+    };
+    let msg = generate_swap_msg(deps.as_ref(), env.contract.address, token_in, token_out.clone())?;
+
+    // The swap is recorded in the reply, a failed swap fails the liquidation
+    PENDING_SWAPS.save(deps.storage, 0, &pending_swap)?;
+    record_outflow(deps.storage, &denom, amount)?;
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(msg, 0))
+        .add_attribute("action", "liquidate_denom")
+        .add_attribute("denom", denom)
+        .add_attribute("amount", amount)
+        .add_attribute("min_out", token_out.amount))
+}
+
+/// Handles the result of a liquidation swap. A failed swap skips its denom,
+/// a successful swap is recorded and its output is added to the pending
+/// proceeds of `LiquidateAndSend`, if any.
+pub fn reply_liquidate(deps: DepsMut, env: Env, reply: Reply) -> Result<Response, ContractError> {
+    let PendingSwap {
+        input,
+        output_denom,
+    } = PENDING_SWAPS.load(deps.storage, reply.id)?;
+    PENDING_SWAPS.remove(deps.storage, reply.id);
+
+    let res = match reply.result {
+        SubMsgResult::Ok(res) => res,
+        SubMsgResult::Err(err) => {
+            cancel_outflow(deps.storage, &input.denom, input.amount)?;
+            return Ok(Response::new().add_event(skip_liquidation_event(&input.denom, err)));
+        }
+    };
+
+    let data = res.data.ok_or_else(|| StdError::generic_err("No data in swap reply"))?;
+    let output = Coin {
+        amount: MsgSwapExactAmountInResponse::try_from(data)?.token_out_amount.parse()?,
+        denom: output_denom,
+    };
+
+    if let Some(proceeds) = PENDING_PROCEEDS.may_load(deps.storage)? {
+        PENDING_PROCEEDS.save(deps.storage, &proceeds.checked_add(output.amount)?)?;
+    }
+
+    // Record the swap
+    let id = NEXT_SWAP_ID.may_load(deps.storage)?.unwrap_or(1);
+    NEXT_SWAP_ID.save(deps.storage, &Uint64::new(id).checked_add(Uint64::one())?.u64())?;
+    let route = ROUTING_TABLE.load(deps.storage, (&input.denom, &output.denom))?;
+    SWAP_HISTORY.save(
+        deps.storage,
+        id,
+        &SwapRecord {
+            id,
+            input: input.clone(),
+            output: output.clone(),
+            route,
+            height: env.block.height,
+        },
+    )?;
+    SWAP_TOTALS.update(deps.storage, (&input.denom, &output.denom), |totals| -> StdResult<_> {
+        let mut totals = totals.unwrap_or_default();
+        totals.input_amount = totals.input_amount.checked_add(input.amount)?;
+        totals.output_amount = totals.output_amount.checked_add(output.amount)?;
+        totals.swap_count = Uint64::new(totals.swap_count).checked_add(Uint64::one())?.u64();
+        Ok(totals)
+    })?;
+
+    let event = Event::new("liquidate_swap")
+        .add_attribute("swap_id", id.to_string())
+        .add_attribute("denom", input.denom)
+        .add_attribute("input", input.amount)
+        .add_attribute("output", output.to_string());
+    Ok(Response::new().add_event(event))
+}
+
+pub fn skip_liquidation_event(denom: &str, reason: String) -> Event {
+    Event::new("skip_liquidation").add_attribute("denom", denom).add_attribute("reason", reason)
 }
 
 pub fn set_route(
@@ -139,6 +496,134 @@ pub fn set_route(
     Ok(Response::new().add_attribute("action", "set_route"))
 }
 
+pub fn set_candidate_pools(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_ids: Vec<u64>,
+) -> Result<Response, ContractError> {
+    ensure!(OWNER.is_admin(deps.as_ref(), &info.sender)?, ContractError::Unauthorized {});
+
+    let mut pool_ids = pool_ids;
+    pool_ids.sort_unstable();
+    pool_ids.dedup();
+    CANDIDATE_POOLS.save(deps.storage, &pool_ids)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_candidate_pools")
+        .add_attribute("pool_ids", format!("{pool_ids:?}")))
+}
+
+pub fn discover_and_set_route(
+    deps: DepsMut,
+    info: MessageInfo,
+    input_denom: String,
+    output_denom: String,
+    max_hops: u32,
+) -> Result<Response, ContractError> {
+    ensure!(OWNER.is_admin(deps.as_ref(), &info.sender)?, ContractError::Unauthorized {});
+    ensure!(
+        max_hops > 0 && max_hops <= MAX_DISCOVERY_HOPS,
+        ContractError::CustomError {
+            val: format!("max_hops must be between 1 and {MAX_DISCOVERY_HOPS}"),
+        }
+    );
+
+    let pool_route = discover_route(deps.as_ref(), &input_denom, &output_denom, max_hops)?;
+
+    validate_pool_route(
+        deps.as_ref(),
+        input_denom.clone(),
+        output_denom.clone(),
+        pool_route.clone(),
+    )?;
+
+    ROUTING_TABLE.save(deps.storage, (&input_denom, &output_denom), &pool_route)?;
+
+    let pool_ids: Vec<u64> = pool_route.iter().map(|route_part| route_part.pool_id).collect();
+    Ok(Response::new()
+        .add_attribute("action", "discover_route")
+        .add_attribute("pool_ids", format!("{pool_ids:?}")))
+}
+
+pub fn set_denom_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    denom: String,
+    max_chunk: Uint128,
+    min_balance: Uint128,
+) -> Result<Response, ContractError> {
+    ensure!(OWNER.is_admin(deps.as_ref(), &info.sender)?, ContractError::Unauthorized {});
+
+    let denom_config = DenomConfig {
+        max_chunk,
+        min_balance,
+    };
+    DENOM_CONFIGS.save(deps.storage, &denom, &denom_config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_denom_config")
+        .add_attribute("denom", denom)
+        .add_attribute("max_chunk", max_chunk)
+        .add_attribute("min_balance", min_balance))
+}
+
+pub fn set_keepers(
+    deps: DepsMut,
+    info: MessageInfo,
+    keepers: Vec<String>,
+) -> Result<Response, ContractError> {
+    ensure!(OWNER.is_admin(deps.as_ref(), &info.sender)?, ContractError::Unauthorized {});
+
+    let mut config = CONFIG.load(deps.storage)?;
+    config.keepers =
+        keepers.iter().map(|keeper| deps.api.addr_validate(keeper)).collect::<StdResult<_>>()?;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_keepers")
+        .add_attribute("keepers", keepers.join(",")))
+}
+
+pub fn set_paused(
+    deps: DepsMut,
+    info: MessageInfo,
+    paused: bool,
+) -> Result<Response, ContractError> {
+    ensure!(OWNER.is_admin(deps.as_ref(), &info.sender)?, ContractError::Unauthorized {});
+
+    let mut config = CONFIG.load(deps.storage)?;
+    config.paused = paused;
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_paused")
+        .add_attribute("paused", paused.to_string()))
+}
+
+pub fn set_reserve(
+    deps: DepsMut,
+    info: MessageInfo,
+    denom: String,
+    reserve: Decimal,
+) -> Result<Response, ContractError> {
+    ensure!(OWNER.is_admin(deps.as_ref(), &info.sender)?, ContractError::Unauthorized {});
+    ensure!(reserve <= Decimal::one(), ContractError::InvalidTokenShare {});
+
+    let mut config = CONFIG.load(deps.storage)?;
+    if reserve.is_zero() {
+        config.reserves.remove(&denom);
+        RESERVED.remove(deps.storage, &denom);
+    } else {
+        config.reserves.insert(denom.clone(), reserve);
+    }
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_reserve")
+        .add_attribute("denom", denom)
+        .add_attribute("reserve", reserve.to_string()))
+}
+
 pub fn remove_route(
     deps: DepsMut,
     info: MessageInfo,
@@ -147,5 +632,46 @@ pub fn remove_route(
 ) -> Result<Response, ContractError> {
     ensure!(OWNER.is_admin(deps.as_ref(), &info.sender)?, ContractError::Unauthorized {});
     ROUTING_TABLE.remove(deps.storage, (input_denom, output_denom));
+    ROUTE_CONFIGS.remove(deps.storage, (input_denom, output_denom));
     Ok(Response::new().add_attribute("action", "delete_route"))
 }
+
+pub fn set_route_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    input_denom: String,
+    output_denom: String,
+    twap_window: u64,
+    max_price_impact: Decimal,
+) -> Result<Response, ContractError> {
+    ensure!(OWNER.is_admin(deps.as_ref(), &info.sender)?, ContractError::Unauthorized {});
+
+    if !ROUTING_TABLE.has(deps.storage, (&input_denom, &output_denom)) {
+        return Err(ContractError::InvalidPoolRoute {
+            reason: format!("No route found for {input_denom} -> {output_denom}"),
+        });
+    }
+    ensure!(
+        twap_window > 0,
+        ContractError::CustomError {
+            val: "TWAP window must be greater than zero".to_string(),
+        }
+    );
+    ensure!(
+        max_price_impact < Decimal::one(),
+        ContractError::CustomError {
+            val: "Max price impact must be less than 100%".to_string(),
+        }
+    );
+
+    let route_config = RouteConfig {
+        twap_window,
+        max_price_impact,
+    };
+    ROUTE_CONFIGS.save(deps.storage, (&input_denom, &output_denom), &route_config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_route_config")
+        .add_attribute("twap_window", twap_window.to_string())
+        .add_attribute("max_price_impact", max_price_impact.to_string()))
+}
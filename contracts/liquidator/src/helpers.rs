@@ -2,20 +2,42 @@
 // Once osmosis and osmisis-test-tube are updated
 // The QueryTotalPoolLiquidityRequest can be move to poolmanager
 // https://github.com/osmosis-labs/osmosis/issues/5812
-#![allow(dead_code)] // remove after implementation
-use std::ops::{Div, Mul};
+use std::ops::Mul;
 
-use cosmwasm_std::{Addr, Coin, Decimal, Deps, Timestamp, Uint128};
+use cosmwasm_std::{
+    ensure, Addr, Coin, Decimal, Decimal256, Deps, Env, StdResult, Storage, Timestamp, Uint128,
+};
 use osmosis_std::{
     shim::Timestamp as OsmosisTimestamp,
     types::osmosis::{
         gamm::v1beta1::QueryTotalPoolLiquidityRequest,
-        poolmanager::v1beta1::{MsgSwapExactAmountIn, SwapAmountInRoute},
+        poolmanager::v1beta1::{
+            EstimateSwapExactAmountInRequest, MsgSwapExactAmountIn, SwapAmountInRoute,
+        },
         twap::v1beta1::TwapQuerier,
     },
 };
 
-use crate::{error::ContractError, state::ROUTING_TABLE};
+use crate::{
+    error::ContractError,
+    state::{
+        Config, Reserved, RouteConfig, CANDIDATE_POOLS, CONFIG, DENOM_CONFIGS, OWNER, REFUNDED,
+        RESERVED, ROUTE_CONFIGS, ROUTING_TABLE,
+    },
+};
+
+// ensure_keeper checks that liquidations and transfers are not paused and
+// that sender is the owner or a keeper, if keepers are set.
+pub fn ensure_keeper(deps: Deps, config: &Config, sender: &Addr) -> Result<(), ContractError> {
+    ensure!(!config.paused, ContractError::Paused {});
+    ensure!(
+        config.keepers.is_empty()
+            || config.keepers.contains(sender)
+            || OWNER.is_admin(deps, sender)?,
+        ContractError::Unauthorized {}
+    );
+    Ok(())
+}
 
 // validate_pool_route validates if the pool route is valid.
 // Returns success if it is, error otherwise.
@@ -74,6 +96,116 @@ pub fn validate_pool_route(
     Ok(())
 }
 
+// discover_route searches the candidate pools for the deepest route from
+// input_denom to output_denom with at most max_hops swaps.
+// The depth of a route is the depth of its shallowest pool, where the depth
+// of a pool is its liquidity of the denom swapped in, valued in input_denom
+// at the balance ratios of the previous pools of the route. Of routes of the
+// same depth the one with the fewest hops is chosen.
+// Returns error if the candidate pools contain no route.
+pub fn discover_route(
+    deps: Deps,
+    input_denom: &str,
+    output_denom: &str,
+    max_hops: u32,
+) -> Result<Vec<SwapAmountInRoute>, ContractError> {
+    let mut pools = vec![];
+    for pool_id in CANDIDATE_POOLS.may_load(deps.storage)?.unwrap_or_default() {
+        let liquidity = QueryTotalPoolLiquidityRequest {
+            pool_id,
+        }
+        .query(&deps.querier)?
+        .liquidity
+        .into_iter()
+        .map(|coin| Ok(Coin::new(coin.amount.parse::<Uint128>()?.u128(), coin.denom)))
+        .collect::<Result<Vec<_>, ContractError>>()?;
+        pools.push((pool_id, liquidity));
+    }
+
+    let mut search = RouteSearch {
+        pools: &pools,
+        output_denom,
+        path: vec![],
+        visited_denoms: vec![input_denom.to_string()],
+        best: None,
+    };
+    search.visit(input_denom, max_hops, Decimal256::one(), Decimal256::MAX);
+
+    match search.best {
+        Some((_, route)) => Ok(route),
+        None => Err(ContractError::InvalidPoolRoute {
+            reason: format!(
+                "No route found for {input_denom} -> {output_denom} within {max_hops} hops"
+            ),
+        }),
+    }
+}
+
+// RouteSearch is the state of the depth first search of discover_route.
+struct RouteSearch<'a> {
+    pools: &'a [(u64, Vec<Coin>)],
+    output_denom: &'a str,
+    path: Vec<SwapAmountInRoute>,
+    visited_denoms: Vec<String>,
+    best: Option<(Decimal256, Vec<SwapAmountInRoute>)>,
+}
+
+impl RouteSearch<'_> {
+    // visit extends the current path with every swap out of denom_in.
+    // rate is the value of one denom_in in the input denom and depth is the
+    // depth of the current path.
+    fn visit(&mut self, denom_in: &str, hops_left: u32, rate: Decimal256, depth: Decimal256) {
+        if hops_left == 0 {
+            return;
+        }
+        let pools = self.pools;
+        for (pool_id, liquidity) in pools {
+            if self.path.iter().any(|route_part| route_part.pool_id == *pool_id) {
+                continue;
+            }
+            let amount_in = match liquidity.iter().find(|coin| coin.denom == denom_in) {
+                Some(coin) if !coin.amount.is_zero() => coin.amount,
+                _ => continue,
+            };
+
+            for coin_out in liquidity {
+                if coin_out.amount.is_zero() || self.visited_denoms.contains(&coin_out.denom) {
+                    continue;
+                }
+
+                let pool_depth = rate.saturating_mul(Decimal256::from_ratio(amount_in, 1u128));
+                let path_depth = depth.min(pool_depth);
+                let rate_out =
+                    rate.saturating_mul(Decimal256::from_ratio(amount_in, coin_out.amount));
+
+                self.path.push(SwapAmountInRoute {
+                    pool_id: *pool_id,
+                    token_out_denom: coin_out.denom.clone(),
+                });
+
+                if coin_out.denom == self.output_denom {
+                    let is_better = match &self.best {
+                        Some((best_depth, best_route)) => {
+                            path_depth > *best_depth
+                                || (path_depth == *best_depth && self.path.len() < best_route.len())
+                        }
+                        None => true,
+                    };
+                    if is_better {
+                        self.best = Some((path_depth, self.path.clone()));
+                    }
+                } else {
+                    self.visited_denoms.push(coin_out.denom.clone());
+                    self.visit(&coin_out.denom, hops_left - 1, rate_out, path_depth);
+                    self.visited_denoms.pop();
+                }
+
+                self.path.pop();
+            }
+        }
+    }
+}
+
 // generate_swap_msg generates and returns an Osmosis
 // MsgSwapExactAmountIn with sender, input token and min_output_token.
 // Returns error if there is no supported route
@@ -95,12 +227,147 @@ pub fn generate_swap_msg(
     })
 }
 
+// available_balance returns the balance of denom of the contract, without the
+// funds of failed transfers, which are only sent again by a retry.
+pub fn available_balance(deps: Deps, env: &Env, denom: &str) -> StdResult<Uint128> {
+    let balance = deps.querier.query_balance(&env.contract.address, denom)?.amount;
+    let refunded = REFUNDED.may_load(deps.storage, denom)?.unwrap_or_default();
+    Ok(balance.saturating_sub(refunded))
+}
+
+// reserved_amount returns the amount of denom set aside at balance, or None
+// if the denom has no reserve. The reserve of the denom is added to the amount
+// set aside so far for the inflow since it was last updated.
+fn reserved_amount(
+    storage: &dyn Storage,
+    denom: &str,
+    balance: Uint128,
+) -> StdResult<Option<Reserved>> {
+    let config = CONFIG.load(storage)?;
+    let reserve = match config.reserves.get(denom) {
+        Some(reserve) => *reserve,
+        None => return Ok(None),
+    };
+
+    let reserved = RESERVED.may_load(storage, denom)?.unwrap_or_default();
+    let inflow = balance.saturating_sub(reserved.balance);
+    Ok(Some(Reserved {
+        amount: reserved.amount.checked_add(inflow.mul(reserve))?.min(balance),
+        balance,
+    }))
+}
+
+// update_reserved saves the amount of denom set aside at balance, before
+// balance is liquidated or transferred, and returns it.
+pub fn update_reserved(
+    storage: &mut dyn Storage,
+    denom: &str,
+    balance: Uint128,
+) -> StdResult<Uint128> {
+    match reserved_amount(storage, denom, balance)? {
+        Some(reserved) => {
+            RESERVED.save(storage, denom, &reserved)?;
+            Ok(reserved.amount)
+        }
+        None => Ok(Uint128::zero()),
+    }
+}
+
+// record_outflow lowers the balance the amount of denom set aside was last
+// updated at by amount sent out of the contract, so that the funds received
+// before its next update are counted as an inflow.
+pub fn record_outflow(storage: &mut dyn Storage, denom: &str, amount: Uint128) -> StdResult<()> {
+    if let Some(mut reserved) = RESERVED.may_load(storage, denom)? {
+        reserved.balance = reserved.balance.saturating_sub(amount);
+        RESERVED.save(storage, denom, &reserved)?;
+    }
+    Ok(())
+}
+
+// cancel_outflow reverts record_outflow for amount returned by a failed swap.
+pub fn cancel_outflow(storage: &mut dyn Storage, denom: &str, amount: Uint128) -> StdResult<()> {
+    if let Some(mut reserved) = RESERVED.may_load(storage, denom)? {
+        reserved.balance = reserved.balance.checked_add(amount)?;
+        RESERVED.save(storage, denom, &reserved)?;
+    }
+    Ok(())
+}
+
+// liquidation_amount returns the amount of denom to sell out of balance,
+// net of the reserved amount of the denom and capped at the max chunk of the
+// denom. Returns None if balance is below the min balance of the denom.
+pub fn liquidation_amount(
+    deps: Deps,
+    denom: &str,
+    balance: Uint128,
+) -> Result<Option<Uint128>, ContractError> {
+    let denom_config = DENOM_CONFIGS.may_load(deps.storage, denom)?.unwrap_or_default();
+    let balance =
+        balance - reserved_amount(deps.storage, denom, balance)?.unwrap_or_default().amount;
+
+    if balance.is_zero() || balance < denom_config.min_balance {
+        return Ok(None);
+    }
+    if denom_config.max_chunk.is_zero() {
+        return Ok(Some(balance));
+    }
+    Ok(Some(balance.min(denom_config.max_chunk)))
+}
+
+// calculate_min_output returns the minimum output of swapping input_token
+// to output_denom along its route with the config of the route.
+// The minimum output is at least one, as swaps require a positive minimum.
+pub fn calculate_min_output(
+    deps: Deps,
+    input_token: Coin,
+    output_denom: String,
+    now: Timestamp,
+) -> Result<Coin, ContractError> {
+    let route_config = ROUTE_CONFIGS
+        .may_load(deps.storage, (&input_token.denom, &output_denom))?
+        .unwrap_or_default();
+
+    let mut min_output =
+        calculate_min_output_from_twap(deps, input_token, output_denom, now, &route_config)?;
+    min_output.amount = min_output.amount.max(Uint128::one());
+    Ok(min_output)
+}
+
+// estimate_swap_output returns the output of swapping input_token to
+// output_denom along its route at the current state of the pools.
+pub fn estimate_swap_output(
+    deps: Deps,
+    input_token: &Coin,
+    output_denom: &str,
+) -> Result<Uint128, ContractError> {
+    let route = ROUTING_TABLE.load(deps.storage, (&input_token.denom, output_denom))?;
+    let first_pool_id = route.first().map(|route_part| route_part.pool_id).ok_or_else(|| {
+        ContractError::InvalidPoolRoute {
+            reason: format!("No route found for {} -> {output_denom}", input_token.denom),
+        }
+    })?;
+
+    let token_out_amount = EstimateSwapExactAmountInRequest {
+        pool_id: first_pool_id,
+        token_in: input_token.to_string(),
+        routes: route,
+        ..Default::default()
+    }
+    .query(&deps.querier)?
+    .token_out_amount;
+
+    Ok(token_out_amount.parse()?)
+}
+
+// calculate_min_output_from_twap returns the minimum output of swapping
+// input_token along its route, i.e. the output at the TWAP price over
+// the window of route_config, minus its max price impact.
 pub fn calculate_min_output_from_twap(
     deps: Deps,
     input_token: Coin,
     output_denom: String,
     now: Timestamp,
-    percentage_impact: Decimal,
+    route_config: &RouteConfig,
 ) -> Result<Coin, ContractError> {
     // get trade route
     let route = ROUTING_TABLE.load(deps.storage, (&input_token.denom, &output_denom))?;
@@ -110,35 +377,31 @@ pub fn calculate_min_output_from_twap(
         });
     }
 
-    let percentage = percentage_impact.div(Uint128::new(100));
-
     let mut twap_price: Decimal = Decimal::one();
 
-    // When swapping from input to output, we need to quote the price in the input token
+    // The TWAP is quoted as the quote asset per unit of the base asset, so each
+    // route part is priced with its input as the base and its output as the quote,
+    // and the prices are chained along the route.
     // For example when selling uosmo to buy uion:
-    // price of <out> is X<in> (i.e.: price of uion is X uosmo)
-    let mut quote_denom = input_token.denom;
+    // price of <in> is X<out> (i.e.: price of uosmo is X uion)
+    let mut denom_in = input_token.denom;
 
-    let start_time = now.minus_seconds(1); // does it makes sense to use TWAP smaller than block?
+    let start_time = now.minus_seconds(route_config.twap_window);
     let start_time = OsmosisTimestamp {
         seconds: start_time.seconds() as i64,
         nanos: 0_i32,
     };
 
     for route_part in route {
-        deps.api.debug(&format!("route part: {route_part:?}"));
-
         let twap = TwapQuerier::new(&deps.querier)
             .arithmetic_twap_to_now(
                 route_part.pool_id,
-                route_part.token_out_denom.clone(), // base_asset
-                quote_denom.clone(),                // quote_asset
+                denom_in.clone(),                   // base_asset
+                route_part.token_out_denom.clone(), // quote_asset
                 Some(start_time.clone()),
             )?
             .arithmetic_twap;
 
-        deps.api.debug(&format!("twap = {twap}"));
-
         let current_twap: Decimal = twap.parse().map_err(|_e| ContractError::CustomError {
             val: "Invalid twap value received from the chain".to_string(),
         })?;
@@ -149,14 +412,12 @@ pub fn calculate_min_output_from_twap(
             })?;
 
         // the current output is the input for the next route_part
-        quote_denom = route_part.token_out_denom;
+        denom_in = route_part.token_out_denom;
     }
 
-    twap_price = twap_price - twap_price.mul(percentage);
-    deps.api.debug(&format!("twap_price minus {percentage_impact}%: {twap_price}"));
+    twap_price = twap_price - twap_price.mul(route_config.max_price_impact);
 
     let min_out: Uint128 = input_token.amount.mul(twap_price);
-    deps.api.debug(&format!("min: {min_out}"));
 
     Ok(Coin::new(min_out.into(), output_denom))
 }
@@ -0,0 +1,198 @@
+use cosmwasm_std::{
+    ensure, Addr, Coin, DepsMut, Env, MessageInfo, Reply, Response, StdError, StdResult, SubMsg,
+};
+use osmosis_std::types::ibc::applications::transfer::v1::{MsgTransfer, MsgTransferResponse};
+
+use crate::{
+    error::ContractError,
+    handle::PACKET_LIFETIME,
+    msg::IbcLifecycleComplete,
+    state::{
+        Transfer, TransferStatus, NEXT_TRANSFER_ID, OWNER, REFUNDED, TRANSFERS, TRANSFER_SEQUENCES,
+    },
+};
+
+/// Reply ids of IBC transfers are the transfer id plus this offset, which keeps
+/// them apart from the reply ids of liquidation swaps
+pub const TRANSFER_REPLY_ID_OFFSET: u64 = 1 << 32;
+
+// transfer_submsg records an outbound IBC transfer and returns the message
+// sending it. The memo asks the IBC-hooks module for a callback to the
+// contract once the transfer is acknowledged or times out.
+pub fn transfer_submsg(
+    deps: DepsMut,
+    env: &Env,
+    channel_id: String,
+    to_address: String,
+    amount: Coin,
+    memo: Option<String>,
+) -> Result<SubMsg, ContractError> {
+    let id = NEXT_TRANSFER_ID.may_load(deps.storage)?.unwrap_or(1);
+    NEXT_TRANSFER_ID.save(deps.storage, &(id + 1))?;
+
+    let msg = MsgTransfer {
+        source_port: "transfer".to_string(),
+        source_channel: channel_id.clone(),
+        token: Some(amount.clone().into()),
+        sender: env.contract.address.to_string(),
+        receiver: to_address.clone(),
+        timeout_height: None,
+        timeout_timestamp: env.block.time.plus_seconds(PACKET_LIFETIME).nanos(),
+        memo: callback_memo(memo.as_deref(), &env.contract.address)?,
+    };
+
+    TRANSFERS.save(
+        deps.storage,
+        id,
+        &Transfer {
+            id,
+            channel_id,
+            to_address,
+            amount,
+            memo,
+            sequence: None,
+            status: TransferStatus::Pending,
+            created_at: env.block.time,
+        },
+    )?;
+
+    Ok(SubMsg::reply_on_success(msg, TRANSFER_REPLY_ID_OFFSET + id))
+}
+
+// callback_memo adds the IBC-hooks callback to memo, which must be empty or a
+// JSON object.
+pub fn callback_memo(memo: Option<&str>, contract: &Addr) -> Result<String, ContractError> {
+    let mut memo = match memo {
+        Some(memo) if !memo.is_empty() => serde_json::from_str::<
+            serde_json::Map<String, serde_json::Value>,
+        >(memo)
+        .map_err(|_e| ContractError::CustomError {
+            val: "IBC transfer memo must be a JSON object".to_string(),
+        })?,
+        _ => serde_json::Map::new(),
+    };
+    memo.insert("ibc_callback".to_string(), contract.to_string().into());
+    Ok(serde_json::Value::Object(memo).to_string())
+}
+
+/// Records the packet sequence of a sent IBC transfer
+pub fn reply_transfer(deps: DepsMut, reply: Reply) -> Result<Response, ContractError> {
+    let id = reply.id - TRANSFER_REPLY_ID_OFFSET;
+
+    let data = reply
+        .result
+        .into_result()
+        .map_err(StdError::generic_err)?
+        .data
+        .ok_or_else(|| StdError::generic_err("No data in IBC transfer reply"))?;
+    let sequence = MsgTransferResponse::try_from(data)?.sequence;
+
+    let mut transfer = TRANSFERS.load(deps.storage, id)?;
+    transfer.sequence = Some(sequence);
+    TRANSFERS.save(deps.storage, id, &transfer)?;
+    TRANSFER_SEQUENCES.save(deps.storage, (&transfer.channel_id, sequence), &id)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "ibc_transfer_sent")
+        .add_attribute("transfer_id", id.to_string())
+        .add_attribute("sequence", sequence.to_string()))
+}
+
+/// Updates the status of an IBC transfer from the IBC-hooks callback
+pub fn ibc_lifecycle_complete(
+    deps: DepsMut,
+    msg: IbcLifecycleComplete,
+) -> Result<Response, ContractError> {
+    let (channel, sequence, status) = match msg {
+        IbcLifecycleComplete::IbcAck {
+            channel,
+            sequence,
+            ack,
+            success,
+        } => {
+            let status = if success {
+                TransferStatus::Succeeded
+            } else {
+                TransferStatus::Failed {
+                    ack,
+                }
+            };
+            (channel, sequence, status)
+        }
+        IbcLifecycleComplete::IbcTimeout {
+            channel,
+            sequence,
+        } => (channel, sequence, TransferStatus::TimedOut),
+    };
+
+    let id = TRANSFER_SEQUENCES.load(deps.storage, (&channel, sequence)).map_err(|_e| {
+        ContractError::CustomError {
+            val: format!("No transfer with sequence {sequence} on {channel}"),
+        }
+    })?;
+    let mut transfer = TRANSFERS.load(deps.storage, id)?;
+    ensure!(
+        transfer.status == TransferStatus::Pending,
+        ContractError::CustomError {
+            val: format!("Transfer {id} was already completed"),
+        }
+    );
+    transfer.status = status;
+    TRANSFERS.save(deps.storage, id, &transfer)?;
+
+    // The funds of a failed transfer were returned to the contract, and are set
+    // aside for a retry
+    if transfer.status != TransferStatus::Succeeded {
+        REFUNDED.update(deps.storage, &transfer.amount.denom, |refunded| -> StdResult<_> {
+            Ok(refunded.unwrap_or_default().checked_add(transfer.amount.amount)?)
+        })?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "ibc_lifecycle_complete")
+        .add_attribute("transfer_id", id.to_string())
+        .add_attribute("status", format!("{:?}", transfer.status)))
+}
+
+/// Sends a failed or timed out IBC transfer again, as a new transfer
+pub fn retry_transfer(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    ensure!(OWNER.is_admin(deps.as_ref(), &info.sender)?, ContractError::Unauthorized {});
+
+    let mut transfer = TRANSFERS.load(deps.storage, id)?;
+    ensure!(
+        matches!(transfer.status, TransferStatus::Failed { .. } | TransferStatus::TimedOut),
+        ContractError::CustomError {
+            val: format!("Transfer {id} has not failed"),
+        }
+    );
+
+    REFUNDED.update(deps.storage, &transfer.amount.denom, |refunded| -> StdResult<_> {
+        Ok(refunded.unwrap_or_default().checked_sub(transfer.amount.amount)?)
+    })?;
+
+    let retry_id = NEXT_TRANSFER_ID.may_load(deps.storage)?.unwrap_or(1);
+    let msg = transfer_submsg(
+        deps.branch(),
+        &env,
+        transfer.channel_id.clone(),
+        transfer.to_address.clone(),
+        transfer.amount.clone(),
+        transfer.memo.clone(),
+    )?;
+
+    transfer.status = TransferStatus::Retried {
+        retry_id,
+    };
+    TRANSFERS.save(deps.storage, id, &transfer)?;
+
+    Ok(Response::new()
+        .add_submessage(msg)
+        .add_attribute("action", "retry_transfer")
+        .add_attribute("transfer_id", id.to_string())
+        .add_attribute("retry_id", retry_id.to_string()))
+}
@@ -1,7 +1,9 @@
 pub mod contract;
+mod cw20_input;
 mod error;
 mod handle;
 mod helpers;
+mod ibc;
 pub mod msg;
 mod query;
 pub mod state;
@@ -1,9 +1,13 @@
 use std::collections::HashMap;
 
 use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{Binary, Coin, Decimal, Uint128};
+use cw20::Cw20ReceiveMsg;
 use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
 
-use crate::state::Config;
+use crate::state::{
+    Config, Cw20Input, DenomConfig, Destination, RouteConfig, SwapRecord, Transfer,
+};
 
 #[cw_serde]
 pub struct InstantiateMsg {
@@ -32,8 +36,110 @@ pub enum ExecuteMsg {
         input_denom: String,
         output_denom: String,
     },
+    /// Set the pool ids searched by `DiscoverRoute`.
+    SetCandidatePools {
+        pool_ids: Vec<u64>,
+    },
+    /// Find the deepest route from `input_denom` to `output_denom` through the
+    /// candidate pools with at most `max_hops` swaps, and set it as the route
+    /// of the pair.
+    DiscoverRoute {
+        input_denom: String,
+        output_denom: String,
+        max_hops: u32,
+    },
+    /// Set the TWAP window in seconds and the max price impact, as a fraction,
+    /// of the swaps along a route. Routes without a config use the defaults.
+    SetRouteConfig {
+        input_denom: String,
+        output_denom: String,
+        twap_window: u64,
+        max_price_impact: Decimal,
+    },
+    /// Swap the balance of every input denom of the routing table along its
+    /// route, with a minimum output based on the TWAP price of the route.
+    /// Denoms whose route cannot be priced or whose swap falls short of the
+    /// minimum output are skipped with a `skip_liquidation` event.
     Liquidate {},
     IbcTransfer {},
+    /// Liquidate like `Liquidate {}`, but only along the routes to the
+    /// liquidation target, and transfer exactly the output of the swaps via
+    /// IBC like `IbcTransfer {}`.
+    LiquidateAndSend {},
+    /// Callback of `LiquidateAndSend {}` transferring the output of the swaps.
+    /// Can only be called by the contract itself.
+    SendProceeds {},
+    /// Swap `amount` of `denom` to the liquidation target, by default the
+    /// balance of `denom` up to its max chunk. `min_out` can only raise the
+    /// TWAP-based minimum output. Fails instead of skipping the denom.
+    LiquidateDenom {
+        denom: String,
+        amount: Option<Uint128>,
+        min_out: Option<Uint128>,
+    },
+    /// Send a failed or timed out IBC transfer again. The funds of the
+    /// transfer must have been returned to the contract.
+    RetryTransfer {
+        id: u64,
+    },
+    /// Set the weighted destinations of the liquidation target.
+    SetDestinations {
+        destinations: Vec<Destination>,
+    },
+    /// Split the balance of the liquidation target between the destinations
+    /// by their weights.
+    Disburse {},
+    /// Set the max amount of `denom` sold per liquidation, zero for no limit,
+    /// and the balance of `denom` below which it is not liquidated.
+    SetDenomConfig {
+        denom: String,
+        max_chunk: Uint128,
+        min_balance: Uint128,
+    },
+    /// Set the accounts allowed to liquidate and transfer besides the owner.
+    /// Anyone is allowed if empty.
+    SetKeepers {
+        keepers: Vec<String>,
+    },
+    /// Pause or resume liquidations and transfers.
+    SetPaused {
+        paused: bool,
+    },
+    /// Set the fraction of the balance of `denom` that is never liquidated,
+    /// zero for none.
+    SetReserve {
+        denom: String,
+        reserve: Decimal,
+    },
+    /// Accept deposits of a registered cw20 input.
+    Receive(Cw20ReceiveMsg),
+    /// Register `token` as a cw20 input liquidated by sending it to `router`
+    /// with `router_msg`. The router must return `denom`, which is swapped
+    /// along its route to the liquidation target.
+    SetCw20Input {
+        token: String,
+        router: String,
+        /// A JSON object with a single variant, whose `minimum_receive` is set
+        /// on every liquidation
+        router_msg: Binary,
+        denom: String,
+        /// Minimum amount of `denom` the router must return per token
+        min_price: Decimal,
+    },
+    RemoveCw20Input {
+        token: String,
+    },
+    /// Callback of `Liquidate {}` and `LiquidateAndSend {}` liquidating a
+    /// chunk of the balance of a cw20 input. Can only be called by the
+    /// contract itself.
+    LiquidateCw20 {
+        token: String,
+    },
+}
+
+#[cw_serde]
+pub enum Cw20HookMsg {
+    Deposit {},
 }
 
 #[cw_serde]
@@ -48,11 +154,56 @@ pub enum QueryMsg {
         input_denom: String,
         output_denom: String,
     },
+    #[returns(RouteConfig)]
+    GetRouteConfig {
+        input_denom: String,
+        output_denom: String,
+    },
+    /// Simulates `Liquidate {}` at the current balances of the contract.
+    #[returns(SimulateLiquidationResponse)]
+    SimulateLiquidation {},
+    /// Simulates swapping `input` along the route to `output_denom`.
+    #[returns(SimulateRouteResponse)]
+    SimulateRoute {
+        input: Coin,
+        output_denom: String,
+    },
+    #[returns(Transfer)]
+    GetTransfer {
+        id: u64,
+    },
+    /// Returns the outbound IBC transfers of the contract by ascending id
+    #[returns(GetTransfersResponse)]
+    GetTransfers {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(GetDestinationsResponse)]
+    GetDestinations {},
+    #[returns(DenomConfig)]
+    GetDenomConfig {
+        denom: String,
+    },
+    #[returns(GetCandidatePoolsResponse)]
+    GetCandidatePools {},
     #[returns(GetAllRoutesResponse)]
     GetAllRoutes {
         start_after: Option<String>,
         limit: Option<u32>,
     },
+    #[returns(GetCw20InputsResponse)]
+    GetCw20Inputs {},
+    /// Returns the executed liquidation swaps by ascending id
+    #[returns(GetSwapHistoryResponse)]
+    GetSwapHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Returns the running totals of the swaps of `input_denom`, per output denom
+    #[returns(GetSwapTotalsResponse)]
+    GetSwapTotals {
+        input_denom: String,
+    },
 }
 
 #[cw_serde]
@@ -65,7 +216,88 @@ pub struct GetRouteResponse {
     pub pool_route: Vec<SwapAmountInRoute>,
 }
 
+/// Sudo messages sent by the IBC-hooks module
+#[cw_serde]
+pub enum SudoMsg {
+    /// Callback for an IBC transfer sent with an `ibc_callback` memo
+    IbcLifecycleComplete(IbcLifecycleComplete),
+}
+
+#[cw_serde]
+pub enum IbcLifecycleComplete {
+    IbcAck {
+        channel: String,
+        sequence: u64,
+        ack: String,
+        success: bool,
+    },
+    IbcTimeout {
+        channel: String,
+        sequence: u64,
+    },
+}
+
+#[cw_serde]
+pub struct GetTransfersResponse {
+    pub transfers: Vec<Transfer>,
+}
+
+#[cw_serde]
+pub struct GetCw20InputsResponse {
+    pub inputs: Vec<Cw20Input>,
+}
+
+#[cw_serde]
+pub struct GetSwapHistoryResponse {
+    pub swaps: Vec<SwapRecord>,
+}
+
+#[cw_serde]
+pub struct GetSwapTotalsResponse {
+    pub input_denom: String,
+    pub totals: Vec<OutputSwapTotals>,
+}
+
+#[cw_serde]
+pub struct OutputSwapTotals {
+    pub output_denom: String,
+    pub input_amount: Uint128,
+    pub output_amount: Uint128,
+    pub swap_count: u64,
+    /// Realised price of the input denom in the output denom
+    pub average_price: Decimal,
+}
+
+#[cw_serde]
+pub struct GetDestinationsResponse {
+    pub destinations: Vec<Destination>,
+}
+
+#[cw_serde]
+pub struct GetCandidatePoolsResponse {
+    pub pool_ids: Vec<u64>,
+}
+
 #[cw_serde]
 pub struct GetAllRoutesResponse {
     pub routes: HashMap<String, Vec<SwapAmountInRoute>>,
 }
+
+#[cw_serde]
+pub struct SimulateLiquidationResponse {
+    /// The simulation of every route whose input denom the contract holds
+    pub routes: Vec<SimulateRouteResponse>,
+}
+
+#[cw_serde]
+pub struct SimulateRouteResponse {
+    pub input: Coin,
+    pub output_denom: String,
+    pub pool_route: Vec<SwapAmountInRoute>,
+    /// Output of the swap at the current state of the pools
+    pub expected_output: Option<Uint128>,
+    /// Minimum output of the swap derived from the TWAP of the route
+    pub min_output: Option<Uint128>,
+    /// Why the swap would be skipped by `Liquidate {}`, if it would be
+    pub skip_reason: Option<String>,
+}
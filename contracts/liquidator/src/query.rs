@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use cosmwasm_std::{Deps, Order, StdError, StdResult};
+use cosmwasm_std::{Coin, Decimal, Deps, Env, Order, StdError, StdResult};
 use cw_storage_plus::Bound;
 
 const MAX_LIMIT: u32 = 30;
@@ -8,8 +8,18 @@ const DEFAULT_LIMIT: u32 = 10;
 
 use crate::{
     error::ContractError,
-    msg::{GetAllRoutesResponse, GetOwnerResponse, GetRouteResponse},
-    state::{Config, CONFIG, OWNER, ROUTING_TABLE},
+    helpers::{available_balance, calculate_min_output, estimate_swap_output, liquidation_amount},
+    msg::{
+        GetAllRoutesResponse, GetCandidatePoolsResponse, GetCw20InputsResponse,
+        GetDestinationsResponse, GetOwnerResponse, GetRouteResponse, GetSwapHistoryResponse,
+        GetSwapTotalsResponse, GetTransfersResponse, OutputSwapTotals, SimulateLiquidationResponse,
+        SimulateRouteResponse,
+    },
+    state::{
+        Config, DenomConfig, RouteConfig, Transfer, CANDIDATE_POOLS, CONFIG, CW20_INPUTS,
+        DENOM_CONFIGS, DESTINATIONS, OWNER, ROUTE_CONFIGS, ROUTING_TABLE, SWAP_HISTORY,
+        SWAP_TOTALS, TRANSFERS,
+    },
 };
 
 /// Queries contract owner from the admin
@@ -45,6 +55,117 @@ pub fn query_route(
     })
 }
 
+/// Queries the config of a route, or the default config if none is set
+pub fn query_route_config(
+    deps: Deps,
+    input_denom: String,
+    output_denom: String,
+) -> StdResult<RouteConfig> {
+    if !ROUTING_TABLE.has(deps.storage, (&input_denom, &output_denom)) {
+        return Err(StdError::not_found("Route"));
+    }
+    Ok(ROUTE_CONFIGS.may_load(deps.storage, (&input_denom, &output_denom))?.unwrap_or_default())
+}
+
+/// Queries an outbound IBC transfer
+pub fn query_transfer(deps: Deps, id: u64) -> StdResult<Transfer> {
+    TRANSFERS.load(deps.storage, id).map_err(|_| StdError::not_found("Transfer"))
+}
+
+/// Queries the outbound IBC transfers
+pub fn query_transfers(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<GetTransfersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let transfers = TRANSFERS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, transfer)| transfer))
+        .collect::<StdResult<_>>()?;
+
+    Ok(GetTransfersResponse {
+        transfers,
+    })
+}
+
+/// Queries the registered cw20 inputs
+pub fn query_cw20_inputs(deps: Deps) -> StdResult<GetCw20InputsResponse> {
+    let inputs = CW20_INPUTS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, input)| input))
+        .collect::<StdResult<_>>()?;
+
+    Ok(GetCw20InputsResponse {
+        inputs,
+    })
+}
+
+/// Queries the executed liquidation swaps
+pub fn query_swap_history(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<GetSwapHistoryResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let swaps = SWAP_HISTORY
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, swap)| swap))
+        .collect::<StdResult<_>>()?;
+
+    Ok(GetSwapHistoryResponse {
+        swaps,
+    })
+}
+
+/// Queries the running swap totals of an input denom
+pub fn query_swap_totals(deps: Deps, input_denom: String) -> StdResult<GetSwapTotalsResponse> {
+    let totals = SWAP_TOTALS
+        .prefix(&input_denom)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            item.map(|(output_denom, totals)| OutputSwapTotals {
+                output_denom,
+                input_amount: totals.input_amount,
+                output_amount: totals.output_amount,
+                swap_count: totals.swap_count,
+                average_price: Decimal::from_ratio(totals.output_amount, totals.input_amount),
+            })
+        })
+        .collect::<StdResult<_>>()?;
+
+    Ok(GetSwapTotalsResponse {
+        input_denom,
+        totals,
+    })
+}
+
+/// Queries the destinations of the liquidation target
+pub fn query_destinations(deps: Deps) -> StdResult<GetDestinationsResponse> {
+    Ok(GetDestinationsResponse {
+        destinations: DESTINATIONS.may_load(deps.storage)?.unwrap_or_default(),
+    })
+}
+
+/// Queries the liquidation limits of a denom, or the default limits if none
+/// are set
+pub fn query_denom_config(deps: Deps, denom: String) -> StdResult<DenomConfig> {
+    Ok(DENOM_CONFIGS.may_load(deps.storage, &denom)?.unwrap_or_default())
+}
+
+/// Queries the pool ids searched by route discovery
+pub fn query_candidate_pools(deps: Deps) -> StdResult<GetCandidatePoolsResponse> {
+    Ok(GetCandidatePoolsResponse {
+        pool_ids: CANDIDATE_POOLS.may_load(deps.storage)?.unwrap_or_default(),
+    })
+}
+
 pub fn query_all_routes(
     deps: Deps,
     start_after: Option<String>,
@@ -63,3 +184,63 @@ pub fn query_all_routes(
         routes: routes?,
     })
 }
+
+/// Simulates liquidating the balance of every input denom of the routing table,
+/// up to its max chunk
+pub fn query_simulate_liquidation(deps: Deps, env: Env) -> StdResult<SimulateLiquidationResponse> {
+    let routes = ROUTING_TABLE
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut simulations = vec![];
+    for (input_denom, output_denom) in routes {
+        let balance = available_balance(deps, &env, &input_denom)?;
+        let amount = liquidation_amount(deps, &input_denom, balance)
+            .map_err(|err| StdError::generic_err(err.to_string()))?;
+        if let Some(amount) = amount {
+            let input = Coin::new(amount.u128(), input_denom);
+            simulations.push(query_simulate_route(deps, &env, input, output_denom)?);
+        }
+    }
+
+    Ok(SimulateLiquidationResponse {
+        routes: simulations,
+    })
+}
+
+/// Simulates swapping input along its route to output_denom
+pub fn query_simulate_route(
+    deps: Deps,
+    env: &Env,
+    input: Coin,
+    output_denom: String,
+) -> StdResult<SimulateRouteResponse> {
+    let pool_route = ROUTING_TABLE
+        .load(deps.storage, (&input.denom, &output_denom))
+        .map_err(|_| StdError::not_found("Route"))?;
+
+    let min_output =
+        calculate_min_output(deps, input.clone(), output_denom.clone(), env.block.time)
+            .map(|coin| coin.amount);
+    let expected_output = estimate_swap_output(deps, &input, &output_denom);
+
+    // Same conditions as in `Liquidate {}`, where a failed TWAP query skips the
+    // denom and a swap below the minimum output is reverted
+    let skip_reason = match (&min_output, &expected_output) {
+        (Err(err), _) => Some(err.to_string()),
+        (Ok(_), Err(err)) => Some(err.to_string()),
+        (Ok(min_output), Ok(expected_output)) if expected_output < min_output => {
+            Some(format!("expected output {expected_output} is below minimum {min_output}"))
+        }
+        _ => None,
+    };
+
+    Ok(SimulateRouteResponse {
+        input,
+        output_denom,
+        pool_route,
+        expected_output: expected_output.ok(),
+        min_output: min_output.ok(),
+        skip_reason,
+    })
+}
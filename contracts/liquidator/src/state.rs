@@ -1,4 +1,6 @@
-use cosmwasm_std::Addr;
+use std::collections::BTreeMap;
+
+use cosmwasm_std::{Addr, Binary, Coin, Decimal, Timestamp, Uint128};
 use cw_controllers::Admin;
 use cw_storage_plus::{Item, Map};
 use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
@@ -8,10 +10,208 @@ use serde::{Deserialize, Serialize};
 pub const CONFIG: Item<Config> = Item::new("config");
 pub const OWNER: Admin = Admin::new("owner");
 pub const ROUTING_TABLE: Map<(&str, &str), Vec<SwapAmountInRoute>> = Map::new("routing_table");
+pub const ROUTE_CONFIGS: Map<(&str, &str), RouteConfig> = Map::new("route_configs");
+pub const DENOM_CONFIGS: Map<&str, DenomConfig> = Map::new("denom_configs");
+/// Destinations of the liquidation target, applied by `Disburse`
+pub const DESTINATIONS: Item<Vec<Destination>> = Item::new("destinations");
+/// Outbound IBC transfers, by transfer id
+pub const TRANSFERS: Map<u64, Transfer> = Map::new("transfers");
+/// Transfer ids by channel id and packet sequence
+pub const TRANSFER_SEQUENCES: Map<(&str, u64), u64> = Map::new("transfer_sequences");
+pub const NEXT_TRANSFER_ID: Item<u64> = Item::new("next_transfer_id");
+/// Funds returned by failed or timed out transfers, by denom. They are only
+/// sent again by `RetryTransfer`, and are excluded from the balance that is
+/// liquidated, transferred or disbursed.
+pub const REFUNDED: Map<&str, Uint128> = Map::new("refunded");
+/// Amounts set aside by the reserves, by denom or cw20 token address
+pub const RESERVED: Map<&str, Reserved> = Map::new("reserved");
+/// Cw20 tokens liquidated through a DEX router, by token address
+pub const CW20_INPUTS: Map<&Addr, Cw20Input> = Map::new("cw20_inputs");
+/// Router swap of the cw20 input being liquidated
+pub const PENDING_CW20_SWAP: Item<PendingCw20Swap> = Item::new("pending_cw20_swap");
+/// Executed swaps, by swap id
+pub const SWAP_HISTORY: Map<u64, SwapRecord> = Map::new("swap_history");
+pub const NEXT_SWAP_ID: Item<u64> = Item::new("next_swap_id");
+/// Running totals of the executed swaps, by input and output denom
+pub const SWAP_TOTALS: Map<(&str, &str), SwapTotals> = Map::new("swap_totals");
+/// Pool ids searched by `DiscoverRoute`
+pub const CANDIDATE_POOLS: Item<Vec<u64>> = Item::new("candidate_pools");
+/// Swaps of the current liquidation, by reply ID
+pub const PENDING_SWAPS: Map<u64, PendingSwap> = Map::new("pending_swaps");
+/// Output of the swaps of the current `LiquidateAndSend`
+pub const PENDING_PROCEEDS: Item<Uint128> = Item::new("pending_proceeds");
+
+/// Max number of hops of a route found by `DiscoverRoute`
+pub const MAX_DISCOVERY_HOPS: u32 = 4;
+
+/// TWAP window in seconds of routes without a `RouteConfig`
+pub const DEFAULT_TWAP_WINDOW: u64 = 10 * 60; // Ten minutes
+/// Max price impact of routes without a `RouteConfig`
+pub const DEFAULT_MAX_PRICE_IMPACT: Decimal = Decimal::percent(1);
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct Config {
     pub ibc_channel_id: String,
     pub ibc_to_address: Addr,
     pub liquidation_target: String,
+    /// Accounts allowed to liquidate and transfer besides the owner, anyone
+    /// if empty
+    #[serde(default)]
+    pub keepers: Vec<Addr>,
+    /// Whether liquidations and transfers are paused
+    #[serde(default)]
+    pub paused: bool,
+    /// Fraction of the balance of each denom that is never liquidated
+    #[serde(default)]
+    pub reserves: BTreeMap<String, Decimal>,
+}
+
+/// Protection of the swaps along a route against price manipulation
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct RouteConfig {
+    /// Window in seconds of the TWAP used to price the swap
+    pub twap_window: u64,
+    /// Max fraction by which the swap output may fall below the TWAP price
+    pub max_price_impact: Decimal,
+}
+
+impl Default for RouteConfig {
+    fn default() -> Self {
+        Self {
+            twap_window: DEFAULT_TWAP_WINDOW,
+            max_price_impact: DEFAULT_MAX_PRICE_IMPACT,
+        }
+    }
+}
+
+/// Limits on the liquidation of an input denom
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema, Default)]
+pub struct DenomConfig {
+    /// Max amount sold per liquidation, zero for no limit
+    pub max_chunk: Uint128,
+    /// Balance below which the denom is not liquidated
+    pub min_balance: Uint128,
+}
+
+/// A destination of the liquidation target, receiving a share of the balance
+/// proportional to its weight
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Destination {
+    pub weight: u64,
+    pub kind: DestinationKind,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DestinationKind {
+    /// An address on this chain
+    Local {
+        address: String,
+    },
+    /// A contract on this chain, executed with `msg` and the tokens as funds
+    Contract {
+        address: String,
+        msg: Binary,
+    },
+    /// An address on another chain, reached by an ICS-20 transfer
+    Ibc {
+        channel_id: String,
+        to_address: String,
+        memo: Option<String>,
+    },
+}
+
+/// An outbound IBC transfer of the contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Transfer {
+    pub id: u64,
+    pub channel_id: String,
+    pub to_address: String,
+    pub amount: Coin,
+    /// Memo of the transfer, without the IBC-hooks callback
+    pub memo: Option<String>,
+    /// Packet sequence, set once the transfer is sent
+    pub sequence: Option<u64>,
+    pub status: TransferStatus,
+    pub created_at: Timestamp,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferStatus {
+    /// Sent and awaiting an acknowledgement or timeout
+    Pending,
+    /// Acknowledged by the receiving chain
+    Succeeded,
+    /// Acknowledged with an error, the funds were returned to the contract
+    Failed {
+        ack: String,
+    },
+    /// Timed out, the funds were returned to the contract
+    TimedOut,
+    /// Failed or timed out and sent again as another transfer
+    Retried {
+        retry_id: u64,
+    },
+}
+
+/// A swap awaiting its reply
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PendingSwap {
+    pub input: Coin,
+    pub output_denom: String,
+}
+
+/// An executed swap of the contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SwapRecord {
+    pub id: u64,
+    pub input: Coin,
+    pub output: Coin,
+    pub route: Vec<SwapAmountInRoute>,
+    pub height: u64,
+}
+
+/// Running totals of the executed swaps of an input denom to an output denom
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema, Default)]
+pub struct SwapTotals {
+    pub input_amount: Uint128,
+    pub output_amount: Uint128,
+    pub swap_count: u64,
+}
+
+/// Amount of a denom set aside by its reserve. The reserve applies to the
+/// inflows above the balance it was last updated at, so that liquidations and
+/// transfers do not shrink it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema, Default)]
+pub struct Reserved {
+    pub amount: Uint128,
+    /// Balance when the reserved amount was last updated
+    pub balance: Uint128,
+}
+
+/// A cw20 token liquidated by sending it to a DEX router, which returns a
+/// native denom that continues along the Osmosis route to the liquidation
+/// target
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Cw20Input {
+    pub token: Addr,
+    pub router: Addr,
+    /// Hook message sent to the router along with the tokens
+    pub router_msg: Binary,
+    /// Native denom returned by the router
+    pub denom: String,
+    /// Minimum amount of `denom` the router must return per token
+    #[serde(default)]
+    pub min_price: Decimal,
+}
+
+/// A router swap of a cw20 input awaiting its reply
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PendingCw20Swap {
+    pub token: Addr,
+    pub amount: Uint128,
+    pub denom: String,
+    /// Balance of `denom` before the router swap
+    pub balance_before: Uint128,
 }
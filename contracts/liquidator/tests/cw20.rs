@@ -0,0 +1,280 @@
+use cosmwasm_std::{
+    from_binary,
+    testing::{mock_dependencies_with_balance, mock_env, mock_info, MockApi, MockQuerier},
+    to_binary, Addr, Binary, Coin, ContractResult, CosmosMsg, Decimal, Env, MemoryStorage,
+    OwnedDeps, Reply, Response, SubMsgResponse, SubMsgResult, SystemResult, Uint128, WasmMsg,
+    WasmQuery,
+};
+use cw20::{BalanceResponse, Cw20ExecuteMsg, Cw20ReceiveMsg};
+use liquidator::{
+    contract::{execute, instantiate, query, reply},
+    msg::{Cw20HookMsg, ExecuteMsg, GetCw20InputsResponse, InstantiateMsg, QueryMsg},
+    state::Cw20Input,
+};
+
+type MockDeps = OwnedDeps<MemoryStorage, MockApi, MockQuerier>;
+
+// liquidate_cw20 runs the callback liquidating the token
+fn liquidate_cw20(deps: &mut MockDeps, env: &Env) -> Response {
+    let contract = env.contract.address.to_string();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info(&contract, &[]),
+        ExecuteMsg::LiquidateCw20 {
+            token: "token".to_string(),
+        },
+    )
+    .unwrap()
+}
+
+// setup instantiates the contract with the token registered as a cw20 input
+// whose router returns at least 0.8 of the liquidation target per token, and a
+// cw20 balance of 500
+fn setup() -> MockDeps {
+    let mut deps = mock_dependencies_with_balance(&[Coin::new(1_000, "uosmo")]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            owner: "owner".to_string(),
+            ibc_channel_id: "channel-10".to_string(),
+            ibc_to_address: "neutron1yrg6daqkxyeqye4aac09stzvvwppqwlsk2jn2k".to_string(),
+            liquidation_target: "uosmo".to_string(),
+        },
+    )
+    .unwrap();
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::SetCw20Input {
+            token: "token".to_string(),
+            router: "router".to_string(),
+            router_msg: Binary::from(br#"{"swap":{}}"#),
+            denom: "uosmo".to_string(),
+            min_price: Decimal::percent(80),
+        },
+    )
+    .unwrap();
+
+    deps.querier.update_wasm(|query| match query {
+        WasmQuery::Smart {
+            contract_addr,
+            ..
+        } if contract_addr == "token" => SystemResult::Ok(ContractResult::Ok(
+            to_binary(&BalanceResponse {
+                balance: Uint128::new(500),
+            })
+            .unwrap(),
+        )),
+        _ => panic!("unexpected query"),
+    });
+    deps
+}
+
+#[test]
+fn test_cw20_inputs() {
+    let mut deps = setup();
+
+    // Only the owner can register cw20 inputs
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user", &[]),
+        ExecuteMsg::SetCw20Input {
+            token: "other".to_string(),
+            router: "router".to_string(),
+            router_msg: Binary::from(br#"{"swap":{}}"#),
+            denom: "uosmo".to_string(),
+            min_price: Decimal::percent(80),
+        },
+    )
+    .unwrap_err();
+
+    // The router message must be a single variant, and the minimum price nonzero
+    for (router_msg, min_price) in [
+        (&br#"{"swap":{}}"#[..], Decimal::zero()),
+        (&br#"{"swap":{},"send":{}}"#[..], Decimal::percent(80)),
+        (&br#"{"swap":"uosmo"}"#[..], Decimal::percent(80)),
+        (&br#"["swap"]"#[..], Decimal::percent(80)),
+    ] {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::SetCw20Input {
+                token: "other".to_string(),
+                router: "router".to_string(),
+                router_msg: Binary::from(router_msg),
+                denom: "uosmo".to_string(),
+                min_price,
+            },
+        )
+        .unwrap_err();
+    }
+
+    let res: GetCw20InputsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetCw20Inputs {}).unwrap())
+            .unwrap();
+    assert_eq!(
+        res.inputs,
+        vec![Cw20Input {
+            token: Addr::unchecked("token"),
+            router: Addr::unchecked("router"),
+            router_msg: Binary::from(br#"{"swap":{}}"#),
+            denom: "uosmo".to_string(),
+            min_price: Decimal::percent(80),
+        }]
+    );
+
+    // Deposits are accepted from registered tokens only
+    let receive = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "user".to_string(),
+        amount: Uint128::new(100),
+        msg: to_binary(&Cw20HookMsg::Deposit {}).unwrap(),
+    });
+    execute(deps.as_mut(), mock_env(), mock_info("token", &[]), receive.clone()).unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info("other", &[]), receive).unwrap_err();
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::RemoveCw20Input {
+            token: "token".to_string(),
+        },
+    )
+    .unwrap();
+    let res: GetCw20InputsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetCw20Inputs {}).unwrap())
+            .unwrap();
+    assert!(res.inputs.is_empty());
+}
+
+#[test]
+fn test_liquidate_cw20() {
+    let mut deps = setup();
+    let env = mock_env();
+
+    // Liquidate calls back the contract for every cw20 input with a balance
+    let res = execute(deps.as_mut(), env.clone(), mock_info("user", &[]), ExecuteMsg::Liquidate {})
+        .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_binary(&ExecuteMsg::LiquidateCw20 {
+                token: "token".to_string(),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+
+    let msg = ExecuteMsg::LiquidateCw20 {
+        token: "token".to_string(),
+    };
+    execute(deps.as_mut(), env.clone(), mock_info("user", &[]), msg).unwrap_err();
+
+    // The callback sends the balance to the router, with the minimum output of
+    // the balance at the minimum price
+    let contract = env.contract.address.to_string();
+    let res = liquidate_cw20(&mut deps, &env);
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "token".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Send {
+                contract: "router".to_string(),
+                amount: Uint128::new(500),
+                msg: Binary::from(br#"{"swap":{"minimum_receive":"400"}}"#),
+            })
+            .unwrap(),
+            funds: vec![],
+        })
+    );
+
+    // The router returns the liquidation target, which needs no further swap
+    deps.querier.update_balance(&contract, vec![Coin::new(1_450, "uosmo")]);
+    let res = reply(
+        deps.as_mut(),
+        env,
+        Reply {
+            id: res.messages[0].id,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: None,
+            }),
+        },
+    )
+    .unwrap();
+    assert!(res.messages.is_empty());
+    let event = res.events.iter().find(|event| event.ty == "liquidate_cw20").unwrap();
+    assert!(event.attributes.iter().any(|attr| attr.key == "output" && attr.value == "450uosmo"));
+}
+
+#[test]
+fn test_failed_router_swap_is_skipped() {
+    let mut deps = setup();
+    let env = mock_env();
+
+    let res = liquidate_cw20(&mut deps, &env);
+    let res = reply(
+        deps.as_mut(),
+        env,
+        Reply {
+            id: res.messages[0].id,
+            result: SubMsgResult::Err("minimum receive not met".to_string()),
+        },
+    )
+    .unwrap();
+
+    // The token is skipped without swapping anything
+    assert!(res.messages.is_empty());
+    let event = res.events.iter().find(|event| event.ty == "skip_liquidation").unwrap();
+    assert!(event.attributes.iter().any(|attr| attr.key == "denom" && attr.value == "token"));
+    assert!(res.events.iter().all(|event| event.ty != "liquidate_cw20"));
+}
+
+#[test]
+fn test_liquidate_and_send_sends_router_output() {
+    let mut deps = setup();
+    let env = mock_env();
+    let contract = env.contract.address.to_string();
+
+    // There are no routes, so only the cw20 input is liquidated before the
+    // proceeds are sent
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("owner", &[]),
+        ExecuteMsg::LiquidateAndSend {},
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 2);
+
+    let res = liquidate_cw20(&mut deps, &env);
+    deps.querier.update_balance(&contract, vec![Coin::new(1_450, "uosmo")]);
+    reply(
+        deps.as_mut(),
+        env.clone(),
+        Reply {
+            id: res.messages[0].id,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: None,
+            }),
+        },
+    )
+    .unwrap();
+
+    // Only the output of the router is sent, not the uosmo the contract
+    // already held
+    let res = execute(deps.as_mut(), env, mock_info(&contract, &[]), ExecuteMsg::SendProceeds {})
+        .unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert!(res.attributes.iter().any(|attr| attr.key == "amount" && attr.value == "450"));
+}
@@ -0,0 +1,93 @@
+mod common;
+use common::TestEnv;
+use liquidator::{
+    msg::{ExecuteMsg, GetDestinationsResponse, QueryMsg},
+    state::{Destination, DestinationKind},
+};
+use osmosis_std::types::cosmos::{
+    bank::v1beta1::{MsgSend, QueryBalanceRequest},
+    base::v1beta1::Coin,
+};
+use osmosis_test_tube::{Account, Bank, Module, Wasm};
+
+#[test]
+fn test_disburse_to_weighted_destinations() {
+    let TestEnv {
+        app,
+        contract_address,
+        owner,
+    } = TestEnv::new();
+    let wasm = Wasm::new(&app);
+    let bank = Bank::new(&app);
+
+    let alice = app.init_account(&[cosmwasm_std::Coin::new(1_000_000, "uion")]).unwrap();
+    let bob = app.init_account(&[cosmwasm_std::Coin::new(1_000_000, "uion")]).unwrap();
+
+    let destinations = vec![
+        Destination {
+            weight: 1,
+            kind: DestinationKind::Local {
+                address: alice.address(),
+            },
+        },
+        Destination {
+            weight: 3,
+            kind: DestinationKind::Local {
+                address: bob.address(),
+            },
+        },
+    ];
+    let set_destinations_msg = ExecuteMsg::SetDestinations {
+        destinations: destinations.clone(),
+    };
+    wasm.execute(&contract_address, &set_destinations_msg, &[], &owner).unwrap();
+
+    let resp: GetDestinationsResponse =
+        wasm.query(&contract_address, &QueryMsg::GetDestinations {}).unwrap();
+    assert_eq!(resp.destinations, destinations);
+
+    // Send the contract some of the liquidation target
+    bank.send(
+        MsgSend {
+            from_address: owner.address(),
+            to_address: contract_address.clone(),
+            amount: vec![Coin {
+                amount: "400".to_string(),
+                denom: "uosmo".to_string(),
+            }],
+        },
+        &owner,
+    )
+    .unwrap();
+
+    wasm.execute(&contract_address, &ExecuteMsg::Disburse {}, &[], &owner).unwrap();
+
+    let query_uosmo_balance = |address: String| {
+        bank.query_balance(&QueryBalanceRequest {
+            address,
+            denom: "uosmo".to_string(),
+        })
+        .unwrap()
+        .balance
+        .unwrap_or_default()
+        .amount
+    };
+
+    assert_eq!(query_uosmo_balance(alice.address()), "100".to_string());
+    assert_eq!(query_uosmo_balance(bob.address()), "300".to_string());
+    assert_eq!(query_uosmo_balance(contract_address), "0".to_string());
+}
+
+#[test]
+fn test_disburse_without_destinations() {
+    let TestEnv {
+        app,
+        contract_address,
+        owner,
+    } = TestEnv::new();
+    let wasm = Wasm::new(&app);
+
+    let res = wasm.execute(&contract_address, &ExecuteMsg::Disburse {}, &[], &owner).unwrap_err();
+
+    assert_eq!(res.to_string(), "execute error: failed to execute message; message index: 0: Custom Error val: \"No destinations set\": execute wasm contract failed");
+}
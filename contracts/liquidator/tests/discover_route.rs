@@ -0,0 +1,117 @@
+mod common;
+use common::TestEnv;
+use liquidator::msg::{ExecuteMsg, GetRouteResponse, QueryMsg};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
+use osmosis_test_tube::{Module, Wasm};
+
+#[test]
+fn test_discover_direct_route() {
+    let TestEnv {
+        app,
+        contract_address,
+        owner,
+    } = TestEnv::new();
+    let wasm = Wasm::new(&app);
+
+    let set_candidate_pools_msg = ExecuteMsg::SetCandidatePools {
+        pool_ids: vec![1, 2, 3],
+    };
+    wasm.execute(&contract_address, &set_candidate_pools_msg, &[], &owner).unwrap();
+
+    // The direct route and the route through stake are equally deep, so the
+    // direct route is chosen
+    let discover_route_msg = ExecuteMsg::DiscoverRoute {
+        input_denom: "uosmo".to_string(),
+        output_denom: "uion".to_string(),
+        max_hops: 2,
+    };
+    wasm.execute(&contract_address, &discover_route_msg, &[], &owner).unwrap();
+
+    let resp: GetRouteResponse = wasm
+        .query(
+            &contract_address,
+            &QueryMsg::GetRoute {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+            },
+        )
+        .unwrap();
+
+    assert_eq!(
+        resp.pool_route,
+        vec![SwapAmountInRoute {
+            pool_id: 1, // uion/uosmo
+            token_out_denom: "uion".to_string(),
+        },],
+    )
+}
+
+#[test]
+fn test_discover_multihop_route() {
+    let TestEnv {
+        app,
+        contract_address,
+        owner,
+    } = TestEnv::new();
+    let wasm = Wasm::new(&app);
+
+    let set_candidate_pools_msg = ExecuteMsg::SetCandidatePools {
+        pool_ids: vec![2, 3],
+    };
+    wasm.execute(&contract_address, &set_candidate_pools_msg, &[], &owner).unwrap();
+
+    let discover_route_msg = ExecuteMsg::DiscoverRoute {
+        input_denom: "uosmo".to_string(),
+        output_denom: "uion".to_string(),
+        max_hops: 2,
+    };
+    wasm.execute(&contract_address, &discover_route_msg, &[], &owner).unwrap();
+
+    let resp: GetRouteResponse = wasm
+        .query(
+            &contract_address,
+            &QueryMsg::GetRoute {
+                input_denom: "uosmo".to_string(),
+                output_denom: "uion".to_string(),
+            },
+        )
+        .unwrap();
+
+    assert_eq!(
+        resp.pool_route,
+        vec![
+            SwapAmountInRoute {
+                pool_id: 2, // stake/uosmo
+                token_out_denom: "stake".to_string(),
+            },
+            SwapAmountInRoute {
+                pool_id: 3, // stake/uion
+                token_out_denom: "uion".to_string(),
+            },
+        ],
+    )
+}
+
+#[test]
+fn test_discover_route_exceeding_max_hops() {
+    let TestEnv {
+        app,
+        contract_address,
+        owner,
+    } = TestEnv::new();
+    let wasm = Wasm::new(&app);
+
+    let set_candidate_pools_msg = ExecuteMsg::SetCandidatePools {
+        pool_ids: vec![2, 3],
+    };
+    wasm.execute(&contract_address, &set_candidate_pools_msg, &[], &owner).unwrap();
+
+    let discover_route_msg = ExecuteMsg::DiscoverRoute {
+        input_denom: "uosmo".to_string(),
+        output_denom: "uion".to_string(),
+        max_hops: 1,
+    };
+    let res = wasm.execute(&contract_address, &discover_route_msg, &[], &owner).unwrap_err();
+
+    assert_eq!(res.to_string(), "execute error: failed to execute message; message index: 0: Invalid Pool Route: \"No route found for uosmo -> uion within 1 hops\": execute wasm contract failed");
+}
@@ -0,0 +1,263 @@
+use cosmwasm_std::{
+    from_binary,
+    testing::{
+        mock_dependencies_with_balance, mock_env, mock_info, MockApi, MockQuerier,
+        MOCK_CONTRACT_ADDR,
+    },
+    Coin, Decimal, MemoryStorage, OwnedDeps, Reply, SubMsgResponse, SubMsgResult,
+};
+use liquidator::{
+    contract::{execute, instantiate, query, reply, sudo},
+    msg::{
+        ExecuteMsg, GetTransfersResponse, IbcLifecycleComplete, InstantiateMsg, QueryMsg, SudoMsg,
+    },
+    state::{Transfer, TransferStatus},
+};
+use osmosis_std::types::ibc::applications::transfer::v1::MsgTransferResponse;
+
+type MockDeps = OwnedDeps<MemoryStorage, MockApi, MockQuerier>;
+
+fn setup() -> MockDeps {
+    let mut deps = mock_dependencies_with_balance(&[Coin::new(1_000, "uosmo")]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            owner: "owner".to_string(),
+            ibc_channel_id: "channel-10".to_string(),
+            ibc_to_address: "neutron1yrg6daqkxyeqye4aac09stzvvwppqwlsk2jn2k".to_string(),
+            liquidation_target: "uosmo".to_string(),
+        },
+    )
+    .unwrap();
+    deps
+}
+
+// send_transfer executes msg and mocks the reply to its IBC transfer
+fn send_transfer(deps: &mut MockDeps, msg: ExecuteMsg, sequence: u64) {
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+    assert_eq!(res.messages.len(), 1);
+
+    reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: res.messages[0].id,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(
+                    MsgTransferResponse {
+                        sequence,
+                    }
+                    .into(),
+                ),
+            }),
+        },
+    )
+    .unwrap();
+}
+
+fn query_transfer(deps: &MockDeps, id: u64) -> Transfer {
+    from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetTransfer {
+                id,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_transfer_acknowledged() {
+    let mut deps = setup();
+    send_transfer(&mut deps, ExecuteMsg::IbcTransfer {}, 7);
+
+    let transfer = query_transfer(&deps, 1);
+    assert_eq!(transfer.sequence, Some(7));
+    assert_eq!(transfer.amount, Coin::new(1_000, "uosmo"));
+    assert_eq!(transfer.status, TransferStatus::Pending);
+
+    // Mock the IBC-hooks callback
+    sudo(
+        deps.as_mut(),
+        mock_env(),
+        SudoMsg::IbcLifecycleComplete(IbcLifecycleComplete::IbcAck {
+            channel: "channel-10".to_string(),
+            sequence: 7,
+            ack: "AQ==".to_string(),
+            success: true,
+        }),
+    )
+    .unwrap();
+
+    assert_eq!(query_transfer(&deps, 1).status, TransferStatus::Succeeded);
+}
+
+#[test]
+fn test_retry_timed_out_transfer() {
+    let mut deps = setup();
+    send_transfer(&mut deps, ExecuteMsg::IbcTransfer {}, 7);
+
+    // Retrying a pending transfer fails
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::RetryTransfer {
+            id: 1,
+        },
+    )
+    .unwrap_err();
+
+    sudo(
+        deps.as_mut(),
+        mock_env(),
+        SudoMsg::IbcLifecycleComplete(IbcLifecycleComplete::IbcTimeout {
+            channel: "channel-10".to_string(),
+            sequence: 7,
+        }),
+    )
+    .unwrap();
+    assert_eq!(query_transfer(&deps, 1).status, TransferStatus::TimedOut);
+
+    send_transfer(
+        &mut deps,
+        ExecuteMsg::RetryTransfer {
+            id: 1,
+        },
+        8,
+    );
+
+    assert_eq!(
+        query_transfer(&deps, 1).status,
+        TransferStatus::Retried {
+            retry_id: 2
+        }
+    );
+    let retry = query_transfer(&deps, 2);
+    assert_eq!(retry.sequence, Some(8));
+    assert_eq!(retry.amount, Coin::new(1_000, "uosmo"));
+    assert_eq!(retry.status, TransferStatus::Pending);
+
+    let resp: GetTransfersResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetTransfers {
+                start_after: Some(1),
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(resp.transfers, vec![retry]);
+}
+
+#[test]
+fn test_refunded_funds_are_only_sent_by_retry() {
+    let mut deps = setup();
+    send_transfer(&mut deps, ExecuteMsg::IbcTransfer {}, 7);
+
+    // The transfer fails and its funds are returned to the contract
+    sudo(
+        deps.as_mut(),
+        mock_env(),
+        SudoMsg::IbcLifecycleComplete(IbcLifecycleComplete::IbcAck {
+            channel: "channel-10".to_string(),
+            sequence: 7,
+            ack: "error".to_string(),
+            success: false,
+        }),
+    )
+    .unwrap();
+    assert_eq!(
+        query_transfer(&deps, 1).status,
+        TransferStatus::Failed {
+            ack: "error".to_string()
+        }
+    );
+
+    // A transfer is completed only once
+    sudo(
+        deps.as_mut(),
+        mock_env(),
+        SudoMsg::IbcLifecycleComplete(IbcLifecycleComplete::IbcTimeout {
+            channel: "channel-10".to_string(),
+            sequence: 7,
+        }),
+    )
+    .unwrap_err();
+
+    // The returned funds are not sent again by a transfer
+    let err =
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), ExecuteMsg::IbcTransfer {})
+            .unwrap_err();
+    assert_eq!(err.to_string(), "Insufficient Funds");
+
+    // Only the funds received since are transferred
+    deps.querier.update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(1_500, "uosmo")]);
+    send_transfer(&mut deps, ExecuteMsg::IbcTransfer {}, 8);
+    assert_eq!(query_transfer(&deps, 2).amount, Coin::new(500, "uosmo"));
+
+    // The returned funds are sent by the retry
+    send_transfer(
+        &mut deps,
+        ExecuteMsg::RetryTransfer {
+            id: 1,
+        },
+        9,
+    );
+    assert_eq!(query_transfer(&deps, 3).amount, Coin::new(1_000, "uosmo"));
+}
+
+#[test]
+fn test_transfers_keep_reserve_of_inflows() {
+    let mut deps = setup();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::SetReserve {
+            denom: "uosmo".to_string(),
+            reserve: Decimal::percent(10),
+        },
+    )
+    .unwrap();
+
+    // A tenth of the balance is kept
+    send_transfer(&mut deps, ExecuteMsg::IbcTransfer {}, 7);
+    assert_eq!(query_transfer(&deps, 1).amount, Coin::new(900, "uosmo"));
+
+    // The reserve does not shrink over repeated transfers
+    deps.querier.update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(100, "uosmo")]);
+    let err =
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), ExecuteMsg::IbcTransfer {})
+            .unwrap_err();
+    assert_eq!(err.to_string(), "Insufficient Funds");
+
+    // A tenth of the funds received since is added to the reserve
+    deps.querier.update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(600, "uosmo")]);
+    send_transfer(&mut deps, ExecuteMsg::IbcTransfer {}, 8);
+    assert_eq!(query_transfer(&deps, 2).amount, Coin::new(450, "uosmo"));
+
+    // Removing the reserve releases the funds kept
+    deps.querier.update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(150, "uosmo")]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::SetReserve {
+            denom: "uosmo".to_string(),
+            reserve: Decimal::zero(),
+        },
+    )
+    .unwrap();
+    send_transfer(&mut deps, ExecuteMsg::IbcTransfer {}, 9);
+    assert_eq!(query_transfer(&deps, 3).amount, Coin::new(150, "uosmo"));
+}
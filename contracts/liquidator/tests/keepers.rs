@@ -0,0 +1,70 @@
+use cosmwasm_std::{
+    from_binary,
+    testing::{mock_dependencies, mock_env, mock_info},
+    Addr, Decimal,
+};
+use liquidator::{
+    contract::{execute, instantiate, query},
+    msg::{ExecuteMsg, InstantiateMsg, QueryMsg},
+    state::Config,
+};
+
+#[test]
+fn test_keepers_and_pause() {
+    let mut deps = mock_dependencies();
+    let msg = InstantiateMsg {
+        ibc_channel_id: "channel-10".to_string(),
+        ibc_to_address: "neutron1yrg6daqkxyeqye4aac09stzvvwppqwlsk2jn2k".to_string(),
+        liquidation_target: "uosmo".to_string(),
+        owner: "addr0000".to_string(),
+    };
+    let info = mock_info("addr0000", &[]);
+
+    instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+    // Anyone can liquidate until keepers are set
+    execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), ExecuteMsg::Liquidate {})
+        .unwrap();
+
+    let msg = ExecuteMsg::SetKeepers {
+        keepers: vec!["addr0001".to_string()],
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), msg.clone()).unwrap_err();
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let err =
+        execute(deps.as_mut(), mock_env(), mock_info("addr0002", &[]), ExecuteMsg::Liquidate {})
+            .unwrap_err();
+    assert_eq!(err.to_string(), "Unauthorized");
+    execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), ExecuteMsg::Liquidate {})
+        .unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), ExecuteMsg::Liquidate {})
+        .unwrap();
+
+    // Nobody can liquidate or transfer while paused
+    let msg = ExecuteMsg::SetPaused {
+        paused: true,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let err =
+        execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), ExecuteMsg::Liquidate {})
+            .unwrap_err();
+    assert_eq!(err.to_string(), "Paused");
+    let err =
+        execute(deps.as_mut(), mock_env(), mock_info("addr0001", &[]), ExecuteMsg::IbcTransfer {})
+            .unwrap_err();
+    assert_eq!(err.to_string(), "Paused");
+
+    let msg = ExecuteMsg::SetReserve {
+        denom: "uion".to_string(),
+        reserve: Decimal::percent(10),
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+
+    let res = query(deps.as_ref(), mock_env(), QueryMsg::GetConfig {}).unwrap();
+    let resp: Config = from_binary(&res).unwrap();
+    assert_eq!(resp.keepers, vec![Addr::unchecked("addr0001")]);
+    assert!(resp.paused);
+    assert_eq!(resp.reserves.get("uion"), Some(&Decimal::percent(10)));
+}
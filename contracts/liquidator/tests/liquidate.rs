@@ -1,13 +1,39 @@
 #[path = "./common/mod.rs"]
 mod common;
+use std::marker::PhantomData;
+
 use common::TestEnv;
-use liquidator::msg::{ExecuteMsg, GetRouteResponse, QueryMsg};
+use cosmwasm_std::{
+    from_binary, from_slice,
+    testing::{
+        mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+        MOCK_CONTRACT_ADDR,
+    },
+    to_binary, ContractResult, CosmosMsg, Decimal, Empty, OwnedDeps, Querier, QuerierResult,
+    QueryRequest, Reply, SubMsgResponse, SubMsgResult, SystemResult, Uint128,
+};
+use liquidator::{
+    contract::{execute, instantiate, query, reply},
+    msg::{
+        ExecuteMsg, GetRouteResponse, GetSwapHistoryResponse, GetSwapTotalsResponse,
+        InstantiateMsg, QueryMsg,
+    },
+    state::{
+        PendingSwap, Transfer, NEXT_SWAP_ID, PENDING_PROCEEDS, PENDING_SWAPS, RESERVED,
+        ROUTING_TABLE,
+    },
+};
 use osmosis_std::types::{
     cosmos::{
         bank::v1beta1::{MsgSend, QueryBalanceRequest},
         base::v1beta1::Coin,
     },
-    osmosis::poolmanager::v1beta1::SwapAmountInRoute,
+    osmosis::{
+        poolmanager::v1beta1::{
+            MsgSwapExactAmountIn, MsgSwapExactAmountInResponse, SwapAmountInRoute,
+        },
+        twap::v1beta1::{ArithmeticTwapToNowRequest, ArithmeticTwapToNowResponse},
+    },
 };
 use osmosis_test_tube::{Account, Bank, Module, Wasm};
 
@@ -52,13 +78,24 @@ fn test_liquidate() {
         },],
     );
 
+    // Allow the 1% swap fee of the pool and the slippage of the swap, which
+    // together take about 2% of the output
+    let set_route_config_msg = ExecuteMsg::SetRouteConfig {
+        input_denom: "uosmo".to_string(),
+        output_denom: "uion".to_string(),
+        twap_window: 1,
+        max_price_impact: Decimal::percent(3),
+    };
+
+    wasm.execute(&contract_address, &set_route_config_msg, &[], &owner).unwrap();
+
     // Send the contract some uosmo
     bank.send(
         MsgSend {
             from_address: owner.address(),
             to_address: contract_address.clone(),
             amount: vec![Coin {
-                amount: (5_00 * 10u128.pow(6)).to_string(),
+                amount: 10u128.pow(6).to_string(),
                 denom: "uosmo".to_string(),
             }],
         },
@@ -77,7 +114,7 @@ fn test_liquidate() {
         .unwrap_or_default()
         .amount;
 
-    assert_eq!(balance, "500000000".to_string());
+    assert_eq!(balance, "1000000".to_string());
 
     // Call liquidate
     let msg = ExecuteMsg::Liquidate {};
@@ -99,7 +136,7 @@ fn test_liquidate() {
     // Verify that the uion balance is greater than zero
     let balance = bank
         .query_balance(&QueryBalanceRequest {
-            address: contract_address,
+            address: contract_address.clone(),
             denom: "uion".to_string(),
         })
         .unwrap()
@@ -107,4 +144,734 @@ fn test_liquidate() {
         .unwrap_or_default()
         .amount;
     assert_ne!(balance, "0".to_string());
+
+    // Verify that the swap was recorded
+    let history: GetSwapHistoryResponse = wasm
+        .query(
+            &contract_address,
+            &QueryMsg::GetSwapHistory {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(history.swaps.len(), 1);
+    let swap = &history.swaps[0];
+    assert_eq!(swap.id, 1);
+    assert_eq!(swap.input.denom, "uosmo");
+    assert_eq!(swap.input.amount.to_string(), "1000000");
+    assert_eq!(swap.output.denom, "uion");
+    assert_eq!(swap.output.amount.to_string(), balance);
+    assert_eq!(swap.route[0].pool_id, 1);
+
+    let totals: GetSwapTotalsResponse = wasm
+        .query(
+            &contract_address,
+            &QueryMsg::GetSwapTotals {
+                input_denom: "uosmo".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(totals.totals.len(), 1);
+    assert_eq!(totals.totals[0].output_denom, "uion");
+    assert_eq!(totals.totals[0].input_amount, swap.input.amount);
+    assert_eq!(totals.totals[0].output_amount, swap.output.amount);
+    assert_eq!(totals.totals[0].swap_count, 1);
+}
+
+#[test]
+fn test_liquidate_skips_route_exceeding_price_impact() {
+    let TestEnv {
+        app,
+        contract_address,
+        owner,
+    } = TestEnv::new();
+    let wasm = Wasm::new(&app);
+    let bank = Bank::new(&app);
+
+    // Set a route for the test uosmo/uion with a max price impact of 1%
+    let set_route_msg = ExecuteMsg::SetRoute {
+        input_denom: "uosmo".to_string(),
+        output_denom: "uion".to_string(),
+        pool_route: vec![SwapAmountInRoute {
+            pool_id: 1,
+            token_out_denom: "uion".to_string(),
+        }],
+    };
+    wasm.execute(&contract_address, &set_route_msg, &[], &owner).unwrap();
+
+    let set_route_config_msg = ExecuteMsg::SetRouteConfig {
+        input_denom: "uosmo".to_string(),
+        output_denom: "uion".to_string(),
+        twap_window: 1,
+        max_price_impact: Decimal::percent(1),
+    };
+    wasm.execute(&contract_address, &set_route_config_msg, &[], &owner).unwrap();
+
+    // Send the contract more uosmo than the pool liquidity
+    bank.send(
+        MsgSend {
+            from_address: owner.address(),
+            to_address: contract_address.clone(),
+            amount: vec![Coin {
+                amount: (5_00 * 10u128.pow(6)).to_string(),
+                denom: "uosmo".to_string(),
+            }],
+        },
+        &owner,
+    )
+    .unwrap();
+
+    // Liquidate succeeds but skips the swap
+    let res = wasm.execute(&contract_address, &ExecuteMsg::Liquidate {}, &[], &owner).unwrap();
+    assert!(res.events.iter().any(|event| event.ty == "wasm-skip_liquidation"));
+
+    // Verify that the uosmo balance is unchanged
+    let balance = bank
+        .query_balance(&QueryBalanceRequest {
+            address: contract_address,
+            denom: "uosmo".to_string(),
+        })
+        .unwrap()
+        .balance
+        .unwrap_or_default()
+        .amount;
+
+    assert_eq!(balance, "500000000".to_string());
+}
+
+#[test]
+fn test_liquidate_in_chunks() {
+    let TestEnv {
+        app,
+        contract_address,
+        owner,
+    } = TestEnv::new();
+    let wasm = Wasm::new(&app);
+    let bank = Bank::new(&app);
+
+    // Set a route for the test uosmo/uion, selling at most 100 osmo at a time
+    let set_route_msg = ExecuteMsg::SetRoute {
+        input_denom: "uosmo".to_string(),
+        output_denom: "uion".to_string(),
+        pool_route: vec![SwapAmountInRoute {
+            pool_id: 1,
+            token_out_denom: "uion".to_string(),
+        }],
+    };
+    wasm.execute(&contract_address, &set_route_msg, &[], &owner).unwrap();
+
+    let set_route_config_msg = ExecuteMsg::SetRouteConfig {
+        input_denom: "uosmo".to_string(),
+        output_denom: "uion".to_string(),
+        twap_window: 1,
+        max_price_impact: Decimal::percent(90),
+    };
+    wasm.execute(&contract_address, &set_route_config_msg, &[], &owner).unwrap();
+
+    let set_denom_config_msg = ExecuteMsg::SetDenomConfig {
+        denom: "uosmo".to_string(),
+        max_chunk: (1_00 * 10u128.pow(6)).into(),
+        min_balance: (1_00 * 10u128.pow(6)).into(),
+    };
+    wasm.execute(&contract_address, &set_denom_config_msg, &[], &owner).unwrap();
+
+    // Send the contract some uosmo
+    bank.send(
+        MsgSend {
+            from_address: owner.address(),
+            to_address: contract_address.clone(),
+            amount: vec![Coin {
+                amount: (1_50 * 10u128.pow(6)).to_string(),
+                denom: "uosmo".to_string(),
+            }],
+        },
+        &owner,
+    )
+    .unwrap();
+
+    let query_uosmo_balance = || {
+        bank.query_balance(&QueryBalanceRequest {
+            address: contract_address.clone(),
+            denom: "uosmo".to_string(),
+        })
+        .unwrap()
+        .balance
+        .unwrap_or_default()
+        .amount
+    };
+
+    // The first liquidation sells one chunk
+    wasm.execute(&contract_address, &ExecuteMsg::Liquidate {}, &[], &owner).unwrap();
+    assert_eq!(query_uosmo_balance(), "50000000".to_string());
+
+    // The rest is below the min balance and is not sold
+    wasm.execute(&contract_address, &ExecuteMsg::Liquidate {}, &[], &owner).unwrap();
+    assert_eq!(query_uosmo_balance(), "50000000".to_string());
+
+    let liquidate_denom_msg = ExecuteMsg::LiquidateDenom {
+        denom: "uosmo".to_string(),
+        amount: None,
+        min_out: None,
+    };
+    let res = wasm.execute(&contract_address, &liquidate_denom_msg, &[], &owner).unwrap_err();
+    assert_eq!(res.to_string(), "execute error: failed to execute message; message index: 0: Custom Error val: \"Balance of uosmo is below the liquidation threshold\": execute wasm contract failed");
+}
+
+#[test]
+fn test_liquidate_keeps_reserve() {
+    let TestEnv {
+        app,
+        contract_address,
+        owner,
+    } = TestEnv::new();
+    let wasm = Wasm::new(&app);
+    let bank = Bank::new(&app);
+
+    // Set a route for the test uosmo/uion, keeping a fifth of the uosmo
+    let set_route_msg = ExecuteMsg::SetRoute {
+        input_denom: "uosmo".to_string(),
+        output_denom: "uion".to_string(),
+        pool_route: vec![SwapAmountInRoute {
+            pool_id: 1,
+            token_out_denom: "uion".to_string(),
+        }],
+    };
+    wasm.execute(&contract_address, &set_route_msg, &[], &owner).unwrap();
+
+    let set_route_config_msg = ExecuteMsg::SetRouteConfig {
+        input_denom: "uosmo".to_string(),
+        output_denom: "uion".to_string(),
+        twap_window: 1,
+        max_price_impact: Decimal::percent(90),
+    };
+    wasm.execute(&contract_address, &set_route_config_msg, &[], &owner).unwrap();
+
+    let set_reserve_msg = ExecuteMsg::SetReserve {
+        denom: "uosmo".to_string(),
+        reserve: Decimal::percent(20),
+    };
+    wasm.execute(&contract_address, &set_reserve_msg, &[], &owner).unwrap();
+
+    // Send the contract some uosmo
+    bank.send(
+        MsgSend {
+            from_address: owner.address(),
+            to_address: contract_address.clone(),
+            amount: vec![Coin {
+                amount: (1_50 * 10u128.pow(6)).to_string(),
+                denom: "uosmo".to_string(),
+            }],
+        },
+        &owner,
+    )
+    .unwrap();
+
+    let query_balance = || {
+        bank.query_balance(&QueryBalanceRequest {
+            address: contract_address.clone(),
+            denom: "uosmo".to_string(),
+        })
+        .unwrap()
+        .balance
+        .unwrap_or_default()
+        .amount
+    };
+
+    // The reserve is not sold
+    wasm.execute(&contract_address, &ExecuteMsg::Liquidate {}, &[], &owner).unwrap();
+    assert_eq!(query_balance(), "30000000".to_string());
+
+    // Nor does it shrink over repeated liquidations
+    wasm.execute(&contract_address, &ExecuteMsg::Liquidate {}, &[], &owner).unwrap();
+    assert_eq!(query_balance(), "30000000".to_string());
+
+    // A fifth of the uosmo received since is added to the reserve
+    bank.send(
+        MsgSend {
+            from_address: owner.address(),
+            to_address: contract_address.clone(),
+            amount: vec![Coin {
+                amount: (50 * 10u128.pow(6)).to_string(),
+                denom: "uosmo".to_string(),
+            }],
+        },
+        &owner,
+    )
+    .unwrap();
+    wasm.execute(&contract_address, &ExecuteMsg::Liquidate {}, &[], &owner).unwrap();
+    assert_eq!(query_balance(), "40000000".to_string());
+}
+
+#[test]
+fn test_liquidate_denom() {
+    let TestEnv {
+        app,
+        contract_address,
+        owner,
+    } = TestEnv::new();
+    let wasm = Wasm::new(&app);
+    let bank = Bank::new(&app);
+
+    // Set a route from uion to the liquidation target uosmo
+    let set_route_msg = ExecuteMsg::SetRoute {
+        input_denom: "uion".to_string(),
+        output_denom: "uosmo".to_string(),
+        pool_route: vec![SwapAmountInRoute {
+            pool_id: 1,
+            token_out_denom: "uosmo".to_string(),
+        }],
+    };
+    wasm.execute(&contract_address, &set_route_msg, &[], &owner).unwrap();
+
+    let set_route_config_msg = ExecuteMsg::SetRouteConfig {
+        input_denom: "uion".to_string(),
+        output_denom: "uosmo".to_string(),
+        twap_window: 1,
+        max_price_impact: Decimal::percent(5),
+    };
+    wasm.execute(&contract_address, &set_route_config_msg, &[], &owner).unwrap();
+
+    // Send the contract some uion
+    bank.send(
+        MsgSend {
+            from_address: owner.address(),
+            to_address: contract_address.clone(),
+            amount: vec![Coin {
+                amount: (10 * 10u128.pow(6)).to_string(),
+                denom: "uion".to_string(),
+            }],
+        },
+        &owner,
+    )
+    .unwrap();
+
+    // A min_out above the output of the swap fails
+    let liquidate_denom_msg = ExecuteMsg::LiquidateDenom {
+        denom: "uion".to_string(),
+        amount: Some((1_000_000u128).into()),
+        min_out: Some((1_000_000u128).into()),
+    };
+    wasm.execute(&contract_address, &liquidate_denom_msg, &[], &owner).unwrap_err();
+
+    // Sell part of the balance
+    let liquidate_denom_msg = ExecuteMsg::LiquidateDenom {
+        denom: "uion".to_string(),
+        amount: Some((1_000_000u128).into()),
+        min_out: None,
+    };
+    wasm.execute(&contract_address, &liquidate_denom_msg, &[], &owner).unwrap();
+
+    let balance = bank
+        .query_balance(&QueryBalanceRequest {
+            address: contract_address,
+            denom: "uion".to_string(),
+        })
+        .unwrap()
+        .balance
+        .unwrap_or_default()
+        .amount;
+
+    assert_eq!(balance, "9000000".to_string());
+}
+
+#[test]
+fn test_liquidate_and_send_without_balance() {
+    let TestEnv {
+        app,
+        contract_address,
+        owner,
+    } = TestEnv::new();
+    let wasm = Wasm::new(&app);
+
+    // Set a route to the liquidation target uosmo
+    let set_route_msg = ExecuteMsg::SetRoute {
+        input_denom: "uion".to_string(),
+        output_denom: "uosmo".to_string(),
+        pool_route: vec![SwapAmountInRoute {
+            pool_id: 1,
+            token_out_denom: "uosmo".to_string(),
+        }],
+    };
+    wasm.execute(&contract_address, &set_route_msg, &[], &owner).unwrap();
+
+    // Without balance nothing is swapped and nothing is sent
+    let res =
+        wasm.execute(&contract_address, &ExecuteMsg::LiquidateAndSend {}, &[], &owner).unwrap();
+    assert!(!res.events.iter().any(|event| event.ty == "wasm-liquidate_swap"));
+
+    // Only the contract can send the proceeds
+    let res =
+        wasm.execute(&contract_address, &ExecuteMsg::SendProceeds {}, &[], &owner).unwrap_err();
+    assert_eq!(res.to_string(), "execute error: failed to execute message; message index: 0: Unauthorized: execute wasm contract failed");
+}
+
+// The outputs of the swaps are an inflow of the liquidation target, so its
+// reserve is set aside from them before they are sent
+#[test]
+fn test_liquidate_and_send_keeps_reserve_of_proceeds() {
+    // The contract holds the uosmo output of the swap, which the mock querier
+    // cannot add to its balance
+    let balances = [cosmwasm_std::Coin::new(1_000, "uion"), cosmwasm_std::Coin::new(650, "uosmo")];
+    let mut deps = OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier: TwapQuerier::new(MockQuerier::new(&[(MOCK_CONTRACT_ADDR, &balances)])),
+        custom_query_type: PhantomData::<Empty>,
+    };
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            owner: "owner".to_string(),
+            ibc_channel_id: "channel-10".to_string(),
+            ibc_to_address: "neutron1yrg6daqkxyeqye4aac09stzvvwppqwlsk2jn2k".to_string(),
+            liquidation_target: "uosmo".to_string(),
+        },
+    )
+    .unwrap();
+    let route = vec![SwapAmountInRoute {
+        pool_id: 1,
+        token_out_denom: "uosmo".to_string(),
+    }];
+    ROUTING_TABLE.save(deps.as_mut().storage, ("uion", "uosmo"), &route).unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::SetReserve {
+            denom: "uosmo".to_string(),
+            reserve: Decimal::percent(20),
+        },
+    )
+    .unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::LiquidateAndSend {},
+    )
+    .unwrap();
+    reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: res.messages[0].id,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(
+                    MsgSwapExactAmountInResponse {
+                        token_out_amount: "650".to_string(),
+                    }
+                    .into(),
+                ),
+            }),
+        },
+    )
+    .unwrap();
+
+    // 20% of the proceeds are set aside and the rest is sent
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::SendProceeds {},
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert!(res.attributes.iter().any(|attr| attr.key == "amount" && attr.value == "520"));
+
+    let reserved = RESERVED.load(deps.as_ref().storage, "uosmo").unwrap();
+    assert_eq!(reserved.amount, Uint128::new(130));
+    assert_eq!(reserved.balance, Uint128::new(130));
+}
+
+/// Answers TWAP queries with the value of the base asset in the quote asset,
+/// where denoms without a value are worth one, and all other queries with the
+/// mock querier
+struct TwapQuerier {
+    querier: MockQuerier,
+    values: Vec<(&'static str, Decimal)>,
+}
+
+impl TwapQuerier {
+    fn new(querier: MockQuerier) -> Self {
+        Self {
+            querier,
+            values: vec![],
+        }
+    }
+
+    fn value(&self, denom: &str) -> Decimal {
+        self.values
+            .iter()
+            .find(|(value_denom, _)| *value_denom == denom)
+            .map_or(Decimal::one(), |(_, value)| *value)
+    }
+}
+
+impl Querier for TwapQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        match from_slice::<QueryRequest<Empty>>(bin_request).unwrap() {
+            QueryRequest::Stargate {
+                path,
+                data,
+            } if path == "/osmosis.twap.v1beta1.Query/ArithmeticTwapToNow" => {
+                let request = ArithmeticTwapToNowRequest::try_from(data).unwrap();
+                let price = self
+                    .value(&request.base_asset)
+                    .checked_div(self.value(&request.quote_asset))
+                    .unwrap();
+                let response = ArithmeticTwapToNowResponse {
+                    arithmetic_twap: price.to_string(),
+                };
+                SystemResult::Ok(ContractResult::Ok(to_binary(&response).unwrap()))
+            }
+            _ => self.querier.raw_query(bin_request),
+        }
+    }
+}
+
+// The IBC transfer of the proceeds cannot be sent by the test app, which has
+// no IBC channels, so the swaps and transfer are mocked
+#[test]
+fn test_liquidate_and_send_sends_swap_outputs() {
+    let balances = [
+        cosmwasm_std::Coin::new(1_000, "stake"),
+        cosmwasm_std::Coin::new(1_000, "uatom"),
+        cosmwasm_std::Coin::new(1_000, "ufoo"),
+        cosmwasm_std::Coin::new(1_000, "uion"),
+        cosmwasm_std::Coin::new(5_000, "uosmo"),
+    ];
+    let mut deps = OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier: TwapQuerier::new(MockQuerier::new(&[(MOCK_CONTRACT_ADDR, &balances)])),
+        custom_query_type: PhantomData::<Empty>,
+    };
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            owner: "owner".to_string(),
+            ibc_channel_id: "channel-10".to_string(),
+            ibc_to_address: "neutron1yrg6daqkxyeqye4aac09stzvvwppqwlsk2jn2k".to_string(),
+            liquidation_target: "uosmo".to_string(),
+        },
+    )
+    .unwrap();
+
+    // Routes to the liquidation target. The route of ufoo is empty, so it cannot
+    // be priced and ufoo is skipped.
+    for (denom, pool_route) in [
+        ("stake", vec![(2, "uosmo")]),
+        ("uatom", vec![(4, "uosmo")]),
+        ("ufoo", vec![]),
+        ("uion", vec![(1, "uosmo")]),
+    ] {
+        let pool_route = pool_route
+            .into_iter()
+            .map(|(pool_id, token_out_denom)| SwapAmountInRoute {
+                pool_id,
+                token_out_denom: token_out_denom.to_string(),
+            })
+            .collect::<Vec<_>>();
+        ROUTING_TABLE.save(deps.as_mut().storage, (denom, "uosmo"), &pool_route).unwrap();
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::LiquidateAndSend {},
+    )
+    .unwrap();
+    assert!(res.events.iter().any(|event| event.ty == "skip_liquidation"));
+
+    // The swaps of stake, uatom and uion, followed by SendProceeds
+    assert_eq!(res.messages.len(), 4);
+    let swap_replies = [
+        SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: Some(
+                MsgSwapExactAmountInResponse {
+                    token_out_amount: "250".to_string(),
+                }
+                .into(),
+            ),
+        }),
+        // The swap of uatom fails and is skipped
+        SubMsgResult::Err("insufficient liquidity".to_string()),
+        SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: Some(
+                MsgSwapExactAmountInResponse {
+                    token_out_amount: "400".to_string(),
+                }
+                .into(),
+            ),
+        }),
+    ];
+    for (msg, result) in res.messages.iter().zip(swap_replies) {
+        reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id: msg.id,
+                result,
+            },
+        )
+        .unwrap();
+    }
+
+    // Only the outputs of the executed swaps are sent, not the uosmo the
+    // contract already held
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::SendProceeds {},
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert!(res.attributes.iter().any(|attr| attr.key == "amount" && attr.value == "650"));
+
+    let transfer: Transfer = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetTransfer {
+                id: 1,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(transfer.amount, cosmwasm_std::Coin::new(650, "uosmo"));
+}
+
+// The pools of the test app are balanced, so the minimum outputs are checked
+// against mocked TWAP prices instead
+#[test]
+fn test_liquidate_min_output_is_output_per_input_along_route() {
+    let balances = [
+        cosmwasm_std::Coin::new(1_000, "stake"),
+        cosmwasm_std::Coin::new(1_000, "uion"),
+        cosmwasm_std::Coin::new(2_000, "uosmo"),
+    ];
+    // One uion is worth two uosmo and one stake is worth four uosmo
+    let mut querier = TwapQuerier::new(MockQuerier::new(&[(MOCK_CONTRACT_ADDR, &balances)]));
+    querier.values = vec![("uion", Decimal::percent(200)), ("stake", Decimal::percent(400))];
+    let mut deps = OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier,
+        custom_query_type: PhantomData::<Empty>,
+    };
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        InstantiateMsg {
+            owner: "owner".to_string(),
+            ibc_channel_id: "channel-10".to_string(),
+            ibc_to_address: "neutron1yrg6daqkxyeqye4aac09stzvvwppqwlsk2jn2k".to_string(),
+            liquidation_target: "uosmo".to_string(),
+        },
+    )
+    .unwrap();
+
+    // A two-hop route, and single-hop routes in both directions of the pool
+    for (denom_in, denom_out, pool_route) in [
+        ("stake", "uosmo", vec![(3, "uion"), (1, "uosmo")]),
+        ("uion", "uosmo", vec![(1, "uosmo")]),
+        ("uosmo", "uion", vec![(1, "uion")]),
+    ] {
+        let pool_route = pool_route
+            .into_iter()
+            .map(|(pool_id, token_out_denom)| SwapAmountInRoute {
+                pool_id,
+                token_out_denom: token_out_denom.to_string(),
+            })
+            .collect::<Vec<_>>();
+        ROUTING_TABLE.save(deps.as_mut().storage, (denom_in, denom_out), &pool_route).unwrap();
+    }
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), ExecuteMsg::Liquidate {})
+        .unwrap();
+
+    // The minimum outputs are the values of the inputs in the output denoms,
+    // minus the default max price impact of 1%
+    let min_outputs = res
+        .messages
+        .iter()
+        .map(|msg| match &msg.msg {
+            CosmosMsg::Stargate {
+                value,
+                ..
+            } => {
+                let swap = MsgSwapExactAmountIn::try_from(value.clone()).unwrap();
+                (swap.token_in.unwrap().denom, swap.token_out_min_amount)
+            }
+            msg => panic!("unexpected message {msg:?}"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        min_outputs,
+        vec![
+            ("stake".to_string(), "3960".to_string()),
+            ("uion".to_string(), "1980".to_string()),
+            ("uosmo".to_string(), "990".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_reply_liquidate_fails_on_overflow() {
+    let mut deps = mock_dependencies();
+    let route = vec![SwapAmountInRoute {
+        pool_id: 1,
+        token_out_denom: "uosmo".to_string(),
+    }];
+    ROUTING_TABLE.save(deps.as_mut().storage, ("uion", "uosmo"), &route).unwrap();
+
+    for (proceeds, next_swap_id, expected) in [
+        (Uint128::MAX, 1, "Cannot Add with 340282366920938463463374607431768211455 and 250"),
+        (Uint128::zero(), u64::MAX, "Cannot Add with 18446744073709551615 and 1"),
+    ] {
+        PENDING_PROCEEDS.save(deps.as_mut().storage, &proceeds).unwrap();
+        NEXT_SWAP_ID.save(deps.as_mut().storage, &next_swap_id).unwrap();
+        PENDING_SWAPS
+            .save(
+                deps.as_mut().storage,
+                0,
+                &PendingSwap {
+                    input: cosmwasm_std::Coin::new(1_000, "uion"),
+                    output_denom: "uosmo".to_string(),
+                },
+            )
+            .unwrap();
+
+        let err = reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id: 0,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: Some(
+                        MsgSwapExactAmountInResponse {
+                            token_out_amount: "250".to_string(),
+                        }
+                        .into(),
+                    ),
+                }),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), expected);
+    }
 }
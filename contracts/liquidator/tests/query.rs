@@ -1,8 +1,14 @@
 mod common;
 use common::TestEnv;
-use liquidator::msg::{ExecuteMsg, GetAllRoutesResponse, GetRouteResponse, QueryMsg};
-use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
-use osmosis_test_tube::{Module, RunnerResult, Wasm};
+use cosmwasm_std::{Coin, Decimal};
+use liquidator::msg::{
+    ExecuteMsg, GetAllRoutesResponse, GetRouteResponse, QueryMsg, SimulateLiquidationResponse,
+    SimulateRouteResponse,
+};
+use osmosis_std::types::{
+    cosmos::bank::v1beta1::MsgSend, osmosis::poolmanager::v1beta1::SwapAmountInRoute,
+};
+use osmosis_test_tube::{Account, Bank, Module, RunnerResult, Wasm};
 
 #[test]
 fn test_query_route() {
@@ -176,3 +182,74 @@ fn test_query_all_routes_no_routes() {
 
     assert_eq!(resp.routes.len(), 0, "Expected exactly 0 routes in the hashmap");
 }
+
+#[test]
+fn test_simulate_liquidation() {
+    let TestEnv {
+        app,
+        contract_address,
+        owner,
+    } = TestEnv::new();
+    let wasm = Wasm::new(&app);
+    let bank = Bank::new(&app);
+
+    // setup route
+    // uosmo/uion = pool(1)
+    let set_route_msg = ExecuteMsg::SetRoute {
+        input_denom: "uosmo".to_string(),
+        output_denom: "uion".to_string(),
+        pool_route: vec![SwapAmountInRoute {
+            pool_id: 1,
+            token_out_denom: "uion".to_string(),
+        }],
+    };
+    wasm.execute(&contract_address, &set_route_msg, &[], &owner).unwrap();
+
+    let set_route_config_msg = ExecuteMsg::SetRouteConfig {
+        input_denom: "uosmo".to_string(),
+        output_denom: "uion".to_string(),
+        twap_window: 1,
+        max_price_impact: Decimal::percent(5),
+    };
+    wasm.execute(&contract_address, &set_route_config_msg, &[], &owner).unwrap();
+
+    // Nothing to simulate without balance
+    let resp: SimulateLiquidationResponse =
+        wasm.query(&contract_address, &QueryMsg::SimulateLiquidation {}).unwrap();
+    assert_eq!(resp.routes.len(), 0);
+
+    // Send the contract more uosmo than the pool liquidity
+    bank.send(
+        MsgSend {
+            from_address: owner.address(),
+            to_address: contract_address.clone(),
+            amount: vec![osmosis_std::types::cosmos::base::v1beta1::Coin {
+                amount: (5_00 * 10u128.pow(6)).to_string(),
+                denom: "uosmo".to_string(),
+            }],
+        },
+        &owner,
+    )
+    .unwrap();
+
+    let resp: SimulateLiquidationResponse =
+        wasm.query(&contract_address, &QueryMsg::SimulateLiquidation {}).unwrap();
+    assert_eq!(resp.routes.len(), 1);
+    let simulation = &resp.routes[0];
+    assert_eq!(simulation.input, Coin::new(500_000_000, "uosmo"));
+    assert!(simulation.expected_output.unwrap() < simulation.min_output.unwrap());
+    assert!(simulation.skip_reason.is_some());
+
+    // A small swap is within the max price impact
+    let resp: SimulateRouteResponse = wasm
+        .query(
+            &contract_address,
+            &QueryMsg::SimulateRoute {
+                input: Coin::new(1_000, "uosmo"),
+                output_denom: "uion".to_string(),
+            },
+        )
+        .unwrap();
+    assert!(resp.expected_output.unwrap() >= resp.min_output.unwrap());
+    assert_eq!(resp.skip_reason, None);
+}
@@ -0,0 +1,141 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    to_binary, Binary, Deps, DepsMut, Env, Event, MessageInfo, Reply, Response, StdResult,
+};
+use cw2::{get_contract_version, set_contract_version};
+use semver::Version;
+
+use crate::error::ContractError;
+use crate::execute::{
+    execute_create_vault, execute_deregister_vault, execute_register_vault, execute_update_config,
+    execute_update_vault_status, reply_create_vault, CREATE_VAULT_REPLY_ID,
+};
+use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+use crate::query::{query_tvl, query_vault, query_vaults, query_vaults_by_pool};
+use crate::state::{Config, ADMIN, CONFIG};
+
+// version info for migration info
+const CONTRACT_NAME: &str = "crates.io:vault-factory";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    // Validate the template so that vault creation does not fail later
+    msg.config_template.check(deps.as_ref())?;
+    CONFIG.save(
+        deps.storage,
+        &Config {
+            vault_code_id: msg.vault_code_id,
+            config_template: msg.config_template,
+            default_lockup_duration: msg.default_lockup_duration,
+        },
+    )?;
+
+    let admin_addr = deps.api.addr_validate(&msg.admin)?;
+    ADMIN.set(deps, Some(admin_addr.clone()))?;
+
+    let event = Event::new("apollo/vault_factory/instantiate")
+        .add_attribute("admin", admin_addr)
+        .add_attribute("vault_code_id", msg.vault_code_id.to_string());
+
+    Ok(Response::new().add_event(event))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::CreateVault {
+            pool_id,
+            lockup_duration,
+            config,
+            vault_token_subdenom,
+        } => {
+            execute_create_vault(deps, info, pool_id, lockup_duration, config, vault_token_subdenom)
+        }
+        ExecuteMsg::RegisterVault {
+            address,
+            pool_id,
+            lockup_duration,
+        } => execute_register_vault(deps, env, info, address, pool_id, lockup_duration),
+        ExecuteMsg::DeregisterVault {
+            address,
+        } => execute_deregister_vault(deps, info, address),
+        ExecuteMsg::UpdateVaultStatus {
+            address,
+            status,
+        } => execute_update_vault_status(deps, info, address, status),
+        ExecuteMsg::UpdateConfig {
+            vault_code_id,
+            config_template,
+            default_lockup_duration,
+        } => execute_update_config(
+            deps,
+            info,
+            vault_code_id,
+            config_template,
+            default_lockup_duration,
+        ),
+        ExecuteMsg::UpdateAdmin {
+            address,
+        } => {
+            let new_admin = deps.api.addr_validate(&address)?;
+            Ok(ADMIN.execute_update_admin(deps, info, Some(new_admin))?)
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_binary(&CONFIG.load(deps.storage)?),
+        QueryMsg::Vault {
+            address,
+        } => to_binary(&query_vault(deps, address)?),
+        QueryMsg::Vaults {
+            start_after,
+            limit,
+        } => to_binary(&query_vaults(deps, start_after, limit)?),
+        QueryMsg::VaultsByPool {
+            pool_id,
+            start_after,
+            limit,
+        } => to_binary(&query_vaults_by_pool(deps, pool_id, start_after, limit)?),
+        QueryMsg::Tvl {
+            start_after,
+            limit,
+        } => to_binary(&query_tvl(deps, start_after, limit)?),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, reply: Reply) -> Result<Response, ContractError> {
+    match reply.id {
+        CREATE_VAULT_REPLY_ID => reply_create_vault(deps, env, reply),
+        id => Err(ContractError::UnknownReplyId(id)),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let version: Version = CONTRACT_VERSION.parse()?;
+    let storage_version: Version = get_contract_version(deps.storage)?.version.parse()?;
+
+    // migrate only if newer
+    if storage_version < version {
+        set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    }
+    Ok(Response::default())
+}
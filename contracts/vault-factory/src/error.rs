@@ -0,0 +1,38 @@
+use cosmwasm_std::StdError;
+use cw_controllers::AdminError;
+use cw_utils::ParseReplyError;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    AdminError(#[from] AdminError),
+
+    #[error("{0}")]
+    ParseReplyError(#[from] ParseReplyError),
+
+    #[error("{0}")]
+    SemVer(String),
+
+    #[error("Unknown reply ID: {0}")]
+    UnknownReplyId(u64),
+
+    #[error("Vault {address} is not registered")]
+    VaultNotFound {
+        address: String,
+    },
+
+    #[error("Vault {address} is already registered")]
+    VaultAlreadyRegistered {
+        address: String,
+    },
+}
+
+impl From<semver::Error> for ContractError {
+    fn from(err: semver::Error) -> Self {
+        Self::SemVer(err.to_string())
+    }
+}
@@ -0,0 +1,436 @@
+use cosmwasm_std::{
+    attr, to_binary, Addr, DepsMut, Env, Event, MessageInfo, Reply, Response, SubMsg, WasmMsg,
+};
+use cw_utils::parse_reply_instantiate_data;
+use cw_vault_standard::msg::VaultInfoResponse;
+use simple_vault::state::ConfigUnchecked;
+
+use crate::error::ContractError;
+use crate::msg::{VaultInstantiateMsg, VaultQueryMsg};
+use crate::state::{vaults, PendingVault, VaultRecord, VaultStatus, ADMIN, CONFIG, PENDING_VAULT};
+
+pub const CREATE_VAULT_REPLY_ID: u64 = 1;
+
+/// Default subdenom of the vault token of created vaults.
+const DEFAULT_VAULT_TOKEN_SUBDENOM: &str = "vault";
+
+/// Instantiate a new vault for `pool_id`. The vault is registered in the
+/// reply of the instantiation.
+pub fn execute_create_vault(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_id: u64,
+    lockup_duration: Option<u64>,
+    config: Option<ConfigUnchecked>,
+    vault_token_subdenom: Option<String>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let cfg = CONFIG.load(deps.storage)?;
+    let admin = ADMIN.get(deps.as_ref())?.unwrap_or(info.sender);
+
+    let lockup_duration = lockup_duration.unwrap_or(cfg.default_lockup_duration);
+    let instantiate_msg = VaultInstantiateMsg {
+        admin: admin.to_string(),
+        pool_id,
+        lockup_duration,
+        config: config.unwrap_or(cfg.config_template),
+        vault_token_subdenom: vault_token_subdenom
+            .unwrap_or_else(|| DEFAULT_VAULT_TOKEN_SUBDENOM.to_string()),
+    };
+
+    PENDING_VAULT.save(
+        deps.storage,
+        &PendingVault {
+            pool_id,
+            lockup_duration,
+        },
+    )?;
+
+    let instantiate = WasmMsg::Instantiate {
+        admin: Some(admin.to_string()),
+        code_id: cfg.vault_code_id,
+        msg: to_binary(&instantiate_msg)?,
+        funds: info.funds,
+        label: format!("osmosis-vault pool {pool_id} lockup {lockup_duration}s"),
+    };
+
+    let event = Event::new("apollo/vault_factory/create_vault").add_attributes(vec![
+        attr("action", "create_vault"),
+        attr("pool_id", pool_id.to_string()),
+        attr("lockup_duration", lockup_duration.to_string()),
+        attr("code_id", cfg.vault_code_id.to_string()),
+    ]);
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(instantiate, CREATE_VAULT_REPLY_ID))
+        .add_event(event))
+}
+
+/// Registers the vault instantiated by [`execute_create_vault`].
+pub fn reply_create_vault(
+    deps: DepsMut,
+    env: Env,
+    reply: Reply,
+) -> Result<Response, ContractError> {
+    let res = parse_reply_instantiate_data(reply)?;
+    let pending = PENDING_VAULT.load(deps.storage)?;
+    PENDING_VAULT.remove(deps.storage);
+
+    let address = deps.api.addr_validate(&res.contract_address)?;
+    register_vault(deps, &env, address, pending.pool_id, pending.lockup_duration)
+}
+
+/// Register a vault that was not created by the factory.
+pub fn execute_register_vault(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    address: String,
+    pool_id: u64,
+    lockup_duration: u64,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let address = deps.api.addr_validate(&address)?;
+    register_vault(deps, &env, address, pool_id, lockup_duration)
+}
+
+/// Adds the vault at `address` to the registry with the base and vault token
+/// reported by the vault.
+fn register_vault(
+    deps: DepsMut,
+    env: &Env,
+    address: Addr,
+    pool_id: u64,
+    lockup_duration: u64,
+) -> Result<Response, ContractError> {
+    if vaults().has(deps.storage, &address) {
+        return Err(ContractError::VaultAlreadyRegistered {
+            address: address.to_string(),
+        });
+    }
+
+    let info: VaultInfoResponse =
+        deps.querier.query_wasm_smart(&address, &VaultQueryMsg::Info {})?;
+    let record = VaultRecord {
+        address: address.clone(),
+        pool_id,
+        lockup_duration,
+        base_token: info.base_token,
+        vault_token: info.vault_token,
+        status: VaultStatus::Active,
+        registered_at: env.block.time.seconds(),
+    };
+    vaults().save(deps.storage, &address, &record)?;
+
+    let event = Event::new("apollo/vault_factory/register_vault").add_attributes(vec![
+        attr("action", "register_vault"),
+        attr("vault", address),
+        attr("pool_id", pool_id.to_string()),
+        attr("lockup_duration", lockup_duration.to_string()),
+        attr("base_token", record.base_token),
+        attr("vault_token", record.vault_token),
+    ]);
+
+    Ok(Response::new().add_event(event))
+}
+
+/// Remove a vault from the registry. The vault itself is not affected.
+pub fn execute_deregister_vault(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let address = deps.api.addr_validate(&address)?;
+    if !vaults().has(deps.storage, &address) {
+        return Err(ContractError::VaultNotFound {
+            address: address.to_string(),
+        });
+    }
+    vaults().remove(deps.storage, &address)?;
+
+    let event = Event::new("apollo/vault_factory/deregister_vault")
+        .add_attributes(vec![attr("action", "deregister_vault"), attr("vault", address)]);
+
+    Ok(Response::new().add_event(event))
+}
+
+/// Update the status of a registered vault.
+pub fn execute_update_vault_status(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+    status: VaultStatus,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let address = deps.api.addr_validate(&address)?;
+    let mut record =
+        vaults().may_load(deps.storage, &address)?.ok_or_else(|| ContractError::VaultNotFound {
+            address: address.to_string(),
+        })?;
+    record.status = status;
+    vaults().save(deps.storage, &address, &record)?;
+
+    let event = Event::new("apollo/vault_factory/update_vault_status").add_attributes(vec![
+        attr("action", "update_vault_status"),
+        attr("vault", address),
+        attr("status", format!("{:?}", record.status)),
+    ]);
+
+    Ok(Response::new().add_event(event))
+}
+
+/// Update the config of the factory.
+pub fn execute_update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    vault_code_id: Option<u64>,
+    config_template: Option<ConfigUnchecked>,
+    default_lockup_duration: Option<u64>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+    let mut cfg = CONFIG.load(deps.storage)?;
+
+    if let Some(vault_code_id) = vault_code_id {
+        cfg.vault_code_id = vault_code_id;
+    }
+    if let Some(config_template) = config_template {
+        // Validate the template so that vault creation does not fail later
+        config_template.check(deps.as_ref())?;
+        cfg.config_template = config_template;
+    }
+    if let Some(default_lockup_duration) = default_lockup_duration {
+        cfg.default_lockup_duration = default_lockup_duration;
+    }
+    CONFIG.save(deps.storage, &cfg)?;
+
+    let event = Event::new("apollo/vault_factory/update_config")
+        .add_attribute("action", "execute_update_config");
+
+    Ok(Response::new().add_event(event))
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+    };
+    use cosmwasm_std::{
+        coins, from_slice, Binary, ContractResult, CosmosMsg, OwnedDeps, SubMsgResponse,
+        SubMsgResult, SystemResult, WasmQuery,
+    };
+    use cw_controllers::AdminError;
+
+    use super::*;
+    use crate::contract::{execute, instantiate, reply};
+    use crate::msg::{ExecuteMsg, InstantiateMsg};
+    use crate::query::query_vault;
+
+    fn config_template() -> ConfigUnchecked {
+        from_slice(
+            br#"{
+                "performance_fee": "0.1",
+                "treasury": "treasury",
+                "router": "router",
+                "reward_assets": [],
+                "reward_liquidation_target": { "native": "uosmo" },
+                "force_withdraw_whitelist": [],
+                "liquidity_helper": "liquidity_helper"
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn setup() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies();
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InstantiateMsg {
+                admin: "admin".to_string(),
+                vault_code_id: 7,
+                config_template: config_template(),
+                default_lockup_duration: 86400,
+            },
+        )
+        .unwrap();
+
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart {
+                contract_addr,
+                ..
+            } => SystemResult::Ok(ContractResult::Ok(
+                to_binary(&VaultInfoResponse {
+                    base_token: "gamm/pool/1".to_string(),
+                    vault_token: format!("factory/{contract_addr}/vault"),
+                })
+                .unwrap(),
+            )),
+            _ => panic!("unexpected query"),
+        });
+        deps
+    }
+
+    fn create_vault_msg(lockup_duration: Option<u64>, subdenom: Option<&str>) -> ExecuteMsg {
+        ExecuteMsg::CreateVault {
+            pool_id: 1,
+            lockup_duration,
+            config: None,
+            vault_token_subdenom: subdenom.map(String::from),
+        }
+    }
+
+    /// Returns the reply to the instantiation of a contract at `address`.
+    fn instantiate_reply(address: &str) -> Reply {
+        // Protobuf encoded MsgInstantiateContractResponse
+        let mut data = vec![0x0a, address.len() as u8];
+        data.extend_from_slice(address.as_bytes());
+        Reply {
+            id: CREATE_VAULT_REPLY_ID,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(Binary::from(data)),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_create_vault() {
+        let mut deps = setup();
+        let funds = coins(10_000_000, "uosmo");
+
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("user", &funds),
+            create_vault_msg(None, None),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::AdminError(AdminError::NotAdmin {}));
+
+        // The defaults of the factory are used and the funds are forwarded
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &funds),
+            create_vault_msg(None, None),
+        )
+        .unwrap();
+        let instantiate_msg = VaultInstantiateMsg {
+            admin: "admin".to_string(),
+            pool_id: 1,
+            lockup_duration: 86400,
+            config: config_template(),
+            vault_token_subdenom: "vault".to_string(),
+        };
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::reply_on_success(
+                WasmMsg::Instantiate {
+                    admin: Some("admin".to_string()),
+                    code_id: 7,
+                    msg: to_binary(&instantiate_msg).unwrap(),
+                    funds: funds.clone(),
+                    label: "osmosis-vault pool 1 lockup 86400s".to_string(),
+                },
+                CREATE_VAULT_REPLY_ID,
+            )]
+        );
+        assert_eq!(
+            PENDING_VAULT.load(deps.as_ref().storage).unwrap(),
+            PendingVault {
+                pool_id: 1,
+                lockup_duration: 86400,
+            }
+        );
+
+        // The lockup duration and subdenom can be overridden
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &funds),
+            create_vault_msg(Some(3600), Some("short")),
+        )
+        .unwrap();
+        let instantiate_msg = VaultInstantiateMsg {
+            lockup_duration: 3600,
+            vault_token_subdenom: "short".to_string(),
+            ..instantiate_msg
+        };
+        match &res.messages[0].msg {
+            CosmosMsg::Wasm(WasmMsg::Instantiate {
+                msg,
+                label,
+                ..
+            }) => {
+                assert_eq!(msg, &to_binary(&instantiate_msg).unwrap());
+                assert_eq!(label, "osmosis-vault pool 1 lockup 3600s");
+            }
+            msg => panic!("unexpected message {msg:?}"),
+        }
+    }
+
+    #[test]
+    fn test_reply_create_vault() {
+        let mut deps = setup();
+        let env = mock_env();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &coins(10_000_000, "uosmo")),
+            create_vault_msg(Some(3600), None),
+        )
+        .unwrap();
+
+        // The instantiated vault is registered with the pending pool and
+        // lockup duration and the tokens reported by the vault
+        reply(deps.as_mut(), env.clone(), instantiate_reply("vault")).unwrap();
+        assert_eq!(
+            query_vault(deps.as_ref(), "vault".to_string()).unwrap(),
+            VaultRecord {
+                address: Addr::unchecked("vault"),
+                pool_id: 1,
+                lockup_duration: 3600,
+                base_token: "gamm/pool/1".to_string(),
+                vault_token: "factory/vault/vault".to_string(),
+                status: VaultStatus::Active,
+                registered_at: env.block.time.seconds(),
+            }
+        );
+        assert!(PENDING_VAULT.may_load(deps.as_ref().storage).unwrap().is_none());
+
+        // A reply without a pending vault fails
+        reply(deps.as_mut(), env.clone(), instantiate_reply("other")).unwrap_err();
+
+        // The vault cannot be registered twice
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            ExecuteMsg::RegisterVault {
+                address: "vault".to_string(),
+                pool_id: 1,
+                lockup_duration: 3600,
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::VaultAlreadyRegistered {
+                address: "vault".to_string(),
+            }
+        );
+
+        let err = reply(
+            deps.as_mut(),
+            env,
+            Reply {
+                id: 2,
+                result: SubMsgResult::Err("error".to_string()),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::UnknownReplyId(2));
+    }
+}
@@ -0,0 +1,163 @@
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{Coin, Uint128};
+use cw_vault_standard::VaultStandardQueryMsg;
+use simple_vault::state::ConfigUnchecked;
+
+use crate::state::{Config, VaultRecord, VaultStatus};
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    /// Address that is allowed to create and register vaults. Also set as the
+    /// admin of created vaults.
+    pub admin: String,
+    /// The code ID new vaults are instantiated from.
+    pub vault_code_id: u64,
+    /// The config new vaults are instantiated with unless overridden.
+    pub config_template: ConfigUnchecked,
+    /// The lockup duration in seconds new vaults are instantiated with unless
+    /// overridden.
+    pub default_lockup_duration: u64,
+}
+
+#[cw_serde]
+pub enum ExecuteMsg {
+    /// Instantiate a new vault for `pool_id` and register it. Can only be
+    /// called by the admin. The 10 OSMO required to create the vault token
+    /// must be sent in the funds and are forwarded to the vault.
+    CreateVault {
+        /// The ID of the pool the vault autocompounds.
+        pool_id: u64,
+        /// The lockup duration in seconds. Defaults to the default lockup
+        /// duration of the factory.
+        lockup_duration: Option<u64>,
+        /// The config of the vault. Defaults to the config template of the
+        /// factory.
+        config: Option<ConfigUnchecked>,
+        /// The subdenom of the vault token. Defaults to "vault".
+        vault_token_subdenom: Option<String>,
+    },
+    /// Register a vault that was not created by the factory. Can only be
+    /// called by the admin.
+    RegisterVault {
+        /// The address of the vault.
+        address: String,
+        /// The ID of the pool the vault autocompounds.
+        pool_id: u64,
+        /// The lockup duration of the vault in seconds.
+        lockup_duration: u64,
+    },
+    /// Remove a vault from the registry. Can only be called by the admin.
+    DeregisterVault {
+        /// The address of the vault.
+        address: String,
+    },
+    /// Update the status of a registered vault. Can only be called by the
+    /// admin.
+    UpdateVaultStatus {
+        /// The address of the vault.
+        address: String,
+        /// The new status.
+        status: VaultStatus,
+    },
+    /// Update the config. Can only be called by the admin. Fields that are
+    /// `None` are not updated.
+    UpdateConfig {
+        /// The new vault code ID.
+        vault_code_id: Option<u64>,
+        /// The new config template.
+        config_template: Option<ConfigUnchecked>,
+        /// The new default lockup duration in seconds.
+        default_lockup_duration: Option<u64>,
+    },
+    /// Update the admin. Can only be called by the admin.
+    UpdateAdmin {
+        /// The new admin address.
+        address: String,
+    },
+}
+
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum QueryMsg {
+    /// Returns the [`Config`] of the factory.
+    #[returns(Config)]
+    Config {},
+    /// Returns the [`VaultRecord`] of the vault at `address`.
+    #[returns(VaultRecord)]
+    Vault {
+        /// The address of the vault.
+        address: String,
+    },
+    /// Returns the registered vaults ordered by address.
+    #[returns(Vec<VaultRecord>)]
+    Vaults {
+        /// Only return vaults with an address after this one.
+        start_after: Option<String>,
+        /// The maximum number of vaults to return.
+        limit: Option<u32>,
+    },
+    /// Returns the registered vaults of `pool_id` ordered by address.
+    #[returns(Vec<VaultRecord>)]
+    VaultsByPool {
+        /// The ID of the pool.
+        pool_id: u64,
+        /// Only return vaults with an address after this one.
+        start_after: Option<String>,
+        /// The maximum number of vaults to return.
+        limit: Option<u32>,
+    },
+    /// Returns the value locked in the registered vaults ordered by address
+    /// as a [`TvlResponse`]. The total value locked of the factory is the sum
+    /// over all pages.
+    #[returns(TvlResponse)]
+    Tvl {
+        /// Only include vaults with an address after this one.
+        start_after: Option<String>,
+        /// The maximum number of vaults to include.
+        limit: Option<u32>,
+    },
+}
+
+/// Response to [`QueryMsg::Tvl`].
+#[cw_serde]
+pub struct TvlResponse {
+    /// The total base tokens held by the vaults of the page, summed per base
+    /// token.
+    pub total: Vec<Coin>,
+    /// The base tokens held by each vault of the page.
+    pub vaults: Vec<VaultTvl>,
+}
+
+/// The value locked in a single vault.
+#[cw_serde]
+pub struct VaultTvl {
+    /// The address of the vault.
+    pub address: String,
+    /// The base token of the vault.
+    pub base_token: String,
+    /// The amount of base tokens held by the vault.
+    pub total_assets: Uint128,
+}
+
+/// InstantiateMsg of the vaults created by the factory. Serialized the same way
+/// as `osmosis_vault::msg::InstantiateMsg`.
+#[cw_serde]
+pub struct VaultInstantiateMsg {
+    /// Address that is allowed to update config.
+    pub admin: String,
+    /// The ID of the pool that this vault will autocompound.
+    pub pool_id: u64,
+    /// The lockup duration in seconds that this vault will use when staking
+    /// LP tokens.
+    pub lockup_duration: u64,
+    /// Configurable parameters for the contract.
+    pub config: ConfigUnchecked,
+    /// The subdenom that will be used for the native vault token.
+    pub vault_token_subdenom: String,
+}
+
+/// QueryMsg of the registered vaults.
+pub type VaultQueryMsg = VaultStandardQueryMsg<simple_vault::msg::ExtensionQueryMsg>;
+
+#[cw_serde]
+pub struct MigrateMsg {}
@@ -0,0 +1,192 @@
+use cosmwasm_std::{Addr, Coin, Deps, Order, StdResult, Uint128};
+use cw_storage_plus::Bound;
+
+use crate::msg::{TvlResponse, VaultQueryMsg, VaultTvl};
+use crate::state::{vaults, VaultRecord, DEFAULT_LIMIT, MAX_LIMIT};
+
+/// Returns the record of the vault at `address`.
+pub fn query_vault(deps: Deps, address: String) -> StdResult<VaultRecord> {
+    vaults().load(deps.storage, &deps.api.addr_validate(&address)?)
+}
+
+/// Returns the registered vaults ordered by address.
+pub fn query_vaults(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<VaultRecord>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start_after = start_after.map(|x| deps.api.addr_validate(&x)).transpose()?;
+    let start = start_after.as_ref().map(Bound::exclusive);
+
+    vaults()
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|x| x.map(|(_, v)| v))
+        .collect()
+}
+
+/// Returns the registered vaults of `pool_id` ordered by address.
+pub fn query_vaults_by_pool(
+    deps: Deps,
+    pool_id: u64,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<VaultRecord>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start_after = start_after.map(|x| deps.api.addr_validate(&x)).transpose()?;
+    let start = start_after.as_ref().map(Bound::exclusive);
+
+    vaults()
+        .idx
+        .pool
+        .prefix(pool_id)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|x| x.map(|(_, v)| v))
+        .collect()
+}
+
+/// Returns the base tokens held by a page of the registered vaults ordered by
+/// address, and their sum per base token.
+pub fn query_tvl(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<TvlResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start_after = start_after.map(|x| deps.api.addr_validate(&x)).transpose()?;
+    let start = start_after.as_ref().map(Bound::exclusive);
+
+    let mut total: Vec<Coin> = vec![];
+    let mut tvls: Vec<VaultTvl> = vec![];
+    for record in vaults().range(deps.storage, start, None, Order::Ascending).take(limit) {
+        let (_, record) = record?;
+        let total_assets: Uint128 =
+            deps.querier.query_wasm_smart(&record.address, &VaultQueryMsg::TotalAssets {})?;
+
+        match total.iter_mut().find(|c| c.denom == record.base_token) {
+            Some(coin) => coin.amount = coin.amount.checked_add(total_assets)?,
+            None => total.push(Coin {
+                denom: record.base_token.clone(),
+                amount: total_assets,
+            }),
+        }
+        tvls.push(VaultTvl {
+            address: record.address.to_string(),
+            base_token: record.base_token,
+            total_assets,
+        });
+    }
+
+    Ok(TvlResponse {
+        total,
+        vaults: tvls,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::{mock_dependencies, MockApi, MockQuerier, MockStorage};
+    use cosmwasm_std::{to_binary, ContractResult, OwnedDeps, SystemResult, WasmQuery};
+    use test_case::test_case;
+
+    use super::*;
+    use crate::state::VaultStatus;
+
+    fn record(i: u64) -> VaultRecord {
+        VaultRecord {
+            address: Addr::unchecked(format!("vault{i:02}")),
+            pool_id: i % 2,
+            lockup_duration: 86400,
+            base_token: format!("gamm/pool/{}", i % 2),
+            vault_token: format!("factory/vault{i:02}/vault"),
+            status: VaultStatus::Active,
+            registered_at: 0,
+        }
+    }
+
+    fn setup(n: u64) -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies();
+        for i in 0..n {
+            let record = record(i);
+            vaults().save(deps.as_mut().storage, &record.address, &record).unwrap();
+        }
+        deps
+    }
+
+    #[test_case(None, None => (0..10).collect::<Vec<_>>() ; "default limit")]
+    #[test_case(None, Some(50) => (0..30).collect::<Vec<_>>() ; "limit capped")]
+    #[test_case(Some(4), Some(3) => vec![5, 6, 7] ; "start after")]
+    #[test_case(Some(39), None => Vec::<u64>::new() ; "start after last")]
+    fn test_query_vaults(start_after: Option<u64>, limit: Option<u32>) -> Vec<u64> {
+        let deps = setup(40);
+        let start_after = start_after.map(|i| record(i).address.to_string());
+        query_vaults(deps.as_ref(), start_after, limit)
+            .unwrap()
+            .into_iter()
+            .map(|r| r.address.as_str()[5..].parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_query_vaults_by_pool() {
+        let deps = setup(10);
+        let res = query_vaults_by_pool(deps.as_ref(), 1, None, None).unwrap();
+        assert_eq!(res, vec![record(1), record(3), record(5), record(7), record(9)]);
+
+        let res =
+            query_vaults_by_pool(deps.as_ref(), 1, Some(record(3).address.to_string()), Some(2))
+                .unwrap();
+        assert_eq!(res, vec![record(5), record(7)]);
+    }
+
+    #[test]
+    fn test_query_tvl() {
+        let mut deps = setup(40);
+        // Vault i holds 100 * i base tokens
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart {
+                contract_addr,
+                ..
+            } => {
+                let i: u128 = contract_addr[5..].parse().unwrap();
+                SystemResult::Ok(ContractResult::Ok(to_binary(&Uint128::new(100 * i)).unwrap()))
+            }
+            _ => panic!("unexpected query"),
+        });
+
+        // The default page holds the first ten vaults
+        let res = query_tvl(deps.as_ref(), None, None).unwrap();
+        assert_eq!(res.vaults.len(), 10);
+        assert_eq!(
+            res.total,
+            vec![Coin::new(2_000, "gamm/pool/0"), Coin::new(2_500, "gamm/pool/1")]
+        );
+
+        let res = query_tvl(deps.as_ref(), Some(record(9).address.to_string()), Some(2)).unwrap();
+        assert_eq!(
+            res.vaults,
+            vec![
+                VaultTvl {
+                    address: "vault10".to_string(),
+                    base_token: "gamm/pool/0".to_string(),
+                    total_assets: Uint128::new(1_000),
+                },
+                VaultTvl {
+                    address: "vault11".to_string(),
+                    base_token: "gamm/pool/1".to_string(),
+                    total_assets: Uint128::new(1_100),
+                },
+            ]
+        );
+        assert_eq!(
+            res.total,
+            vec![Coin::new(1_000, "gamm/pool/0"), Coin::new(1_100, "gamm/pool/1")]
+        );
+
+        // Pages are capped at the maximum limit
+        let res = query_tvl(deps.as_ref(), None, Some(50)).unwrap();
+        assert_eq!(res.vaults.len(), 30);
+    }
+}
@@ -0,0 +1,90 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::Addr;
+use cw_controllers::Admin;
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, MultiIndex};
+use simple_vault::state::ConfigUnchecked;
+
+/// Default number of vaults returned by paginated queries.
+pub const DEFAULT_LIMIT: u32 = 10;
+/// Maximum number of vaults returned by paginated queries.
+pub const MAX_LIMIT: u32 = 30;
+
+/// The config of the factory.
+#[cw_serde]
+pub struct Config {
+    /// The code ID new vaults are instantiated from.
+    pub vault_code_id: u64,
+    /// The config new vaults are instantiated with unless overridden.
+    pub config_template: ConfigUnchecked,
+    /// The lockup duration in seconds new vaults are instantiated with unless
+    /// overridden.
+    pub default_lockup_duration: u64,
+}
+
+/// The status of a registered vault.
+#[cw_serde]
+pub enum VaultStatus {
+    /// The vault accepts deposits and is listed to users.
+    Active,
+    /// The vault should not receive new deposits. Existing depositors can
+    /// still withdraw.
+    Deprecated,
+}
+
+/// A vault in the registry.
+#[cw_serde]
+pub struct VaultRecord {
+    /// The address of the vault.
+    pub address: Addr,
+    /// The ID of the pool the vault autocompounds.
+    pub pool_id: u64,
+    /// The lockup duration of the vault in seconds.
+    pub lockup_duration: u64,
+    /// The base token of the vault.
+    pub base_token: String,
+    /// The vault token of the vault.
+    pub vault_token: String,
+    /// The status of the vault.
+    pub status: VaultStatus,
+    /// Block time in seconds at which the vault was registered.
+    pub registered_at: u64,
+}
+
+/// A vault being instantiated by the factory. Saved before the instantiation
+/// and removed in its reply.
+#[cw_serde]
+pub struct PendingVault {
+    /// The ID of the pool of the vault.
+    pub pool_id: u64,
+    /// The lockup duration of the vault in seconds.
+    pub lockup_duration: u64,
+}
+
+/// Helper struct for indexing vaults. Needed by the [`IndexedMap`]
+/// implementation.
+pub struct VaultIndexes<'a> {
+    /// Index mapping a pool ID to all vaults of that pool.
+    pub pool: MultiIndex<'a, u64, VaultRecord, &'a Addr>,
+}
+
+impl<'a> IndexList<VaultRecord> for VaultIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<VaultRecord>> + '_> {
+        let v: Vec<&dyn Index<VaultRecord>> = vec![&self.pool];
+        Box::new(v.into_iter())
+    }
+}
+
+/// The registered vaults, keyed by address and indexed by pool ID.
+pub fn vaults<'a>() -> IndexedMap<'a, &'a Addr, VaultRecord, VaultIndexes<'a>> {
+    let indexes = VaultIndexes {
+        pool: MultiIndex::new(|_pk, d| d.pool_id, "vaults", "vaults__pool"),
+    };
+    IndexedMap::new("vaults", indexes)
+}
+
+/// The admin address that is allowed to create and register vaults.
+pub const ADMIN: Admin = Admin::new("admin");
+/// The config of the factory.
+pub const CONFIG: Item<Config> = Item::new("config");
+/// The vault currently being instantiated.
+pub const PENDING_VAULT: Item<PendingVault> = Item::new("pending_vault");
@@ -0,0 +1,365 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdError, StdResult,
+};
+use cw2::{get_contract_version, set_contract_version};
+use cw20::MinterResponse;
+use cw20_base::{
+    allowances::{
+        execute_decrease_allowance, execute_increase_allowance, execute_send_from,
+        execute_transfer_from, query_allowance,
+    },
+    contract::{execute_send, execute_transfer, query_balance, query_token_info},
+    enumerable::{query_all_accounts, query_owner_allowances},
+    msg::InstantiateMsg as Cw20InstantiateMsg,
+};
+use cw_dex::{
+    astroport::{AstroportPool, AstroportStaking},
+    traits::Pool,
+};
+use cw_vault_standard::msg::{VaultInfoResponse, VaultStandardInfoResponse};
+use cw_vault_token::cw4626::Cw4626;
+use semver::Version;
+use simple_vault::{
+    error::ContractError,
+    execute_compound::{PROVIDE_LIQUIDITY_REPLY_ID, SELL_REWARDS_REPLY_ID},
+    msg::{
+        CallbackMsg, ExtensionExecuteMsg, ExtensionQueryMsg, SimpleExtensionExecuteMsg,
+        SimpleExtensionQueryMsg,
+    },
+    SimpleVault,
+};
+
+use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+
+// version info for migration info
+const CONTRACT_NAME: &str = "crates.io:astroport-vault";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Constants passed to VaultStandardInfo query
+const VAULT_STANDARD_VERSION: u16 = 1;
+const VAULT_STANDARD_EXTENSIONS: [&str; 1] = ["cw4626"];
+
+/// Decimals of the cw20 vault token
+const VAULT_TOKEN_DECIMALS: u8 = 6;
+
+pub type AstroportVaultContract<'a> = SimpleVault<'a, AstroportStaking, AstroportPool, Cw4626>;
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    let contract = AstroportVaultContract::default();
+
+    let admin_addr = deps.api.addr_validate(&msg.admin)?;
+    let config = msg.config.check(deps.as_ref())?;
+
+    // Create the pool object
+    let pair_addr = deps.api.addr_validate(&msg.pair_addr)?;
+    let pool = AstroportPool::new(deps.as_ref(), pair_addr)?;
+
+    let staking = AstroportStaking {
+        lp_token_addr: deps.api.addr_validate(&pool.lp_token().to_string())?,
+        generator_addr: deps.api.addr_validate(&msg.generator_addr)?,
+        astro_addr: deps.api.addr_validate(&msg.astro_token_addr)?,
+    };
+
+    // The vault token is a cw20 token implemented by this contract, the vault
+    // itself is the only minter.
+    let vault_token = Cw4626::new(&env);
+    let init_info = to_binary(&Cw20InstantiateMsg {
+        name: msg.vault_token_name,
+        symbol: msg.vault_token_symbol,
+        decimals: VAULT_TOKEN_DECIMALS,
+        initial_balances: vec![],
+        mint: Some(MinterResponse {
+            minter: env.contract.address.to_string(),
+            cap: None,
+        }),
+        marketing: None,
+    })?;
+
+    contract.init(deps, admin_addr, pool, staking, config, vault_token, Some(init_info))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    let contract = AstroportVaultContract::default();
+
+    match msg {
+        ExecuteMsg::Deposit {
+            amount,
+            recipient,
+        } => contract.execute_deposit(deps, env, &info, amount, recipient, None),
+        ExecuteMsg::Redeem {
+            recipient,
+            amount,
+        } => contract.execute_redeem(deps, env, &info, amount, recipient),
+        ExecuteMsg::Transfer {
+            recipient,
+            amount,
+        } => Ok(execute_transfer(deps, env, info, recipient, amount)?),
+        ExecuteMsg::Send {
+            contract,
+            amount,
+            msg,
+        } => Ok(execute_send(deps, env, info, contract, amount, msg)?),
+        ExecuteMsg::IncreaseAllowance {
+            spender,
+            amount,
+            expires,
+        } => Ok(execute_increase_allowance(deps, env, info, spender, amount, expires)?),
+        ExecuteMsg::DecreaseAllowance {
+            spender,
+            amount,
+            expires,
+        } => Ok(execute_decrease_allowance(deps, env, info, spender, amount, expires)?),
+        ExecuteMsg::TransferFrom {
+            owner,
+            recipient,
+            amount,
+        } => Ok(execute_transfer_from(deps, env, info, owner, recipient, amount)?),
+        ExecuteMsg::SendFrom {
+            owner,
+            contract,
+            amount,
+            msg,
+        } => Ok(execute_send_from(deps, env, info, owner, contract, amount, msg)?),
+        ExecuteMsg::VaultExtension(msg) => match msg {
+            ExtensionExecuteMsg::Simple(msg) => match msg {
+                SimpleExtensionExecuteMsg::UpdateConfig {
+                    updates,
+                } => contract.execute_update_config(deps, env, info, updates),
+                SimpleExtensionExecuteMsg::UpdateAdmin {
+                    address,
+                } => contract.execute_update_admin(deps, info, address),
+                SimpleExtensionExecuteMsg::AcceptAdminTransfer {} => {
+                    contract.execute_accept_admin_transfer(deps, info)
+                }
+                SimpleExtensionExecuteMsg::DropAdminTransfer {} => {
+                    contract.execute_drop_admin_transfer(deps, info)
+                }
+                SimpleExtensionExecuteMsg::Deposit {
+                    amount,
+                    recipient,
+                    referrer,
+                } => contract.execute_deposit(deps, env, &info, amount, recipient, referrer),
+                SimpleExtensionExecuteMsg::ClaimReferralFees {
+                    recipient,
+                } => contract.execute_claim_referral_fees(deps, info, recipient),
+                SimpleExtensionExecuteMsg::SyncReferral {
+                    address,
+                } => contract.execute_sync_referral(deps, address),
+                SimpleExtensionExecuteMsg::InstantRedeem {
+                    amount,
+                    recipient,
+                } => contract.execute_instant_redeem(deps, env, &info, amount, recipient),
+                SimpleExtensionExecuteMsg::SettleEpoch {} => {
+                    contract.execute_settle_epoch(deps, env)
+                }
+                SimpleExtensionExecuteMsg::ClaimEpochShares {
+                    epoch_id,
+                    recipient,
+                } => contract.execute_claim_epoch_shares(deps, env, info, epoch_id, recipient),
+                SimpleExtensionExecuteMsg::Sweep {
+                    asset,
+                    recipient,
+                } => contract.execute_sweep(deps, env, info, asset, recipient),
+                SimpleExtensionExecuteMsg::ZapIn {
+                    min_base_tokens,
+                    recipient,
+                } => contract.execute_zap_in(deps, env, &info, min_base_tokens, recipient),
+                // Only reachable when `simple-vault` is compiled with features
+                // enabled by other contracts in the workspace.
+                #[allow(unreachable_patterns)]
+                _ => Err(ContractError::from("Lockup is not supported for unlocked vaults")),
+            },
+            ExtensionExecuteMsg::Callback(msg) => {
+                // Assert that only the contract itself can call this
+                if info.sender != env.contract.address {
+                    return Err(ContractError::Unauthorized {});
+                }
+
+                match msg {
+                    CallbackMsg::SellRewards {} => {
+                        contract.execute_callback_sell_rewards(deps, env, info)
+                    }
+                    CallbackMsg::ProvideLiquidity {} => {
+                        contract.execute_callback_provide_liquidity(deps, env, info)
+                    }
+                    CallbackMsg::Stake {
+                        base_token_balance_before,
+                        ..
+                    } => contract.execute_callback_stake(deps, env, base_token_balance_before),
+                    CallbackMsg::CheckSharePrice {
+                        total_staked_base_tokens_before,
+                        vault_token_supply_before,
+                        pending_mint_amount,
+                    } => contract.execute_callback_check_share_price(
+                        deps,
+                        total_staked_base_tokens_before,
+                        vault_token_supply_before,
+                        pending_mint_amount,
+                    ),
+                    CallbackMsg::ZapInDeposit {
+                        base_token_balance_before,
+                        min_base_tokens,
+                        recipient,
+                    } => contract.execute_callback_zap_in_deposit(
+                        deps,
+                        env,
+                        base_token_balance_before,
+                        min_base_tokens,
+                        recipient,
+                    ),
+                    CallbackMsg::MintVaultToken {
+                        amount,
+                        recipient,
+                    } => contract.execute_callback_mint_vault_token(deps, env, amount, recipient),
+                    CallbackMsg::InstantRedeem {
+                        recipient,
+                        amount,
+                    } => contract.execute_callback_instant_redeem(deps, env, amount, recipient),
+                    CallbackMsg::SettleEpochDeposits {
+                        epoch_id,
+                    } => contract.execute_callback_settle_epoch_deposits(deps, env, epoch_id),
+                    CallbackMsg::Redeem {
+                        recipient,
+                        amount,
+                    } => contract.execute_callback_redeem(deps, env, amount, recipient),
+                    // Only reachable when `simple-vault` is compiled with features
+                    // enabled by other contracts in the workspace.
+                    #[allow(unreachable_patterns)]
+                    _ => Err(ContractError::from("Unsupported callback for unlocked vaults")),
+                }
+            }
+            // Lockup extensions only exist when `simple-vault` is compiled with
+            // features enabled by other contracts in the workspace.
+            #[allow(unreachable_patterns)]
+            _ => Err(ContractError::from("Lockup is not supported for unlocked vaults")),
+        },
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    let contract = AstroportVaultContract::default();
+    let base_vault = &contract.base_vault;
+
+    match msg {
+        QueryMsg::VaultStandardInfo {} => to_binary(&VaultStandardInfoResponse {
+            version: VAULT_STANDARD_VERSION,
+            extensions: VAULT_STANDARD_EXTENSIONS.iter().map(|&s| s.into()).collect(),
+        }),
+        QueryMsg::Info {} => {
+            let vault_token = base_vault.vault_token.load(deps.storage)?;
+            let base_token = base_vault.base_token.load(deps.storage)?;
+
+            to_binary(&VaultInfoResponse {
+                base_token: base_token.to_string(),
+                vault_token: vault_token.to_string(),
+            })
+        }
+        QueryMsg::PreviewDeposit {
+            amount,
+        } => to_binary(&base_vault.query_simulate_deposit(deps, amount)?),
+        QueryMsg::PreviewRedeem {
+            amount,
+        } => to_binary(&base_vault.query_simulate_withdraw(deps, amount)?),
+        QueryMsg::TotalAssets {} => to_binary(&base_vault.query_total_assets(deps)?),
+        QueryMsg::TotalVaultTokenSupply {} => {
+            to_binary(&base_vault.query_total_vault_token_supply(deps)?)
+        }
+        QueryMsg::ConvertToShares {
+            amount,
+        } => to_binary(&base_vault.query_simulate_deposit(deps, amount)?),
+        QueryMsg::ConvertToAssets {
+            amount,
+        } => to_binary(&base_vault.query_simulate_withdraw(deps, amount)?),
+        QueryMsg::Balance {
+            address,
+        } => to_binary(&query_balance(deps, address)?),
+        QueryMsg::TokenInfo {} => to_binary(&query_token_info(deps)?),
+        QueryMsg::Allowance {
+            owner,
+            spender,
+        } => to_binary(&query_allowance(deps, owner, spender)?),
+        QueryMsg::AllAllowances {
+            owner,
+            start_after,
+            limit,
+        } => to_binary(&query_owner_allowances(deps, owner, start_after, limit)?),
+        QueryMsg::AllAccounts {
+            start_after,
+            limit,
+        } => to_binary(&query_all_accounts(deps, start_after, limit)?),
+        QueryMsg::VaultExtension(msg) => match msg {
+            ExtensionQueryMsg::Simple(msg) => match msg {
+                SimpleExtensionQueryMsg::State {} => to_binary(&contract.query_state(deps, env)?),
+                SimpleExtensionQueryMsg::Referral {
+                    address,
+                } => to_binary(&contract.query_referral(deps, address)?),
+                SimpleExtensionQueryMsg::Buffer {} => to_binary(&contract.query_buffer(deps)?),
+                SimpleExtensionQueryMsg::CurrentEpoch {} => {
+                    to_binary(&contract.query_current_epoch(deps, env)?)
+                }
+                SimpleExtensionQueryMsg::SettledEpoch {
+                    epoch_id,
+                } => to_binary(&contract.query_settled_epoch(deps, epoch_id)?),
+                SimpleExtensionQueryMsg::EpochPosition {
+                    epoch_id,
+                    address,
+                } => to_binary(&contract.query_epoch_position(deps, epoch_id, address)?),
+                SimpleExtensionQueryMsg::LastCompoundFailure {} => {
+                    to_binary(&contract.query_last_compound_failure(deps)?)
+                }
+                SimpleExtensionQueryMsg::UnsoldRewards {} => {
+                    to_binary(&contract.query_unsold_rewards(deps)?)
+                }
+                SimpleExtensionQueryMsg::UnaccountedBalances {
+                    cw20s,
+                } => to_binary(&contract.query_unaccounted_balances(deps, env, cw20s)?),
+            },
+            // Lockup queries only exist when `simple-vault` is compiled with
+            // features enabled by other contracts in the workspace.
+            #[allow(unreachable_patterns)]
+            _ => Err(StdError::generic_err("Lockup is not supported for unlocked vaults")),
+        },
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, reply: Reply) -> Result<Response, ContractError> {
+    let contract = AstroportVaultContract::default();
+
+    match reply.id {
+        // The steps of a compound only reply on error, which is recorded instead of
+        // reverting the transaction
+        SELL_REWARDS_REPLY_ID | PROVIDE_LIQUIDITY_REPLY_ID => {
+            contract.reply_compound_failure(deps, env, reply)
+        }
+        id => Err(ContractError::UnknownReplyId(id)),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let version: Version = CONTRACT_VERSION.parse()?;
+    let storage_version: Version = get_contract_version(deps.storage)?.version.parse()?;
+
+    // migrate only if newer
+    if storage_version < version {
+        set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    }
+    Ok(Response::default())
+}
@@ -0,0 +1,97 @@
+#[path = "./common/mod.rs"]
+mod common;
+use astroport_vault::msg::{ExecuteMsg, QueryMsg};
+use common::{TestEnv, ADMIN, INITIAL_LP_BALANCE, USER};
+use cosmwasm_std::{Addr, Decimal, Uint128};
+use cw_multi_test::Executor;
+use simple_vault::msg::{
+    BufferResponse, ExtensionExecuteMsg, ExtensionQueryMsg, SimpleExtensionExecuteMsg,
+    SimpleExtensionQueryMsg,
+};
+use simple_vault::state::ConfigUpdates;
+
+fn update_buffer_ratio(env: &mut TestEnv, buffer_ratio: Decimal) {
+    env.app
+        .execute_contract(
+            Addr::unchecked(ADMIN),
+            env.vault_addr.clone(),
+            &ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Simple(
+                SimpleExtensionExecuteMsg::UpdateConfig {
+                    updates: ConfigUpdates::default().buffer_ratio(buffer_ratio).clone(),
+                },
+            )),
+            &[],
+        )
+        .unwrap();
+}
+
+fn query_buffer(env: &TestEnv) -> BufferResponse {
+    env.app
+        .wrap()
+        .query_wasm_smart(
+            &env.vault_addr,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::Simple(
+                SimpleExtensionQueryMsg::Buffer {},
+            )),
+        )
+        .unwrap()
+}
+
+#[test]
+fn test_redeem_with_buffer() {
+    let mut env = TestEnv::new();
+    update_buffer_ratio(&mut env, Decimal::percent(50));
+
+    // Half of the deposit is kept in the buffer, the other half is staked
+    let deposit_amount = Uint128::new(1_000_000);
+    env.deposit(USER, deposit_amount).unwrap();
+    assert_eq!(env.staked_lp_tokens(&env.vault_addr), Uint128::new(500_000));
+    assert_eq!(query_buffer(&env).amount, Uint128::new(500_000));
+
+    // Redeem more base tokens than are staked. The rest is paid from the buffer.
+    let vault_token_balance = env.cw20_balance(&env.vault_addr, USER);
+    env.app
+        .execute_contract(
+            Addr::unchecked(USER),
+            env.vault_addr.clone(),
+            &ExecuteMsg::Redeem {
+                recipient: None,
+                amount: vault_token_balance.multiply_ratio(4u128, 5u128),
+            },
+            &[],
+        )
+        .unwrap();
+
+    assert_eq!(env.staked_lp_tokens(&env.vault_addr), Uint128::zero());
+    assert_eq!(query_buffer(&env).amount, Uint128::new(200_000));
+    assert_eq!(
+        env.cw20_balance(&env.lp_token_addr, USER),
+        Uint128::new(INITIAL_LP_BALANCE - 200_000)
+    );
+    assert_eq!(
+        env.cw20_balance(&env.lp_token_addr, env.vault_addr.as_str()),
+        Uint128::new(200_000)
+    );
+}
+
+#[test]
+fn test_lowering_buffer_ratio_stakes_excess() {
+    let mut env = TestEnv::new();
+    update_buffer_ratio(&mut env, Decimal::percent(50));
+
+    let deposit_amount = Uint128::new(1_000_000);
+    env.deposit(USER, deposit_amount).unwrap();
+    assert_eq!(query_buffer(&env).amount, Uint128::new(500_000));
+
+    // The part of the buffer above the new target is staked
+    update_buffer_ratio(&mut env, Decimal::percent(10));
+    let buffer = query_buffer(&env);
+    assert_eq!(buffer.amount, Uint128::new(100_000));
+    assert_eq!(buffer.target_amount, Uint128::new(100_000));
+    assert_eq!(env.staked_lp_tokens(&env.vault_addr), Uint128::new(900_000));
+
+    // Raising the ratio again only refills the buffer from new deposits
+    update_buffer_ratio(&mut env, Decimal::percent(20));
+    assert_eq!(query_buffer(&env).amount, Uint128::new(100_000));
+    assert_eq!(env.staked_lp_tokens(&env.vault_addr), Uint128::new(900_000));
+}
@@ -0,0 +1,446 @@
+#![allow(dead_code)]
+use apollo_cw_asset::AssetInfoUnchecked;
+use astroport_vault::msg::InstantiateMsg;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    from_binary, to_binary, Addr, Binary, Decimal, Deps, DepsMut, Empty, Env, MessageInfo,
+    Response, StdError, StdResult, Uint128, WasmMsg,
+};
+use cw20::{Cw20Coin, Cw20ExecuteMsg, Cw20ReceiveMsg, MinterResponse};
+use cw_multi_test::{App, Contract, ContractWrapper, Executor};
+use cw_storage_plus::{Item, Map};
+use simple_vault::state::ConfigUnchecked;
+
+pub const ADMIN: &str = "admin";
+pub const USER: &str = "user";
+pub const TREASURY: &str = "treasury";
+pub const INITIAL_LP_BALANCE: u128 = 1_000_000_000;
+
+pub struct TestEnv {
+    pub app: App,
+    pub vault_addr: Addr,
+    pub lp_token_addr: Addr,
+    pub pair_addr: Addr,
+    pub generator_addr: Addr,
+}
+
+impl TestEnv {
+    pub fn new() -> Self {
+        let mut app = App::default();
+        let admin = Addr::unchecked(ADMIN);
+
+        let cw20_code_id = app.store_code(cw20_contract());
+        let pair_code_id = app.store_code(mock_pair_contract());
+        let generator_code_id = app.store_code(mock_generator_contract());
+        let vault_code_id = app.store_code(vault_contract());
+
+        let lp_token_addr = instantiate_cw20(&mut app, cw20_code_id, "LP token", "ulp", USER);
+        let astro_token_addr =
+            instantiate_cw20(&mut app, cw20_code_id, "Astro token", "astro", ADMIN);
+
+        let pair_addr = app
+            .instantiate_contract(
+                pair_code_id,
+                admin.clone(),
+                &mock_pair::InstantiateMsg {
+                    asset_denoms: vec!["uluna".to_string(), "uusd".to_string()],
+                    lp_token_addr: lp_token_addr.to_string(),
+                },
+                &[],
+                "mock pair",
+                None,
+            )
+            .unwrap();
+
+        let generator_addr = app
+            .instantiate_contract(
+                generator_code_id,
+                admin.clone(),
+                &Empty {},
+                &[],
+                "mock generator",
+                None,
+            )
+            .unwrap();
+
+        let vault_addr = app
+            .instantiate_contract(
+                vault_code_id,
+                admin.clone(),
+                &InstantiateMsg {
+                    admin: ADMIN.to_string(),
+                    pair_addr: pair_addr.to_string(),
+                    generator_addr: generator_addr.to_string(),
+                    astro_token_addr: astro_token_addr.to_string(),
+                    config: ConfigUnchecked {
+                        performance_fee: Decimal::percent(5),
+                        treasury: TREASURY.to_string(),
+                        router: "router".to_string().into(),
+                        reward_assets: vec![],
+                        reward_liquidation_target: AssetInfoUnchecked::Native("uluna".to_string()),
+                        force_withdraw_whitelist: vec![],
+                        liquidity_helper: liquidity_helper::helper::LiquidityHelperBase(
+                            "liquidity_helper".to_string(),
+                        ),
+                        referral_fee_share: Decimal::zero(),
+                        epoch_duration: 0,
+                        buffer_ratio: Decimal::zero(),
+                        exit_fee: Decimal::zero(),
+                        max_sell_per_compound: vec![],
+                        max_share_price_increase: Decimal::zero(),
+                        ibc_withdraw_channel: String::new(),
+                        ibc_transfer_timeout: 0,
+                    },
+                    vault_token_name: "Astroport vault token".to_string(),
+                    vault_token_symbol: "vLP".to_string(),
+                },
+                &[],
+                "astroport vault",
+                Some(ADMIN.to_string()),
+            )
+            .unwrap();
+
+        Self {
+            app,
+            vault_addr,
+            lp_token_addr,
+            pair_addr,
+            generator_addr,
+        }
+    }
+
+    /// Queries the cw20 balance of `address` for the token `token_addr`.
+    pub fn cw20_balance(&self, token_addr: &Addr, address: &str) -> Uint128 {
+        let res: cw20::BalanceResponse = self
+            .app
+            .wrap()
+            .query_wasm_smart(
+                token_addr,
+                &cw20::Cw20QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap();
+        res.balance
+    }
+
+    /// Queries the amount of LP tokens `user` has staked in the mock generator.
+    pub fn staked_lp_tokens(&self, user: &Addr) -> Uint128 {
+        self.app
+            .wrap()
+            .query_wasm_smart(
+                &self.generator_addr,
+                &mock_generator::QueryMsg::Deposit {
+                    lp_token: self.lp_token_addr.to_string(),
+                    user: user.to_string(),
+                },
+            )
+            .unwrap()
+    }
+
+    /// Approves the vault to spend `amount` of the user's LP tokens and deposits
+    /// them into the vault.
+    pub fn deposit(&mut self, user: &str, amount: Uint128) -> anyhow::Result<()> {
+        self.app.execute_contract(
+            Addr::unchecked(user),
+            self.lp_token_addr.clone(),
+            &Cw20ExecuteMsg::IncreaseAllowance {
+                spender: self.vault_addr.to_string(),
+                amount,
+                expires: None,
+            },
+            &[],
+        )?;
+        self.app.execute_contract(
+            Addr::unchecked(user),
+            self.vault_addr.clone(),
+            &astroport_vault::msg::ExecuteMsg::Deposit {
+                amount,
+                recipient: None,
+            },
+            &[],
+        )?;
+        Ok(())
+    }
+}
+
+pub fn instantiate_cw20(
+    app: &mut App,
+    code_id: u64,
+    name: &str,
+    symbol: &str,
+    holder: &str,
+) -> Addr {
+    app.instantiate_contract(
+        code_id,
+        Addr::unchecked(ADMIN),
+        &cw20_base::msg::InstantiateMsg {
+            name: name.to_string(),
+            symbol: symbol.to_string(),
+            decimals: 6,
+            initial_balances: vec![Cw20Coin {
+                address: holder.to_string(),
+                amount: Uint128::new(INITIAL_LP_BALANCE),
+            }],
+            mint: Some(MinterResponse {
+                minter: ADMIN.to_string(),
+                cap: None,
+            }),
+            marketing: None,
+        },
+        &[],
+        name,
+        None,
+    )
+    .unwrap()
+}
+
+fn vault_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        astroport_vault::contract::execute,
+        astroport_vault::contract::instantiate,
+        astroport_vault::contract::query,
+    ))
+}
+
+pub fn cw20_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        cw20_base::contract::execute,
+        cw20_base::contract::instantiate,
+        cw20_base::contract::query,
+    ))
+}
+
+fn mock_pair_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(mock_pair::execute, mock_pair::instantiate, mock_pair::query))
+}
+
+fn mock_generator_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        mock_generator::execute,
+        mock_generator::instantiate,
+        mock_generator::query,
+    ))
+}
+
+/// Minimal astroport style pair. Only answers the queries needed to set up the
+/// vault and holds no liquidity.
+pub mod mock_pair {
+    use super::*;
+
+    const PAIR_INFO: Item<PairInfo> = Item::new("pair_info");
+
+    #[cw_serde]
+    pub struct InstantiateMsg {
+        pub asset_denoms: Vec<String>,
+        pub lp_token_addr: String,
+    }
+
+    #[cw_serde]
+    pub enum AssetInfo {
+        Token {
+            contract_addr: Addr,
+        },
+        NativeToken {
+            denom: String,
+        },
+    }
+
+    #[cw_serde]
+    pub struct Asset {
+        pub info: AssetInfo,
+        pub amount: Uint128,
+    }
+
+    #[cw_serde]
+    pub enum PairType {
+        Xyk {},
+        Stable {},
+    }
+
+    #[cw_serde]
+    pub struct PairInfo {
+        pub asset_infos: Vec<AssetInfo>,
+        pub contract_addr: Addr,
+        pub liquidity_token: Addr,
+        pub pair_type: PairType,
+    }
+
+    #[cw_serde]
+    pub struct PoolResponse {
+        pub assets: Vec<Asset>,
+        pub total_share: Uint128,
+    }
+
+    #[cw_serde]
+    pub enum QueryMsg {
+        Pair {},
+        Pool {},
+    }
+
+    pub fn instantiate(
+        deps: DepsMut,
+        env: Env,
+        _info: MessageInfo,
+        msg: InstantiateMsg,
+    ) -> StdResult<Response> {
+        PAIR_INFO.save(
+            deps.storage,
+            &PairInfo {
+                asset_infos: msg
+                    .asset_denoms
+                    .into_iter()
+                    .map(|denom| AssetInfo::NativeToken {
+                        denom,
+                    })
+                    .collect(),
+                contract_addr: env.contract.address,
+                liquidity_token: deps.api.addr_validate(&msg.lp_token_addr)?,
+                pair_type: PairType::Xyk {},
+            },
+        )?;
+        Ok(Response::default())
+    }
+
+    pub fn execute(
+        _deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        _msg: Empty,
+    ) -> StdResult<Response> {
+        Err(StdError::generic_err("mock pair does not support execute"))
+    }
+
+    pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+        let pair_info = PAIR_INFO.load(deps.storage)?;
+        match msg {
+            QueryMsg::Pair {} => to_binary(&pair_info),
+            QueryMsg::Pool {} => to_binary(&PoolResponse {
+                assets: pair_info
+                    .asset_infos
+                    .into_iter()
+                    .map(|info| Asset {
+                        info,
+                        amount: Uint128::zero(),
+                    })
+                    .collect(),
+                total_share: Uint128::zero(),
+            }),
+        }
+    }
+}
+
+/// Minimal astroport style generator. LP tokens can be deposited with a cw20
+/// `Send` and withdrawn instantly. No rewards are paid out.
+pub mod mock_generator {
+    use super::*;
+
+    /// Staked amount per (lp token, user)
+    const DEPOSITS: Map<(&Addr, &Addr), Uint128> = Map::new("deposits");
+
+    #[cw_serde]
+    pub enum ExecuteMsg {
+        Receive(Cw20ReceiveMsg),
+        Withdraw {
+            lp_token: String,
+            amount: Uint128,
+        },
+        ClaimRewards {
+            lp_tokens: Vec<String>,
+        },
+    }
+
+    #[cw_serde]
+    pub enum Cw20HookMsg {
+        Deposit {},
+    }
+
+    #[cw_serde]
+    pub enum QueryMsg {
+        Deposit {
+            lp_token: String,
+            user: String,
+        },
+        PendingToken {
+            lp_token: String,
+            user: String,
+        },
+    }
+
+    #[cw_serde]
+    pub struct PendingTokenResponse {
+        pub pending: Uint128,
+        pub pending_on_proxy: Option<Vec<mock_pair::Asset>>,
+    }
+
+    pub fn instantiate(
+        _deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        _msg: Empty,
+    ) -> StdResult<Response> {
+        Ok(Response::default())
+    }
+
+    pub fn execute(
+        deps: DepsMut,
+        _env: Env,
+        info: MessageInfo,
+        msg: ExecuteMsg,
+    ) -> StdResult<Response> {
+        match msg {
+            ExecuteMsg::Receive(cw20_msg) => match from_binary(&cw20_msg.msg)? {
+                Cw20HookMsg::Deposit {} => {
+                    let user = deps.api.addr_validate(&cw20_msg.sender)?;
+                    DEPOSITS.update(deps.storage, (&info.sender, &user), |x| {
+                        x.unwrap_or_default().checked_add(cw20_msg.amount).map_err(StdError::from)
+                    })?;
+                    Ok(Response::default())
+                }
+            },
+            ExecuteMsg::Withdraw {
+                lp_token,
+                amount,
+            } => {
+                let lp_token = deps.api.addr_validate(&lp_token)?;
+                DEPOSITS.update(deps.storage, (&lp_token, &info.sender), |x| {
+                    x.unwrap_or_default().checked_sub(amount).map_err(StdError::from)
+                })?;
+                Ok(Response::new().add_message(WasmMsg::Execute {
+                    contract_addr: lp_token.to_string(),
+                    msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                        recipient: info.sender.to_string(),
+                        amount,
+                    })?,
+                    funds: vec![],
+                }))
+            }
+            ExecuteMsg::ClaimRewards {
+                lp_tokens: _,
+            } => Ok(Response::default()),
+        }
+    }
+
+    pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+        match msg {
+            QueryMsg::Deposit {
+                lp_token,
+                user,
+            } => to_binary(
+                &DEPOSITS
+                    .may_load(
+                        deps.storage,
+                        (&deps.api.addr_validate(&lp_token)?, &deps.api.addr_validate(&user)?),
+                    )?
+                    .unwrap_or_default(),
+            ),
+            QueryMsg::PendingToken {
+                lp_token: _,
+                user: _,
+            } => to_binary(&PendingTokenResponse {
+                pending: Uint128::zero(),
+                pending_on_proxy: None,
+            }),
+        }
+    }
+}
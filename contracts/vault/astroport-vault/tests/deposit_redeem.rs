@@ -0,0 +1,106 @@
+#[path = "./common/mod.rs"]
+mod common;
+use astroport_vault::msg::{ExecuteMsg, QueryMsg};
+use common::{TestEnv, INITIAL_LP_BALANCE, USER};
+use cosmwasm_std::{Addr, Uint128};
+use cw_multi_test::Executor;
+use cw_vault_standard::msg::VaultStandardInfoResponse;
+
+#[test]
+fn test_vault_standard_info() {
+    let TestEnv {
+        app,
+        vault_addr,
+        ..
+    } = TestEnv::new();
+
+    let res: VaultStandardInfoResponse =
+        app.wrap().query_wasm_smart(vault_addr, &QueryMsg::VaultStandardInfo {}).unwrap();
+    assert_eq!(res.extensions, vec!["cw4626".to_string()]);
+}
+
+#[test]
+fn test_deposit_and_redeem() {
+    let mut env = TestEnv::new();
+    let deposit_amount = Uint128::new(1_000_000);
+
+    env.deposit(USER, deposit_amount).unwrap();
+
+    // The LP tokens are staked in the generator by the vault
+    assert_eq!(env.staked_lp_tokens(&env.vault_addr), deposit_amount);
+    assert_eq!(
+        env.cw20_balance(&env.lp_token_addr, USER),
+        Uint128::new(INITIAL_LP_BALANCE) - deposit_amount
+    );
+
+    // All vault tokens were minted to the user
+    let vault_token_supply: Uint128 = env
+        .app
+        .wrap()
+        .query_wasm_smart(&env.vault_addr, &QueryMsg::TotalVaultTokenSupply {})
+        .unwrap();
+    assert!(!vault_token_supply.is_zero());
+    assert_eq!(env.cw20_balance(&env.vault_addr, USER), vault_token_supply);
+
+    let total_assets: Uint128 =
+        env.app.wrap().query_wasm_smart(&env.vault_addr, &QueryMsg::TotalAssets {}).unwrap();
+    assert_eq!(total_assets, deposit_amount);
+
+    // Redeem half of the vault tokens
+    let redeem_amount = vault_token_supply.multiply_ratio(1u128, 2u128);
+    env.app
+        .execute_contract(
+            Addr::unchecked(USER),
+            env.vault_addr.clone(),
+            &ExecuteMsg::Redeem {
+                recipient: None,
+                amount: redeem_amount,
+            },
+            &[],
+        )
+        .unwrap();
+
+    let half_deposit = deposit_amount.multiply_ratio(1u128, 2u128);
+    assert_eq!(env.staked_lp_tokens(&env.vault_addr), deposit_amount - half_deposit);
+    assert_eq!(
+        env.cw20_balance(&env.lp_token_addr, USER),
+        Uint128::new(INITIAL_LP_BALANCE) - deposit_amount + half_deposit
+    );
+    assert_eq!(env.cw20_balance(&env.vault_addr, USER), vault_token_supply - redeem_amount);
+
+    // Redeem the rest to another recipient
+    env.app
+        .execute_contract(
+            Addr::unchecked(USER),
+            env.vault_addr.clone(),
+            &ExecuteMsg::Redeem {
+                recipient: Some("recipient".to_string()),
+                amount: vault_token_supply - redeem_amount,
+            },
+            &[],
+        )
+        .unwrap();
+
+    assert_eq!(env.staked_lp_tokens(&env.vault_addr), Uint128::zero());
+    assert_eq!(env.cw20_balance(&env.lp_token_addr, "recipient"), deposit_amount - half_deposit);
+    assert_eq!(env.cw20_balance(&env.vault_addr, USER), Uint128::zero());
+}
+
+#[test]
+fn test_redeem_more_than_balance_fails() {
+    let mut env = TestEnv::new();
+    env.deposit(USER, Uint128::new(1_000_000)).unwrap();
+
+    let balance = env.cw20_balance(&env.vault_addr, USER);
+    env.app
+        .execute_contract(
+            Addr::unchecked(USER),
+            env.vault_addr.clone(),
+            &ExecuteMsg::Redeem {
+                recipient: None,
+                amount: balance + Uint128::one(),
+            },
+            &[],
+        )
+        .unwrap_err();
+}
@@ -0,0 +1,135 @@
+#[path = "./common/mod.rs"]
+mod common;
+use apollo_cw_asset::{AssetInfo, AssetInfoUnchecked, AssetList};
+use astroport_vault::msg::{ExecuteMsg, QueryMsg};
+use common::{cw20_contract, instantiate_cw20, TestEnv, ADMIN, INITIAL_LP_BALANCE, USER};
+use cosmwasm_std::{coins, Addr, Uint128};
+use cw20::Cw20ExecuteMsg;
+use cw_multi_test::Executor;
+use simple_vault::msg::{
+    ExtensionExecuteMsg, ExtensionQueryMsg, SimpleExtensionExecuteMsg, SimpleExtensionQueryMsg,
+};
+
+fn sweep_msg(asset: AssetInfoUnchecked) -> ExecuteMsg {
+    ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Simple(SimpleExtensionExecuteMsg::Sweep {
+        asset,
+        recipient: ADMIN.to_string(),
+    }))
+}
+
+fn query_unaccounted_balances(env: &TestEnv, cw20s: Vec<String>) -> AssetList {
+    env.app
+        .wrap()
+        .query_wasm_smart(
+            &env.vault_addr,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::Simple(
+                SimpleExtensionQueryMsg::UnaccountedBalances {
+                    cw20s,
+                },
+            )),
+        )
+        .unwrap()
+}
+
+#[test]
+fn test_sweep_cw20() {
+    let mut env = TestEnv::new();
+    env.deposit(USER, Uint128::new(1_000_000)).unwrap();
+
+    // Send a cw20 token the vault does not account for to the vault
+    let cw20_code_id = env.app.store_code(cw20_contract());
+    let stray_token_addr = instantiate_cw20(&mut env.app, cw20_code_id, "Stray", "stray", ADMIN);
+    let stray_amount = Uint128::new(1_000);
+    env.app
+        .execute_contract(
+            Addr::unchecked(ADMIN),
+            stray_token_addr.clone(),
+            &Cw20ExecuteMsg::Transfer {
+                recipient: env.vault_addr.to_string(),
+                amount: stray_amount,
+            },
+            &[],
+        )
+        .unwrap();
+
+    // Only the cw20 tokens in the query are listed, and the base token and vault
+    // token are excluded
+    assert_eq!(query_unaccounted_balances(&env, vec![]).len(), 0);
+    let unaccounted = query_unaccounted_balances(
+        &env,
+        vec![
+            stray_token_addr.to_string(),
+            env.lp_token_addr.to_string(),
+            env.vault_addr.to_string(),
+        ],
+    );
+    assert_eq!(unaccounted.len(), 1);
+    assert_eq!(
+        unaccounted.find(&AssetInfo::Cw20(stray_token_addr.clone())).unwrap().amount,
+        stray_amount
+    );
+
+    // Only the admin can sweep
+    let msg = sweep_msg(AssetInfoUnchecked::Cw20(stray_token_addr.to_string()));
+    env.app.execute_contract(Addr::unchecked(USER), env.vault_addr.clone(), &msg, &[]).unwrap_err();
+
+    env.app.execute_contract(Addr::unchecked(ADMIN), env.vault_addr.clone(), &msg, &[]).unwrap();
+    assert_eq!(env.cw20_balance(&stray_token_addr, env.vault_addr.as_str()), Uint128::zero());
+    assert_eq!(env.cw20_balance(&stray_token_addr, ADMIN), Uint128::new(INITIAL_LP_BALANCE));
+}
+
+#[test]
+fn test_sweep_native() {
+    let mut env = TestEnv::new();
+
+    let vault_addr = env.vault_addr.clone();
+    env.app.init_modules(|router, _, storage| {
+        router.bank.init_balance(storage, &vault_addr, coins(1_000, "ustray")).unwrap()
+    });
+
+    let unaccounted = query_unaccounted_balances(&env, vec![]);
+    assert_eq!(unaccounted.len(), 1);
+    assert_eq!(
+        unaccounted.find(&AssetInfo::Native("ustray".to_string())).unwrap().amount,
+        Uint128::new(1_000)
+    );
+
+    env.app
+        .execute_contract(
+            Addr::unchecked(ADMIN),
+            env.vault_addr.clone(),
+            &sweep_msg(AssetInfoUnchecked::Native("ustray".to_string())),
+            &[],
+        )
+        .unwrap();
+    assert_eq!(env.app.wrap().query_balance(ADMIN, "ustray").unwrap().amount, Uint128::new(1_000));
+    assert_eq!(query_unaccounted_balances(&env, vec![]).len(), 0);
+}
+
+#[test]
+fn test_sweep_rejects_accounted_assets() {
+    let mut env = TestEnv::new();
+    env.deposit(USER, Uint128::new(1_000_000)).unwrap();
+
+    let accounted = vec![
+        // The base token
+        AssetInfoUnchecked::Cw20(env.lp_token_addr.to_string()),
+        // The vault token
+        AssetInfoUnchecked::Cw20(env.vault_addr.to_string()),
+        // The pool assets
+        AssetInfoUnchecked::Native("uluna".to_string()),
+        AssetInfoUnchecked::Native("uusd".to_string()),
+    ];
+    for asset in accounted {
+        let err = env
+            .app
+            .execute_contract(
+                Addr::unchecked(ADMIN),
+                env.vault_addr.clone(),
+                &sweep_msg(asset),
+                &[],
+            )
+            .unwrap_err();
+        assert!(err.root_cause().to_string().contains("which is accounted for by the vault"));
+    }
+}
@@ -0,0 +1,189 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    to_binary, Binary, Deps, DepsMut, Env, Event, MessageInfo, Response, StdError, StdResult,
+    Uint128,
+};
+use cw2::{get_contract_version, set_contract_version};
+use cw_vault_standard::msg::{VaultInfoResponse, VaultStandardInfoResponse};
+use cw_vault_token::osmosis::OsmosisDenom;
+use cw_vault_token::VaultToken;
+use semver::Version;
+
+use crate::error::ContractError;
+use crate::execute::{
+    add_vault, assert_weights, execute_callback_allocate, execute_callback_deposit_lp,
+    execute_callback_mint_shares, execute_callback_sell_pool_assets,
+    execute_callback_withdraw_liquidity, execute_deposit, execute_rebalance, execute_redeem,
+    execute_update_vaults,
+};
+use crate::msg::{
+    CallbackMsg, ExecuteMsg, ExtensionExecuteMsg, ExtensionQueryMsg, InstantiateMsg,
+    MetaExecuteMsg, MetaQueryMsg, MigrateMsg, QueryMsg,
+};
+use crate::query::{
+    query_allocation, query_convert_to_assets, query_convert_to_shares, query_total_assets,
+};
+use crate::state::{ADMIN, CONFIG, DEPOSIT_DENOM, VAULT_TOKEN};
+
+// version info for migration info
+const CONTRACT_NAME: &str = "crates.io:meta-vault";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Constants passed to VaultStandardInfo query
+const VAULT_STANDARD_VERSION: u16 = 1;
+const VAULT_STANDARD_EXTENSIONS: [&str; 1] = ["meta"];
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    let admin_addr = deps.api.addr_validate(&msg.admin)?;
+    let config = msg.config.check(deps.as_ref())?;
+
+    // Validate that 10 osmo for vault token creation are sent
+    let osmo_amount = info
+        .funds
+        .iter()
+        .find(|coin| coin.denom == "uosmo")
+        .map(|coin| coin.amount)
+        .unwrap_or_default();
+    if osmo_amount < Uint128::new(10_000_000) {
+        return Err(StdError::generic_err(
+            "A minimum of 10_000_000 uosmo must be sent to create the vault token",
+        )
+        .into());
+    }
+
+    ADMIN.set(deps.branch(), Some(admin_addr))?;
+    CONFIG.save(deps.storage, &config)?;
+    DEPOSIT_DENOM.save(deps.storage, &msg.deposit_denom)?;
+    for vault in msg.vaults {
+        let address = deps.api.addr_validate(&vault.address)?;
+        add_vault(deps.branch(), address, vault.weight)?;
+    }
+    assert_weights(deps.as_ref())?;
+
+    let vault_token = OsmosisDenom::new(env.contract.address.to_string(), msg.vault_token_subdenom);
+    VAULT_TOKEN.save(deps.storage, &vault_token)?;
+
+    Ok(vault_token.instantiate(deps, None)?)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::Deposit {
+            amount,
+            recipient,
+        } => execute_deposit(deps, env, info, amount, recipient, Uint128::zero()),
+        ExecuteMsg::Redeem {
+            recipient,
+            amount,
+        } => execute_redeem(deps, env, info, amount, recipient),
+        ExecuteMsg::VaultExtension(msg) => match msg {
+            ExtensionExecuteMsg::Meta(msg) => match msg {
+                MetaExecuteMsg::Deposit {
+                    amount,
+                    min_shares,
+                    recipient,
+                } => execute_deposit(deps, env, info, amount, recipient, min_shares),
+                MetaExecuteMsg::Rebalance {} => execute_rebalance(deps, env, info),
+                MetaExecuteMsg::UpdateVaults {
+                    vaults,
+                } => execute_update_vaults(deps, env, info, vaults),
+                MetaExecuteMsg::UpdateConfig {
+                    config,
+                } => {
+                    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+                    CONFIG.save(deps.storage, &config.check(deps.as_ref())?)?;
+                    let event = Event::new("apollo/vaults/meta_vault/update_config")
+                        .add_attribute("action", "execute_update_config");
+                    Ok(Response::new().add_event(event))
+                }
+                MetaExecuteMsg::UpdateAdmin {
+                    address,
+                } => {
+                    let new_admin = deps.api.addr_validate(&address)?;
+                    Ok(ADMIN.execute_update_admin(deps, info, Some(new_admin))?)
+                }
+            },
+            ExtensionExecuteMsg::Callback(msg) => {
+                // Assert that only the contract itself can call this
+                if info.sender != env.contract.address {
+                    return Err(ContractError::Unauthorized {});
+                }
+
+                match msg {
+                    CallbackMsg::Allocate {} => execute_callback_allocate(deps, env),
+                    CallbackMsg::DepositLp {
+                        vault,
+                    } => execute_callback_deposit_lp(deps, env, vault),
+                    CallbackMsg::MintShares {} => execute_callback_mint_shares(deps, env),
+                    CallbackMsg::WithdrawLiquidity {} => {
+                        execute_callback_withdraw_liquidity(deps, env)
+                    }
+                    CallbackMsg::SellPoolAssets {} => execute_callback_sell_pool_assets(deps, env),
+                }
+            }
+        },
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::VaultStandardInfo {} => to_binary(&VaultStandardInfoResponse {
+            version: VAULT_STANDARD_VERSION,
+            extensions: VAULT_STANDARD_EXTENSIONS.iter().map(|&s| s.into()).collect(),
+        }),
+        QueryMsg::Info {} => to_binary(&VaultInfoResponse {
+            base_token: DEPOSIT_DENOM.load(deps.storage)?,
+            vault_token: VAULT_TOKEN.load(deps.storage)?.to_string(),
+        }),
+        QueryMsg::PreviewDeposit {
+            amount,
+        } => to_binary(&query_convert_to_shares(deps, env, amount)?),
+        QueryMsg::PreviewRedeem {
+            amount,
+        } => to_binary(&query_convert_to_assets(deps, env, amount)?),
+        QueryMsg::TotalAssets {} => to_binary(&query_total_assets(deps, env)?),
+        QueryMsg::TotalVaultTokenSupply {} => {
+            to_binary(&VAULT_TOKEN.load(deps.storage)?.query_total_supply(deps)?)
+        }
+        QueryMsg::ConvertToShares {
+            amount,
+        } => to_binary(&query_convert_to_shares(deps, env, amount)?),
+        QueryMsg::ConvertToAssets {
+            amount,
+        } => to_binary(&query_convert_to_assets(deps, env, amount)?),
+        QueryMsg::VaultExtension(msg) => match msg {
+            ExtensionQueryMsg::Meta(msg) => match msg {
+                MetaQueryMsg::Allocation {} => to_binary(&query_allocation(deps, env)?),
+                MetaQueryMsg::Config {} => to_binary(&CONFIG.load(deps.storage)?),
+            },
+        },
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let version: Version = CONTRACT_VERSION.parse()?;
+    let storage_version: Version = get_contract_version(deps.storage)?.version.parse()?;
+
+    // migrate only if newer
+    if storage_version < version {
+        set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    }
+    Ok(Response::default())
+}
@@ -0,0 +1,65 @@
+use cosmwasm_std::{Coin, OverflowError, StdError, Uint128};
+use cw_controllers::AdminError;
+use cw_dex::CwDexError;
+use cw_vault_token::CwTokenError;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+
+    #[error("{0}")]
+    CwTokenError(#[from] CwTokenError),
+
+    #[error("{0}")]
+    CwDexError(#[from] CwDexError),
+
+    #[error("{0}")]
+    AdminError(#[from] AdminError),
+
+    #[error("{0}")]
+    SemVer(String),
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("Unexpected funds sent. Expected: {expected:?}, Actual: {actual:?}")]
+    UnexpectedFunds {
+        expected: Vec<Coin>,
+        actual: Vec<Coin>,
+    },
+
+    #[error("Sum of vault weights must be greater than zero")]
+    ZeroWeights {},
+
+    #[error("Vault {address} has base token {base_token}, expected an osmosis LP token containing {deposit_denom}")]
+    UnsupportedVault {
+        address: String,
+        base_token: String,
+        deposit_denom: String,
+    },
+
+    #[error("Vault {address} still holds assets of the meta-vault. Set its weight to zero and rebalance first")]
+    VaultNotEmpty {
+        address: String,
+    },
+
+    #[error("Deposit would mint {shares} shares, less than the minimum of {min_shares}")]
+    InsufficientShares {
+        shares: Uint128,
+        min_shares: Uint128,
+    },
+
+    #[error("Nothing to redeem, all assets of the vault are unlocking")]
+    NothingToRedeem {},
+}
+
+impl From<semver::Error> for ContractError {
+    fn from(err: semver::Error) -> Self {
+        Self::SemVer(err.to_string())
+    }
+}
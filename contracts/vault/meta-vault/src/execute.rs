@@ -0,0 +1,507 @@
+use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+use apollo_utils::responses::merge_responses;
+use cosmwasm_std::{
+    attr, coin, to_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal, Deps, DepsMut, Env, Event,
+    MessageInfo, Order, Response, StdError, StdResult, Uint128, WasmMsg,
+};
+use cw_dex::osmosis::OsmosisPool;
+use cw_dex::traits::Pool;
+use cw_vault_standard::extensions::lockup::LockupExecuteMsg;
+use cw_vault_token::VaultToken;
+use simple_vault::msg::ExtensionExecuteMsg;
+
+use crate::error::ContractError;
+use crate::helpers::{
+    min_lp_output, min_swap_output, query_unlocking_positions, query_vault_info, shares_for_value,
+    split_by_ratios, split_by_weights, value_for_shares, Holdings,
+};
+use crate::msg::{CallbackMsg, UnderlyingExecuteMsg, VaultWeight};
+use crate::state::{
+    PendingDeposit, UnderlyingVault, ADMIN, CONFIG, DEPOSIT_DENOM, PENDING_DEPOSIT, VAULTS,
+    VAULT_TOKEN,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Execute
+//--------------------------------------------------------------------------------------------------
+
+/// Deposit the deposit asset into the vault. The deposit is allocated to the
+/// underlying vaults and shares are minted for the value it added, so the
+/// depositor bears the slippage of their own allocation. Fails if fewer than
+/// `min_shares` shares would be minted.
+pub fn execute_deposit(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+    recipient: Option<String>,
+    min_shares: Uint128,
+) -> Result<Response, ContractError> {
+    let deposit_denom = DEPOSIT_DENOM.load(deps.storage)?;
+    let recipient = recipient.map_or(Ok(info.sender.clone()), |x| deps.api.addr_validate(&x))?;
+
+    if amount.is_zero() || info.funds != vec![coin(amount.u128(), &deposit_denom)] {
+        return Err(ContractError::UnexpectedFunds {
+            expected: vec![coin(amount.u128(), deposit_denom)],
+            actual: info.funds,
+        });
+    }
+
+    // The deposit is already in the contract balance and counted as idle
+    let value_before = Holdings::load(deps.as_ref(), &env)?.total_value()?.checked_sub(amount)?;
+    let supply_before = VAULT_TOKEN.load(deps.storage)?.query_total_supply(deps.as_ref())?;
+    PENDING_DEPOSIT.save(
+        deps.storage,
+        &PendingDeposit {
+            recipient: recipient.clone(),
+            value_before,
+            supply_before,
+            min_shares,
+        },
+    )?;
+
+    let event = Event::new("apollo/vaults/meta_vault/deposit").add_attributes(vec![
+        attr("action", "deposit"),
+        attr("recipient", recipient),
+        attr("amount", amount),
+    ]);
+
+    Ok(Response::new()
+        .add_message(CallbackMsg::Allocate {}.into_cosmos_msg(&env)?)
+        .add_message(CallbackMsg::MintShares {}.into_cosmos_msg(&env)?)
+        .add_event(event))
+}
+
+/// Redeem shares for the underlying vault tokens and idle deposit asset they
+/// represent. The recipient unlocks the underlying vault tokens themselves.
+///
+/// Assets that are unlocking cannot be transferred, so the redeemer is paid
+/// their share of the unlocking value from the assets that can.
+pub fn execute_redeem(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    let recipient = recipient.map_or(Ok(info.sender.clone()), |x| deps.api.addr_validate(&x))?;
+    let vault_token = VAULT_TOKEN.load(deps.storage)?;
+
+    // Receive the shares, or validate that they were sent
+    vault_token.receive(deps.branch(), &env, &info, amount)?;
+
+    let holdings = Holdings::load(deps.as_ref(), &env)?;
+    let held_value = holdings.held_value()?;
+    if held_value.is_zero() {
+        return Err(ContractError::NothingToRedeem {});
+    }
+    let supply = vault_token.query_total_supply(deps.as_ref())?;
+    let redeem_value = value_for_shares(amount, holdings.total_value()?, supply)?;
+    let ratio = Decimal::from_ratio(redeem_value, held_value).min(Decimal::one());
+
+    let deposit_denom = DEPOSIT_DENOM.load(deps.storage)?;
+    let payout = holdings
+        .vaults
+        .iter()
+        .map(|x| coin((x.vault_tokens * ratio).u128(), &x.vault.vault_token))
+        .chain(std::iter::once(coin((holdings.idle * ratio).u128(), deposit_denom)))
+        .filter(|x| !x.amount.is_zero())
+        .collect::<Vec<_>>();
+
+    let burn_res = vault_token.burn(deps.branch(), &env, amount)?;
+
+    let event = Event::new("apollo/vaults/meta_vault/redeem").add_attributes(vec![
+        attr("action", "redeem"),
+        attr("recipient", recipient.to_string()),
+        attr("amount", amount),
+        attr("value", redeem_value),
+        attr("payout", payout.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(",")),
+    ]);
+
+    let mut res = burn_res.add_event(event);
+    if !payout.is_empty() {
+        res = res.add_message(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: payout,
+        });
+    }
+    Ok(res)
+}
+
+/// Move the allocation towards the target weights. Unlocks the value above
+/// target of vaults that exceed their target by more than the rebalance
+/// threshold, withdraws unlocking positions that have finished and allocates
+/// the withdrawn value to the vaults below their target.
+pub fn execute_rebalance(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    if !ADMIN.is_admin(deps.as_ref(), &info.sender)? && !cfg.is_keeper(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let holdings = Holdings::load(deps.as_ref(), &env)?;
+    let total_value = holdings.total_value()?;
+    let threshold = total_value * cfg.rebalance_threshold;
+
+    let mut msgs: Vec<CosmosMsg> = vec![];
+    let mut unlocked: Vec<Coin> = vec![];
+    for (holding, target) in holdings.vaults.iter().zip(holdings.target_values()?) {
+        let excess = holding.value.saturating_sub(target);
+        if excess.is_zero() || excess <= threshold {
+            continue;
+        }
+
+        let vault_tokens = holding.vault_tokens.multiply_ratio(excess, holding.value);
+        if vault_tokens.is_zero() {
+            continue;
+        }
+        let vault_token = coin(vault_tokens.u128(), &holding.vault.vault_token);
+        msgs.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: holding.vault.address.to_string(),
+            msg: to_binary(&UnderlyingExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(
+                LockupExecuteMsg::Unlock {
+                    amount: vault_tokens,
+                },
+            )))?,
+            funds: vec![vault_token.clone()],
+        }));
+        unlocked.push(vault_token);
+    }
+
+    for (vault, lockup_id) in &holdings.matured {
+        msgs.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: vault.to_string(),
+            msg: to_binary(&UnderlyingExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(
+                LockupExecuteMsg::WithdrawUnlocked {
+                    recipient: None,
+                    lockup_id: *lockup_id,
+                },
+            )))?,
+            funds: vec![],
+        }));
+    }
+
+    let event = Event::new("apollo/vaults/meta_vault/rebalance").add_attributes(vec![
+        attr("action", "rebalance"),
+        attr("total_value", total_value),
+        attr("unlocked", unlocked.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(",")),
+        attr("withdrawn_lockups", holdings.matured.len().to_string()),
+    ]);
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_message(CallbackMsg::WithdrawLiquidity {}.into_cosmos_msg(&env)?)
+        .add_message(CallbackMsg::SellPoolAssets {}.into_cosmos_msg(&env)?)
+        .add_message(CallbackMsg::Allocate {}.into_cosmos_msg(&env)?)
+        .add_event(event))
+}
+
+/// Replace the set of underlying vaults and their weights. Vaults that are not
+/// in `vaults` are removed, which requires that the meta-vault holds nothing in
+/// them.
+pub fn execute_update_vaults(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    vaults: Vec<VaultWeight>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let vaults = vaults
+        .into_iter()
+        .map(|x| Ok((deps.api.addr_validate(&x.address)?, x.weight)))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    // Remove the vaults that are not in the new set
+    let existing =
+        VAULTS.keys(deps.storage, None, None, Order::Ascending).collect::<StdResult<Vec<_>>>()?;
+    for address in existing.iter().filter(|a| !vaults.iter().any(|(b, _)| &b == a)) {
+        let vault = VAULTS.load(deps.storage, address)?;
+        let vault_tokens =
+            deps.querier.query_balance(&env.contract.address, &vault.vault_token)?.amount;
+        if !vault_tokens.is_zero()
+            || !query_unlocking_positions(deps.as_ref(), address, &env.contract.address)?.is_empty()
+        {
+            return Err(ContractError::VaultNotEmpty {
+                address: address.to_string(),
+            });
+        }
+        VAULTS.remove(deps.storage, address);
+    }
+
+    for (address, weight) in &vaults {
+        match VAULTS.may_load(deps.storage, address)? {
+            Some(mut vault) => {
+                vault.weight = *weight;
+                VAULTS.save(deps.storage, address, &vault)?;
+            }
+            None => add_vault(deps.branch(), address.clone(), *weight)?,
+        }
+    }
+    assert_weights(deps.as_ref())?;
+
+    let event = Event::new("apollo/vaults/meta_vault/update_vaults").add_attributes(
+        vaults.iter().map(|(address, weight)| attr(address.to_string(), weight.to_string())),
+    );
+
+    Ok(Response::new().add_attribute("action", "execute_update_vaults").add_event(event))
+}
+
+/// Adds the underlying vault at `address` with `weight`. The base token of
+/// the vault must be an LP token of an osmosis pool containing the deposit
+/// asset.
+pub fn add_vault(deps: DepsMut, address: Addr, weight: Decimal) -> Result<(), ContractError> {
+    let deposit_denom = DEPOSIT_DENOM.load(deps.storage)?;
+    let info = query_vault_info(deps.as_ref(), &address)?;
+    let unsupported = || ContractError::UnsupportedVault {
+        address: address.to_string(),
+        base_token: info.base_token.clone(),
+        deposit_denom: deposit_denom.clone(),
+    };
+
+    let pool_id = info
+        .base_token
+        .strip_prefix("gamm/pool/")
+        .and_then(|id| id.parse::<u64>().ok())
+        .ok_or_else(unsupported)?;
+    let pool = OsmosisPool::new(pool_id, deps.as_ref())?;
+    if !pool.pool_assets(deps.as_ref())?.contains(&AssetInfo::Native(deposit_denom.clone())) {
+        return Err(unsupported());
+    }
+
+    VAULTS.save(
+        deps.storage,
+        &address,
+        &UnderlyingVault {
+            address: address.clone(),
+            weight,
+            base_token: info.base_token,
+            vault_token: info.vault_token,
+            pool_id,
+            pool,
+        },
+    )?;
+    Ok(())
+}
+
+/// Checks that the weights of the underlying vaults do not all sum to zero.
+pub fn assert_weights(deps: Deps) -> Result<(), ContractError> {
+    let weights = VAULTS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|x| x.map(|(_, v)| v.weight))
+        .collect::<StdResult<Vec<_>>>()?;
+    if weights.iter().all(|w| w.is_zero()) {
+        return Err(ContractError::ZeroWeights {});
+    }
+    Ok(())
+}
+
+//--------------------------------------------------------------------------------------------------
+// Callbacks
+//--------------------------------------------------------------------------------------------------
+
+/// Allocates all idle deposit asset to the underlying vaults. The vaults below
+/// their target receive the idle asset in proportion to how far below target
+/// they are. If no vault is below target the idle asset is split by weight.
+pub fn execute_callback_allocate(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let holdings = Holdings::load(deps.as_ref(), &env)?;
+    if holdings.idle.is_zero() {
+        return Ok(Response::new());
+    }
+
+    let deficits = holdings
+        .vaults
+        .iter()
+        .zip(holdings.target_values()?)
+        .map(|(holding, target)| target.saturating_sub(holding.value))
+        .collect::<Vec<_>>();
+    let amounts = if deficits.iter().all(|x| x.is_zero()) {
+        let weights = holdings.vaults.iter().map(|x| x.vault.weight).collect::<Vec<_>>();
+        split_by_weights(holdings.idle, &weights)
+    } else {
+        split_by_ratios(holdings.idle, &deficits)
+    };
+
+    let cfg = CONFIG.load(deps.storage)?;
+    let deposit_denom = DEPOSIT_DENOM.load(deps.storage)?;
+    let deposit_asset = AssetInfo::Native(deposit_denom.clone());
+    let mut msgs = vec![];
+    let mut event = Event::new("apollo/vaults/meta_vault/allocate")
+        .add_attribute("action", "execute_callback_allocate");
+    for (holding, amount) in holdings.vaults.iter().zip(amounts) {
+        if amount.is_zero() {
+            continue;
+        }
+
+        let assets: AssetList = vec![Asset::new(deposit_asset.clone(), amount)].into();
+        let min_out =
+            min_lp_output(deps.as_ref(), &env, holding.vault.pool_id, amount, &deposit_denom)?;
+        msgs.append(&mut cfg.liquidity_helper.balancing_provide_liquidity(
+            assets,
+            min_out,
+            to_binary(&holding.vault.pool)?,
+            None,
+        )?);
+        msgs.push(
+            CallbackMsg::DepositLp {
+                vault: holding.vault.address.clone(),
+            }
+            .into_cosmos_msg(&env)?,
+        );
+        event = event.add_attribute(holding.vault.address.to_string(), amount);
+    }
+
+    Ok(Response::new().add_messages(msgs).add_event(event))
+}
+
+/// Deposits all base tokens of `vault` in the contract into `vault`.
+pub fn execute_callback_deposit_lp(
+    deps: DepsMut,
+    env: Env,
+    vault: Addr,
+) -> Result<Response, ContractError> {
+    let vault = VAULTS.load(deps.storage, &vault)?;
+    let amount = deps.querier.query_balance(&env.contract.address, &vault.base_token)?.amount;
+    if amount.is_zero() {
+        return Ok(Response::new());
+    }
+
+    let event = Event::new("apollo/vaults/meta_vault/allocate").add_attributes(vec![
+        attr("action", "execute_callback_deposit_lp"),
+        attr("vault", vault.address.to_string()),
+        attr("amount", amount),
+    ]);
+
+    Ok(Response::new()
+        .add_message(WasmMsg::Execute {
+            contract_addr: vault.address.to_string(),
+            msg: to_binary(&UnderlyingExecuteMsg::Deposit {
+                amount,
+                recipient: None,
+            })?,
+            funds: vec![coin(amount.u128(), vault.base_token)],
+        })
+        .add_event(event))
+}
+
+/// Mints shares to the recipient of the pending deposit for the value the
+/// deposit added to the vault.
+pub fn execute_callback_mint_shares(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let pending = PENDING_DEPOSIT.load(deps.storage)?;
+    PENDING_DEPOSIT.remove(deps.storage);
+
+    let value_after = Holdings::load(deps.as_ref(), &env)?.total_value()?;
+    let value_added = value_after.saturating_sub(pending.value_before);
+    let shares = shares_for_value(value_added, pending.value_before, pending.supply_before)?;
+    if shares.is_zero() {
+        return Err(StdError::generic_err("Deposit is too small to mint shares").into());
+    }
+    if shares < pending.min_shares {
+        return Err(ContractError::InsufficientShares {
+            shares,
+            min_shares: pending.min_shares,
+        });
+    }
+
+    let event = Event::new("apollo/vaults/meta_vault/deposit").add_attributes(vec![
+        attr("action", "execute_callback_mint_shares"),
+        attr("recipient", pending.recipient.to_string()),
+        attr("value_added", value_added),
+        attr("shares", shares),
+    ]);
+
+    let vault_token = VAULT_TOKEN.load(deps.storage)?;
+    Ok(vault_token.mint(deps, &env, &pending.recipient, shares)?.add_event(event))
+}
+
+/// Withdraws liquidity with all LP tokens of the underlying pools in the
+/// contract.
+pub fn execute_callback_withdraw_liquidity(
+    deps: DepsMut,
+    env: Env,
+) -> Result<Response, ContractError> {
+    let mut withdrawn: Vec<String> = vec![];
+    let mut responses = vec![];
+    for vault in VAULTS.range(deps.storage, None, None, Order::Ascending) {
+        let (_, vault) = vault?;
+        // Several vaults may share a base token
+        if withdrawn.contains(&vault.base_token) {
+            continue;
+        }
+
+        let amount = deps.querier.query_balance(&env.contract.address, &vault.base_token)?.amount;
+        if !amount.is_zero() {
+            responses.push(vault.pool.withdraw_liquidity(
+                deps.as_ref(),
+                &env,
+                Asset::new(vault.pool.lp_token(), amount),
+            )?);
+        }
+        withdrawn.push(vault.base_token);
+    }
+
+    let event = Event::new("apollo/vaults/meta_vault/rebalance")
+        .add_attribute("action", "execute_callback_withdraw_liquidity")
+        .add_attribute("lp_tokens", withdrawn.join(","));
+
+    Ok(merge_responses(responses).add_event(event))
+}
+
+/// Sells all assets of the underlying pools in the contract for the deposit
+/// asset, each in the pool it was withdrawn from.
+pub fn execute_callback_sell_pool_assets(
+    deps: DepsMut,
+    env: Env,
+) -> Result<Response, ContractError> {
+    let deposit_denom = DEPOSIT_DENOM.load(deps.storage)?;
+    let deposit_asset = AssetInfo::Native(deposit_denom.clone());
+    let mut sold: Vec<AssetInfo> = vec![deposit_asset.clone()];
+    let mut sold_assets = AssetList::new();
+    let mut responses = vec![];
+    for vault in VAULTS.range(deps.storage, None, None, Order::Ascending) {
+        let (_, vault) = vault?;
+        for info in vault.pool.pool_assets(deps.as_ref())? {
+            // Several pools may share an asset, and the whole balance is sold
+            // in the first one
+            if sold.contains(&info) {
+                continue;
+            }
+
+            let amount = info.query_balance(&deps.querier, &env.contract.address)?;
+            // Amounts too small to be worth anything at the TWAP price are
+            // left for a later rebalance
+            let min_out = match &info {
+                AssetInfo::Native(denom) if !amount.is_zero() => min_swap_output(
+                    deps.as_ref(),
+                    &env,
+                    vault.pool_id,
+                    denom,
+                    amount,
+                    &deposit_denom,
+                )?,
+                _ => Uint128::zero(),
+            };
+            if !min_out.is_zero() {
+                let asset = Asset::new(info.clone(), amount);
+                responses.push(vault.pool.swap(
+                    deps.as_ref(),
+                    &env,
+                    asset.clone(),
+                    deposit_asset.clone(),
+                    min_out,
+                )?);
+                sold_assets.add(&asset)?;
+            }
+            sold.push(info);
+        }
+    }
+
+    let event = Event::new("apollo/vaults/meta_vault/rebalance")
+        .add_attribute("action", "execute_callback_sell_pool_assets")
+        .add_attribute("sold_assets", sold_assets.to_string());
+
+    Ok(merge_responses(responses).add_event(event))
+}
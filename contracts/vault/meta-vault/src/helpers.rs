@@ -0,0 +1,325 @@
+use std::str::FromStr;
+
+use apollo_cw_asset::{Asset, AssetInfo};
+use base_vault::DEFAULT_VAULT_TOKENS_PER_STAKED_BASE_TOKEN;
+use cosmwasm_std::{Addr, Decimal, Deps, Env, Order, StdError, StdResult, Uint128};
+use cw_dex::osmosis::OsmosisPool;
+use cw_dex::traits::Pool;
+use cw_vault_standard::extensions::lockup::{LockupQueryMsg, UnlockingPosition};
+use cw_vault_standard::msg::VaultInfoResponse;
+use osmosis_std::types::osmosis::gamm::v1beta1::GammQuerier;
+use pablo_vault_types::twap::query_arithmetic_twap_price;
+use simple_vault::msg::ExtensionQueryMsg;
+
+use crate::msg::UnderlyingQueryMsg;
+use crate::state::{UnderlyingVault, CONFIG, DEPOSIT_DENOM, VAULTS};
+
+/// Page size used when querying unlocking positions of the underlying vaults.
+const UNLOCKING_POSITIONS_PAGE_SIZE: u32 = 30;
+
+/// The holdings of the meta-vault in an underlying vault.
+pub struct VaultHolding {
+    /// The underlying vault.
+    pub vault: UnderlyingVault,
+    /// The amount of vault tokens of the vault held by the meta-vault.
+    pub vault_tokens: Uint128,
+    /// The value of `vault_tokens` in the deposit asset.
+    pub value: Uint128,
+}
+
+/// Everything the meta-vault holds, valued in the deposit asset.
+pub struct Holdings {
+    /// The holdings in each underlying vault, in the order of the vaults map.
+    pub vaults: Vec<VaultHolding>,
+    /// Value of the base tokens in unlocking positions of the underlying
+    /// vaults.
+    pub unlocking_value: Uint128,
+    /// Unlocking positions that have finished unlocking, as the vault and
+    /// lockup ID.
+    pub matured: Vec<(Addr, u64)>,
+    /// Amount of deposit asset held by the contract.
+    pub idle: Uint128,
+}
+
+impl Holdings {
+    /// Loads the holdings of the meta-vault.
+    pub fn load(deps: Deps, env: &Env) -> StdResult<Self> {
+        let deposit_asset = AssetInfo::Native(DEPOSIT_DENOM.load(deps.storage)?);
+        let vaults = VAULTS
+            .range(deps.storage, None, None, Order::Ascending)
+            .map(|x| x.map(|(_, v)| v))
+            .collect::<StdResult<Vec<_>>>()?;
+
+        let mut holdings = Self {
+            vaults: Vec::with_capacity(vaults.len()),
+            unlocking_value: Uint128::zero(),
+            matured: vec![],
+            idle: deposit_asset.query_balance(&deps.querier, &env.contract.address)?,
+        };
+
+        for vault in vaults {
+            for position in query_unlocking_positions(deps, &vault.address, &env.contract.address)?
+            {
+                if position.release_at.is_expired(&env.block) {
+                    holdings.matured.push((vault.address.clone(), position.id));
+                }
+                holdings.unlocking_value = holdings.unlocking_value.checked_add(lp_value(
+                    deps,
+                    &vault.pool,
+                    position.base_token_amount,
+                    &deposit_asset,
+                )?)?;
+            }
+
+            let vault_tokens =
+                deps.querier.query_balance(&env.contract.address, &vault.vault_token)?.amount;
+            let value = if vault_tokens.is_zero() {
+                Uint128::zero()
+            } else {
+                let base_tokens: Uint128 = deps.querier.query_wasm_smart(
+                    &vault.address,
+                    &UnderlyingQueryMsg::ConvertToAssets {
+                        amount: vault_tokens,
+                    },
+                )?;
+                lp_value(deps, &vault.pool, base_tokens, &deposit_asset)?
+            };
+
+            holdings.vaults.push(VaultHolding {
+                vault,
+                vault_tokens,
+                value,
+            });
+        }
+
+        Ok(holdings)
+    }
+
+    /// The value of everything the meta-vault holds that can be redeemed
+    /// right away, i.e. excluding unlocking positions.
+    pub fn held_value(&self) -> StdResult<Uint128> {
+        self.vaults.iter().try_fold(self.idle, |acc, x| Ok(acc.checked_add(x.value)?))
+    }
+
+    /// The total value of the meta-vault.
+    pub fn total_value(&self) -> StdResult<Uint128> {
+        Ok(self.held_value()?.checked_add(self.unlocking_value)?)
+    }
+
+    /// The value each underlying vault should hold according to its weight.
+    pub fn target_values(&self) -> StdResult<Vec<Uint128>> {
+        let weights = self.vaults.iter().map(|x| x.vault.weight).collect::<Vec<_>>();
+        Ok(split_by_weights(self.total_value()?, &weights))
+    }
+}
+
+/// Queries all unlocking positions owned by `owner` in the underlying vault at
+/// `vault`.
+pub fn query_unlocking_positions(
+    deps: Deps,
+    vault: &Addr,
+    owner: &Addr,
+) -> StdResult<Vec<UnlockingPosition>> {
+    let mut positions: Vec<UnlockingPosition> = vec![];
+    loop {
+        let page: Vec<UnlockingPosition> = deps.querier.query_wasm_smart(
+            vault,
+            &UnderlyingQueryMsg::VaultExtension(ExtensionQueryMsg::Lockup(
+                LockupQueryMsg::UnlockingPositions {
+                    owner: owner.to_string(),
+                    start_after: positions.last().map(|x| x.id),
+                    limit: Some(UNLOCKING_POSITIONS_PAGE_SIZE),
+                },
+            )),
+        )?;
+        let done = page.len() < UNLOCKING_POSITIONS_PAGE_SIZE as usize;
+        positions.extend(page);
+        if done {
+            return Ok(positions);
+        }
+    }
+}
+
+/// Queries the vault info of the underlying vault at `vault`.
+pub fn query_vault_info(deps: Deps, vault: &Addr) -> StdResult<VaultInfoResponse> {
+    deps.querier.query_wasm_smart(vault, &UnderlyingQueryMsg::Info {})
+}
+
+/// Returns the value in `deposit_asset` of `lp_amount` LP tokens of `pool`,
+/// i.e. the amount of `deposit_asset` received when withdrawing the liquidity
+/// and selling the other assets in the same pool.
+pub fn lp_value(
+    deps: Deps,
+    pool: &OsmosisPool,
+    lp_amount: Uint128,
+    deposit_asset: &AssetInfo,
+) -> StdResult<Uint128> {
+    if lp_amount.is_zero() {
+        return Ok(Uint128::zero());
+    }
+
+    pool.simulate_withdraw_liquidity(deps, &Asset::new(pool.lp_token(), lp_amount))?
+        .into_iter()
+        .try_fold(Uint128::zero(), |acc, asset| {
+            let value = if &asset.info == deposit_asset || asset.amount.is_zero() {
+                asset.amount
+            } else {
+                pool.simulate_swap(deps, asset.clone(), deposit_asset.clone(), None)?
+            };
+            Ok(acc.checked_add(value)?)
+        })
+}
+
+/// Queries the arithmetic TWAP price of `denom` in `deposit_denom` in pool
+/// `pool_id`, i.e. the amount of deposit asset per unit of `denom`, over the
+/// TWAP window of the config.
+pub fn query_twap_price(
+    deps: Deps,
+    env: &Env,
+    pool_id: u64,
+    denom: &str,
+    deposit_denom: &str,
+) -> StdResult<Decimal> {
+    if denom == deposit_denom {
+        return Ok(Decimal::one());
+    }
+
+    let cfg = CONFIG.load(deps.storage)?;
+    let start_time = env.block.time.minus_seconds(cfg.twap_window);
+    query_arithmetic_twap_price(&deps.querier, pool_id, denom, deposit_denom, start_time)
+}
+
+/// Returns the minimum amount of deposit asset to accept when selling
+/// `amount` of `denom` in pool `pool_id`, i.e. the value at the TWAP price
+/// minus the max slippage.
+pub fn min_swap_output(
+    deps: Deps,
+    env: &Env,
+    pool_id: u64,
+    denom: &str,
+    amount: Uint128,
+    deposit_denom: &str,
+) -> StdResult<Uint128> {
+    let cfg = CONFIG.load(deps.storage)?;
+    let price = query_twap_price(deps, env, pool_id, denom, deposit_denom)?;
+    Ok(amount * price * (Decimal::one() - cfg.max_slippage))
+}
+
+/// Returns the minimum amount of LP tokens of pool `pool_id` to accept when
+/// providing `amount` of the deposit asset, i.e. the LP tokens worth `amount`
+/// at the TWAP prices of the pool assets minus the max slippage.
+pub fn min_lp_output(
+    deps: Deps,
+    env: &Env,
+    pool_id: u64,
+    amount: Uint128,
+    deposit_denom: &str,
+) -> StdResult<Uint128> {
+    let cfg = CONFIG.load(deps.storage)?;
+    let gamm = GammQuerier::new(&deps.querier);
+
+    let total_shares = match gamm.total_shares(pool_id)?.total_shares {
+        Some(coin) => Uint128::from_str(&coin.amount)?,
+        None => Uint128::zero(),
+    };
+    let mut pool_value = Uint128::zero();
+    for coin in gamm.total_pool_liquidity(pool_id)?.liquidity {
+        let price = query_twap_price(deps, env, pool_id, &coin.denom, deposit_denom)?;
+        pool_value = pool_value.checked_add(Uint128::from_str(&coin.amount)? * price)?;
+    }
+    if pool_value.is_zero() {
+        return Err(StdError::generic_err(format!("Pool {} has no liquidity", pool_id)));
+    }
+
+    Ok(total_shares.multiply_ratio(amount * (Decimal::one() - cfg.max_slippage), pool_value))
+}
+
+/// Splits `amount` proportionally to `weights`. The remainder of rounding down
+/// goes to the last entry with a non-zero weight. Returns all zeros if the
+/// weights sum to zero.
+pub fn split_by_weights(amount: Uint128, weights: &[Decimal]) -> Vec<Uint128> {
+    let atomics = weights.iter().map(|x| x.atomics()).collect::<Vec<_>>();
+    split_by_ratios(amount, &atomics)
+}
+
+/// Splits `amount` proportionally to `ratios`. The remainder of rounding down
+/// goes to the last entry with a non-zero ratio. Returns all zeros if the
+/// ratios sum to zero.
+pub fn split_by_ratios(amount: Uint128, ratios: &[Uint128]) -> Vec<Uint128> {
+    let total: Uint128 = ratios.iter().sum();
+    if total.is_zero() {
+        return vec![Uint128::zero(); ratios.len()];
+    }
+
+    let mut amounts = ratios.iter().map(|x| amount.multiply_ratio(*x, total)).collect::<Vec<_>>();
+    let remainder = amount - amounts.iter().sum::<Uint128>();
+    if let Some(i) = ratios.iter().rposition(|x| !x.is_zero()) {
+        amounts[i] += remainder;
+    }
+    amounts
+}
+
+/// Converts `value` of deposit asset to shares.
+pub fn shares_for_value(
+    value: Uint128,
+    total_value: Uint128,
+    supply: Uint128,
+) -> StdResult<Uint128> {
+    if total_value.is_zero() || supply.is_zero() {
+        Ok(value.checked_mul(DEFAULT_VAULT_TOKENS_PER_STAKED_BASE_TOKEN)?)
+    } else {
+        Ok(supply.multiply_ratio(value, total_value))
+    }
+}
+
+/// Converts `shares` to their value in the deposit asset.
+pub fn value_for_shares(
+    shares: Uint128,
+    total_value: Uint128,
+    supply: Uint128,
+) -> StdResult<Uint128> {
+    if supply.is_zero() {
+        Ok(shares.checked_div(DEFAULT_VAULT_TOKENS_PER_STAKED_BASE_TOKEN)?)
+    } else {
+        Ok(total_value.multiply_ratio(shares, supply))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::*;
+
+    #[test_case(100, &[1, 1], &[50, 50] ; "equal weights")]
+    #[test_case(100, &[1, 2], &[33, 67] ; "remainder to last")]
+    #[test_case(100, &[1, 2, 0], &[33, 67, 0] ; "remainder skips zero weight")]
+    #[test_case(100, &[0, 0], &[0, 0] ; "zero weights")]
+    #[test_case(0, &[1, 3], &[0, 0] ; "zero amount")]
+    fn test_split_by_ratios(amount: u128, ratios: &[u128], expected: &[u128]) {
+        let ratios = ratios.iter().map(|x| Uint128::new(*x)).collect::<Vec<_>>();
+        let expected = expected.iter().map(|x| Uint128::new(*x)).collect::<Vec<_>>();
+        assert_eq!(split_by_ratios(Uint128::new(amount), &ratios), expected);
+    }
+
+    #[test]
+    fn test_split_by_weights() {
+        let weights = [Decimal::percent(25), Decimal::percent(75)];
+        assert_eq!(
+            split_by_weights(Uint128::new(1000), &weights),
+            vec![Uint128::new(250), Uint128::new(750)]
+        );
+    }
+
+    #[test]
+    fn test_shares_value_roundtrip() {
+        // Initial deposit uses the default ratio
+        let shares = shares_for_value(Uint128::new(10), Uint128::zero(), Uint128::zero()).unwrap();
+        assert_eq!(shares, Uint128::new(10) * DEFAULT_VAULT_TOKENS_PER_STAKED_BASE_TOKEN);
+
+        let total_value = Uint128::new(1000);
+        let supply = Uint128::new(1000) * DEFAULT_VAULT_TOKENS_PER_STAKED_BASE_TOKEN;
+        let shares = shares_for_value(Uint128::new(100), total_value, supply).unwrap();
+        assert_eq!(value_for_shares(shares, total_value, supply).unwrap(), Uint128::new(100));
+    }
+}
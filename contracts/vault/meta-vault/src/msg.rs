@@ -0,0 +1,180 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{to_binary, Addr, CosmosMsg, Decimal, Env, StdResult, Uint128, WasmMsg};
+use cw_vault_standard::{VaultStandardExecuteMsg, VaultStandardQueryMsg};
+
+use crate::state::{Config, ConfigUnchecked};
+
+/// ExecuteMsg for the meta-vault.
+///
+/// `Deposit` takes the deposit asset in the funds and allocates it to the
+/// underlying vaults. `Redeem` sends the share of the underlying vault tokens
+/// and idle deposit asset represented by the shares to the recipient.
+pub type ExecuteMsg = VaultStandardExecuteMsg<ExtensionExecuteMsg>;
+
+/// QueryMsg for the meta-vault. `TotalAssets`, `ConvertToShares` and
+/// `ConvertToAssets` are denominated in the deposit asset.
+pub type QueryMsg = VaultStandardQueryMsg<ExtensionQueryMsg>;
+
+/// ExecuteMsg of the underlying vaults.
+pub type UnderlyingExecuteMsg = VaultStandardExecuteMsg<simple_vault::msg::ExtensionExecuteMsg>;
+
+/// QueryMsg of the underlying vaults.
+pub type UnderlyingQueryMsg = VaultStandardQueryMsg<simple_vault::msg::ExtensionQueryMsg>;
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    /// Address that is allowed to update config and vaults.
+    pub admin: String,
+    /// The native denom that is deposited into the vault.
+    pub deposit_denom: String,
+    /// The underlying vaults and their target weights.
+    pub vaults: Vec<VaultWeight>,
+    /// Configurable parameters for the contract.
+    pub config: ConfigUnchecked,
+    /// The subdenom that will be used for the native vault token, e.g.
+    /// the denom of the vault token will be:
+    /// "factory/{vault_contract}/{vault_token_subdenom}".
+    pub vault_token_subdenom: String,
+}
+
+/// An underlying vault and its target weight.
+#[cw_serde]
+pub struct VaultWeight {
+    /// The address of the underlying vault.
+    pub address: String,
+    /// The target weight of the vault.
+    pub weight: Decimal,
+}
+
+/// Extension execute messages for the meta-vault.
+#[cw_serde]
+pub enum ExtensionExecuteMsg {
+    /// Execute a callback message.
+    Callback(CallbackMsg),
+    /// Execute a meta-vault specific message.
+    Meta(MetaExecuteMsg),
+}
+
+/// Meta-vault specific messages.
+#[cw_serde]
+pub enum MetaExecuteMsg {
+    /// Deposit like the standard `Deposit`, but fail if fewer than
+    /// `min_shares` shares would be minted.
+    Deposit {
+        /// The amount of the deposit asset to deposit.
+        amount: Uint128,
+        /// The minimum amount of shares to mint.
+        min_shares: Uint128,
+        /// The recipient of the shares. Defaults to the sender.
+        recipient: Option<String>,
+    },
+    /// Move the allocation towards the target weights. Can only be called by
+    /// the admin or a keeper. The value of vaults above their target is
+    /// unlocked, and unlocking that has finished is withdrawn and allocated
+    /// to the vaults below their target.
+    Rebalance {},
+    /// Replace the set of underlying vaults and their weights. Can only be
+    /// called by the admin. Vaults can only be removed once the meta-vault
+    /// holds no assets in them.
+    UpdateVaults {
+        /// The new underlying vaults and their weights.
+        vaults: Vec<VaultWeight>,
+    },
+    /// Update the config. Can only be called by the admin.
+    UpdateConfig {
+        /// The new config.
+        config: ConfigUnchecked,
+    },
+    /// Update the admin. Can only be called by the admin.
+    UpdateAdmin {
+        /// The new admin address.
+        address: String,
+    },
+}
+
+/// Callback messages, only callable by the vault itself.
+#[cw_serde]
+pub enum CallbackMsg {
+    /// Allocate all idle deposit asset to the underlying vaults, favouring
+    /// the vaults furthest below their target.
+    Allocate {},
+    /// Deposit all base tokens of `vault` in the contract into `vault`.
+    DepositLp {
+        /// The vault to deposit into.
+        vault: Addr,
+    },
+    /// Mint shares for the value added by the pending deposit.
+    MintShares {},
+    /// Withdraw liquidity with all LP tokens of the underlying pools in the
+    /// contract.
+    WithdrawLiquidity {},
+    /// Sell all assets of the underlying pools in the contract for the
+    /// deposit asset.
+    SellPoolAssets {},
+}
+
+impl CallbackMsg {
+    /// Convert the callback message to a [`CosmosMsg`] executing the vault
+    /// itself.
+    pub fn into_cosmos_msg(&self, env: &Env) -> StdResult<CosmosMsg> {
+        Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_binary(&VaultStandardExecuteMsg::VaultExtension(
+                ExtensionExecuteMsg::Callback(self.clone()),
+            ))?,
+            funds: vec![],
+        }))
+    }
+}
+
+/// Extension query messages for the meta-vault.
+#[cw_serde]
+pub enum ExtensionQueryMsg {
+    /// Meta-vault specific queries.
+    Meta(MetaQueryMsg),
+}
+
+/// Meta-vault specific queries.
+#[cw_serde]
+pub enum MetaQueryMsg {
+    /// Returns the current and target allocation as an
+    /// [`AllocationResponse`].
+    Allocation {},
+    /// Returns the [`Config`] of the vault.
+    Config {},
+}
+
+/// Response to [`MetaQueryMsg::Allocation`]. All values are denominated in the
+/// deposit asset.
+#[cw_serde]
+pub struct AllocationResponse {
+    /// The allocation to each underlying vault.
+    pub vaults: Vec<VaultAllocation>,
+    /// Value of the base tokens that are unlocking from the underlying vaults.
+    pub unlocking_value: Uint128,
+    /// Amount of deposit asset held by the contract.
+    pub idle_amount: Uint128,
+    /// Total value of the vault.
+    pub total_value: Uint128,
+}
+
+/// The allocation to an underlying vault.
+#[cw_serde]
+pub struct VaultAllocation {
+    /// The address of the vault.
+    pub address: String,
+    /// The target weight of the vault.
+    pub weight: Decimal,
+    /// The amount of vault tokens of the vault held by the meta-vault.
+    pub vault_token_amount: Uint128,
+    /// The value of the held vault tokens.
+    pub value: Uint128,
+    /// The value the vault should hold according to its weight.
+    pub target_value: Uint128,
+}
+
+/// Response to [`MetaQueryMsg::Config`].
+pub type ConfigResponse = Config;
+
+#[cw_serde]
+pub struct MigrateMsg {}
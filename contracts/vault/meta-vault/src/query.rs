@@ -0,0 +1,48 @@
+use cosmwasm_std::{Deps, Env, StdResult, Uint128};
+use cw_vault_token::VaultToken;
+
+use crate::helpers::{shares_for_value, value_for_shares, Holdings};
+use crate::msg::{AllocationResponse, VaultAllocation};
+use crate::state::VAULT_TOKEN;
+
+/// Returns the current and target allocation of the vault.
+pub fn query_allocation(deps: Deps, env: Env) -> StdResult<AllocationResponse> {
+    let holdings = Holdings::load(deps, &env)?;
+    let targets = holdings.target_values()?;
+
+    Ok(AllocationResponse {
+        total_value: holdings.total_value()?,
+        unlocking_value: holdings.unlocking_value,
+        idle_amount: holdings.idle,
+        vaults: holdings
+            .vaults
+            .into_iter()
+            .zip(targets)
+            .map(|(holding, target_value)| VaultAllocation {
+                address: holding.vault.address.to_string(),
+                weight: holding.vault.weight,
+                vault_token_amount: holding.vault_tokens,
+                value: holding.value,
+                target_value,
+            })
+            .collect(),
+    })
+}
+
+/// Returns the total value of the vault in the deposit asset.
+pub fn query_total_assets(deps: Deps, env: Env) -> StdResult<Uint128> {
+    Holdings::load(deps, &env)?.total_value()
+}
+
+/// Returns the amount of shares that `amount` of deposit asset would be
+/// converted to, not accounting for the slippage of allocating it.
+pub fn query_convert_to_shares(deps: Deps, env: Env, amount: Uint128) -> StdResult<Uint128> {
+    let supply = VAULT_TOKEN.load(deps.storage)?.query_total_supply(deps)?;
+    shares_for_value(amount, Holdings::load(deps, &env)?.total_value()?, supply)
+}
+
+/// Returns the value of `amount` shares in the deposit asset.
+pub fn query_convert_to_assets(deps: Deps, env: Env, amount: Uint128) -> StdResult<Uint128> {
+    let supply = VAULT_TOKEN.load(deps.storage)?.query_total_supply(deps)?;
+    value_for_shares(amount, Holdings::load(deps, &env)?.total_value()?, supply)
+}
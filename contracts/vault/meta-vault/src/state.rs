@@ -0,0 +1,122 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Decimal, Deps, StdError, StdResult, Uint128};
+use cw_controllers::Admin;
+use cw_dex::osmosis::OsmosisPool;
+use cw_storage_plus::{Item, Map};
+use cw_vault_token::osmosis::OsmosisDenom;
+use liquidity_helper::LiquidityHelperBase;
+
+//--------------------------------------------------------------------------------------------------
+// Config
+//--------------------------------------------------------------------------------------------------
+
+/// Base config struct for the contract.
+#[cw_serde]
+pub struct ConfigBase<T> {
+    /// Addresses that are allowed to rebalance the vault in addition to the
+    /// admin.
+    pub keepers: Vec<T>,
+    /// Helper used to provide liquidity with the deposit asset alone.
+    pub liquidity_helper: LiquidityHelperBase<T>,
+    /// Share of the total value that the value of an underlying vault must
+    /// exceed its target by before `Rebalance` unlocks the excess.
+    pub rebalance_threshold: Decimal,
+    /// The maximum deviation from the TWAP prices of the underlying pools
+    /// accepted when providing liquidity and selling pool assets. Providing
+    /// liquidity with the deposit asset alone swaps part of it, so this must
+    /// also cover the swap fee of the pools.
+    pub max_slippage: Decimal,
+    /// The window in seconds of the TWAP prices the minimum amounts of
+    /// provided liquidity and swaps are derived from.
+    pub twap_window: u64,
+}
+
+/// Config with non-validated addresses.
+pub type ConfigUnchecked = ConfigBase<String>;
+/// Config with validated addresses.
+pub type Config = ConfigBase<Addr>;
+
+impl ConfigUnchecked {
+    /// Validate the addresses and values of the config.
+    pub fn check(&self, deps: Deps) -> StdResult<Config> {
+        if self.rebalance_threshold > Decimal::one() {
+            return Err(StdError::generic_err("Rebalance threshold cannot be greater than 100%"));
+        }
+        if self.max_slippage >= Decimal::one() {
+            return Err(StdError::generic_err("Max slippage must be less than 100%"));
+        }
+        if self.twap_window == 0 {
+            return Err(StdError::generic_err("TWAP window cannot be zero"));
+        }
+
+        Ok(Config {
+            keepers: self
+                .keepers
+                .iter()
+                .map(|x| deps.api.addr_validate(x))
+                .collect::<StdResult<_>>()?,
+            liquidity_helper: self.liquidity_helper.check(deps.api)?,
+            rebalance_threshold: self.rebalance_threshold,
+            max_slippage: self.max_slippage,
+            twap_window: self.twap_window,
+        })
+    }
+}
+
+impl Config {
+    /// Whether `addr` is allowed to rebalance the vault, not counting the
+    /// admin.
+    pub fn is_keeper(&self, addr: &Addr) -> bool {
+        self.keepers.contains(addr)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Underlying vaults
+//--------------------------------------------------------------------------------------------------
+
+/// An underlying vault that the deposit asset is allocated to.
+#[cw_serde]
+pub struct UnderlyingVault {
+    /// The address of the vault.
+    pub address: Addr,
+    /// The target weight of the vault. Targets are the weights divided by the
+    /// sum of all weights.
+    pub weight: Decimal,
+    /// The base token of the vault, an LP token of `pool`.
+    pub base_token: String,
+    /// The vault token of the vault.
+    pub vault_token: String,
+    /// The ID of the pool of the base token.
+    pub pool_id: u64,
+    /// The pool of the base token. The deposit asset is one of its assets.
+    pub pool: OsmosisPool,
+}
+
+/// A deposit that is being allocated to the underlying vaults. Saved before
+/// allocating and removed when the shares are minted.
+#[cw_serde]
+pub struct PendingDeposit {
+    /// The recipient of the shares.
+    pub recipient: Addr,
+    /// The total value of the vault before the deposit.
+    pub value_before: Uint128,
+    /// The share supply before the deposit.
+    pub supply_before: Uint128,
+    /// The minimum amount of shares to mint for the deposit.
+    pub min_shares: Uint128,
+}
+
+/// The admin address that is allowed to update the config and vaults.
+pub const ADMIN: Admin = Admin::new("admin");
+/// The config of the vault.
+pub const CONFIG: Item<Config> = Item::new("config");
+/// The denom of the asset that is deposited and that values are denominated
+/// in.
+pub const DEPOSIT_DENOM: Item<String> = Item::new("deposit_denom");
+/// The underlying vaults, keyed by address.
+pub const VAULTS: Map<&Addr, UnderlyingVault> = Map::new("vaults");
+/// The shares of the vault.
+pub const VAULT_TOKEN: Item<OsmosisDenom> = Item::new("vault_token");
+/// The deposit currently being allocated.
+pub const PENDING_DEPOSIT: Item<PendingDeposit> = Item::new("pending_deposit");
@@ -0,0 +1,234 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    to_binary, Binary, Deps, DepsMut, Env, Event, MessageInfo, Reply, Response, StdError,
+    StdResult, SubMsgResponse, SubMsgResult, Uint128,
+};
+use cw2::{get_contract_version, set_contract_version};
+use cw_vault_standard::msg::{VaultInfoResponse, VaultStandardInfoResponse};
+use cw_vault_token::osmosis::OsmosisDenom;
+use cw_vault_token::VaultToken;
+use semver::Version;
+
+use crate::error::ContractError;
+use crate::execute::{
+    execute_callback_add_to_position, execute_callback_create_position,
+    execute_callback_provide_deposit, execute_callback_sell_rewards,
+    execute_callback_swap_to_position_ratio, execute_compound, execute_deposit, execute_redeem,
+    execute_update_range, query_cl_pool, reply_collect, reply_compound, reply_compound_failure,
+    reply_deposit, reply_redeem, reply_update_range_create, reply_update_range_withdraw,
+    COLLECT_INCENTIVES_REPLY_ID, COLLECT_SPREAD_REWARDS_REPLY_ID, COMPOUND_FAILURE_REPLY_ID,
+    COMPOUND_REPLY_ID, DEPOSIT_ADD_TO_POSITION_REPLY_ID, DEPOSIT_CREATE_POSITION_REPLY_ID,
+    REDEEM_REPLY_ID, UPDATE_RANGE_CREATE_POSITION_REPLY_ID, UPDATE_RANGE_WITHDRAW_REPLY_ID,
+};
+use crate::msg::{
+    CallbackMsg, ClExecuteMsg, ClQueryMsg, ExecuteMsg, ExtensionExecuteMsg, ExtensionQueryMsg,
+    InstantiateMsg, MigrateMsg, QueryMsg,
+};
+use crate::query::{
+    query_convert_to_assets, query_convert_to_shares, query_in_range, query_range,
+    query_total_assets, query_vault_position,
+};
+use crate::state::{PoolConfig, Range, ADMIN, CONFIG, POOL, RANGE, VAULT_TOKEN};
+
+// version info for migration info
+const CONTRACT_NAME: &str = "crates.io:osmosis-cl-vault";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Constants passed to VaultStandardInfo query
+const VAULT_STANDARD_VERSION: u16 = 1;
+const VAULT_STANDARD_EXTENSIONS: [&str; 1] = ["concentrated-liquidity"];
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    let admin_addr = deps.api.addr_validate(&msg.admin)?;
+    let config = msg.config.check(deps.as_ref())?;
+
+    // Validate that 10 osmo for vault token creation are sent
+    let osmo_amount = info
+        .funds
+        .iter()
+        .find(|coin| coin.denom == "uosmo")
+        .map(|coin| coin.amount)
+        .unwrap_or_default();
+    if osmo_amount < Uint128::new(10_000_000) {
+        return Err(StdError::generic_err(
+            "A minimum of 10_000_000 uosmo must be sent to create the vault token",
+        )
+        .into());
+    }
+
+    let pool = query_cl_pool(deps.as_ref(), msg.pool_id)?;
+    let range = msg.range.unwrap_or_else(Range::full);
+    range.validate(pool.tick_spacing)?;
+
+    ADMIN.set(deps.branch(), Some(admin_addr))?;
+    CONFIG.save(deps.storage, &config)?;
+    POOL.save(
+        deps.storage,
+        &PoolConfig {
+            pool_id: msg.pool_id,
+            token0: pool.token0,
+            token1: pool.token1,
+        },
+    )?;
+    RANGE.save(deps.storage, &range)?;
+
+    let vault_token = OsmosisDenom::new(env.contract.address.to_string(), msg.vault_token_subdenom);
+    VAULT_TOKEN.save(deps.storage, &vault_token)?;
+
+    Ok(vault_token.instantiate(deps, None)?)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::Deposit {
+            amount,
+            recipient,
+        } => execute_deposit(deps, env, info, amount, recipient),
+        ExecuteMsg::Redeem {
+            recipient,
+            amount,
+        } => execute_redeem(deps, env, info, amount, recipient),
+        ExecuteMsg::VaultExtension(msg) => match msg {
+            ExtensionExecuteMsg::ConcentratedLiquidity(msg) => match msg {
+                ClExecuteMsg::Compound {} => execute_compound(deps, env),
+                ClExecuteMsg::UpdateRange {
+                    range,
+                } => execute_update_range(deps, env, info, range),
+                ClExecuteMsg::UpdateConfig {
+                    config,
+                } => {
+                    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+                    CONFIG.save(deps.storage, &config.check(deps.as_ref())?)?;
+                    let event = Event::new("apollo/vaults/osmosis_cl/update_config")
+                        .add_attribute("action", "execute_update_config");
+                    Ok(Response::new().add_event(event))
+                }
+                ClExecuteMsg::UpdateAdmin {
+                    address,
+                } => {
+                    let new_admin = deps.api.addr_validate(&address)?;
+                    Ok(ADMIN.execute_update_admin(deps, info, Some(new_admin))?)
+                }
+            },
+            ExtensionExecuteMsg::Callback(msg) => {
+                // Assert that only the contract itself can call this
+                if info.sender != env.contract.address {
+                    return Err(ContractError::Unauthorized {});
+                }
+
+                match msg {
+                    CallbackMsg::SellRewards {} => execute_callback_sell_rewards(deps, env),
+                    CallbackMsg::SwapToPositionRatio {} => {
+                        execute_callback_swap_to_position_ratio(deps, env)
+                    }
+                    CallbackMsg::AddToPosition {} => execute_callback_add_to_position(deps, env),
+                    CallbackMsg::ProvideDeposit {} => execute_callback_provide_deposit(deps, env),
+                    CallbackMsg::CreatePosition {} => execute_callback_create_position(deps, env),
+                }
+            }
+        },
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::VaultStandardInfo {} => to_binary(&VaultStandardInfoResponse {
+            version: VAULT_STANDARD_VERSION,
+            extensions: VAULT_STANDARD_EXTENSIONS.iter().map(|&s| s.into()).collect(),
+        }),
+        QueryMsg::Info {} => to_binary(&VaultInfoResponse {
+            base_token: POOL.load(deps.storage)?.token0,
+            vault_token: VAULT_TOKEN.load(deps.storage)?.to_string(),
+        }),
+        QueryMsg::PreviewDeposit {
+            amount: _,
+        } => Err(StdError::generic_err(
+            "PreviewDeposit is not supported, the liquidity added depends on the pool price",
+        )),
+        QueryMsg::PreviewRedeem {
+            amount,
+        } => to_binary(&query_convert_to_assets(deps, amount)?),
+        QueryMsg::TotalAssets {} => to_binary(&query_total_assets(deps)?),
+        QueryMsg::TotalVaultTokenSupply {} => {
+            to_binary(&VAULT_TOKEN.load(deps.storage)?.query_total_supply(deps)?)
+        }
+        QueryMsg::ConvertToShares {
+            amount,
+        } => to_binary(&query_convert_to_shares(deps, amount)?),
+        QueryMsg::ConvertToAssets {
+            amount,
+        } => to_binary(&query_convert_to_assets(deps, amount)?),
+        QueryMsg::VaultExtension(msg) => match msg {
+            ExtensionQueryMsg::ConcentratedLiquidity(msg) => match msg {
+                ClQueryMsg::Range {} => to_binary(&query_range(deps)?),
+                ClQueryMsg::InRange {} => to_binary(&query_in_range(deps)?),
+                ClQueryMsg::Position {} => to_binary(&query_vault_position(deps)?),
+                ClQueryMsg::Config {} => to_binary(&CONFIG.load(deps.storage)?),
+            },
+        },
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, reply: Reply) -> Result<Response, ContractError> {
+    let data = match reply.result {
+        // The compound before a deposit only replies on error, which must not
+        // fail the deposit
+        SubMsgResult::Err(e) if reply.id == COMPOUND_FAILURE_REPLY_ID => {
+            return reply_compound_failure(e)
+        }
+        SubMsgResult::Err(e) => return Err(ContractError::Std(StdError::generic_err(e))),
+        SubMsgResult::Ok(SubMsgResponse {
+            data,
+            events: _,
+        }) => data,
+    };
+
+    match reply.id {
+        // Nothing may have been collected, in which case there is no data
+        COLLECT_SPREAD_REWARDS_REPLY_ID | COLLECT_INCENTIVES_REPLY_ID => {
+            reply_collect(deps, reply.id, data)
+        }
+        UPDATE_RANGE_WITHDRAW_REPLY_ID => reply_update_range_withdraw(deps, env),
+        id => {
+            let data = data.ok_or(ContractError::NoDataInSubMsgResponse {})?;
+            match id {
+                COMPOUND_REPLY_ID => reply_compound(deps, data),
+                DEPOSIT_CREATE_POSITION_REPLY_ID | DEPOSIT_ADD_TO_POSITION_REPLY_ID => {
+                    reply_deposit(deps, env, id, data)
+                }
+                REDEEM_REPLY_ID => reply_redeem(deps, data),
+                UPDATE_RANGE_CREATE_POSITION_REPLY_ID => reply_update_range_create(deps, data),
+                id => Err(ContractError::UnknownReplyId(id)),
+            }
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let version: Version = CONTRACT_VERSION.parse()?;
+    let storage_version: Version = get_contract_version(deps.storage)?.version.parse()?;
+
+    // migrate only if newer
+    if storage_version < version {
+        set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    }
+    Ok(Response::default())
+}
@@ -0,0 +1,55 @@
+use cosmwasm_std::{Coin, OverflowError, StdError};
+use cw_controllers::AdminError;
+use cw_vault_token::CwTokenError;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+
+    #[error("{0}")]
+    CwTokenError(#[from] CwTokenError),
+
+    #[error("{0}")]
+    AdminError(#[from] AdminError),
+
+    #[error("{0}")]
+    SemVer(String),
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("Unknown reply ID: {0}")]
+    UnknownReplyId(u64),
+
+    #[error("No data in SubMsgResponse")]
+    NoDataInSubMsgResponse {},
+
+    #[error("Unexpected funds sent. Expected: {expected:?}, Actual: {actual:?}")]
+    UnexpectedFunds {
+        expected: Vec<Coin>,
+        actual: Vec<Coin>,
+    },
+
+    #[error("Invalid range [{lower_tick}, {upper_tick}). Ticks must be multiples of the tick spacing {tick_spacing} within [{min_tick}, {max_tick}]")]
+    InvalidRange {
+        lower_tick: i64,
+        upper_tick: i64,
+        tick_spacing: u64,
+        min_tick: i64,
+        max_tick: i64,
+    },
+
+    #[error("The vault has no position")]
+    NoPosition {},
+}
+
+impl From<semver::Error> for ContractError {
+    fn from(err: semver::Error) -> Self {
+        Self::SemVer(err.to_string())
+    }
+}
@@ -0,0 +1,857 @@
+use std::str::FromStr;
+
+use apollo_cw_asset::{Asset, AssetInfo, AssetList};
+use cosmwasm_std::{
+    attr, coin, BankMsg, Binary, Coin, Decimal, Decimal256, Deps, DepsMut, Env, Event,
+    MessageInfo, Response, StdError, StdResult, SubMsg, Uint128, Uint256,
+};
+use cw_vault_token::VaultToken;
+use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::{
+    ConcentratedliquidityQuerier, FullPositionBreakdown, MsgAddToPosition,
+    MsgAddToPositionResponse, MsgCollectIncentives, MsgCollectIncentivesResponse,
+    MsgCollectSpreadRewards, MsgCollectSpreadRewardsResponse, MsgCreatePosition,
+    MsgCreatePositionResponse, MsgWithdrawPosition, MsgWithdrawPositionResponse, Pool,
+};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{
+    MsgSwapExactAmountIn, PoolmanagerQuerier, SwapAmountInRoute,
+};
+use pablo_vault_types::twap::query_arithmetic_twap_price;
+use prost::Message;
+
+use crate::error::ContractError;
+use crate::math::{amounts_used, swap_to_ratio};
+use crate::msg::CallbackMsg;
+use crate::state::{
+    PendingDeposit, Range, ADMIN, CONFIG, PENDING_DEPOSIT, PENDING_RANGE, PENDING_REDEEM_RECIPIENT,
+    POOL, POSITION_ID, RANGE, VAULT_TOKEN,
+};
+
+pub const COLLECT_SPREAD_REWARDS_REPLY_ID: u64 = 1;
+pub const COLLECT_INCENTIVES_REPLY_ID: u64 = 2;
+pub const COMPOUND_REPLY_ID: u64 = 3;
+pub const DEPOSIT_CREATE_POSITION_REPLY_ID: u64 = 4;
+pub const DEPOSIT_ADD_TO_POSITION_REPLY_ID: u64 = 5;
+pub const REDEEM_REPLY_ID: u64 = 6;
+pub const UPDATE_RANGE_WITHDRAW_REPLY_ID: u64 = 7;
+pub const UPDATE_RANGE_CREATE_POSITION_REPLY_ID: u64 = 8;
+pub const COMPOUND_FAILURE_REPLY_ID: u64 = 9;
+
+//--------------------------------------------------------------------------------------------------
+// Helpers
+//--------------------------------------------------------------------------------------------------
+
+/// Queries the concentrated liquidity pool with ID `pool_id`.
+pub fn query_cl_pool(deps: Deps, pool_id: u64) -> StdResult<Pool> {
+    let pool = PoolmanagerQuerier::new(&deps.querier)
+        .pool(pool_id)?
+        .pool
+        .ok_or_else(|| StdError::generic_err(format!("Pool {} not found", pool_id)))?;
+
+    Pool::decode(pool.value.as_slice()).map_err(|_| {
+        StdError::generic_err(format!("Pool {} is not a concentrated liquidity pool", pool_id))
+    })
+}
+
+/// Queries the position of the vault. Returns `None` if the vault has no
+/// position.
+pub fn query_position(deps: Deps) -> StdResult<Option<FullPositionBreakdown>> {
+    POSITION_ID
+        .may_load(deps.storage)?
+        .map(|position_id| {
+            ConcentratedliquidityQuerier::new(&deps.querier)
+                .position_by_id(position_id)?
+                .position
+                .ok_or_else(|| StdError::generic_err(format!("Position {} not found", position_id)))
+        })
+        .transpose()
+}
+
+/// Returns the liquidity of the position of the vault, or zero if the vault
+/// has no position.
+pub fn query_position_liquidity(deps: Deps) -> StdResult<Decimal256> {
+    query_position(deps)?
+        .and_then(|breakdown| breakdown.position)
+        .map_or(Ok(Decimal256::zero()), |position| Decimal256::from_str(&position.liquidity))
+}
+
+/// Returns the amount of vault tokens to mint for `added` liquidity, given
+/// the liquidity of the position and the vault token supply before.
+pub fn vault_tokens_for_liquidity(
+    added: Decimal256,
+    liquidity_before: Decimal256,
+    vault_token_supply: Uint128,
+) -> StdResult<Uint128> {
+    if liquidity_before.is_zero() || vault_token_supply.is_zero() {
+        return Ok((Uint256::one() * added).try_into()?);
+    }
+    Ok(Uint256::from(vault_token_supply)
+        .multiply_ratio(added.atomics(), liquidity_before.atomics())
+        .try_into()?)
+}
+
+/// Returns the liquidity represented by `vault_tokens`.
+pub fn liquidity_for_vault_tokens(
+    vault_tokens: Uint128,
+    total_liquidity: Decimal256,
+    vault_token_supply: Uint128,
+) -> Decimal256 {
+    if vault_token_supply.is_zero() {
+        return Decimal256::zero();
+    }
+    Decimal256::new(total_liquidity.atomics().multiply_ratio(vault_tokens, vault_token_supply))
+}
+
+/// Returns the non-zero coins of the two pool assets sorted by denom.
+fn pool_coins(denom0: &str, amount0: Uint128, denom1: &str, amount1: Uint128) -> Vec<Coin> {
+    let mut coins: Vec<Coin> = vec![coin(amount0.u128(), denom0), coin(amount1.u128(), denom1)]
+        .into_iter()
+        .filter(|c| !c.amount.is_zero())
+        .collect();
+    coins.sort_by(|a, b| a.denom.cmp(&b.denom));
+    coins
+}
+
+fn parse_amount(amount: &str) -> StdResult<Uint128> {
+    Uint128::from_str(amount)
+}
+
+/// Queries the arithmetic TWAP price of token0 in token1, i.e. the amount of
+/// token1 per token0 as in the square root price of the pool, over the TWAP
+/// window of the config.
+pub fn query_twap_price(deps: Deps, env: &Env) -> StdResult<Decimal256> {
+    let cfg = CONFIG.load(deps.storage)?;
+    let pool = POOL.load(deps.storage)?;
+    let start_time = env.block.time.minus_seconds(cfg.twap_window);
+
+    let price: Decimal256 = query_arithmetic_twap_price(
+        &deps.querier,
+        pool.pool_id,
+        &pool.token0,
+        &pool.token1,
+        start_time,
+    )?;
+    if price.is_zero() {
+        return Err(StdError::generic_err("TWAP price of the pool is zero"));
+    }
+    Ok(price)
+}
+
+/// Returns the minimum amounts of token0 and token1 to accept when adding
+/// `amount0` and `amount1` to a position in `range`, i.e. the amounts used at
+/// the TWAP price minus the max slippage.
+fn min_amounts(
+    deps: Deps,
+    env: &Env,
+    range: &Range,
+    amount0: Uint128,
+    amount1: Uint128,
+) -> StdResult<(Uint128, Uint128)> {
+    let cfg = CONFIG.load(deps.storage)?;
+    let twap_sqrt_price = query_twap_price(deps, env)?.sqrt();
+    let (used0, used1) = amounts_used(twap_sqrt_price, range, amount0, amount1)?;
+
+    let ratio = Decimal::one() - cfg.max_slippage;
+    Ok((used0 * ratio, used1 * ratio))
+}
+
+/// Returns the balances of the pool assets held by the contract, excluding a
+/// pending deposit.
+fn available_balances(deps: Deps, env: &Env) -> Result<(Uint128, Uint128), ContractError> {
+    let pool = POOL.load(deps.storage)?;
+    let pending = PENDING_DEPOSIT.may_load(deps.storage)?;
+
+    let balance0 = deps
+        .querier
+        .query_balance(&env.contract.address, &pool.token0)?
+        .amount
+        .checked_sub(pending.as_ref().map(|x| x.amount0).unwrap_or_default())?;
+    let balance1 = deps
+        .querier
+        .query_balance(&env.contract.address, &pool.token1)?
+        .amount
+        .checked_sub(pending.as_ref().map(|x| x.amount1).unwrap_or_default())?;
+    Ok((balance0, balance1))
+}
+
+fn create_position_msg(
+    deps: Deps,
+    env: &Env,
+    range: &Range,
+    amount0: Uint128,
+    amount1: Uint128,
+    reply_id: u64,
+) -> StdResult<SubMsg> {
+    let pool = POOL.load(deps.storage)?;
+    let (min_amount0, min_amount1) = min_amounts(deps, env, range, amount0, amount1)?;
+    let msg = MsgCreatePosition {
+        pool_id: pool.pool_id,
+        sender: env.contract.address.to_string(),
+        lower_tick: range.lower_tick,
+        upper_tick: range.upper_tick,
+        tokens_provided: pool_coins(&pool.token0, amount0, &pool.token1, amount1)
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+        token_min_amount0: min_amount0.to_string(),
+        token_min_amount1: min_amount1.to_string(),
+    };
+    Ok(SubMsg::reply_on_success(msg, reply_id))
+}
+
+fn add_to_position_msg(
+    deps: Deps,
+    env: &Env,
+    position_id: u64,
+    amount0: Uint128,
+    amount1: Uint128,
+    reply_id: u64,
+) -> StdResult<SubMsg> {
+    let range = RANGE.load(deps.storage)?;
+    let (min_amount0, min_amount1) = min_amounts(deps, env, &range, amount0, amount1)?;
+    let msg = MsgAddToPosition {
+        position_id,
+        sender: env.contract.address.to_string(),
+        amount0: amount0.to_string(),
+        amount1: amount1.to_string(),
+        token_min_amount0: min_amount0.to_string(),
+        token_min_amount1: min_amount1.to_string(),
+    };
+    Ok(SubMsg::reply_on_success(msg, reply_id))
+}
+
+/// Collects spread rewards and incentives of the position, sells the
+/// incentives that are not pool assets, swaps the pool assets to the ratio of
+/// the position and adds everything to the position. If `catch_failures` is
+/// set, a failing sell, swap or add is reverted on its own instead of failing
+/// the transaction. Returns an empty response if the vault has no position.
+fn compound(deps: Deps, env: &Env, catch_failures: bool) -> Result<Response, ContractError> {
+    let position_id = match POSITION_ID.may_load(deps.storage)? {
+        Some(position_id) => position_id,
+        None => return Ok(Response::new()),
+    };
+
+    let collect_spread_rewards = MsgCollectSpreadRewards {
+        position_ids: vec![position_id],
+        sender: env.contract.address.to_string(),
+    };
+    let collect_incentives = MsgCollectIncentives {
+        position_ids: vec![position_id],
+        sender: env.contract.address.to_string(),
+    };
+
+    let event = Event::new("apollo/vaults/osmosis_cl/compound")
+        .add_attribute("action", "compound")
+        .add_attribute("position_id", position_id.to_string());
+
+    let callbacks = [
+        CallbackMsg::SellRewards {},
+        CallbackMsg::SwapToPositionRatio {},
+        CallbackMsg::AddToPosition {},
+    ]
+    .iter()
+    .map(|callback| -> StdResult<SubMsg> {
+        let msg = callback.into_cosmos_msg(env)?;
+        Ok(if catch_failures {
+            SubMsg::reply_on_error(msg, COMPOUND_FAILURE_REPLY_ID)
+        } else {
+            SubMsg::new(msg)
+        })
+    })
+    .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(
+            collect_spread_rewards,
+            COLLECT_SPREAD_REWARDS_REPLY_ID,
+        ))
+        .add_submessage(SubMsg::reply_on_success(collect_incentives, COLLECT_INCENTIVES_REPLY_ID))
+        .add_submessages(callbacks)
+        .add_event(event))
+}
+
+//--------------------------------------------------------------------------------------------------
+// Execute
+//--------------------------------------------------------------------------------------------------
+
+/// Deposit token0 and optionally token1 of the pool into the vault. Pending
+/// rewards are compounded first, then the deposit is added to the position and
+/// vault tokens are minted proportional to the added liquidity. The part of
+/// the deposit that could not be added is refunded. A failing compound does
+/// not fail the deposit.
+pub fn execute_deposit(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    let pool = POOL.load(deps.storage)?;
+    let recipient = recipient.map_or(Ok(info.sender.clone()), |x| deps.api.addr_validate(&x))?;
+
+    // Only the pool assets may be sent, and `amount` must match the token0 sent
+    let amount0 =
+        info.funds.iter().find(|c| c.denom == pool.token0).map(|c| c.amount).unwrap_or_default();
+    let amount1 =
+        info.funds.iter().find(|c| c.denom == pool.token1).map(|c| c.amount).unwrap_or_default();
+    if amount0 != amount
+        || (amount0.is_zero() && amount1.is_zero())
+        || info.funds.iter().any(|c| c.denom != pool.token0 && c.denom != pool.token1)
+    {
+        return Err(ContractError::UnexpectedFunds {
+            expected: vec![coin(amount.u128(), pool.token0), coin(0, pool.token1)],
+            actual: info.funds,
+        });
+    }
+
+    PENDING_DEPOSIT.save(
+        deps.storage,
+        &PendingDeposit {
+            recipient: recipient.clone(),
+            amount0,
+            amount1,
+            liquidity_before: None,
+        },
+    )?;
+
+    let event = Event::new("apollo/vaults/osmosis_cl/deposit").add_attributes(vec![
+        attr("action", "deposit"),
+        attr("recipient", recipient),
+        attr("amount0", amount0),
+        attr("amount1", amount1),
+    ]);
+
+    Ok(compound(deps.as_ref(), &env, true)?
+        .add_message(CallbackMsg::ProvideDeposit {}.into_cosmos_msg(&env)?)
+        .add_event(event))
+}
+
+/// Redeem vault tokens for the share of the position liquidity they represent.
+/// The withdrawn pool assets are sent to the recipient.
+pub fn execute_redeem(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    let recipient = recipient.map_or(Ok(info.sender.clone()), |x| deps.api.addr_validate(&x))?;
+    let position_id = POSITION_ID.may_load(deps.storage)?.ok_or(ContractError::NoPosition {})?;
+    let vault_token = VAULT_TOKEN.load(deps.storage)?;
+
+    // Receive the vault tokens, or validate that they were sent
+    vault_token.receive(deps.branch(), &env, &info, amount)?;
+
+    let vault_token_supply = vault_token.query_total_supply(deps.as_ref())?;
+    let liquidity = liquidity_for_vault_tokens(
+        amount,
+        query_position_liquidity(deps.as_ref())?,
+        vault_token_supply,
+    );
+
+    let burn_res = vault_token.burn(deps.branch(), &env, amount)?;
+
+    PENDING_REDEEM_RECIPIENT.save(deps.storage, &recipient)?;
+    let withdraw = MsgWithdrawPosition {
+        position_id,
+        sender: env.contract.address.to_string(),
+        liquidity_amount: liquidity.to_string(),
+    };
+
+    let event = Event::new("apollo/vaults/osmosis_cl/redeem").add_attributes(vec![
+        attr("action", "redeem"),
+        attr("recipient", recipient),
+        attr("vault_token_amount", amount),
+        attr("liquidity", liquidity.to_string()),
+    ]);
+
+    Ok(burn_res
+        .add_submessage(SubMsg::reply_on_success(withdraw, REDEEM_REPLY_ID))
+        .add_event(event))
+}
+
+/// Compound the collected spread rewards and incentives into the position.
+pub fn execute_compound(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    if POSITION_ID.may_load(deps.storage)?.is_none() {
+        return Err(ContractError::NoPosition {});
+    }
+    compound(deps.as_ref(), &env, false)
+}
+
+/// Move the position to a new range. All liquidity is withdrawn and added to
+/// a new position in the reply.
+pub fn execute_update_range(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    range: Option<Range>,
+) -> Result<Response, ContractError> {
+    ADMIN.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let range = range.unwrap_or_else(Range::full);
+    let pool = query_cl_pool(deps.as_ref(), POOL.load(deps.storage)?.pool_id)?;
+    range.validate(pool.tick_spacing)?;
+
+    let event = Event::new("apollo/vaults/osmosis_cl/update_range").add_attributes(vec![
+        attr("action", "execute_update_range"),
+        attr("lower_tick", range.lower_tick.to_string()),
+        attr("upper_tick", range.upper_tick.to_string()),
+    ]);
+
+    // Without a position the new range is used for the first deposit
+    let position = match query_position(deps.as_ref())?.and_then(|x| x.position) {
+        Some(position) => position,
+        None => {
+            RANGE.save(deps.storage, &range)?;
+            return Ok(Response::new().add_event(event));
+        }
+    };
+
+    PENDING_RANGE.save(deps.storage, &range)?;
+    let withdraw = MsgWithdrawPosition {
+        position_id: position.position_id,
+        sender: env.contract.address.to_string(),
+        liquidity_amount: position.liquidity,
+    };
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(withdraw, UPDATE_RANGE_WITHDRAW_REPLY_ID))
+        .add_event(event))
+}
+
+//--------------------------------------------------------------------------------------------------
+// Callbacks
+//--------------------------------------------------------------------------------------------------
+
+/// Sells all reward assets that are not pool assets for token0.
+pub fn execute_callback_sell_rewards(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    let pool = POOL.load(deps.storage)?;
+    let token0 = AssetInfo::Native(pool.token0.clone());
+    let token1 = AssetInfo::Native(pool.token1.clone());
+
+    let mut rewards = AssetList::new();
+    for info in cfg.reward_assets {
+        if info == token0 || info == token1 {
+            continue;
+        }
+        let balance = info.query_balance(&deps.querier, env.contract.address.clone())?;
+        if !balance.is_zero() {
+            rewards.add(&Asset::new(info, balance))?;
+        }
+    }
+
+    if rewards.len() == 0 {
+        return Ok(Response::new());
+    }
+
+    let swap_msgs = cfg.router.basket_liquidate_msgs(rewards.clone(), &token0, None, None)?;
+
+    let event = Event::new("apollo/vaults/osmosis_cl/compound").add_attributes(vec![
+        attr("action", "execute_callback_sell_rewards"),
+        attr("rewards", rewards.to_string()),
+    ]);
+
+    Ok(Response::new().add_messages(swap_msgs).add_event(event))
+}
+
+/// Swaps the pool assets held by the contract, except a pending deposit, to the
+/// ratio of the position at the current price of the pool, so that they can all
+/// be added to the position. The minimum output is the output at the TWAP price
+/// minus the max slippage.
+pub fn execute_callback_swap_to_position_ratio(
+    deps: DepsMut,
+    env: Env,
+) -> Result<Response, ContractError> {
+    let cfg = CONFIG.load(deps.storage)?;
+    let pool_config = POOL.load(deps.storage)?;
+    let (balance0, balance1) = available_balances(deps.as_ref(), &env)?;
+
+    let pool = query_cl_pool(deps.as_ref(), pool_config.pool_id)?;
+    let sqrt_price = Decimal256::from_str(&pool.current_sqrt_price)?;
+    let range = RANGE.load(deps.storage)?;
+    let (amount_in, sell0) = match swap_to_ratio(sqrt_price, &range, balance0, balance1)? {
+        Some(swap) => swap,
+        None => return Ok(Response::new()),
+    };
+
+    let twap_price = query_twap_price(deps.as_ref(), &env)?;
+    let (denom_in, denom_out, expected_out) = if sell0 {
+        (pool_config.token0, pool_config.token1, Uint256::from(amount_in) * twap_price)
+    } else {
+        let expected_out = Uint256::from(amount_in)
+            .multiply_ratio(Decimal256::one().atomics(), twap_price.atomics());
+        (pool_config.token1, pool_config.token0, expected_out)
+    };
+    let min_out = Uint128::try_from(expected_out).map_err(StdError::from)?
+        * (Decimal::one() - cfg.max_slippage);
+    // Osmosis rejects swaps without a positive minimum output
+    if min_out.is_zero() {
+        return Ok(Response::new());
+    }
+
+    let swap = MsgSwapExactAmountIn {
+        sender: env.contract.address.to_string(),
+        routes: vec![SwapAmountInRoute {
+            pool_id: pool_config.pool_id,
+            token_out_denom: denom_out.clone(),
+        }],
+        token_in: Some(coin(amount_in.u128(), &denom_in).into()),
+        token_out_min_amount: min_out.to_string(),
+    };
+
+    let event = Event::new("apollo/vaults/osmosis_cl/compound").add_attributes(vec![
+        attr("action", "execute_callback_swap_to_position_ratio"),
+        attr("offer", coin(amount_in.u128(), denom_in).to_string()),
+        attr("min_out", coin(min_out.u128(), denom_out).to_string()),
+    ]);
+
+    Ok(Response::new().add_message(swap).add_event(event))
+}
+
+/// Adds all pool assets held by the contract, except a pending deposit, to the
+/// position.
+pub fn execute_callback_add_to_position(
+    deps: DepsMut,
+    env: Env,
+) -> Result<Response, ContractError> {
+    let position_id = match POSITION_ID.may_load(deps.storage)? {
+        Some(position_id) => position_id,
+        None => return Ok(Response::new()),
+    };
+    let (balance0, balance1) = available_balances(deps.as_ref(), &env)?;
+
+    if balance0.is_zero() && balance1.is_zero() {
+        return Ok(Response::new());
+    }
+
+    let event = Event::new("apollo/vaults/osmosis_cl/compound").add_attributes(vec![
+        attr("action", "execute_callback_add_to_position"),
+        attr("amount0", balance0),
+        attr("amount1", balance1),
+    ]);
+
+    Ok(Response::new()
+        .add_submessage(add_to_position_msg(
+            deps.as_ref(),
+            &env,
+            position_id,
+            balance0,
+            balance1,
+            COMPOUND_REPLY_ID,
+        )?)
+        .add_event(event))
+}
+
+/// Adds the pending deposit to the position, creating the position if the
+/// vault does not have one.
+pub fn execute_callback_provide_deposit(
+    deps: DepsMut,
+    env: Env,
+) -> Result<Response, ContractError> {
+    let mut pending = PENDING_DEPOSIT.load(deps.storage)?;
+
+    let msg = match POSITION_ID.may_load(deps.storage)? {
+        Some(position_id) => {
+            pending.liquidity_before = Some(query_position_liquidity(deps.as_ref())?);
+            add_to_position_msg(
+                deps.as_ref(),
+                &env,
+                position_id,
+                pending.amount0,
+                pending.amount1,
+                DEPOSIT_ADD_TO_POSITION_REPLY_ID,
+            )?
+        }
+        None => {
+            pending.liquidity_before = Some(Decimal256::zero());
+            create_position_msg(
+                deps.as_ref(),
+                &env,
+                &RANGE.load(deps.storage)?,
+                pending.amount0,
+                pending.amount1,
+                DEPOSIT_CREATE_POSITION_REPLY_ID,
+            )?
+        }
+    };
+    PENDING_DEPOSIT.save(deps.storage, &pending)?;
+
+    Ok(Response::new().add_submessage(msg))
+}
+
+/// Creates a position in the range of the vault with all pool assets held by
+/// the contract, except a pending deposit.
+pub fn execute_callback_create_position(
+    deps: DepsMut,
+    env: Env,
+) -> Result<Response, ContractError> {
+    let (balance0, balance1) = available_balances(deps.as_ref(), &env)?;
+
+    Ok(Response::new().add_submessage(create_position_msg(
+        deps.as_ref(),
+        &env,
+        &RANGE.load(deps.storage)?,
+        balance0,
+        balance1,
+        UPDATE_RANGE_CREATE_POSITION_REPLY_ID,
+    )?))
+}
+
+//--------------------------------------------------------------------------------------------------
+// Replies
+//--------------------------------------------------------------------------------------------------
+
+/// Takes the performance fee from collected spread rewards or incentives.
+pub fn reply_collect(
+    deps: DepsMut,
+    id: u64,
+    data: Option<Binary>,
+) -> Result<Response, ContractError> {
+    let collected = match data {
+        Some(b) if id == COLLECT_SPREAD_REWARDS_REPLY_ID => {
+            let res: MsgCollectSpreadRewardsResponse = b.try_into().map_err(ContractError::Std)?;
+            res.collected_spread_rewards
+        }
+        Some(b) => {
+            let res: MsgCollectIncentivesResponse = b.try_into().map_err(ContractError::Std)?;
+            res.collected_incentives
+        }
+        None => vec![],
+    };
+
+    let cfg = CONFIG.load(deps.storage)?;
+    let fees = collected
+        .into_iter()
+        .map(|c| -> StdResult<Coin> {
+            Ok(coin((parse_amount(&c.amount)? * cfg.performance_fee).u128(), c.denom))
+        })
+        .collect::<StdResult<Vec<_>>>()?
+        .into_iter()
+        .filter(|c| !c.amount.is_zero())
+        .collect::<Vec<_>>();
+
+    if fees.is_empty() {
+        return Ok(Response::new());
+    }
+
+    let event = Event::new("apollo/vaults/osmosis_cl/compound").add_attributes(vec![
+        attr("action", "reply_collect"),
+        attr("fees_to_treasury", fees.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(",")),
+    ]);
+
+    Ok(Response::new()
+        .add_message(BankMsg::Send {
+            to_address: cfg.treasury.to_string(),
+            amount: fees,
+        })
+        .add_event(event))
+}
+
+/// Records a failed step of the compound that runs before a deposit. The step
+/// is reverted and the deposit goes ahead, the rewards are compounded later.
+pub fn reply_compound_failure(error: String) -> Result<Response, ContractError> {
+    let event = Event::new("apollo/vaults/osmosis_cl/compound")
+        .add_attributes(vec![attr("action", "reply_compound_failure"), attr("error", error)]);
+
+    Ok(Response::new().add_event(event))
+}
+
+/// Saves the new position ID after compounding.
+pub fn reply_compound(deps: DepsMut, data: Binary) -> Result<Response, ContractError> {
+    let res: MsgAddToPositionResponse = data.try_into().map_err(ContractError::Std)?;
+    POSITION_ID.save(deps.storage, &res.position_id)?;
+
+    let event = Event::new("apollo/vaults/osmosis_cl/compound").add_attributes(vec![
+        attr("action", "reply_compound"),
+        attr("position_id", res.position_id.to_string()),
+        attr("amount0", res.amount0),
+        attr("amount1", res.amount1),
+    ]);
+
+    Ok(Response::new().add_event(event))
+}
+
+/// Mints vault tokens for the liquidity added by the pending deposit and
+/// refunds the unused part of the deposit.
+pub fn reply_deposit(
+    mut deps: DepsMut,
+    env: Env,
+    id: u64,
+    data: Binary,
+) -> Result<Response, ContractError> {
+    let pending = PENDING_DEPOSIT.load(deps.storage)?;
+    PENDING_DEPOSIT.remove(deps.storage);
+    let liquidity_before = pending.liquidity_before.unwrap_or_default();
+
+    let (position_id, used0, used1, liquidity_added) = if id == DEPOSIT_CREATE_POSITION_REPLY_ID {
+        let res: MsgCreatePositionResponse = data.try_into().map_err(ContractError::Std)?;
+        (res.position_id, res.amount0, res.amount1, Decimal256::from_str(&res.liquidity_created)?)
+    } else {
+        let res: MsgAddToPositionResponse = data.try_into().map_err(ContractError::Std)?;
+        // Adding to a position replaces it with a new one, which must be saved
+        // before querying its liquidity
+        POSITION_ID.save(deps.storage, &res.position_id)?;
+        let liquidity_after = query_position_liquidity(deps.as_ref())?;
+        (res.position_id, res.amount0, res.amount1, liquidity_after.checked_sub(liquidity_before)?)
+    };
+    POSITION_ID.save(deps.storage, &position_id)?;
+
+    // Mint vault tokens proportional to the added liquidity
+    let vault_token = VAULT_TOKEN.load(deps.storage)?;
+    let vault_tokens = vault_tokens_for_liquidity(
+        liquidity_added,
+        liquidity_before,
+        vault_token.query_total_supply(deps.as_ref())?,
+    )?;
+    let mint_res = vault_token.mint(deps.branch(), &env, &pending.recipient, vault_tokens)?;
+
+    // Refund the part of the deposit that was not added to the position
+    let pool = POOL.load(deps.storage)?;
+    let refund = pool_coins(
+        &pool.token0,
+        pending.amount0.checked_sub(parse_amount(&used0)?)?,
+        &pool.token1,
+        pending.amount1.checked_sub(parse_amount(&used1)?)?,
+    );
+
+    let event = Event::new("apollo/vaults/osmosis_cl/deposit").add_attributes(vec![
+        attr("action", "reply_deposit"),
+        attr("recipient", pending.recipient.to_string()),
+        attr("position_id", position_id.to_string()),
+        attr("liquidity_added", liquidity_added.to_string()),
+        attr("mint_amount", vault_tokens),
+    ]);
+
+    let mut res = mint_res.add_event(event);
+    if !refund.is_empty() {
+        res = res.add_message(BankMsg::Send {
+            to_address: pending.recipient.to_string(),
+            amount: refund,
+        });
+    }
+    Ok(res)
+}
+
+/// Sends the assets withdrawn by a redemption to the recipient.
+pub fn reply_redeem(deps: DepsMut, data: Binary) -> Result<Response, ContractError> {
+    let res: MsgWithdrawPositionResponse = data.try_into().map_err(ContractError::Std)?;
+    let recipient = PENDING_REDEEM_RECIPIENT.load(deps.storage)?;
+    PENDING_REDEEM_RECIPIENT.remove(deps.storage);
+
+    // Withdrawing all liquidity removes the position
+    if VAULT_TOKEN.load(deps.storage)?.query_total_supply(deps.as_ref())?.is_zero() {
+        POSITION_ID.remove(deps.storage);
+    }
+
+    let pool = POOL.load(deps.storage)?;
+    let withdrawn = pool_coins(
+        &pool.token0,
+        parse_amount(&res.amount0)?,
+        &pool.token1,
+        parse_amount(&res.amount1)?,
+    );
+
+    let event = Event::new("apollo/vaults/osmosis_cl/redeem").add_attributes(vec![
+        attr("action", "reply_redeem"),
+        attr("recipient", recipient.to_string()),
+        attr("amount0", res.amount0),
+        attr("amount1", res.amount1),
+    ]);
+
+    let mut response = Response::new().add_event(event);
+    if !withdrawn.is_empty() {
+        response = response.add_message(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: withdrawn,
+        });
+    }
+    Ok(response)
+}
+
+/// Swaps the pool assets held by the contract after the old position was
+/// withdrawn to the ratio of the new range, and creates the position in the new
+/// range with them.
+pub fn reply_update_range_withdraw(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let range = PENDING_RANGE.load(deps.storage)?;
+    PENDING_RANGE.remove(deps.storage);
+    RANGE.save(deps.storage, &range)?;
+    POSITION_ID.remove(deps.storage);
+
+    Ok(Response::new()
+        .add_message(CallbackMsg::SwapToPositionRatio {}.into_cosmos_msg(&env)?)
+        .add_message(CallbackMsg::CreatePosition {}.into_cosmos_msg(&env)?))
+}
+
+/// Saves the ID of the position created in the new range.
+pub fn reply_update_range_create(deps: DepsMut, data: Binary) -> Result<Response, ContractError> {
+    let res: MsgCreatePositionResponse = data.try_into().map_err(ContractError::Std)?;
+    POSITION_ID.save(deps.storage, &res.position_id)?;
+
+    let event = Event::new("apollo/vaults/osmosis_cl/update_range").add_attributes(vec![
+        attr("action", "reply_update_range_create"),
+        attr("position_id", res.position_id.to_string()),
+        attr("lower_tick", res.lower_tick.to_string()),
+        attr("upper_tick", res.upper_tick.to_string()),
+        attr("liquidity", res.liquidity_created),
+    ]);
+
+    Ok(Response::new().add_event(event))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vault_tokens_for_liquidity() {
+        // First deposit mints the liquidity rounded down
+        assert_eq!(
+            vault_tokens_for_liquidity(
+                Decimal256::from_str("1000.5").unwrap(),
+                Decimal256::zero(),
+                Uint128::zero()
+            )
+            .unwrap(),
+            Uint128::new(1000)
+        );
+
+        // Later deposits mint proportional to the liquidity before
+        assert_eq!(
+            vault_tokens_for_liquidity(
+                Decimal256::from_str("500").unwrap(),
+                Decimal256::from_str("2000").unwrap(),
+                Uint128::new(1_000_000)
+            )
+            .unwrap(),
+            Uint128::new(250_000)
+        );
+    }
+
+    #[test]
+    fn test_liquidity_for_vault_tokens() {
+        assert_eq!(
+            liquidity_for_vault_tokens(
+                Uint128::new(250_000),
+                Decimal256::from_str("2000").unwrap(),
+                Uint128::new(1_000_000)
+            ),
+            Decimal256::from_str("500").unwrap()
+        );
+        assert_eq!(
+            liquidity_for_vault_tokens(Uint128::one(), Decimal256::one(), Uint128::zero()),
+            Decimal256::zero()
+        );
+    }
+
+    #[test]
+    fn test_pool_coins() {
+        assert_eq!(
+            pool_coins("uosmo", Uint128::new(10), "uatom", Uint128::new(20)),
+            vec![coin(20, "uatom"), coin(10, "uosmo")]
+        );
+        assert_eq!(
+            pool_coins("uosmo", Uint128::zero(), "uatom", Uint128::new(20)),
+            vec![coin(20, "uatom")]
+        );
+    }
+}
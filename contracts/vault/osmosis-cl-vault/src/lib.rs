@@ -0,0 +1,7 @@
+pub mod contract;
+pub mod error;
+pub mod execute;
+pub mod math;
+pub mod msg;
+pub mod query;
+pub mod state;
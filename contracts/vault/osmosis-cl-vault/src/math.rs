@@ -0,0 +1,217 @@
+use cosmwasm_std::{Decimal256, StdError, StdResult, Uint128, Uint256};
+
+use crate::state::Range;
+
+/// The number of ticks after which the price increment per tick grows by a
+/// power of ten.
+const GEOMETRIC_EXPONENT_INCREMENT_DISTANCE_IN_TICKS: i64 = 9_000_000;
+/// The exponent of the price increment per tick at a price of one.
+const EXPONENT_AT_PRICE_ONE: i64 = -6;
+
+/// Returns `value * 10^exponent`. Digits below the precision of
+/// [`Decimal256`] are truncated.
+fn scale(value: u64, exponent: i64) -> StdResult<Decimal256> {
+    if exponent >= 0 {
+        let atomics =
+            Uint256::from(value).checked_mul(Uint256::from(10u128).pow(exponent as u32))?;
+        return Decimal256::from_atomics(atomics, 0)
+            .map_err(|_| StdError::generic_err(format!("Price overflow at 10^{}", exponent)));
+    }
+    Decimal256::from_atomics(value, exponent.unsigned_abs() as u32)
+        .map_err(|_| StdError::generic_err(format!("Price overflow at 10^{}", exponent)))
+}
+
+/// Returns the price of token0 in token1 at `tick`, following the geometric
+/// tick spacing of osmosis concentrated liquidity pools.
+pub fn tick_to_price(tick: i64) -> StdResult<Decimal256> {
+    if tick == 0 {
+        return Ok(Decimal256::one());
+    }
+
+    // Integer division truncates towards zero, like osmosis does
+    let geometric_exponent_delta = tick / GEOMETRIC_EXPONENT_INCREMENT_DISTANCE_IN_TICKS;
+    let mut exponent_at_tick = EXPONENT_AT_PRICE_ONE + geometric_exponent_delta;
+    if tick < 0 {
+        exponent_at_tick -= 1;
+    }
+    let num_additive_ticks =
+        tick - geometric_exponent_delta * GEOMETRIC_EXPONENT_INCREMENT_DISTANCE_IN_TICKS;
+
+    let base = scale(1, geometric_exponent_delta)?;
+    let additive = scale(num_additive_ticks.unsigned_abs(), exponent_at_tick)?;
+    if num_additive_ticks < 0 {
+        Ok(base.checked_sub(additive)?)
+    } else {
+        Ok(base.checked_add(additive)?)
+    }
+}
+
+/// Returns the amounts of token0 and token1 that one unit of liquidity of a
+/// position in `range` holds at `sqrt_price`.
+pub fn amounts_per_liquidity(
+    sqrt_price: Decimal256,
+    range: &Range,
+) -> StdResult<(Decimal256, Decimal256)> {
+    let sqrt_price_lower = tick_to_price(range.lower_tick)?.sqrt();
+    let sqrt_price_upper = tick_to_price(range.upper_tick)?.sqrt();
+    let sqrt_price = sqrt_price.max(sqrt_price_lower).min(sqrt_price_upper);
+
+    let amount0 = (sqrt_price_upper - sqrt_price)
+        .checked_div(sqrt_price.checked_mul(sqrt_price_upper)?)
+        .map_err(|e| StdError::generic_err(e.to_string()))?;
+    let amount1 = sqrt_price - sqrt_price_lower;
+    Ok((amount0, amount1))
+}
+
+/// Returns the amounts of token0 and token1, rounded down, that are used when
+/// adding `amount0` and `amount1` to a position in `range` at `sqrt_price`.
+pub fn amounts_used(
+    sqrt_price: Decimal256,
+    range: &Range,
+    amount0: Uint128,
+    amount1: Uint128,
+) -> StdResult<(Uint128, Uint128)> {
+    let (per_liquidity0, per_liquidity1) = amounts_per_liquidity(sqrt_price, range)?;
+    let liquidity_for = |amount: Uint128, per_liquidity: Decimal256| {
+        Decimal256::from_ratio(amount, 1u128)
+            .checked_div(per_liquidity)
+            .map_err(|e| StdError::generic_err(e.to_string()))
+    };
+
+    // The liquidity is limited by the token that runs out first
+    let liquidity = match (per_liquidity0.is_zero(), per_liquidity1.is_zero()) {
+        (false, false) => {
+            liquidity_for(amount0, per_liquidity0)?.min(liquidity_for(amount1, per_liquidity1)?)
+        }
+        (false, true) => liquidity_for(amount0, per_liquidity0)?,
+        (true, false) => liquidity_for(amount1, per_liquidity1)?,
+        (true, true) => Decimal256::zero(),
+    };
+
+    Ok((
+        (Uint256::one() * liquidity.checked_mul(per_liquidity0)?).try_into()?,
+        (Uint256::one() * liquidity.checked_mul(per_liquidity1)?).try_into()?,
+    ))
+}
+
+/// Returns the amount of token0 to sell, or of token1 if the boolean is
+/// false, so that `amount0` and `amount1` are in the ratio a position in
+/// `range` holds at `sqrt_price`. Swap fees and price impact are ignored, any
+/// leftover is added on the next compound. Returns `None` if nothing needs to
+/// be swapped.
+pub fn swap_to_ratio(
+    sqrt_price: Decimal256,
+    range: &Range,
+    amount0: Uint128,
+    amount1: Uint128,
+) -> StdResult<Option<(Uint128, bool)>> {
+    let (per_liquidity0, per_liquidity1) = amounts_per_liquidity(sqrt_price, range)?;
+    let price = sqrt_price.checked_mul(sqrt_price)?;
+    let amount0 = Decimal256::from_ratio(amount0, 1u128);
+    let amount1 = Decimal256::from_ratio(amount1, 1u128);
+
+    // Selling x of token0 for x * price of token1 gives the ratio when
+    // (amount0 - x) * per_liquidity1 == (amount1 + x * price) * per_liquidity0,
+    // and likewise when selling token1.
+    let excess0 = amount0.checked_mul(per_liquidity1)?;
+    let excess1 = amount1.checked_mul(per_liquidity0)?;
+    let (sell0, numerator, denominator) = if excess0 > excess1 {
+        (true, excess0 - excess1, per_liquidity1 + price.checked_mul(per_liquidity0)?)
+    } else {
+        let per_liquidity1_in_token0 =
+            per_liquidity1.checked_div(price).map_err(|e| StdError::generic_err(e.to_string()))?;
+        (false, excess1 - excess0, per_liquidity0 + per_liquidity1_in_token0)
+    };
+    if numerator.is_zero() || denominator.is_zero() {
+        return Ok(None);
+    }
+
+    let amount_in: Uint128 = (Uint256::one()
+        * numerator.checked_div(denominator).map_err(|e| StdError::generic_err(e.to_string()))?)
+    .try_into()?;
+    Ok(Some((amount_in, sell0)).filter(|(amount, _)| !amount.is_zero()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::state::{MAX_TICK, MIN_TICK};
+
+    fn dec(value: &str) -> Decimal256 {
+        Decimal256::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_tick_to_price() {
+        assert_eq!(tick_to_price(0).unwrap(), Decimal256::one());
+        assert_eq!(tick_to_price(1).unwrap(), dec("1.000001"));
+        assert_eq!(tick_to_price(-1).unwrap(), dec("0.9999999"));
+        assert_eq!(tick_to_price(9_000_000).unwrap(), dec("10"));
+        assert_eq!(tick_to_price(-9_000_000).unwrap(), dec("0.1"));
+        assert_eq!(tick_to_price(40_000_000).unwrap(), dec("50000"));
+        assert_eq!(tick_to_price(MIN_TICK).unwrap(), dec("0.000000000001"));
+        assert_eq!(
+            tick_to_price(MAX_TICK).unwrap(),
+            Decimal256::from_ratio(Uint256::from(10u128).pow(38), 1u128)
+        );
+    }
+
+    #[test]
+    fn test_amounts_used() {
+        // At a price of one the full range holds equal amounts of both tokens,
+        // so the smaller amount limits the liquidity
+        let (used0, used1) =
+            amounts_used(Decimal256::one(), &Range::full(), Uint128::new(1_000), Uint128::new(500))
+                .unwrap();
+        assert!(used0 >= Uint128::new(499) && used0 <= Uint128::new(500));
+        assert!(used1 >= Uint128::new(499) && used1 <= Uint128::new(500));
+
+        // Below the range only token0 is used
+        let range = Range {
+            lower_tick: 9_000_000,
+            upper_tick: 18_000_000,
+        };
+        let (used0, used1) =
+            amounts_used(Decimal256::one(), &range, Uint128::new(1_000), Uint128::new(500))
+                .unwrap();
+        assert!(used0 >= Uint128::new(999));
+        assert_eq!(used1, Uint128::zero());
+    }
+
+    #[test]
+    fn test_swap_to_ratio() {
+        // Half of token0 is sold when there is no token1 at a price of one
+        let (amount_in, sell0) =
+            swap_to_ratio(Decimal256::one(), &Range::full(), Uint128::new(1_000), Uint128::zero())
+                .unwrap()
+                .unwrap();
+        assert!(sell0);
+        assert!(amount_in >= Uint128::new(499) && amount_in <= Uint128::new(500));
+
+        // Token1 is sold for token0 at a price of four
+        let (amount_in, sell0) =
+            swap_to_ratio(dec("2"), &Range::full(), Uint128::zero(), Uint128::new(1_000))
+                .unwrap()
+                .unwrap();
+        assert!(!sell0);
+        assert!(amount_in >= Uint128::new(499) && amount_in <= Uint128::new(500));
+
+        // All token1 is sold when the price is below the range
+        let range = Range {
+            lower_tick: 9_000_000,
+            upper_tick: 18_000_000,
+        };
+        assert_eq!(
+            swap_to_ratio(Decimal256::one(), &range, Uint128::zero(), Uint128::new(1_000)).unwrap(),
+            Some((Uint128::new(1_000), false))
+        );
+
+        // Balanced amounts need no swap
+        assert_eq!(
+            swap_to_ratio(Decimal256::one(), &range, Uint128::new(1_000), Uint128::zero()).unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{to_binary, CosmosMsg, Decimal256, Env, StdResult, WasmMsg};
+use cw_vault_standard::{VaultStandardExecuteMsg, VaultStandardQueryMsg};
+
+use crate::state::{Config, ConfigUnchecked, Range};
+
+/// ExecuteMsg for the concentrated liquidity vault.
+///
+/// `Deposit` takes token0 and optionally token1 of the pool in the funds,
+/// where `amount` must be the amount of token0 sent. `Redeem` withdraws the
+/// share of the position liquidity represented by the vault tokens and sends
+/// both pool assets to the recipient.
+pub type ExecuteMsg = VaultStandardExecuteMsg<ExtensionExecuteMsg>;
+
+/// QueryMsg for the concentrated liquidity vault. Vault tokens are issued
+/// proportional to the liquidity of the position, so `TotalAssets`,
+/// `ConvertToShares` and `ConvertToAssets` are denominated in liquidity.
+pub type QueryMsg = VaultStandardQueryMsg<ExtensionQueryMsg>;
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    /// Address that is allowed to update config and range.
+    pub admin: String,
+    /// The ID of the concentrated liquidity pool.
+    pub pool_id: u64,
+    /// The range of the position. Defaults to the full range.
+    pub range: Option<Range>,
+    /// Configurable parameters for the contract.
+    pub config: ConfigUnchecked,
+    /// The subdenom that will be used for the native vault token, e.g.
+    /// the denom of the vault token will be:
+    /// "factory/{vault_contract}/{vault_token_subdenom}".
+    pub vault_token_subdenom: String,
+}
+
+/// Extension execute messages for the concentrated liquidity vault.
+#[cw_serde]
+pub enum ExtensionExecuteMsg {
+    /// Execute a callback message.
+    Callback(CallbackMsg),
+    /// Execute a concentrated liquidity vault specific message.
+    ConcentratedLiquidity(ClExecuteMsg),
+}
+
+/// Concentrated liquidity vault specific messages.
+#[cw_serde]
+pub enum ClExecuteMsg {
+    /// Compound the collected spread rewards and incentives into the
+    /// position.
+    Compound {},
+    /// Move the position to a new range. Can only be called by the admin. All
+    /// liquidity is withdrawn and a new position is created in the new range.
+    UpdateRange {
+        /// The new range. Defaults to the full range.
+        range: Option<Range>,
+    },
+    /// Update the config. Can only be called by the admin.
+    UpdateConfig {
+        /// The new config.
+        config: ConfigUnchecked,
+    },
+    /// Update the admin. Can only be called by the admin.
+    UpdateAdmin {
+        /// The new admin address.
+        address: String,
+    },
+}
+
+/// Callback messages, only callable by the vault itself.
+#[cw_serde]
+pub enum CallbackMsg {
+    /// Sell all reward assets in the contract for token0.
+    SellRewards {},
+    /// Swap the pool assets in the contract, except a pending deposit, to the
+    /// ratio of the position at the current price.
+    SwapToPositionRatio {},
+    /// Add all pool assets in the contract, except a pending deposit, to the
+    /// position.
+    AddToPosition {},
+    /// Add the pending deposit to the position, creating the position if it
+    /// does not exist.
+    ProvideDeposit {},
+    /// Create a position in the range of the vault with all pool assets in
+    /// the contract, except a pending deposit.
+    CreatePosition {},
+}
+
+impl CallbackMsg {
+    /// Convert the callback message to a [`CosmosMsg`] executing the vault
+    /// itself.
+    pub fn into_cosmos_msg(&self, env: &Env) -> StdResult<CosmosMsg> {
+        Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_binary(&VaultStandardExecuteMsg::VaultExtension(
+                ExtensionExecuteMsg::Callback(self.clone()),
+            ))?,
+            funds: vec![],
+        }))
+    }
+}
+
+/// Extension query messages for the concentrated liquidity vault.
+#[cw_serde]
+pub enum ExtensionQueryMsg {
+    /// Concentrated liquidity vault specific queries.
+    ConcentratedLiquidity(ClQueryMsg),
+}
+
+/// Concentrated liquidity vault specific queries.
+#[cw_serde]
+pub enum ClQueryMsg {
+    /// Returns the range of the position as a [`RangeResponse`].
+    Range {},
+    /// Returns whether the current tick of the pool is within the range of the
+    /// position as an [`InRangeResponse`].
+    InRange {},
+    /// Returns the position of the vault as a [`PositionResponse`].
+    Position {},
+    /// Returns the [`Config`] of the vault.
+    Config {},
+}
+
+#[cw_serde]
+pub struct RangeResponse {
+    /// The lower tick of the position, inclusive.
+    pub lower_tick: i64,
+    /// The upper tick of the position, exclusive.
+    pub upper_tick: i64,
+    /// Whether the position covers the full range.
+    pub full_range: bool,
+}
+
+#[cw_serde]
+pub struct InRangeResponse {
+    /// Whether the current tick is within the range of the position.
+    pub in_range: bool,
+    /// The current tick of the pool.
+    pub current_tick: i64,
+}
+
+#[cw_serde]
+pub struct PositionResponse {
+    /// The ID of the position. `None` before the first deposit.
+    pub position_id: Option<u64>,
+    /// The liquidity of the position.
+    pub liquidity: Decimal256,
+    /// The amount of token0 in the position.
+    pub amount0: String,
+    /// The amount of token1 in the position.
+    pub amount1: String,
+}
+
+/// Response to [`ClQueryMsg::Config`].
+pub type ConfigResponse = Config;
+
+#[cw_serde]
+pub struct MigrateMsg {}
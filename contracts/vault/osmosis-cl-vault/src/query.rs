@@ -0,0 +1,71 @@
+use cosmwasm_std::{Decimal256, Deps, StdResult, Uint128, Uint256};
+use cw_vault_token::VaultToken;
+use osmosis_std::types::cosmos::base::v1beta1::Coin;
+
+use crate::execute::{
+    liquidity_for_vault_tokens, query_cl_pool, query_position, query_position_liquidity,
+    vault_tokens_for_liquidity,
+};
+use crate::msg::{InRangeResponse, PositionResponse, RangeResponse};
+use crate::state::{POOL, POSITION_ID, RANGE, VAULT_TOKEN};
+
+/// Returns the range of the position.
+pub fn query_range(deps: Deps) -> StdResult<RangeResponse> {
+    let range = RANGE.load(deps.storage)?;
+    Ok(RangeResponse {
+        lower_tick: range.lower_tick,
+        upper_tick: range.upper_tick,
+        full_range: range.is_full(),
+    })
+}
+
+/// Returns whether the current tick of the pool is within the range of the
+/// position.
+pub fn query_in_range(deps: Deps) -> StdResult<InRangeResponse> {
+    let range = RANGE.load(deps.storage)?;
+    let pool = query_cl_pool(deps, POOL.load(deps.storage)?.pool_id)?;
+    Ok(InRangeResponse {
+        in_range: range.contains(pool.current_tick),
+        current_tick: pool.current_tick,
+    })
+}
+
+/// Returns the position of the vault and the assets in it.
+pub fn query_vault_position(deps: Deps) -> StdResult<PositionResponse> {
+    let breakdown = query_position(deps)?;
+    let amount =
+        |coin: Option<&Coin>| coin.map(|c| c.amount.clone()).unwrap_or_else(|| "0".to_string());
+
+    Ok(PositionResponse {
+        position_id: POSITION_ID.may_load(deps.storage)?,
+        liquidity: query_position_liquidity(deps)?,
+        amount0: amount(breakdown.as_ref().and_then(|b| b.asset0.as_ref())),
+        amount1: amount(breakdown.as_ref().and_then(|b| b.asset1.as_ref())),
+    })
+}
+
+/// Returns the liquidity of the position, rounded down.
+pub fn query_total_assets(deps: Deps) -> StdResult<Uint128> {
+    Ok((Uint256::one() * query_position_liquidity(deps)?).try_into()?)
+}
+
+/// Returns the amount of vault tokens that `liquidity` would be converted to.
+pub fn query_convert_to_shares(deps: Deps, liquidity: Uint128) -> StdResult<Uint128> {
+    let vault_token_supply = VAULT_TOKEN.load(deps.storage)?.query_total_supply(deps)?;
+    vault_tokens_for_liquidity(
+        Decimal256::from_ratio(liquidity, 1u128),
+        query_position_liquidity(deps)?,
+        vault_token_supply,
+    )
+}
+
+/// Returns the liquidity, rounded down, that `vault_tokens` represent.
+pub fn query_convert_to_assets(deps: Deps, vault_tokens: Uint128) -> StdResult<Uint128> {
+    let vault_token_supply = VAULT_TOKEN.load(deps.storage)?.query_total_supply(deps)?;
+    let liquidity = liquidity_for_vault_tokens(
+        vault_tokens,
+        query_position_liquidity(deps)?,
+        vault_token_supply,
+    );
+    Ok((Uint256::one() * liquidity).try_into()?)
+}
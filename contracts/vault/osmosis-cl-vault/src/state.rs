@@ -0,0 +1,224 @@
+use apollo_cw_asset::AssetInfoBase;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Decimal, Decimal256, Deps, StdError, StdResult, Uint128};
+use cw_controllers::Admin;
+use cw_dex_router::helpers::CwDexRouterBase;
+use cw_storage_plus::Item;
+use cw_vault_token::osmosis::OsmosisDenom;
+
+use crate::error::ContractError;
+
+/// The smallest tick a position can start at.
+pub const MIN_TICK: i64 = -108_000_000;
+/// The largest tick a position can end at.
+pub const MAX_TICK: i64 = 342_000_000;
+
+//--------------------------------------------------------------------------------------------------
+// Config
+//--------------------------------------------------------------------------------------------------
+
+/// Base config struct for the contract.
+#[cw_serde]
+pub struct ConfigBase<T> {
+    /// Percentage of collected spread rewards and incentives charged as
+    /// performance fee.
+    pub performance_fee: Decimal,
+    /// Account to receive fee payments.
+    pub treasury: T,
+    /// Router used to sell incentives that are not one of the pool assets.
+    pub router: CwDexRouterBase<T>,
+    /// Incentives that are not one of the pool assets. These are sold for
+    /// token0 of the pool before being added to the position.
+    pub reward_assets: Vec<AssetInfoBase<T>>,
+    /// The maximum deviation from the TWAP price of the pool accepted when
+    /// swapping between the pool assets and when adding liquidity.
+    pub max_slippage: Decimal,
+    /// The window in seconds of the TWAP price the minimum amounts of swaps
+    /// and added liquidity are derived from.
+    pub twap_window: u64,
+}
+
+/// Config with non-validated addresses.
+pub type ConfigUnchecked = ConfigBase<String>;
+/// Config with validated addresses.
+pub type Config = ConfigBase<Addr>;
+
+impl ConfigUnchecked {
+    /// Validate the addresses and values of the config.
+    pub fn check(&self, deps: Deps) -> StdResult<Config> {
+        if self.performance_fee > Decimal::one() {
+            return Err(StdError::generic_err("Performance fee cannot be greater than 100%"));
+        }
+        if self.max_slippage >= Decimal::one() {
+            return Err(StdError::generic_err("Max slippage must be less than 100%"));
+        }
+        if self.twap_window == 0 {
+            return Err(StdError::generic_err("TWAP window cannot be zero"));
+        }
+
+        Ok(Config {
+            performance_fee: self.performance_fee,
+            treasury: deps.api.addr_validate(&self.treasury)?,
+            router: self.router.check(deps.api)?,
+            reward_assets: self
+                .reward_assets
+                .iter()
+                .map(|x| x.check(deps.api))
+                .collect::<StdResult<_>>()?,
+            max_slippage: self.max_slippage,
+            twap_window: self.twap_window,
+        })
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Pool and position
+//--------------------------------------------------------------------------------------------------
+
+/// The concentrated liquidity pool the vault provides liquidity to.
+#[cw_serde]
+pub struct PoolConfig {
+    /// The ID of the pool.
+    pub pool_id: u64,
+    /// The first denom of the pool. This is the base token of the vault.
+    pub token0: String,
+    /// The second denom of the pool.
+    pub token1: String,
+}
+
+/// The range of ticks the position of the vault covers.
+#[cw_serde]
+pub struct Range {
+    /// The lower tick of the position, inclusive.
+    pub lower_tick: i64,
+    /// The upper tick of the position, exclusive.
+    pub upper_tick: i64,
+}
+
+impl Range {
+    /// The range covering all prices.
+    pub fn full() -> Self {
+        Self {
+            lower_tick: MIN_TICK,
+            upper_tick: MAX_TICK,
+        }
+    }
+
+    /// Whether this is the full range.
+    pub fn is_full(&self) -> bool {
+        self == &Self::full()
+    }
+
+    /// Checks that the ticks are ordered, within bounds and multiples of the
+    /// tick spacing of the pool.
+    pub fn validate(&self, tick_spacing: u64) -> Result<(), ContractError> {
+        let spacing = tick_spacing as i64;
+        if self.lower_tick >= self.upper_tick
+            || self.lower_tick < MIN_TICK
+            || self.upper_tick > MAX_TICK
+            || spacing == 0
+            || self.lower_tick % spacing != 0
+            || self.upper_tick % spacing != 0
+        {
+            return Err(ContractError::InvalidRange {
+                lower_tick: self.lower_tick,
+                upper_tick: self.upper_tick,
+                tick_spacing,
+                min_tick: MIN_TICK,
+                max_tick: MAX_TICK,
+            });
+        }
+        Ok(())
+    }
+
+    /// Whether `tick` is within the range.
+    pub fn contains(&self, tick: i64) -> bool {
+        self.lower_tick <= tick && tick < self.upper_tick
+    }
+}
+
+/// A deposit that is being added to the position. Saved before the deposit is
+/// added and removed in the reply of the add.
+#[cw_serde]
+pub struct PendingDeposit {
+    /// The recipient of the vault tokens.
+    pub recipient: Addr,
+    /// The amount of token0 deposited.
+    pub amount0: Uint128,
+    /// The amount of token1 deposited.
+    pub amount1: Uint128,
+    /// The liquidity of the position before the deposit was added. Set when
+    /// the deposit is provided.
+    pub liquidity_before: Option<Decimal256>,
+}
+
+/// The admin address that is allowed to update the config and range.
+pub const ADMIN: Admin = Admin::new("admin");
+/// The config of the vault.
+pub const CONFIG: Item<Config> = Item::new("config");
+/// The pool the vault provides liquidity to.
+pub const POOL: Item<PoolConfig> = Item::new("pool");
+/// The range of the position.
+pub const RANGE: Item<Range> = Item::new("range");
+/// The ID of the position of the vault. Not set before the first deposit and
+/// after all liquidity has been withdrawn.
+pub const POSITION_ID: Item<u64> = Item::new("position_id");
+/// The vault token.
+pub const VAULT_TOKEN: Item<OsmosisDenom> = Item::new("vault_token");
+/// The deposit currently being added to the position.
+pub const PENDING_DEPOSIT: Item<PendingDeposit> = Item::new("pending_deposit");
+/// The recipient of the redemption currently being withdrawn from the
+/// position.
+pub const PENDING_REDEEM_RECIPIENT: Item<Addr> = Item::new("pending_redeem_recipient");
+/// The range the position is being moved to.
+pub const PENDING_RANGE: Item<Range> = Item::new("pending_range");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_range_validate() {
+        assert!(Range::full().validate(100).is_ok());
+        assert!(Range {
+            lower_tick: -1000,
+            upper_tick: 1000,
+        }
+        .validate(100)
+        .is_ok());
+
+        // Not a multiple of the tick spacing
+        assert!(Range {
+            lower_tick: -1050,
+            upper_tick: 1000,
+        }
+        .validate(100)
+        .is_err());
+        // Lower tick above upper tick
+        assert!(Range {
+            lower_tick: 1000,
+            upper_tick: -1000,
+        }
+        .validate(100)
+        .is_err());
+        // Out of bounds
+        assert!(Range {
+            lower_tick: MIN_TICK - 100,
+            upper_tick: 0,
+        }
+        .validate(100)
+        .is_err());
+    }
+
+    #[test]
+    fn test_range_contains() {
+        let range = Range {
+            lower_tick: -100,
+            upper_tick: 100,
+        };
+        assert!(range.contains(-100));
+        assert!(range.contains(0));
+        assert!(!range.contains(100));
+        assert!(!range.contains(-101));
+    }
+}
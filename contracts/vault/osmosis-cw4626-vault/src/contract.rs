@@ -0,0 +1,542 @@
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    from_binary, to_binary, Binary, Deps, DepsMut, Env, Event, MessageInfo, Reply, Response,
+    StdError, StdResult, SubMsgResponse, SubMsgResult,
+};
+use cw2::{get_contract_version, set_contract_version};
+use cw20::{Cw20ReceiveMsg, MinterResponse};
+use cw20_base::{
+    allowances::{
+        execute_decrease_allowance, execute_increase_allowance, execute_send_from,
+        execute_transfer_from, query_allowance,
+    },
+    contract::{execute_send, execute_transfer, query_balance, query_token_info},
+    enumerable::{query_all_accounts, query_owner_allowances},
+    msg::InstantiateMsg as Cw20InstantiateMsg,
+};
+use cw_dex::{
+    osmosis::{
+        OsmosisPool, OsmosisStaking, OSMOSIS_LOCK_TOKENS_REPLY_ID, OSMOSIS_UNLOCK_TOKENS_REPLY_ID,
+    },
+    traits::{LockedStaking, Pool},
+};
+use cw_vault_standard::{
+    extensions::{
+        force_unlock::ForceUnlockExecuteMsg,
+        lockup::{LockupExecuteMsg, LockupQueryMsg},
+    },
+    msg::{VaultInfoResponse, VaultStandardInfoResponse},
+};
+use cw_vault_token::cw4626::Cw4626;
+use osmosis_std::types::osmosis::lockup::{MsgBeginUnlockingResponse, MsgLockTokensResponse};
+use semver::Version;
+use simple_vault::{
+    error::ContractError,
+    execute_compound::{PROVIDE_LIQUIDITY_REPLY_ID, SELL_REWARDS_REPLY_ID},
+    execute_remote_withdrawal::WITHDRAW_UNLOCKED_REMOTE_REPLY_ID_OFFSET,
+    msg::{
+        CallbackMsg, ExtensionExecuteMsg, ExtensionQueryMsg, SimpleExtensionExecuteMsg,
+        SimpleExtensionQueryMsg, SudoMsg,
+    },
+    SimpleVault,
+};
+
+use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, ReceiveMsg};
+
+// version info for migration info
+const CONTRACT_NAME: &str = "crates.io:osmosis-cw4626-vault";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Constants passed to VaultStandardInfo query
+const VAULT_STANDARD_VERSION: u16 = 1;
+const VAULT_STANDARD_EXTENSIONS: [&str; 3] = ["lockup", "force-unlock", "cw4626"];
+
+/// Decimals of the cw20 vault token
+const VAULT_TOKEN_DECIMALS: u8 = 6;
+
+pub type OsmosisCw4626VaultContract<'a> = SimpleVault<'a, OsmosisStaking, OsmosisPool, Cw4626>;
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    let contract = OsmosisCw4626VaultContract::default();
+
+    let admin_addr = deps.api.addr_validate(&msg.admin)?;
+    let config = msg.config.check(deps.as_ref())?;
+
+    // Create the pool object
+    let pool = OsmosisPool::new(msg.pool_id, deps.as_ref())?;
+
+    let staking = OsmosisStaking::new(msg.lockup_duration, None, pool.lp_token().to_string())?;
+
+    // The vault token is a cw20 token implemented by this contract, the vault
+    // itself is the only minter.
+    let vault_token = Cw4626::new(&env);
+    let init_info = to_binary(&Cw20InstantiateMsg {
+        name: msg.vault_token_name,
+        symbol: msg.vault_token_symbol,
+        decimals: VAULT_TOKEN_DECIMALS,
+        initial_balances: vec![],
+        mint: Some(MinterResponse {
+            minter: env.contract.address.to_string(),
+            cap: None,
+        }),
+        marketing: None,
+    })?;
+
+    contract.init(deps, admin_addr, pool, staking, config, vault_token, Some(init_info))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    let contract = OsmosisCw4626VaultContract::default();
+
+    match msg {
+        ExecuteMsg::Deposit {
+            amount,
+            recipient,
+        } => contract.execute_deposit(deps, env, &info, amount, recipient, None),
+        ExecuteMsg::Redeem {
+            recipient: _,
+            amount: _,
+        } => Err(ContractError::from(
+            "Redeem is not supported for locked vaults. Use Unlock and WithdrawUnlocked.",
+        )),
+        ExecuteMsg::Receive(msg) => receive(deps, env, info, msg),
+        // Transfers of vault tokens cap the vault tokens of the debited account
+        // that are attributed to its referrer at its new balance
+        ExecuteMsg::Transfer {
+            recipient,
+            amount,
+        } => {
+            let sender = info.sender.clone();
+            let res = execute_transfer(deps.branch(), env, info, recipient, amount)?;
+            contract.sync_referral(deps, &sender)?;
+            Ok(res)
+        }
+        ExecuteMsg::Send {
+            contract: recipient,
+            amount,
+            msg,
+        } => {
+            let sender = info.sender.clone();
+            let res = execute_send(deps.branch(), env, info, recipient, amount, msg)?;
+            contract.sync_referral(deps, &sender)?;
+            Ok(res)
+        }
+        ExecuteMsg::IncreaseAllowance {
+            spender,
+            amount,
+            expires,
+        } => Ok(execute_increase_allowance(deps, env, info, spender, amount, expires)?),
+        ExecuteMsg::DecreaseAllowance {
+            spender,
+            amount,
+            expires,
+        } => Ok(execute_decrease_allowance(deps, env, info, spender, amount, expires)?),
+        ExecuteMsg::TransferFrom {
+            owner,
+            recipient,
+            amount,
+        } => {
+            let owner_addr = deps.api.addr_validate(&owner)?;
+            let res = execute_transfer_from(deps.branch(), env, info, owner, recipient, amount)?;
+            contract.sync_referral(deps, &owner_addr)?;
+            Ok(res)
+        }
+        ExecuteMsg::SendFrom {
+            owner,
+            contract: recipient,
+            amount,
+            msg,
+        } => {
+            let owner_addr = deps.api.addr_validate(&owner)?;
+            let res = execute_send_from(deps.branch(), env, info, owner, recipient, amount, msg)?;
+            contract.sync_referral(deps, &owner_addr)?;
+            Ok(res)
+        }
+        ExecuteMsg::VaultExtension(msg) => match msg {
+            ExtensionExecuteMsg::Lockup(msg) => match msg {
+                LockupExecuteMsg::WithdrawUnlocked {
+                    recipient,
+                    lockup_id,
+                } => contract.execute_withdraw_unlocked(deps, env, &info, lockup_id, recipient),
+                LockupExecuteMsg::Unlock {
+                    amount,
+                } => contract.execute_unlock(deps, env, &info, amount),
+            },
+            ExtensionExecuteMsg::ForceUnlock(msg) => match msg {
+                ForceUnlockExecuteMsg::ForceRedeem {
+                    recipient,
+                    amount,
+                } => contract.execute_force_redeem(deps, env, info, amount, recipient),
+                ForceUnlockExecuteMsg::ForceWithdrawUnlocking {
+                    lockup_id,
+                    amount,
+                    recipient,
+                } => contract.execute_force_withdraw_unlocking(
+                    deps, env, info, lockup_id, amount, recipient,
+                ),
+                ForceUnlockExecuteMsg::UpdateForceWithdrawWhitelist {
+                    add_addresses,
+                    remove_addresses,
+                } => contract.execute_update_force_withdraw_whitelist(
+                    deps,
+                    info,
+                    add_addresses,
+                    remove_addresses,
+                ),
+            },
+            ExtensionExecuteMsg::Simple(msg) => match msg {
+                SimpleExtensionExecuteMsg::UpdateConfig {
+                    updates,
+                } => contract.execute_update_config(deps, env, info, updates),
+                SimpleExtensionExecuteMsg::UpdateAdmin {
+                    address,
+                } => contract.execute_update_admin(deps, info, address),
+                SimpleExtensionExecuteMsg::AcceptAdminTransfer {} => {
+                    contract.execute_accept_admin_transfer(deps, info)
+                }
+                SimpleExtensionExecuteMsg::DropAdminTransfer {} => {
+                    contract.execute_drop_admin_transfer(deps, info)
+                }
+                SimpleExtensionExecuteMsg::Deposit {
+                    amount,
+                    recipient,
+                    referrer,
+                } => contract.execute_deposit(deps, env, &info, amount, recipient, referrer),
+                SimpleExtensionExecuteMsg::ClaimReferralFees {
+                    recipient,
+                } => contract.execute_claim_referral_fees(deps, info, recipient),
+                SimpleExtensionExecuteMsg::SyncReferral {
+                    address,
+                } => contract.execute_sync_referral(deps, address),
+                SimpleExtensionExecuteMsg::InstantRedeem {
+                    amount,
+                    recipient,
+                } => contract.execute_instant_redeem(deps, env, &info, amount, recipient),
+                SimpleExtensionExecuteMsg::SettleEpoch {} => {
+                    contract.execute_settle_epoch(deps, env)
+                }
+                SimpleExtensionExecuteMsg::ClaimEpochShares {
+                    epoch_id,
+                    recipient,
+                } => contract.execute_claim_epoch_shares(deps, env, info, epoch_id, recipient),
+                SimpleExtensionExecuteMsg::Sweep {
+                    asset,
+                    recipient,
+                } => contract.execute_sweep(deps, env, info, asset, recipient),
+                SimpleExtensionExecuteMsg::ZapIn {
+                    min_base_tokens,
+                    recipient,
+                } => contract.execute_zap_in(deps, env, &info, min_base_tokens, recipient),
+                SimpleExtensionExecuteMsg::WithdrawUnlockedRemote {
+                    lockup_id,
+                    remote_recipient,
+                } => contract.execute_withdraw_unlocked_remote(
+                    deps,
+                    env,
+                    &info,
+                    lockup_id,
+                    remote_recipient,
+                ),
+                SimpleExtensionExecuteMsg::WithdrawEpochUnlocked {
+                    epoch_id,
+                    recipient,
+                } => contract.execute_withdraw_epoch_unlocked(deps, env, info, epoch_id, recipient),
+            },
+            ExtensionExecuteMsg::Callback(msg) => {
+                // Assert that only the contract itself can call this
+                if info.sender != env.contract.address {
+                    return Err(ContractError::Unauthorized {});
+                }
+
+                match msg {
+                    CallbackMsg::SellRewards {} => {
+                        contract.execute_callback_sell_rewards(deps, env, info)
+                    }
+                    CallbackMsg::ProvideLiquidity {} => {
+                        contract.execute_callback_provide_liquidity(deps, env, info)
+                    }
+                    CallbackMsg::Stake {
+                        base_token_balance_before,
+                        ..
+                    } => contract.execute_callback_stake(deps, env, base_token_balance_before),
+                    CallbackMsg::CheckSharePrice {
+                        total_staked_base_tokens_before,
+                        vault_token_supply_before,
+                        pending_mint_amount,
+                    } => contract.execute_callback_check_share_price(
+                        deps,
+                        total_staked_base_tokens_before,
+                        vault_token_supply_before,
+                        pending_mint_amount,
+                    ),
+                    CallbackMsg::ZapInDeposit {
+                        base_token_balance_before,
+                        min_base_tokens,
+                        recipient,
+                    } => contract.execute_callback_zap_in_deposit(
+                        deps,
+                        env,
+                        base_token_balance_before,
+                        min_base_tokens,
+                        recipient,
+                    ),
+                    CallbackMsg::MintVaultToken {
+                        amount,
+                        recipient,
+                    } => contract.execute_callback_mint_vault_token(deps, env, amount, recipient),
+                    CallbackMsg::InstantRedeem {
+                        recipient,
+                        amount,
+                    } => contract.execute_callback_instant_redeem(deps, env, amount, recipient),
+                    CallbackMsg::SettleEpochDeposits {
+                        epoch_id,
+                    } => contract.execute_callback_settle_epoch_deposits(deps, env, epoch_id),
+                    CallbackMsg::SettleEpochUnlocks {
+                        epoch_id,
+                    } => contract.execute_callback_settle_epoch_unlocks(deps, epoch_id),
+                    CallbackMsg::Unlock {
+                        owner,
+                        vault_token_amount,
+                    } => {
+                        contract.execute_callback_unlock(deps, env, info, owner, vault_token_amount)
+                    }
+                    CallbackMsg::SaveClaim {} => contract.execute_callback_save_claim(deps),
+                    // Only reachable when `simple-vault` is compiled with features
+                    // enabled by other contracts in the workspace.
+                    #[allow(unreachable_patterns)]
+                    _ => Err(ContractError::from("Unsupported callback for locked vaults")),
+                }
+            }
+            // Tranche extensions only exist when `simple-vault` is compiled with
+            // features enabled by other contracts in the workspace.
+            #[allow(unreachable_patterns)]
+            _ => Err(ContractError::from("Tranches are not supported by this vault")),
+        },
+    }
+}
+
+/// Handles vault tokens sent to the vault with a cw20 `Send`. Since the vault
+/// token is implemented by this contract, the tokens are already in the
+/// contract's balance when the hook is called.
+fn receive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    cw20_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    // Only vault tokens can be received
+    if info.sender != env.contract.address {
+        return Err(ContractError::InvalidVaultTokenDeposited {});
+    }
+
+    let contract = OsmosisCw4626VaultContract::default();
+    let sender = deps.api.addr_validate(&cw20_msg.sender)?;
+
+    match from_binary(&cw20_msg.msg)? {
+        ReceiveMsg::Unlock {} => {
+            contract.execute_unlock_received(deps, env, sender, cw20_msg.amount)
+        }
+        ReceiveMsg::ForceRedeem {
+            recipient,
+        } => contract.execute_force_redeem_received(deps, env, sender, cw20_msg.amount, recipient),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    let contract = OsmosisCw4626VaultContract::default();
+    let base_vault = &contract.base_vault;
+
+    match msg {
+        QueryMsg::VaultStandardInfo {} => to_binary(&VaultStandardInfoResponse {
+            version: VAULT_STANDARD_VERSION,
+            extensions: VAULT_STANDARD_EXTENSIONS.iter().map(|&s| s.into()).collect(),
+        }),
+        QueryMsg::Info {} => {
+            let vault_token = base_vault.vault_token.load(deps.storage)?;
+            let base_token = base_vault.base_token.load(deps.storage)?;
+
+            to_binary(&VaultInfoResponse {
+                base_token: base_token.to_string(),
+                vault_token: vault_token.to_string(),
+            })
+        }
+        QueryMsg::PreviewDeposit {
+            amount,
+        } => to_binary(&base_vault.query_simulate_deposit(deps, amount)?),
+        QueryMsg::PreviewRedeem {
+            amount,
+        } => to_binary(&base_vault.query_simulate_withdraw(deps, amount)?),
+        QueryMsg::TotalAssets {} => to_binary(&base_vault.query_total_assets(deps)?),
+        QueryMsg::TotalVaultTokenSupply {} => {
+            to_binary(&base_vault.query_total_vault_token_supply(deps)?)
+        }
+        QueryMsg::ConvertToShares {
+            amount,
+        } => to_binary(&base_vault.query_simulate_deposit(deps, amount)?),
+        QueryMsg::ConvertToAssets {
+            amount,
+        } => to_binary(&base_vault.query_simulate_withdraw(deps, amount)?),
+        QueryMsg::Balance {
+            address,
+        } => to_binary(&query_balance(deps, address)?),
+        QueryMsg::TokenInfo {} => to_binary(&query_token_info(deps)?),
+        QueryMsg::Allowance {
+            owner,
+            spender,
+        } => to_binary(&query_allowance(deps, owner, spender)?),
+        QueryMsg::AllAllowances {
+            owner,
+            start_after,
+            limit,
+        } => to_binary(&query_owner_allowances(deps, owner, start_after, limit)?),
+        QueryMsg::AllAccounts {
+            start_after,
+            limit,
+        } => to_binary(&query_all_accounts(deps, start_after, limit)?),
+        QueryMsg::VaultExtension(msg) => match msg {
+            ExtensionQueryMsg::Lockup(msg) => match msg {
+                LockupQueryMsg::UnlockingPositions {
+                    owner,
+                    start_after,
+                    limit,
+                } => to_binary(&contract.query_unlocking_positions(
+                    deps,
+                    owner,
+                    start_after,
+                    limit,
+                )?),
+                LockupQueryMsg::UnlockingPosition {
+                    lockup_id,
+                } => to_binary(&contract.claims.query_claim_by_id(deps, lockup_id)?),
+                LockupQueryMsg::LockupDuration {} => {
+                    to_binary(&contract.staking.load(deps.storage)?.get_lockup_duration(deps)?)
+                }
+            },
+            ExtensionQueryMsg::Simple(msg) => match msg {
+                SimpleExtensionQueryMsg::State {} => to_binary(&contract.query_state(deps, env)?),
+                SimpleExtensionQueryMsg::Referral {
+                    address,
+                } => to_binary(&contract.query_referral(deps, address)?),
+                SimpleExtensionQueryMsg::Buffer {} => to_binary(&contract.query_buffer(deps)?),
+                SimpleExtensionQueryMsg::CurrentEpoch {} => {
+                    to_binary(&contract.query_current_epoch(deps, env)?)
+                }
+                SimpleExtensionQueryMsg::SettledEpoch {
+                    epoch_id,
+                } => to_binary(&contract.query_settled_epoch(deps, epoch_id)?),
+                SimpleExtensionQueryMsg::EpochPosition {
+                    epoch_id,
+                    address,
+                } => to_binary(&contract.query_epoch_position(deps, epoch_id, address)?),
+                SimpleExtensionQueryMsg::LastCompoundFailure {} => {
+                    to_binary(&contract.query_last_compound_failure(deps)?)
+                }
+                SimpleExtensionQueryMsg::UnsoldRewards {} => {
+                    to_binary(&contract.query_unsold_rewards(deps)?)
+                }
+                SimpleExtensionQueryMsg::UnaccountedBalances {
+                    cw20s,
+                } => to_binary(&contract.query_unaccounted_balances(deps, env, cw20s)?),
+            },
+            // Tranche queries only exist when `simple-vault` is compiled with
+            // features enabled by other contracts in the workspace.
+            #[allow(unreachable_patterns)]
+            _ => Err(StdError::generic_err("Tranches are not supported by this vault")),
+        },
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, reply: Reply) -> Result<Response, ContractError> {
+    let contract = OsmosisCw4626VaultContract::default();
+
+    // The steps of a compound only reply on error, which is recorded instead of
+    // reverting the transaction
+    if reply.id == SELL_REWARDS_REPLY_ID || reply.id == PROVIDE_LIQUIDITY_REPLY_ID {
+        return contract.reply_compound_failure(deps, env, reply);
+    }
+
+    // The transfer of a remote withdrawal replies with the ID of the withdrawal
+    // added to the offset
+    if reply.id >= WITHDRAW_UNLOCKED_REMOTE_REPLY_ID_OFFSET {
+        return contract.reply_withdraw_unlocked_remote(deps, reply);
+    }
+
+    if let SubMsgResult::Err(e) = reply.result {
+        return Err(ContractError::Std(StdError::generic_err(e)));
+    }
+
+    if let SubMsgResult::Ok(SubMsgResponse {
+        data: Some(b),
+        events: _,
+    }) = reply.result
+    {
+        match reply.id {
+            OSMOSIS_LOCK_TOKENS_REPLY_ID => {
+                // Save the lockup_id of the lock created by the first stake. If a lock
+                // already exists an "add_tokens_to_lock" event will be emitted instead.
+                let res: MsgLockTokensResponse = b.try_into().map_err(ContractError::Std)?;
+
+                let mut staking = contract.staking.load(deps.storage)?;
+                staking.lock_id = Some(res.id);
+                contract.staking.save(deps.storage, &staking)?;
+
+                let event = Event::new("apollo/vault/lock/reply")
+                    .add_attribute("vault_type", "osmosis_cw4626")
+                    .add_attribute("lock_id", res.id.to_string());
+                Ok(Response::default().add_event(event))
+            }
+            OSMOSIS_UNLOCK_TOKENS_REPLY_ID => {
+                let res: MsgBeginUnlockingResponse = b.try_into().map_err(ContractError::Std)?;
+
+                let mut pending_claim = contract.claims.get_pending_claim(deps.storage)?;
+                pending_claim.id = res.unlocking_lock_id;
+                contract.claims.set_pending_claim(deps.storage, &pending_claim)?;
+
+                let event = Event::new("apollo/vault/unlock/reply")
+                    .add_attribute("vault_type", "osmosis_cw4626")
+                    .add_attribute("lock_id", res.unlocking_lock_id.to_string());
+                Ok(Response::default().add_event(event))
+            }
+            id => Err(ContractError::UnknownReplyId(id)),
+        }
+    } else {
+        Err(ContractError::NoDataInSubMsgResponse {})
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
+    let contract = OsmosisCw4626VaultContract::default();
+
+    match msg {
+        SudoMsg::IbcLifecycleComplete(msg) => contract.sudo_ibc_lifecycle_complete(deps, msg),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let version: Version = CONTRACT_VERSION.parse()?;
+    let storage_version: Version = get_contract_version(deps.storage)?.version.parse()?;
+
+    // migrate only if newer
+    if storage_version < version {
+        set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    }
+    Ok(Response::default())
+}
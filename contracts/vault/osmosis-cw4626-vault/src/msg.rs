@@ -0,0 +1,169 @@
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{Binary, Empty, Uint128};
+use cw20::{
+    AllAccountsResponse, AllAllowancesResponse, AllowanceResponse, BalanceResponse, Cw20ReceiveMsg,
+    Expiration, TokenInfoResponse,
+};
+use cw_vault_standard::msg::{VaultInfoResponse, VaultStandardInfoResponse};
+use simple_vault::{
+    msg::{ExtensionExecuteMsg, ExtensionQueryMsg},
+    state::ConfigUnchecked,
+};
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    /// Address that is allowed to update config.
+    pub admin: String,
+    /// The ID of the pool that this vault will autocompound.
+    pub pool_id: u64,
+    /// The lockup duration in seconds that this vault will use when staking
+    /// LP tokens.
+    pub lockup_duration: u64,
+    /// Configurable parameters for the contract.
+    pub config: ConfigUnchecked,
+    /// The name of the cw20 vault token.
+    pub vault_token_name: String,
+    /// The symbol of the cw20 vault token.
+    pub vault_token_symbol: String,
+}
+
+/// ExecuteMsg for an Autocompounding Vault with a Cw4626 vault token. The
+/// vault standard messages are serialized the same way as in
+/// [`cw_vault_standard::VaultStandardExecuteMsg`], the vault token is
+/// operated through the top level cw20 messages.
+#[cw_serde]
+pub enum ExecuteMsg {
+    /// Called to deposit base tokens into the vault.
+    Deposit {
+        /// The amount of base tokens to deposit.
+        amount: Uint128,
+        /// The optional recipient of the vault token. If not set, the caller
+        /// address will be used instead.
+        recipient: Option<String>,
+    },
+    /// Not supported for locked vaults. Use `Unlock` and `WithdrawUnlocked`.
+    Redeem {
+        /// An optional field containing which address should receive the
+        /// withdrawn base tokens.
+        recipient: Option<String>,
+        /// The amount of vault tokens sent to the contract.
+        amount: Uint128,
+    },
+    /// Vault extension messages.
+    VaultExtension(ExtensionExecuteMsg),
+    /// Handles vault tokens sent to the vault with a cw20 `Send`. The `msg`
+    /// must be a [`ReceiveMsg`].
+    Receive(Cw20ReceiveMsg),
+    /// Transfer vault tokens to another account.
+    Transfer {
+        recipient: String,
+        amount: Uint128,
+    },
+    /// Send vault tokens to a contract and trigger an action on the receiving
+    /// contract.
+    Send {
+        contract: String,
+        amount: Uint128,
+        msg: Binary,
+    },
+    /// Allow `spender` to access an additional `amount` of the sender's vault
+    /// tokens.
+    IncreaseAllowance {
+        spender: String,
+        amount: Uint128,
+        expires: Option<Expiration>,
+    },
+    /// Lower the allowance of `spender` by `amount`.
+    DecreaseAllowance {
+        spender: String,
+        amount: Uint128,
+        expires: Option<Expiration>,
+    },
+    /// Transfer vault tokens from `owner` using an allowance.
+    TransferFrom {
+        owner: String,
+        recipient: String,
+        amount: Uint128,
+    },
+    /// Send vault tokens from `owner` to a contract using an allowance.
+    SendFrom {
+        owner: String,
+        contract: String,
+        amount: Uint128,
+        msg: Binary,
+    },
+}
+
+/// Hook messages for vault tokens sent to the vault with a cw20 `Send`.
+#[cw_serde]
+pub enum ReceiveMsg {
+    /// Burn the sent vault tokens and start the unlocking process. The
+    /// unlocking position is owned by the sender of the vault tokens.
+    Unlock {},
+    /// Force redeem the sent vault tokens. The sender of the vault tokens must
+    /// be whitelisted.
+    ForceRedeem {
+        /// Optional address to receive the base tokens. Defaults to the sender
+        /// of the vault tokens.
+        recipient: Option<String>,
+    },
+}
+
+/// QueryMsg for an Autocompounding Vault with a Cw4626 vault token. The vault
+/// standard queries are serialized the same way as in
+/// [`cw_vault_standard::VaultStandardQueryMsg`].
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum QueryMsg {
+    #[returns(VaultStandardInfoResponse)]
+    VaultStandardInfo {},
+    #[returns(VaultInfoResponse)]
+    Info {},
+    #[returns(Uint128)]
+    PreviewDeposit {
+        amount: Uint128,
+    },
+    #[returns(Uint128)]
+    PreviewRedeem {
+        amount: Uint128,
+    },
+    #[returns(Uint128)]
+    TotalAssets {},
+    #[returns(Uint128)]
+    TotalVaultTokenSupply {},
+    #[returns(Uint128)]
+    ConvertToShares {
+        amount: Uint128,
+    },
+    #[returns(Uint128)]
+    ConvertToAssets {
+        amount: Uint128,
+    },
+    #[returns(Empty)]
+    VaultExtension(ExtensionQueryMsg),
+    #[returns(BalanceResponse)]
+    Balance {
+        address: String,
+    },
+    #[returns(TokenInfoResponse)]
+    TokenInfo {},
+    #[returns(AllowanceResponse)]
+    Allowance {
+        owner: String,
+        spender: String,
+    },
+    #[returns(AllAllowancesResponse)]
+    AllAllowances {
+        owner: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(AllAccountsResponse)]
+    AllAccounts {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+}
+
+#[cw_serde]
+pub struct MigrateMsg {}
@@ -0,0 +1,508 @@
+use apollo_cw_asset::AssetInfo;
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+    to_binary, Binary, Deps, DepsMut, Env, Event, MessageInfo, Order, Reply, Response, StdError,
+    StdResult, SubMsgResponse, SubMsgResult, Uint128,
+};
+use cw2::{get_contract_version, set_contract_version};
+use cw_dex::osmosis::OsmosisPool;
+use cw_dex::traits::{LockedStaking, Pool};
+use cw_storage_plus::Bound;
+use cw_vault_standard::extensions::lockup::{LockupExecuteMsg, LockupQueryMsg};
+use cw_vault_standard::msg::{VaultInfoResponse, VaultStandardInfoResponse};
+use cw_vault_token::osmosis::OsmosisDenom;
+use osmosis_std::types::osmosis::superfluid::{
+    MsgLockAndSuperfluidDelegateResponse, MsgSuperfluidUndelegateAndUnbondLockResponse,
+};
+use semver::Version;
+use simple_vault::error::ContractError;
+use simple_vault::execute_compound::{PROVIDE_LIQUIDITY_REPLY_ID, SELL_REWARDS_REPLY_ID};
+use simple_vault::execute_remote_withdrawal::WITHDRAW_UNLOCKED_REMOTE_REPLY_ID_OFFSET;
+use simple_vault::msg::{CallbackMsg, SimpleExtensionExecuteMsg, SimpleExtensionQueryMsg, SudoMsg};
+use simple_vault::SimpleVault;
+
+use crate::msg::{
+    ExecuteMsg, ExtensionExecuteMsg, ExtensionQueryMsg, InstantiateMsg, MigrateMsg, QueryMsg,
+    SuperfluidExecuteMsg, SuperfluidQueryMsg, ValidatorsResponse,
+};
+use crate::staking::{
+    locks, OsmosisSuperfluidStaking, SuperfluidLock, SUPERFLUID_DELEGATE_REPLY_ID,
+    SUPERFLUID_UNDELEGATE_REPLY_ID_FLAG, UNBONDING_LOCKS,
+};
+
+// version info for migration info
+const CONTRACT_NAME: &str = "crates.io:osmosis-superfluid-vault";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Constants passed to VaultStandardInfo query
+const VAULT_STANDARD_VERSION: u16 = 1;
+const VAULT_STANDARD_EXTENSIONS: [&str; 2] = ["lockup", "superfluid"];
+
+/// Superfluid staking rewards are paid out in this denom
+const SUPERFLUID_REWARD_DENOM: &str = "uosmo";
+
+/// Settings for pagination of the superfluid locks
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+pub type OsmosisSuperfluidVaultContract<'a> =
+    SimpleVault<'a, OsmosisSuperfluidStaking, OsmosisPool, OsmosisDenom>;
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    let contract = OsmosisSuperfluidVaultContract::default();
+
+    let admin_addr = deps.api.addr_validate(&msg.admin)?;
+    let config = msg.config.check(deps.as_ref())?;
+
+    // Validate that 10 osmo for vault token creation are sent
+    let osmo_amount = info
+        .funds
+        .iter()
+        .find(|coin| coin.denom == "uosmo")
+        .map(|coin| coin.amount)
+        .unwrap_or_default();
+    if osmo_amount < Uint128::new(10_000_000) {
+        return Err(ContractError::from(
+            "A minimum of 10_000_000 uosmo must be sent to create the vault token",
+        ));
+    }
+
+    // Create the pool object
+    let pool = OsmosisPool::new(msg.pool_id, deps.as_ref())?;
+
+    // Superfluid staking rewards must be compounded, either by selling them or
+    // by providing them as liquidity
+    let superfluid_reward = AssetInfo::Native(SUPERFLUID_REWARD_DENOM.to_string());
+    if !config.reward_assets.contains(&superfluid_reward)
+        && !pool.pool_assets(deps.as_ref())?.contains(&superfluid_reward)
+    {
+        return Err(ContractError::from(
+            "uosmo must be a reward asset or a pool asset to compound superfluid staking rewards",
+        ));
+    }
+
+    let staking = OsmosisSuperfluidStaking::new(
+        msg.lockup_duration,
+        pool.lp_token().to_string(),
+        msg.validators,
+    )?;
+
+    let vault_token = OsmosisDenom::new(env.contract.address.to_string(), msg.vault_token_subdenom);
+
+    contract.init(deps, admin_addr, pool, staking, config, vault_token, None)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    let contract = OsmosisSuperfluidVaultContract::default();
+
+    match msg {
+        ExecuteMsg::Deposit {
+            amount,
+            recipient,
+        } => contract.execute_deposit(deps, env, &info, amount, recipient, None),
+        ExecuteMsg::Redeem {
+            recipient: _,
+            amount: _,
+        } => Err(ContractError::from(
+            "Redeem is not supported for locked vaults. Use Unlock and WithdrawUnlocked.",
+        )),
+        ExecuteMsg::VaultExtension(msg) => match msg {
+            ExtensionExecuteMsg::Lockup(msg) => match msg {
+                LockupExecuteMsg::WithdrawUnlocked {
+                    recipient,
+                    lockup_id,
+                } => {
+                    UNBONDING_LOCKS.remove(deps.storage, lockup_id);
+                    contract.execute_withdraw_unlocked(deps, env, &info, lockup_id, recipient)
+                }
+                LockupExecuteMsg::Unlock {
+                    amount,
+                } => contract.execute_unlock(deps, env, &info, amount),
+            },
+            ExtensionExecuteMsg::Superfluid(msg) => match msg {
+                SuperfluidExecuteMsg::UpdateValidators {
+                    add,
+                    remove,
+                } => execute_update_validators(deps, info, add, remove),
+            },
+            ExtensionExecuteMsg::Simple(msg) => match msg {
+                SimpleExtensionExecuteMsg::UpdateConfig {
+                    updates,
+                } => contract.execute_update_config(deps, env, info, updates),
+                SimpleExtensionExecuteMsg::UpdateAdmin {
+                    address,
+                } => contract.execute_update_admin(deps, info, address),
+                SimpleExtensionExecuteMsg::AcceptAdminTransfer {} => {
+                    contract.execute_accept_admin_transfer(deps, info)
+                }
+                SimpleExtensionExecuteMsg::DropAdminTransfer {} => {
+                    contract.execute_drop_admin_transfer(deps, info)
+                }
+                SimpleExtensionExecuteMsg::Deposit {
+                    amount,
+                    recipient,
+                    referrer,
+                } => contract.execute_deposit(deps, env, &info, amount, recipient, referrer),
+                SimpleExtensionExecuteMsg::ClaimReferralFees {
+                    recipient,
+                } => contract.execute_claim_referral_fees(deps, info, recipient),
+                SimpleExtensionExecuteMsg::SyncReferral {
+                    address,
+                } => contract.execute_sync_referral(deps, address),
+                SimpleExtensionExecuteMsg::InstantRedeem {
+                    amount,
+                    recipient,
+                } => contract.execute_instant_redeem(deps, env, &info, amount, recipient),
+                SimpleExtensionExecuteMsg::SettleEpoch {} => {
+                    contract.execute_settle_epoch(deps, env)
+                }
+                SimpleExtensionExecuteMsg::ClaimEpochShares {
+                    epoch_id,
+                    recipient,
+                } => contract.execute_claim_epoch_shares(deps, env, info, epoch_id, recipient),
+                SimpleExtensionExecuteMsg::Sweep {
+                    asset,
+                    recipient,
+                } => contract.execute_sweep(deps, env, info, asset, recipient),
+                SimpleExtensionExecuteMsg::ZapIn {
+                    min_base_tokens,
+                    recipient,
+                } => contract.execute_zap_in(deps, env, &info, min_base_tokens, recipient),
+                SimpleExtensionExecuteMsg::WithdrawUnlockedRemote {
+                    lockup_id,
+                    remote_recipient,
+                } => {
+                    UNBONDING_LOCKS.remove(deps.storage, lockup_id);
+                    contract.execute_withdraw_unlocked_remote(
+                        deps,
+                        env,
+                        &info,
+                        lockup_id,
+                        remote_recipient,
+                    )
+                }
+                SimpleExtensionExecuteMsg::WithdrawEpochUnlocked {
+                    epoch_id,
+                    recipient,
+                } => contract.execute_withdraw_epoch_unlocked(deps, env, info, epoch_id, recipient),
+            },
+            ExtensionExecuteMsg::Callback(msg) => {
+                // Assert that only the contract itself can call this
+                if info.sender != env.contract.address {
+                    return Err(ContractError::Unauthorized {});
+                }
+
+                match msg {
+                    CallbackMsg::SellRewards {} => {
+                        contract.execute_callback_sell_rewards(deps, env, info)
+                    }
+                    CallbackMsg::ProvideLiquidity {} => {
+                        contract.execute_callback_provide_liquidity(deps, env, info)
+                    }
+                    CallbackMsg::Stake {
+                        base_token_balance_before,
+                        ..
+                    } => contract.execute_callback_stake(deps, env, base_token_balance_before),
+                    CallbackMsg::CheckSharePrice {
+                        total_staked_base_tokens_before,
+                        vault_token_supply_before,
+                        pending_mint_amount,
+                    } => contract.execute_callback_check_share_price(
+                        deps,
+                        total_staked_base_tokens_before,
+                        vault_token_supply_before,
+                        pending_mint_amount,
+                    ),
+                    CallbackMsg::ZapInDeposit {
+                        base_token_balance_before,
+                        min_base_tokens,
+                        recipient,
+                    } => contract.execute_callback_zap_in_deposit(
+                        deps,
+                        env,
+                        base_token_balance_before,
+                        min_base_tokens,
+                        recipient,
+                    ),
+                    CallbackMsg::MintVaultToken {
+                        amount,
+                        recipient,
+                    } => contract.execute_callback_mint_vault_token(deps, env, amount, recipient),
+                    CallbackMsg::InstantRedeem {
+                        recipient,
+                        amount,
+                    } => contract.execute_callback_instant_redeem(deps, env, amount, recipient),
+                    CallbackMsg::SettleEpochDeposits {
+                        epoch_id,
+                    } => contract.execute_callback_settle_epoch_deposits(deps, env, epoch_id),
+                    CallbackMsg::SettleEpochUnlocks {
+                        epoch_id,
+                    } => contract.execute_callback_settle_epoch_unlocks(deps, epoch_id),
+                    CallbackMsg::Unlock {
+                        owner,
+                        vault_token_amount,
+                    } => {
+                        contract.execute_callback_unlock(deps, env, info, owner, vault_token_amount)
+                    }
+                    CallbackMsg::SaveClaim {} => contract.execute_callback_save_claim(deps),
+                    // Only reachable when `simple-vault` is compiled with features
+                    // enabled by other contracts in the workspace.
+                    #[allow(unreachable_patterns)]
+                    _ => Err(ContractError::from("Unsupported callback for locked vaults")),
+                }
+            }
+        },
+    }
+}
+
+/// Add and remove validators from the superfluid validator set. Only callable
+/// by the admin.
+pub fn execute_update_validators(
+    deps: DepsMut,
+    info: MessageInfo,
+    add: Vec<String>,
+    remove: Vec<String>,
+) -> Result<Response, ContractError> {
+    let contract = OsmosisSuperfluidVaultContract::default();
+    contract.admin.assert_admin(deps.as_ref(), &info.sender)?;
+
+    let mut staking = contract.staking.load(deps.storage)?;
+    staking.update_validators(add.clone(), remove.clone())?;
+    contract.staking.save(deps.storage, &staking)?;
+
+    let event = Event::new("apollo/vaults/superfluid").add_attributes(vec![
+        ("action", "execute_update_validators"),
+        ("added", &add.join(",")),
+        ("removed", &remove.join(",")),
+        ("validators", &staking.validators.join(",")),
+    ]);
+
+    Ok(Response::new().add_event(event))
+}
+
+/// Returns the superfluid delegated locks owned by the vault, ordered by lock
+/// ID.
+pub fn query_locks(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<SuperfluidLock>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    locks()
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, lock)| lock))
+        .collect()
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    let contract = OsmosisSuperfluidVaultContract::default();
+    let base_vault = &contract.base_vault;
+
+    match msg {
+        QueryMsg::VaultStandardInfo {} => to_binary(&VaultStandardInfoResponse {
+            version: VAULT_STANDARD_VERSION,
+            extensions: VAULT_STANDARD_EXTENSIONS.iter().map(|&s| s.into()).collect(),
+        }),
+        QueryMsg::Info {} => {
+            let vault_token = base_vault.vault_token.load(deps.storage)?;
+            let base_token = base_vault.base_token.load(deps.storage)?;
+
+            to_binary(&VaultInfoResponse {
+                base_token: base_token.to_string(),
+                vault_token: vault_token.to_string(),
+            })
+        }
+        QueryMsg::PreviewDeposit {
+            amount,
+        } => to_binary(&base_vault.query_simulate_deposit(deps, amount)?),
+        QueryMsg::PreviewRedeem {
+            amount,
+        } => to_binary(&base_vault.query_simulate_withdraw(deps, amount)?),
+        QueryMsg::TotalAssets {} => to_binary(&base_vault.query_total_assets(deps)?),
+        QueryMsg::TotalVaultTokenSupply {} => {
+            to_binary(&base_vault.query_total_vault_token_supply(deps)?)
+        }
+        QueryMsg::ConvertToShares {
+            amount,
+        } => to_binary(&base_vault.query_simulate_deposit(deps, amount)?),
+        QueryMsg::ConvertToAssets {
+            amount,
+        } => to_binary(&base_vault.query_simulate_withdraw(deps, amount)?),
+        QueryMsg::VaultExtension(msg) => match msg {
+            ExtensionQueryMsg::Lockup(msg) => match msg {
+                LockupQueryMsg::UnlockingPositions {
+                    owner,
+                    start_after,
+                    limit,
+                } => to_binary(&contract.query_unlocking_positions(
+                    deps,
+                    owner,
+                    start_after,
+                    limit,
+                )?),
+                LockupQueryMsg::UnlockingPosition {
+                    lockup_id,
+                } => to_binary(&contract.claims.query_claim_by_id(deps, lockup_id)?),
+                LockupQueryMsg::LockupDuration {} => {
+                    to_binary(&contract.staking.load(deps.storage)?.get_lockup_duration(deps)?)
+                }
+            },
+            ExtensionQueryMsg::Simple(msg) => match msg {
+                SimpleExtensionQueryMsg::State {} => to_binary(&contract.query_state(deps, env)?),
+                SimpleExtensionQueryMsg::Referral {
+                    address,
+                } => to_binary(&contract.query_referral(deps, address)?),
+                SimpleExtensionQueryMsg::Buffer {} => to_binary(&contract.query_buffer(deps)?),
+                SimpleExtensionQueryMsg::CurrentEpoch {} => {
+                    to_binary(&contract.query_current_epoch(deps, env)?)
+                }
+                SimpleExtensionQueryMsg::SettledEpoch {
+                    epoch_id,
+                } => to_binary(&contract.query_settled_epoch(deps, epoch_id)?),
+                SimpleExtensionQueryMsg::EpochPosition {
+                    epoch_id,
+                    address,
+                } => to_binary(&contract.query_epoch_position(deps, epoch_id, address)?),
+                SimpleExtensionQueryMsg::LastCompoundFailure {} => {
+                    to_binary(&contract.query_last_compound_failure(deps)?)
+                }
+                SimpleExtensionQueryMsg::UnsoldRewards {} => {
+                    to_binary(&contract.query_unsold_rewards(deps)?)
+                }
+                SimpleExtensionQueryMsg::UnaccountedBalances {
+                    cw20s,
+                } => to_binary(&contract.query_unaccounted_balances(deps, env, cw20s)?),
+            },
+            ExtensionQueryMsg::Superfluid(msg) => match msg {
+                SuperfluidQueryMsg::Validators {} => {
+                    let staking = contract.staking.load(deps.storage)?;
+                    to_binary(&ValidatorsResponse {
+                        validators: staking.validators,
+                        delegations: staking.delegations,
+                    })
+                }
+                SuperfluidQueryMsg::Locks {
+                    start_after,
+                    limit,
+                } => to_binary(&query_locks(deps, start_after, limit)?),
+                SuperfluidQueryMsg::UnbondingLocks {
+                    lockup_id,
+                } => to_binary(
+                    &UNBONDING_LOCKS.may_load(deps.storage, lockup_id)?.unwrap_or_default(),
+                ),
+            },
+        },
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(mut deps: DepsMut, env: Env, reply: Reply) -> Result<Response, ContractError> {
+    let contract = OsmosisSuperfluidVaultContract::default();
+
+    // The steps of a compound only reply on error, which is recorded instead of
+    // reverting the transaction
+    if reply.id == SELL_REWARDS_REPLY_ID || reply.id == PROVIDE_LIQUIDITY_REPLY_ID {
+        return contract.reply_compound_failure(deps, env, reply);
+    }
+
+    // The transfer of a remote withdrawal replies with the ID of the withdrawal
+    // added to the offset
+    if reply.id >= WITHDRAW_UNLOCKED_REMOTE_REPLY_ID_OFFSET {
+        return contract.reply_withdraw_unlocked_remote(deps, reply);
+    }
+
+    if let SubMsgResult::Err(e) = reply.result {
+        return Err(ContractError::Std(StdError::generic_err(e)));
+    }
+
+    if let SubMsgResult::Ok(SubMsgResponse {
+        data: Some(b),
+        events: _,
+    }) = reply.result
+    {
+        match reply.id {
+            SUPERFLUID_DELEGATE_REPLY_ID => {
+                // Every stake creates a new superfluid delegated lock which we
+                // start tracking here.
+                let res: MsgLockAndSuperfluidDelegateResponse =
+                    b.try_into().map_err(ContractError::Std)?;
+
+                let mut staking = contract.staking.load(deps.storage)?;
+                staking.add_lock(deps.branch(), res.id)?;
+                contract.staking.save(deps.storage, &staking)?;
+
+                let event = Event::new("apollo/vault/lock/reply")
+                    .add_attribute("vault_type", "osmosis_superfluid")
+                    .add_attribute("lock_id", res.id.to_string());
+                Ok(Response::default().add_event(event))
+            }
+            id if id & SUPERFLUID_UNDELEGATE_REPLY_ID_FLAG != 0 => {
+                // The unbonding lock is either the undelegated lock itself or a
+                // new lock split off from it. A single unlock can undelegate
+                // several locks, so the claim keeps its own ID and all of its
+                // unbonding locks are recorded.
+                let res: MsgSuperfluidUndelegateAndUnbondLockResponse =
+                    b.try_into().map_err(ContractError::Std)?;
+
+                let pending_claim = contract.claims.get_pending_claim(deps.storage)?;
+                UNBONDING_LOCKS.update(deps.storage, pending_claim.id, |ids| -> StdResult<_> {
+                    let mut ids = ids.unwrap_or_default();
+                    ids.push(res.lock_id);
+                    Ok(ids)
+                })?;
+
+                // Only the undelegated lock, encoded in the reply ID, changed
+                let mut staking = contract.staking.load(deps.storage)?;
+                staking.sync_lock(deps.branch(), id & !SUPERFLUID_UNDELEGATE_REPLY_ID_FLAG)?;
+                contract.staking.save(deps.storage, &staking)?;
+
+                let event = Event::new("apollo/vault/unlock/reply")
+                    .add_attribute("vault_type", "osmosis_superfluid")
+                    .add_attribute("lock_id", res.lock_id.to_string());
+                Ok(Response::default().add_event(event))
+            }
+            id => Err(ContractError::UnknownReplyId(id)),
+        }
+    } else {
+        Err(ContractError::NoDataInSubMsgResponse {})
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
+    let contract = OsmosisSuperfluidVaultContract::default();
+
+    match msg {
+        SudoMsg::IbcLifecycleComplete(msg) => contract.sudo_ibc_lifecycle_complete(deps, msg),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let version: Version = CONTRACT_VERSION.parse()?;
+    let storage_version: Version = get_contract_version(deps.storage)?.version.parse()?;
+
+    // migrate only if newer
+    if storage_version < version {
+        set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    }
+    Ok(Response::default())
+}
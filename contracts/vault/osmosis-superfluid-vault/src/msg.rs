@@ -0,0 +1,107 @@
+use cosmwasm_schema::cw_serde;
+use cw_vault_standard::extensions::lockup::{LockupExecuteMsg, LockupQueryMsg};
+use cw_vault_standard::{VaultStandardExecuteMsg, VaultStandardQueryMsg};
+use simple_vault::msg::{CallbackMsg, SimpleExtensionExecuteMsg, SimpleExtensionQueryMsg};
+use simple_vault::state::ConfigUnchecked;
+
+use crate::staking::Delegation;
+
+/// ExecuteMsg for the superfluid vault.
+pub type ExecuteMsg = VaultStandardExecuteMsg<ExtensionExecuteMsg>;
+
+/// QueryMsg for the superfluid vault.
+pub type QueryMsg = VaultStandardQueryMsg<ExtensionQueryMsg>;
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    /// Address that is allowed to update config.
+    pub admin: String,
+    /// The ID of the pool that this vault will autocompound.
+    pub pool_id: u64,
+    /// The lockup duration in seconds that this vault will use when staking
+    /// LP tokens. Must be equal to the unbonding duration of the chain.
+    pub lockup_duration: u64,
+    /// The validators the LP tokens are superfluid delegated to.
+    pub validators: Vec<String>,
+    /// Configurable parameters for the contract.
+    pub config: ConfigUnchecked,
+    /// The subdenom that will be used for the native vault token, e.g.
+    /// the denom of the vault token will be:
+    /// "factory/{vault_contract}/{vault_token_subdenom}".
+    pub vault_token_subdenom: String,
+}
+
+/// Extension execute messages for the superfluid vault. The variants shared
+/// with [`simple_vault::msg::ExtensionExecuteMsg`] are serialized the same way,
+/// so that the simple vault callbacks can be handled by this contract.
+#[cw_serde]
+pub enum ExtensionExecuteMsg {
+    /// Execute a callback message.
+    Callback(CallbackMsg),
+    /// Execute a Simple vault specific message.
+    Simple(SimpleExtensionExecuteMsg),
+    /// Execute a message from the lockup extension.
+    Lockup(LockupExecuteMsg),
+    /// Execute a superfluid vault specific message.
+    Superfluid(SuperfluidExecuteMsg),
+}
+
+/// Messages to manage the superfluid validator set.
+#[cw_serde]
+pub enum SuperfluidExecuteMsg {
+    /// Add and remove validators from the validator set. Can only be called by
+    /// the admin. Locks delegated to removed validators stay delegated until
+    /// they are unlocked.
+    UpdateValidators {
+        /// Validators to add to the validator set.
+        add: Vec<String>,
+        /// Validators to remove from the validator set.
+        remove: Vec<String>,
+    },
+}
+
+/// Extension query messages for the superfluid vault.
+#[cw_serde]
+pub enum ExtensionQueryMsg {
+    /// Queries related to the lockup extension.
+    Lockup(LockupQueryMsg),
+    /// Apollo extension queries.
+    Simple(SimpleExtensionQueryMsg),
+    /// Superfluid vault specific queries.
+    Superfluid(SuperfluidQueryMsg),
+}
+
+/// Queries specific to the superfluid vault.
+#[cw_serde]
+pub enum SuperfluidQueryMsg {
+    /// Returns the validator set and the LP tokens superfluid delegated to
+    /// each validator as a [`ValidatorsResponse`].
+    Validators {},
+    /// Returns the superfluid delegated locks owned by the vault as a
+    /// `Vec<SuperfluidLock>`, ordered by lock ID.
+    Locks {
+        /// The lock ID to start after.
+        start_after: Option<u64>,
+        /// The maximum number of locks to return.
+        limit: Option<u32>,
+    },
+    /// Returns the IDs of the unbonding Osmosis locks backing the unlocking
+    /// position with ID `lockup_id` as a `Vec<u64>`.
+    UnbondingLocks {
+        /// The ID of the unlocking position.
+        lockup_id: u64,
+    },
+}
+
+/// Response to [`SuperfluidQueryMsg::Validators`].
+#[cw_serde]
+pub struct ValidatorsResponse {
+    /// The validators new stakes are delegated to.
+    pub validators: Vec<String>,
+    /// The LP tokens superfluid delegated to each validator, including
+    /// removed validators that still have locks delegated to them.
+    pub delegations: Vec<Delegation>,
+}
+
+#[cw_serde]
+pub struct MigrateMsg {}
@@ -0,0 +1,469 @@
+use apollo_cw_asset::AssetList;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    Addr, Deps, DepsMut, Env, Event, Order, QuerierWrapper, Response, StdError, StdResult,
+    Storage, SubMsg, Uint128,
+};
+use cw_dex::traits::{LockedStaking, Rewards, Stake, Unlock};
+use cw_dex::CwDexError;
+use cw_storage_plus::{Index, IndexList, IndexedMap, Map, MultiIndex};
+use cw_utils::Duration;
+use osmosis_std::types::cosmos::base::v1beta1::Coin;
+use osmosis_std::types::osmosis::lockup::LockupQuerier;
+use osmosis_std::types::osmosis::superfluid::{
+    MsgLockAndSuperfluidDelegate, MsgSuperfluidUndelegateAndUnbondLock, SuperfluidQuerier,
+};
+
+/// Reply ID of the `MsgLockAndSuperfluidDelegate` submessages sent by
+/// [`OsmosisSuperfluidStaking::stake`].
+pub const SUPERFLUID_DELEGATE_REPLY_ID: u64 = 7001;
+/// Flag set in the reply ID of the `MsgSuperfluidUndelegateAndUnbondLock`
+/// submessages sent by [`OsmosisSuperfluidStaking::unlock`]. The remaining bits
+/// hold the ID of the undelegated lock, so that only that lock has to be synced
+/// in the reply.
+pub const SUPERFLUID_UNDELEGATE_REPLY_ID_FLAG: u64 = 1 << 63;
+
+/// The IDs of the unbonding locks backing each claim, indexed by claim ID. A
+/// single unlock can undelegate several locks.
+pub const UNBONDING_LOCKS: Map<u64, Vec<u64>> = Map::new("unbonding_locks");
+
+/// A lock owned by the vault that is superfluid delegated to a validator.
+#[cw_serde]
+pub struct SuperfluidLock {
+    /// The ID of the lock.
+    pub id: u64,
+    /// The validator the lock is superfluid delegated to.
+    pub validator: String,
+    /// The amount of LP tokens in the lock.
+    pub amount: Uint128,
+}
+
+/// The LP tokens superfluid delegated to a validator by the vault's locks.
+#[cw_serde]
+pub struct Delegation {
+    /// The validator address.
+    pub validator: String,
+    /// The total amount of LP tokens in the locks delegated to the validator.
+    pub amount: Uint128,
+}
+
+/// Helper struct for indexing the superfluid locks. Needed by the
+/// [`IndexedMap`] implementation.
+pub struct LockIndexes<'a> {
+    /// Index mapping a validator to all locks delegated to it.
+    pub validator: MultiIndex<'a, String, SuperfluidLock, u64>,
+}
+
+impl<'a> IndexList<SuperfluidLock> for LockIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<SuperfluidLock>> + '_> {
+        let v: Vec<&dyn Index<SuperfluidLock>> = vec![&self.validator];
+        Box::new(v.into_iter())
+    }
+}
+
+/// The superfluid delegated locks owned by the vault, indexed by lock ID.
+pub fn locks<'a>() -> IndexedMap<'a, u64, SuperfluidLock, LockIndexes<'a>> {
+    let indexes = LockIndexes {
+        validator: MultiIndex::new(
+            |_pk, lock| lock.validator.clone(),
+            "superfluid_locks",
+            "superfluid_locks__validator",
+        ),
+    };
+    IndexedMap::new("superfluid_locks", indexes)
+}
+
+/// Staking implementation that locks LP tokens and superfluid delegates them
+/// to a set of validators.
+///
+/// Every stake creates a new lock delegated to the active validator with the
+/// smallest superfluid delegation, since `MsgLockAndSuperfluidDelegate` cannot
+/// add to an existing lock. The locks are tracked in [`locks`], and their total
+/// per validator in `delegations`, so that picking a validator does not
+/// depend on the number of locks. Both are kept in sync with the chain in the
+/// replies of the stake and unlock submessages.
+#[cw_serde]
+pub struct OsmosisSuperfluidStaking {
+    /// The lockup duration in seconds. Must be equal to the unbonding duration
+    /// of the chain for superfluid delegation to be allowed.
+    pub lockup_duration: u64,
+    /// The denom of the LP token.
+    pub lp_token_denom: String,
+    /// The validators new stakes are delegated to.
+    pub validators: Vec<String>,
+    /// The LP tokens superfluid delegated to each validator. Validators are
+    /// removed once they have no delegation and are not in the validator set.
+    pub delegations: Vec<Delegation>,
+}
+
+impl OsmosisSuperfluidStaking {
+    pub fn new(
+        lockup_duration: u64,
+        lp_token_denom: String,
+        validators: Vec<String>,
+    ) -> StdResult<Self> {
+        let mut staking = Self {
+            lockup_duration,
+            lp_token_denom,
+            validators: vec![],
+            delegations: vec![],
+        };
+        staking.update_validators(validators, vec![])?;
+
+        Ok(staking)
+    }
+
+    /// Adds and removes validators from the validator set. Locks delegated to
+    /// removed validators stay delegated until they are unlocked, and are
+    /// unlocked before any other locks.
+    pub fn update_validators(&mut self, add: Vec<String>, remove: Vec<String>) -> StdResult<()> {
+        for validator in add {
+            if validator.is_empty() {
+                return Err(StdError::generic_err("Validator address cannot be empty"));
+            }
+            if !self.validators.contains(&validator) {
+                self.validators.push(validator);
+            }
+        }
+        self.validators.retain(|v| !remove.contains(v));
+
+        if self.validators.is_empty() {
+            return Err(StdError::generic_err("Validator set cannot be empty"));
+        }
+        self.prune_delegations();
+
+        Ok(())
+    }
+
+    /// Returns the amount of LP tokens superfluid delegated to `validator`.
+    pub fn delegated_amount(&self, validator: &str) -> Uint128 {
+        self.delegations
+            .iter()
+            .find(|d| d.validator == validator)
+            .map(|d| d.amount)
+            .unwrap_or_default()
+    }
+
+    /// Sets the amount of LP tokens superfluid delegated to `validator`.
+    fn set_delegated_amount(&mut self, validator: &str, amount: Uint128) {
+        match self.delegations.iter_mut().find(|d| d.validator == validator) {
+            Some(delegation) => delegation.amount = amount,
+            None => self.delegations.push(Delegation {
+                validator: validator.to_string(),
+                amount,
+            }),
+        }
+        self.prune_delegations();
+    }
+
+    /// Removes the delegations of validators outside of the validator set that
+    /// have nothing left delegated to them.
+    fn prune_delegations(&mut self) {
+        let validators = &self.validators;
+        self.delegations.retain(|d| !d.amount.is_zero() || validators.contains(&d.validator));
+    }
+
+    /// Returns the active validator with the smallest superfluid delegation.
+    /// Ties are broken by the order of the validator set.
+    pub fn next_validator(&self) -> StdResult<&String> {
+        self.validators
+            .iter()
+            .min_by_key(|v| self.delegated_amount(v))
+            .ok_or_else(|| StdError::generic_err("Validator set cannot be empty"))
+    }
+
+    /// Returns the locks to undelegate and the amount to unbond from each of
+    /// them in order to unlock `amount` LP tokens. Locks delegated to
+    /// validators that are no longer in the validator set are unlocked first,
+    /// then the locks of the validators with the largest delegations, oldest
+    /// lock first. Only the locks needed to cover `amount` are read.
+    pub fn locks_to_unlock(
+        &self,
+        storage: &dyn Storage,
+        amount: Uint128,
+    ) -> StdResult<Vec<(u64, Uint128)>> {
+        let mut delegations: Vec<&Delegation> = self.delegations.iter().collect();
+        delegations.sort_by(|a, b| {
+            let a_active = self.validators.contains(&a.validator);
+            let b_active = self.validators.contains(&b.validator);
+            a_active.cmp(&b_active).then(b.amount.cmp(&a.amount))
+        });
+
+        let locks = locks();
+        let mut remaining = amount;
+        let mut to_unlock = vec![];
+        for delegation in delegations {
+            let validator_locks = locks.idx.validator.prefix(delegation.validator.clone()).range(
+                storage,
+                None,
+                None,
+                Order::Ascending,
+            );
+            for lock in validator_locks {
+                if remaining.is_zero() {
+                    break;
+                }
+                let (id, lock) = lock?;
+                let unlock_amount = remaining.min(lock.amount);
+                to_unlock.push((id, unlock_amount));
+                remaining -= unlock_amount;
+            }
+        }
+
+        if !remaining.is_zero() {
+            return Err(StdError::generic_err(format!(
+                "Not enough superfluid delegated LP tokens to unlock {}",
+                amount
+            )));
+        }
+
+        Ok(to_unlock)
+    }
+
+    /// Starts tracking the lock with ID `lock_id` created by
+    /// `MsgLockAndSuperfluidDelegate`.
+    pub fn add_lock(&mut self, deps: DepsMut, lock_id: u64) -> StdResult<()> {
+        let validator = SuperfluidQuerier::new(&deps.querier)
+            .connected_intermediary_account(lock_id)?
+            .account
+            .ok_or_else(|| {
+                StdError::generic_err(format!("Lock {} is not superfluid delegated", lock_id))
+            })?
+            .val_addr;
+        let amount = self.query_lock_amount(&deps.querier, lock_id)?.unwrap_or_default();
+
+        let delegated = self.delegated_amount(&validator);
+        self.set_delegated_amount(&validator, delegated + amount);
+        locks().save(
+            deps.storage,
+            lock_id,
+            &SuperfluidLock {
+                id: lock_id,
+                validator,
+                amount,
+            },
+        )
+    }
+
+    /// Updates the amount of the tracked lock with ID `lock_id` from the chain.
+    /// The lock is removed if it no longer exists or has started unlocking.
+    pub fn sync_lock(&mut self, deps: DepsMut, lock_id: u64) -> StdResult<()> {
+        let mut lock = match locks().may_load(deps.storage, lock_id)? {
+            Some(lock) => lock,
+            None => return Ok(()),
+        };
+        let amount = self.query_lock_amount(&deps.querier, lock_id)?.unwrap_or_default();
+
+        let delegated = self.delegated_amount(&lock.validator);
+        self.set_delegated_amount(&lock.validator, delegated - lock.amount + amount);
+        if amount.is_zero() {
+            locks().remove(deps.storage, lock_id)
+        } else {
+            lock.amount = amount;
+            locks().save(deps.storage, lock_id, &lock)
+        }
+    }
+
+    /// Queries the amount of LP tokens in the lock with ID `lock_id`. Returns
+    /// `None` if the lock does not exist or has started unlocking.
+    fn query_lock_amount(
+        &self,
+        querier: &QuerierWrapper,
+        lock_id: u64,
+    ) -> StdResult<Option<Uint128>> {
+        let lock = match LockupQuerier::new(querier).locked_by_id(lock_id) {
+            Ok(res) => res.lock,
+            Err(_) => None,
+        };
+
+        lock.filter(|lock| lock.end_time.as_ref().map_or(true, |t| t.seconds <= 0))
+            .map(|lock| {
+                lock.coins
+                    .iter()
+                    .filter(|c| c.denom == self.lp_token_denom)
+                    .map(|c| c.amount.parse::<Uint128>())
+                    .sum::<StdResult<Uint128>>()
+            })
+            .transpose()
+    }
+}
+
+impl Rewards for OsmosisSuperfluidStaking {
+    fn claim_rewards(&self, _deps: Deps, _env: &Env) -> Result<Response, CwDexError> {
+        // Gauge incentives and superfluid staking rewards are automatically
+        // distributed to the lock owner every epoch.
+        Ok(Response::new())
+    }
+
+    fn query_pending_rewards(
+        &self,
+        _querier: &QuerierWrapper,
+        _user: &Addr,
+    ) -> Result<AssetList, CwDexError> {
+        // Rewards are automatically distributed every epoch.
+        Ok(AssetList::new())
+    }
+}
+
+impl Stake for OsmosisSuperfluidStaking {
+    fn stake(&self, _deps: Deps, env: &Env, amount: Uint128) -> Result<Response, CwDexError> {
+        let validator = self.next_validator()?;
+
+        let lock_msg = MsgLockAndSuperfluidDelegate {
+            sender: env.contract.address.to_string(),
+            coins: vec![Coin {
+                denom: self.lp_token_denom.clone(),
+                amount: amount.to_string(),
+            }],
+            val_addr: validator.clone(),
+        };
+
+        let event = Event::new("apollo/vaults/superfluid_staking")
+            .add_attribute("action", "stake")
+            .add_attribute("validator", validator)
+            .add_attribute("amount", amount);
+
+        Ok(Response::new()
+            .add_submessage(SubMsg::reply_on_success(lock_msg, SUPERFLUID_DELEGATE_REPLY_ID))
+            .add_event(event))
+    }
+}
+
+impl Unlock for OsmosisSuperfluidStaking {
+    fn unlock(&self, deps: Deps, env: &Env, amount: Uint128) -> Result<Response, CwDexError> {
+        let undelegate_msgs = self
+            .locks_to_unlock(deps.storage, amount)?
+            .into_iter()
+            .map(|(lock_id, amount)| {
+                SubMsg::reply_on_success(
+                    MsgSuperfluidUndelegateAndUnbondLock {
+                        sender: env.contract.address.to_string(),
+                        lock_id,
+                        coin: Some(Coin {
+                            denom: self.lp_token_denom.clone(),
+                            amount: amount.to_string(),
+                        }),
+                    },
+                    SUPERFLUID_UNDELEGATE_REPLY_ID_FLAG | lock_id,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Response::new().add_submessages(undelegate_msgs))
+    }
+
+    fn withdraw_unlocked(
+        &self,
+        _deps: Deps,
+        _env: &Env,
+        _amount: Uint128,
+    ) -> Result<Response, CwDexError> {
+        // Unbonded tokens are automatically returned to the lock owner when the
+        // unlocking period ends.
+        Ok(Response::new())
+    }
+}
+
+impl LockedStaking for OsmosisSuperfluidStaking {
+    fn get_lockup_duration(&self, _deps: Deps) -> Result<Duration, CwDexError> {
+        Ok(Duration::Time(self.lockup_duration))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::MockStorage;
+
+    use super::*;
+
+    fn staking(
+        storage: &mut dyn Storage,
+        locks: Vec<(u64, &str, u128)>,
+    ) -> OsmosisSuperfluidStaking {
+        let mut staking = OsmosisSuperfluidStaking::new(
+            1209600,
+            "gamm/pool/1".to_string(),
+            vec!["val1".to_string(), "val2".to_string()],
+        )
+        .unwrap();
+        for (id, validator, amount) in locks {
+            let delegated = staking.delegated_amount(validator);
+            staking.set_delegated_amount(validator, delegated + Uint128::new(amount));
+            super::locks()
+                .save(
+                    storage,
+                    id,
+                    &SuperfluidLock {
+                        id,
+                        validator: validator.to_string(),
+                        amount: Uint128::new(amount),
+                    },
+                )
+                .unwrap();
+        }
+        staking
+    }
+
+    #[test]
+    fn test_update_validators() {
+        let mut storage = MockStorage::new();
+        let mut staking = staking(&mut storage, vec![(1, "val2", 100)]);
+
+        staking.update_validators(vec!["val3".to_string(), "val1".to_string()], vec![]).unwrap();
+        assert_eq!(staking.validators, vec!["val1", "val2", "val3"]);
+
+        // The delegation of a removed validator is kept until it is unlocked
+        staking.update_validators(vec![], vec!["val2".to_string()]).unwrap();
+        assert_eq!(staking.validators, vec!["val1", "val3"]);
+        assert_eq!(staking.delegated_amount("val2"), Uint128::new(100));
+
+        staking.set_delegated_amount("val2", Uint128::zero());
+        assert!(staking.delegations.iter().all(|d| d.validator != "val2"));
+
+        let err = staking
+            .update_validators(vec![], vec!["val1".to_string(), "val3".to_string()])
+            .unwrap_err();
+        assert_eq!(err, StdError::generic_err("Validator set cannot be empty"));
+    }
+
+    #[test]
+    fn test_next_validator() {
+        let mut storage = MockStorage::new();
+        assert_eq!(staking(&mut storage, vec![]).next_validator().unwrap(), "val1");
+        assert_eq!(staking(&mut storage, vec![(1, "val1", 100)]).next_validator().unwrap(), "val2");
+
+        // Locks delegated to the same validator are summed up
+        let staking =
+            staking(&mut storage, vec![(1, "val1", 100), (2, "val2", 50), (3, "val2", 100)]);
+        assert_eq!(staking.delegated_amount("val2"), Uint128::new(150));
+        assert_eq!(staking.delegations.len(), 2);
+        assert_eq!(staking.next_validator().unwrap(), "val1");
+    }
+
+    #[test]
+    fn test_locks_to_unlock() {
+        let mut storage = MockStorage::new();
+        let mut staking = staking(
+            &mut storage,
+            vec![(1, "val1", 100), (2, "val2", 200), (3, "val3", 50), (4, "val2", 100)],
+        );
+        staking.validators = vec!["val1".to_string(), "val2".to_string()];
+
+        // Locks of removed validators are unlocked first, then the locks of
+        // the validator with the largest delegation
+        assert_eq!(
+            staking.locks_to_unlock(&storage, Uint128::new(400)).unwrap(),
+            vec![
+                (3, Uint128::new(50)),
+                (2, Uint128::new(200)),
+                (4, Uint128::new(100)),
+                (1, Uint128::new(50))
+            ]
+        );
+        assert_eq!(
+            staking.locks_to_unlock(&storage, Uint128::new(20)).unwrap(),
+            vec![(3, Uint128::new(20))]
+        );
+        staking.locks_to_unlock(&storage, Uint128::new(451)).unwrap_err();
+    }
+}
@@ -1,10 +1,13 @@
 use cosmwasm_schema::write_api;
-use pablo_vault_types::vault::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use osmosis_vault::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+use simple_vault::msg::SudoMsg;
 
 fn main() {
     write_api! {
         instantiate: InstantiateMsg,
         execute: ExecuteMsg,
         query: QueryMsg,
+        migrate: MigrateMsg,
+        sudo: SudoMsg,
     }
 }
@@ -23,14 +23,17 @@ use osmosis_std::types::osmosis::lockup::{MsgBeginUnlockingResponse, MsgLockToke
 use semver::Version;
 use simple_vault::{
     error::ContractError,
+    execute_compound::{PROVIDE_LIQUIDITY_REPLY_ID, SELL_REWARDS_REPLY_ID},
+    execute_remote_withdrawal::WITHDRAW_UNLOCKED_REMOTE_REPLY_ID_OFFSET,
     msg::{
         CallbackMsg, ExtensionExecuteMsg, ExtensionQueryMsg, SimpleExtensionExecuteMsg,
-        SimpleExtensionQueryMsg,
+        SimpleExtensionQueryMsg, SudoMsg, TrancheCallbackMsg, TrancheExecuteMsg, TrancheQueryMsg,
     },
     SimpleVault,
 };
 
 use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+use crate::tranche;
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:osmosis-vault";
@@ -91,7 +94,7 @@ pub fn execute(
         ExecuteMsg::Deposit {
             amount,
             recipient,
-        } => contract.execute_deposit(deps, env, &info, amount, recipient),
+        } => contract.execute_deposit(deps, env, &info, amount, recipient, None),
         ExecuteMsg::Redeem {
             recipient: _,
             amount: _,
@@ -133,7 +136,7 @@ pub fn execute(
             ExtensionExecuteMsg::Simple(msg) => match msg {
                 SimpleExtensionExecuteMsg::UpdateConfig {
                     updates,
-                } => contract.execute_update_config(deps, info, updates),
+                } => contract.execute_update_config(deps, env, info, updates),
                 SimpleExtensionExecuteMsg::UpdateAdmin {
                     address,
                 } => contract.execute_update_admin(deps, info, address),
@@ -143,6 +146,50 @@ pub fn execute(
                 SimpleExtensionExecuteMsg::DropAdminTransfer {} => {
                     contract.execute_drop_admin_transfer(deps, info)
                 }
+                SimpleExtensionExecuteMsg::Deposit {
+                    amount,
+                    recipient,
+                    referrer,
+                } => contract.execute_deposit(deps, env, &info, amount, recipient, referrer),
+                SimpleExtensionExecuteMsg::ClaimReferralFees {
+                    recipient,
+                } => contract.execute_claim_referral_fees(deps, info, recipient),
+                SimpleExtensionExecuteMsg::SyncReferral {
+                    address,
+                } => contract.execute_sync_referral(deps, address),
+                SimpleExtensionExecuteMsg::InstantRedeem {
+                    amount,
+                    recipient,
+                } => contract.execute_instant_redeem(deps, env, &info, amount, recipient),
+                SimpleExtensionExecuteMsg::SettleEpoch {} => {
+                    contract.execute_settle_epoch(deps, env)
+                }
+                SimpleExtensionExecuteMsg::ClaimEpochShares {
+                    epoch_id,
+                    recipient,
+                } => contract.execute_claim_epoch_shares(deps, env, info, epoch_id, recipient),
+                SimpleExtensionExecuteMsg::Sweep {
+                    asset,
+                    recipient,
+                } => contract.execute_sweep(deps, env, info, asset, recipient),
+                SimpleExtensionExecuteMsg::ZapIn {
+                    min_base_tokens,
+                    recipient,
+                } => contract.execute_zap_in(deps, env, &info, min_base_tokens, recipient),
+                SimpleExtensionExecuteMsg::WithdrawUnlockedRemote {
+                    lockup_id,
+                    remote_recipient,
+                } => contract.execute_withdraw_unlocked_remote(
+                    deps,
+                    env,
+                    &info,
+                    lockup_id,
+                    remote_recipient,
+                ),
+                SimpleExtensionExecuteMsg::WithdrawEpochUnlocked {
+                    epoch_id,
+                    recipient,
+                } => contract.execute_withdraw_epoch_unlocked(deps, env, info, epoch_id, recipient),
             },
             ExtensionExecuteMsg::Callback(msg) => {
                 // Assert that only the contract itself can call this
@@ -159,11 +206,48 @@ pub fn execute(
                     }
                     CallbackMsg::Stake {
                         base_token_balance_before,
-                    } => contract.execute_callback_stake(deps, env, base_token_balance_before),
+                        user_deposit_amount,
+                    } => tranche::execute_callback_stake(
+                        deps,
+                        env,
+                        base_token_balance_before,
+                        user_deposit_amount,
+                    ),
+                    CallbackMsg::CheckSharePrice {
+                        total_staked_base_tokens_before,
+                        vault_token_supply_before,
+                        pending_mint_amount,
+                    } => contract.execute_callback_check_share_price(
+                        deps,
+                        total_staked_base_tokens_before,
+                        vault_token_supply_before,
+                        pending_mint_amount,
+                    ),
+                    CallbackMsg::ZapInDeposit {
+                        base_token_balance_before,
+                        min_base_tokens,
+                        recipient,
+                    } => contract.execute_callback_zap_in_deposit(
+                        deps,
+                        env,
+                        base_token_balance_before,
+                        min_base_tokens,
+                        recipient,
+                    ),
                     CallbackMsg::MintVaultToken {
                         amount,
                         recipient,
                     } => contract.execute_callback_mint_vault_token(deps, env, amount, recipient),
+                    CallbackMsg::InstantRedeem {
+                        recipient,
+                        amount,
+                    } => contract.execute_callback_instant_redeem(deps, env, amount, recipient),
+                    CallbackMsg::SettleEpochDeposits {
+                        epoch_id,
+                    } => contract.execute_callback_settle_epoch_deposits(deps, env, epoch_id),
+                    CallbackMsg::SettleEpochUnlocks {
+                        epoch_id,
+                    } => contract.execute_callback_settle_epoch_unlocks(deps, epoch_id),
                     CallbackMsg::Unlock {
                         owner,
                         vault_token_amount,
@@ -171,8 +255,75 @@ pub fn execute(
                         contract.execute_callback_unlock(deps, env, info, owner, vault_token_amount)
                     }
                     CallbackMsg::SaveClaim {} => contract.execute_callback_save_claim(deps),
+                    // Only reachable when `simple-vault` is compiled with features
+                    // enabled by other contracts in the workspace.
+                    #[allow(unreachable_patterns)]
+                    _ => Err(ContractError::from("Unsupported callback for locked vaults")),
                 }
             }
+            ExtensionExecuteMsg::Tranche(msg) => match msg {
+                TrancheExecuteMsg::AddTranche {
+                    lockup_duration,
+                    vault_token_subdenom,
+                    reward_weight,
+                } => tranche::execute_add_tranche(
+                    deps,
+                    env,
+                    info,
+                    lockup_duration,
+                    vault_token_subdenom,
+                    reward_weight,
+                ),
+                TrancheExecuteMsg::UpdateRewardWeight {
+                    tranche_id,
+                    reward_weight,
+                } => tranche::execute_update_reward_weight(deps, info, tranche_id, reward_weight),
+                TrancheExecuteMsg::Deposit {
+                    tranche_id,
+                    amount,
+                    recipient,
+                } => tranche::execute_deposit(deps, env, info, tranche_id, amount, recipient),
+                TrancheExecuteMsg::Unlock {
+                    tranche_id,
+                    amount,
+                } => tranche::execute_unlock(deps, env, info, tranche_id, amount),
+                TrancheExecuteMsg::ForceRedeem {
+                    tranche_id,
+                    amount,
+                    recipient,
+                } => tranche::execute_force_redeem(deps, env, info, tranche_id, amount, recipient),
+                TrancheExecuteMsg::Callback(msg) => {
+                    // Assert that only the contract itself can call this
+                    if info.sender != env.contract.address {
+                        return Err(ContractError::Unauthorized {});
+                    }
+
+                    match msg {
+                        TrancheCallbackMsg::Stake {
+                            tranche_id,
+                            amount,
+                        } => tranche::execute_callback_stake_tranche(deps, env, tranche_id, amount),
+                        TrancheCallbackMsg::MintVaultToken {
+                            tranche_id,
+                            amount,
+                            recipient,
+                        } => tranche::execute_callback_mint_vault_token(
+                            deps, env, tranche_id, amount, recipient,
+                        ),
+                        TrancheCallbackMsg::Unlock {
+                            tranche_id,
+                            owner,
+                            vault_token_amount,
+                        } => tranche::execute_callback_unlock(
+                            deps,
+                            env,
+                            tranche_id,
+                            owner,
+                            vault_token_amount,
+                        ),
+                    }
+                }
+            },
         },
     }
 }
@@ -233,15 +384,60 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
             },
             ExtensionQueryMsg::Simple(msg) => match msg {
                 SimpleExtensionQueryMsg::State {} => to_binary(&contract.query_state(deps, env)?),
+                SimpleExtensionQueryMsg::Referral {
+                    address,
+                } => to_binary(&contract.query_referral(deps, address)?),
+                SimpleExtensionQueryMsg::Buffer {} => to_binary(&contract.query_buffer(deps)?),
+                SimpleExtensionQueryMsg::CurrentEpoch {} => {
+                    to_binary(&contract.query_current_epoch(deps, env)?)
+                }
+                SimpleExtensionQueryMsg::SettledEpoch {
+                    epoch_id,
+                } => to_binary(&contract.query_settled_epoch(deps, epoch_id)?),
+                SimpleExtensionQueryMsg::EpochPosition {
+                    epoch_id,
+                    address,
+                } => to_binary(&contract.query_epoch_position(deps, epoch_id, address)?),
+                SimpleExtensionQueryMsg::LastCompoundFailure {} => {
+                    to_binary(&contract.query_last_compound_failure(deps)?)
+                }
+                SimpleExtensionQueryMsg::UnsoldRewards {} => {
+                    to_binary(&contract.query_unsold_rewards(deps)?)
+                }
+                SimpleExtensionQueryMsg::UnaccountedBalances {
+                    cw20s,
+                } => to_binary(&contract.query_unaccounted_balances(deps, env, cw20s)?),
+            },
+            ExtensionQueryMsg::Tranche(msg) => match msg {
+                TrancheQueryMsg::Tranches {} => to_binary(&tranche::query_tranches(deps)?),
+                TrancheQueryMsg::Tranche {
+                    tranche_id,
+                } => to_binary(&tranche::query_tranche(deps, tranche_id)?),
+                TrancheQueryMsg::ConvertToAssets {
+                    tranche_id,
+                    amount,
+                } => to_binary(&tranche::query_convert_to_assets(deps, tranche_id, amount)?),
             },
         },
     }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn reply(deps: DepsMut, _env: Env, reply: Reply) -> Result<Response, ContractError> {
+pub fn reply(deps: DepsMut, env: Env, reply: Reply) -> Result<Response, ContractError> {
     let contract = OsmosisVaultContract::default();
 
+    // The steps of a compound only reply on error, which is recorded instead of
+    // reverting the transaction
+    if reply.id == SELL_REWARDS_REPLY_ID || reply.id == PROVIDE_LIQUIDITY_REPLY_ID {
+        return contract.reply_compound_failure(deps, env, reply);
+    }
+
+    // The transfer of a remote withdrawal replies with the ID of the withdrawal
+    // added to the offset
+    if reply.id >= WITHDRAW_UNLOCKED_REMOTE_REPLY_ID_OFFSET {
+        return contract.reply_withdraw_unlocked_remote(deps, reply);
+    }
+
     if let SubMsgResult::Err(e) = reply.result {
         return Err(ContractError::Std(StdError::generic_err(e)));
     }
@@ -260,9 +456,9 @@ pub fn reply(deps: DepsMut, _env: Env, reply: Reply) -> Result<Response, Contrac
                 // already exists an "add_tokens_to_lock" event will be emitted instead.
                 let res: MsgLockTokensResponse = b.try_into().map_err(ContractError::Std)?;
 
-                let mut staking = contract.staking.load(deps.storage)?;
-                staking.lock_id = Some(res.id);
-                contract.staking.save(deps.storage, &staking)?;
+                // The lock belongs to a tranche if one is locking, otherwise to the primary
+                // tranche.
+                tranche::save_lock_id(deps, res.id)?;
 
                 let event = Event::new("apollo/vault/lock/reply")
                     .add_attribute("vault_type", "osmosis")
@@ -288,6 +484,15 @@ pub fn reply(deps: DepsMut, _env: Env, reply: Reply) -> Result<Response, Contrac
     }
 }
 
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
+    let contract = OsmosisVaultContract::default();
+
+    match msg {
+        SudoMsg::IbcLifecycleComplete(msg) => contract.sudo_ibc_lifecycle_complete(deps, msg),
+    }
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
     let version: Version = CONTRACT_VERSION.parse()?;
@@ -1,2 +1,4 @@
 pub mod contract;
 pub mod msg;
+pub mod state;
+pub mod tranche;
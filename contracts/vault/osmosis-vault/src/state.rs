@@ -0,0 +1,42 @@
+use cosmwasm_std::{Decimal, Order, StdResult, Storage, Uint128};
+use cw_dex::osmosis::OsmosisStaking;
+use cw_storage_plus::{Item, Map};
+use cw_vault_token::osmosis::OsmosisDenom;
+use serde::{Deserialize, Serialize};
+
+/// A lockup tranche in addition to the primary tranche of the vault. The
+/// primary tranche is stored by `SimpleVault` and has ID 0.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Tranche {
+    /// The staking implementation locking the base tokens of the tranche.
+    pub staking: OsmosisStaking,
+    /// The vault token of the tranche.
+    pub vault_token: OsmosisDenom,
+    /// The base tokens locked in the tranche.
+    pub total_staked_base_tokens: Uint128,
+    /// The weight of the tranche when splitting compounded rewards, per staked
+    /// base token.
+    pub reward_weight: Decimal,
+}
+
+/// The tranches of the vault, by ID. IDs start at 1.
+pub const TRANCHES: Map<u64, Tranche> = Map::new("tranches");
+
+/// The weight of the primary tranche when splitting compounded rewards. One if
+/// not set.
+pub const PRIMARY_REWARD_WEIGHT: Item<Decimal> = Item::new("primary_reward_weight");
+
+/// The ID of the tranche whose lock is awaiting the reply of the lockup
+/// module. Not set when the primary tranche is locking.
+pub const PENDING_TRANCHE_LOCK: Item<u64> = Item::new("pending_tranche_lock");
+
+/// Returns all tranches, excluding the primary tranche, in order of ID.
+pub fn load_tranches(storage: &dyn Storage) -> StdResult<Vec<(u64, Tranche)>> {
+    TRANCHES.range(storage, None, None, Order::Ascending).collect()
+}
+
+/// Returns the weight of the primary tranche when splitting compounded
+/// rewards.
+pub fn primary_reward_weight(storage: &dyn Storage) -> StdResult<Decimal> {
+    Ok(PRIMARY_REWARD_WEIGHT.may_load(storage)?.unwrap_or_else(Decimal::one))
+}
@@ -0,0 +1,556 @@
+use apollo_cw_asset::Asset;
+use apollo_utils::assets::receive_asset;
+use apollo_utils::responses::merge_responses;
+use cosmwasm_std::{
+    attr, Addr, Decimal, Deps, DepsMut, Env, Event, MessageInfo, Response, StdError, StdResult,
+    Uint128,
+};
+use cw_dex::osmosis::OsmosisStaking;
+use cw_dex::traits::{ForceUnlock, LockedStaking, Pool, Stake};
+use cw_utils::Duration;
+use cw_vault_token::osmosis::OsmosisDenom;
+use cw_vault_token::VaultToken;
+use simple_vault::error::ContractError;
+use simple_vault::msg::{CallbackMsg, TrancheCallbackMsg, TrancheResponse};
+
+use crate::contract::OsmosisVaultContract;
+use crate::state::{
+    load_tranches, primary_reward_weight, Tranche, PENDING_TRANCHE_LOCK, PRIMARY_REWARD_WEIGHT,
+    TRANCHES,
+};
+
+/// The ID of the tranche the vault was instantiated with.
+pub const PRIMARY_TRANCHE_ID: u64 = 0;
+
+fn load_tranche(deps: Deps, tranche_id: u64) -> Result<Tranche, ContractError> {
+    TRANCHES
+        .may_load(deps.storage, tranche_id)?
+        .ok_or_else(|| ContractError::from(format!("Tranche {} does not exist", tranche_id)))
+}
+
+/// Returns an error if the vault is in batched mode. Deposits and unlocks of
+/// tranches are not queued in epochs.
+fn assert_not_batched(deps: Deps) -> Result<(), ContractError> {
+    let contract = OsmosisVaultContract::default();
+    if contract.config.load(deps.storage)?.epoch_duration > 0 {
+        return Err(ContractError::from(
+            "Tranche deposits and unlocks are not supported in batched mode",
+        ));
+    }
+    Ok(())
+}
+
+/// Add a tranche locking the base tokens with `lockup_duration`. Can only be
+/// called by the admin.
+pub fn execute_add_tranche(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    lockup_duration: u64,
+    vault_token_subdenom: String,
+    reward_weight: Decimal,
+) -> Result<Response, ContractError> {
+    let contract = OsmosisVaultContract::default();
+    contract.admin.assert_admin(deps.as_ref(), &info.sender)?;
+
+    // Validate that 10 osmo for vault token creation are sent
+    let osmo_amount = info
+        .funds
+        .iter()
+        .find(|coin| coin.denom == "uosmo")
+        .map(|coin| coin.amount)
+        .unwrap_or_default();
+    if osmo_amount < Uint128::new(10_000_000) {
+        return Err(ContractError::from(
+            "A minimum of 10_000_000 uosmo must be sent to create the vault token",
+        ));
+    }
+
+    // Locks of the contract with the same duration would be merged by the lockup
+    // module, so each tranche must have its own duration.
+    let tranches = load_tranches(deps.storage)?;
+    let mut durations =
+        vec![contract.staking.load(deps.storage)?.get_lockup_duration(deps.as_ref())?];
+    for (_, tranche) in &tranches {
+        durations.push(tranche.staking.get_lockup_duration(deps.as_ref())?);
+    }
+    if durations.contains(&Duration::Time(lockup_duration)) {
+        return Err(ContractError::from(format!(
+            "A tranche with lockup duration {} already exists",
+            lockup_duration
+        )));
+    }
+
+    let tranche_id = tranches.last().map_or(PRIMARY_TRANCHE_ID, |(id, _)| *id) + 1;
+    let lp_token = contract.pool.load(deps.storage)?.lp_token().to_string();
+    let staking = OsmosisStaking::new(lockup_duration, None, lp_token)?;
+    let vault_token = OsmosisDenom::new(env.contract.address.to_string(), vault_token_subdenom);
+
+    TRANCHES.save(
+        deps.storage,
+        tranche_id,
+        &Tranche {
+            staking,
+            vault_token: vault_token.clone(),
+            total_staked_base_tokens: Uint128::zero(),
+            reward_weight,
+        },
+    )?;
+
+    let event = Event::new("apollo/vaults/osmosis/tranche").add_attributes(vec![
+        attr("action", "add_tranche"),
+        attr("tranche_id", tranche_id.to_string()),
+        attr("lockup_duration", lockup_duration.to_string()),
+        attr("vault_token", vault_token.to_string()),
+        attr("reward_weight", reward_weight.to_string()),
+    ]);
+
+    Ok(vault_token.instantiate(deps, None)?.add_event(event))
+}
+
+/// Update the weight of a tranche when splitting compounded rewards. Can only
+/// be called by the admin.
+pub fn execute_update_reward_weight(
+    deps: DepsMut,
+    info: MessageInfo,
+    tranche_id: u64,
+    reward_weight: Decimal,
+) -> Result<Response, ContractError> {
+    let contract = OsmosisVaultContract::default();
+    contract.admin.assert_admin(deps.as_ref(), &info.sender)?;
+
+    if tranche_id == PRIMARY_TRANCHE_ID {
+        PRIMARY_REWARD_WEIGHT.save(deps.storage, &reward_weight)?;
+    } else {
+        let mut tranche = load_tranche(deps.as_ref(), tranche_id)?;
+        tranche.reward_weight = reward_weight;
+        TRANCHES.save(deps.storage, tranche_id, &tranche)?;
+    }
+
+    let event = Event::new("apollo/vaults/osmosis/tranche").add_attributes(vec![
+        attr("action", "update_reward_weight"),
+        attr("tranche_id", tranche_id.to_string()),
+        attr("reward_weight", reward_weight.to_string()),
+    ]);
+
+    Ok(Response::new().add_event(event))
+}
+
+/// Deposit base tokens into a tranche. This will first compound the pending
+/// rewards, then the deposited tokens will be locked in the tranche and vault
+/// tokens of the tranche will be minted to the recipient.
+pub fn execute_deposit(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    tranche_id: u64,
+    amount: Uint128,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    let contract = OsmosisVaultContract::default();
+    if tranche_id == PRIMARY_TRANCHE_ID {
+        return contract.execute_deposit(deps, env, &info, amount, recipient, None);
+    }
+    load_tranche(deps.as_ref(), tranche_id)?;
+    assert_not_batched(deps.as_ref())?;
+
+    // Unwrap recipient or use caller's address
+    let recipient = recipient.map_or(Ok(info.sender.clone()), |x| deps.api.addr_validate(&x))?;
+
+    // The base token is an LP share denom, so the deposit is already part of the
+    // contract balance and is not staked by the compound.
+    let receive_res = receive_asset(
+        &info,
+        &env,
+        &Asset::new(contract.base_vault.base_token.load(deps.storage)?, amount),
+    )?;
+    let compound_res = contract.compound(deps, &env, Uint128::zero())?;
+
+    let stake_msg = TrancheCallbackMsg::Stake {
+        tranche_id,
+        amount,
+    }
+    .into_cosmos_msg(&env)?;
+    let mint_msg = TrancheCallbackMsg::MintVaultToken {
+        tranche_id,
+        amount,
+        recipient: recipient.clone(),
+    }
+    .into_cosmos_msg(&env)?;
+
+    let event = Event::new("apollo/vaults/osmosis/tranche").add_attributes(vec![
+        attr("action", "deposit"),
+        attr("tranche_id", tranche_id.to_string()),
+        attr("recipient", recipient),
+        attr("amount", amount),
+    ]);
+
+    Ok(merge_responses(vec![receive_res, compound_res])
+        .add_message(stake_msg)
+        .add_message(mint_msg)
+        .add_event(event))
+}
+
+/// Burn vault tokens of a tranche and start unlocking the base tokens they
+/// represent with the lockup duration of the tranche. The vault tokens must
+/// be sent in the `info.funds` field.
+pub fn execute_unlock(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    tranche_id: u64,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let contract = OsmosisVaultContract::default();
+    if tranche_id == PRIMARY_TRANCHE_ID {
+        return contract.execute_unlock(deps, env, &info, amount);
+    }
+    let tranche = load_tranche(deps.as_ref(), tranche_id)?;
+    assert_not_batched(deps.as_ref())?;
+
+    // Validate that the vault tokens were sent
+    tranche.vault_token.receive(deps.branch(), &env, &info, amount)?;
+
+    // First compound the vault
+    let compound_res = contract.compound(deps, &env, Uint128::zero())?;
+
+    // Continue with the unlock after compounding and store the claim, which is
+    // shared with the primary tranche
+    let unlock_msg = TrancheCallbackMsg::Unlock {
+        tranche_id,
+        owner: info.sender.clone(),
+        vault_token_amount: amount,
+    }
+    .into_cosmos_msg(&env)?;
+    let store_claim_msg = CallbackMsg::SaveClaim {}.into_cosmos_msg(&env)?;
+
+    let event = Event::new("apollo/vaults/osmosis/tranche").add_attributes(vec![
+        attr("action", "unlock"),
+        attr("tranche_id", tranche_id.to_string()),
+        attr("owner", info.sender),
+        attr("amount", amount),
+    ]);
+
+    Ok(compound_res.add_message(unlock_msg).add_message(store_claim_msg).add_event(event))
+}
+
+/// Burn vault tokens of a tranche and force unlock the base tokens they
+/// represent, skipping the lockup duration of the tranche. The sender must be
+/// in the force withdraw whitelist and the vault tokens must be sent in the
+/// `info.funds` field.
+pub fn execute_force_redeem(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    tranche_id: u64,
+    amount: Uint128,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    let contract = OsmosisVaultContract::default();
+    if tranche_id == PRIMARY_TRANCHE_ID {
+        return contract.execute_force_redeem(deps, env, info, amount, recipient);
+    }
+    let mut tranche = load_tranche(deps.as_ref(), tranche_id)?;
+
+    // Check ForceWithdraw whitelist
+    if !contract.config.load(deps.storage)?.force_withdraw_whitelist.contains(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // Unwrap recipient or use caller's address
+    let recipient = recipient.map_or(Ok(info.sender.clone()), |x| deps.api.addr_validate(&x))?;
+
+    // Validate that the vault tokens were sent
+    tranche.vault_token.receive(deps.branch(), &env, &info, amount)?;
+
+    let vault_token_supply = tranche.vault_token.query_total_supply(deps.as_ref())?;
+    let base_tokens = contract.base_vault.calculate_base_tokens(
+        amount,
+        tranche.total_staked_base_tokens,
+        vault_token_supply,
+    )?;
+    tranche.total_staked_base_tokens = tranche.total_staked_base_tokens.checked_sub(base_tokens)?;
+    TRANCHES.save(deps.storage, tranche_id, &tranche)?;
+
+    let burn_res = tranche.vault_token.burn(deps.branch(), &env, amount)?;
+    let force_unlock_res = tranche.staking.force_unlock(deps.as_ref(), &env, None, base_tokens)?;
+    let send_res = contract.base_vault.send_base_tokens(deps, &recipient, base_tokens)?;
+
+    let event = Event::new("apollo/vaults/osmosis/tranche").add_attributes(vec![
+        attr("action", "force_redeem"),
+        attr("tranche_id", tranche_id.to_string()),
+        attr("recipient", recipient),
+        attr("vault_token_amount", amount),
+        attr("redeem_amount", base_tokens),
+    ]);
+
+    Ok(merge_responses(vec![burn_res, force_unlock_res, send_res]).add_event(event))
+}
+
+/// Replaces `CallbackMsg::Stake` of `simple_vault`. Splits the compounded
+/// rewards between the tranches by their staked base tokens times their reward
+/// weight. The share of the primary tranche and any user deposit are staked by
+/// `execute_callback_stake` of `simple_vault`, the shares of the other
+/// tranches are locked by `TrancheCallbackMsg::Stake`. The share price of the
+/// primary tranche is checked by `CallbackMsg::CheckSharePrice`, the share
+/// prices of the other tranches are checked here.
+pub fn execute_callback_stake(
+    deps: DepsMut,
+    env: Env,
+    base_token_balance_before: Uint128,
+    user_deposit_amount: Uint128,
+) -> Result<Response, ContractError> {
+    let contract = OsmosisVaultContract::default();
+    let tranches = load_tranches(deps.storage)?;
+    if tranches.is_empty() {
+        return contract.execute_callback_stake(deps, env, base_token_balance_before);
+    }
+
+    let base_token_balance = contract
+        .base_vault
+        .base_token
+        .load(deps.storage)?
+        .query_balance(&deps.querier, env.contract.address.clone())?;
+    let rewards = base_token_balance
+        .saturating_sub(base_token_balance_before)
+        .saturating_sub(user_deposit_amount);
+
+    let primary_weight = contract.base_vault.total_staked_base_tokens.load(deps.storage)?
+        * primary_reward_weight(deps.storage)?;
+    let tranche_weights: Vec<_> = tranches
+        .iter()
+        .map(|(id, t)| (*id, t.total_staked_base_tokens * t.reward_weight))
+        .collect();
+    let total_weight =
+        tranche_weights.iter().try_fold(primary_weight, |acc, (_, w)| acc.checked_add(*w))?;
+
+    let max_increase = contract.config.load(deps.storage)?.max_share_price_increase;
+    let mut msgs = vec![];
+    let mut event = Event::new("apollo/vaults/osmosis/tranche")
+        .add_attribute("action", "execute_callback_stake");
+    let mut tranche_rewards = Uint128::zero();
+    if !total_weight.is_zero() {
+        for ((tranche_id, weight), (_, tranche)) in tranche_weights.into_iter().zip(&tranches) {
+            let amount = rewards.multiply_ratio(weight, total_weight);
+            if amount.is_zero() {
+                continue;
+            }
+
+            // The rewards only raise the share price of the tranche, which must
+            // stay within `max_share_price_increase`
+            if !max_increase.is_zero() {
+                let vault_token_supply = tranche.vault_token.query_total_supply(deps.as_ref())?;
+                if !vault_token_supply.is_zero() {
+                    let before =
+                        Decimal::from_ratio(tranche.total_staked_base_tokens, vault_token_supply);
+                    let after = Decimal::from_ratio(
+                        tranche.total_staked_base_tokens.checked_add(amount)?,
+                        vault_token_supply,
+                    );
+                    if after > before * (Decimal::one() + max_increase) {
+                        return Err(ContractError::SharePriceOutOfBounds {
+                            before,
+                            after,
+                        });
+                    }
+                }
+            }
+
+            tranche_rewards = tranche_rewards.checked_add(amount)?;
+            msgs.push(
+                TrancheCallbackMsg::Stake {
+                    tranche_id,
+                    amount,
+                }
+                .into_cosmos_msg(&env)?,
+            );
+            event = event.add_attribute(format!("tranche_{}_rewards", tranche_id), amount);
+        }
+    }
+
+    // The rewards of the other tranches stay in the contract until their
+    // callbacks, so they are excluded from what the primary tranche stakes
+    let primary_res = contract.execute_callback_stake(
+        deps,
+        env,
+        base_token_balance_before.checked_add(tranche_rewards)?,
+    )?;
+
+    Ok(primary_res.add_messages(msgs).add_event(event))
+}
+
+/// Lock `amount` base tokens held by the contract in a tranche.
+pub fn execute_callback_stake_tranche(
+    deps: DepsMut,
+    env: Env,
+    tranche_id: u64,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let mut tranche = load_tranche(deps.as_ref(), tranche_id)?;
+    tranche.total_staked_base_tokens = tranche.total_staked_base_tokens.checked_add(amount)?;
+    TRANCHES.save(deps.storage, tranche_id, &tranche)?;
+
+    // Mark the tranche so that the lock reply updates its lock ID instead of the
+    // one of the primary tranche
+    PENDING_TRANCHE_LOCK.save(deps.storage, &tranche_id)?;
+
+    let event = Event::new("apollo/vaults/osmosis/tranche").add_attributes(vec![
+        attr("action", "execute_callback_stake_tranche"),
+        attr("tranche_id", tranche_id.to_string()),
+        attr("amount_to_stake", amount),
+    ]);
+
+    Ok(tranche.staking.stake(deps.as_ref(), &env, amount)?.add_event(event))
+}
+
+/// Mint vault tokens of a tranche to `recipient` for `amount` deposited base
+/// tokens. Called after the deposit was locked by
+/// `execute_callback_stake_tranche`.
+pub fn execute_callback_mint_vault_token(
+    deps: DepsMut,
+    env: Env,
+    tranche_id: u64,
+    amount: Uint128,
+    recipient: Addr,
+) -> Result<Response, ContractError> {
+    let contract = OsmosisVaultContract::default();
+    let tranche = load_tranche(deps.as_ref(), tranche_id)?;
+    let vault_token_supply = tranche.vault_token.query_total_supply(deps.as_ref())?;
+
+    // The deposit was already added to the tranche when it was locked
+    let vault_tokens = contract.base_vault.calculate_vault_tokens(
+        amount,
+        tranche.total_staked_base_tokens.checked_sub(amount)?,
+        vault_token_supply,
+    )?;
+
+    let event = Event::new("apollo/vaults/osmosis/tranche").add_attributes(vec![
+        attr("action", "execute_callback_mint_vault_token"),
+        attr("tranche_id", tranche_id.to_string()),
+        attr("recipient", recipient.to_string()),
+        attr("mint_amount", vault_tokens),
+    ]);
+
+    Ok(tranche.vault_token.mint(deps, &env, &recipient, vault_tokens)?.add_event(event))
+}
+
+/// Burn `vault_token_amount` vault tokens of a tranche held by the contract and
+/// start unlocking the base tokens they represent. The pending claim is saved
+/// by `CallbackMsg::SaveClaim` once the lockup module replied with its ID.
+pub fn execute_callback_unlock(
+    mut deps: DepsMut,
+    env: Env,
+    tranche_id: u64,
+    owner: Addr,
+    vault_token_amount: Uint128,
+) -> Result<Response, ContractError> {
+    let contract = OsmosisVaultContract::default();
+    let mut tranche = load_tranche(deps.as_ref(), tranche_id)?;
+    let vault_token_supply = tranche.vault_token.query_total_supply(deps.as_ref())?;
+
+    let base_tokens = contract.base_vault.calculate_base_tokens(
+        vault_token_amount,
+        tranche.total_staked_base_tokens,
+        vault_token_supply,
+    )?;
+    tranche.total_staked_base_tokens = tranche.total_staked_base_tokens.checked_sub(base_tokens)?;
+    TRANCHES.save(deps.storage, tranche_id, &tranche)?;
+
+    let burn_res = tranche.vault_token.burn(deps.branch(), &env, vault_token_amount)?;
+
+    let expiration = tranche.staking.get_lockup_duration(deps.as_ref())?.after(&env.block);
+    contract.claims.create_pending_claim(deps.storage, &owner, base_tokens, expiration, None)?;
+
+    let unlock_res = tranche.staking.unlock(deps.as_ref(), &env, base_tokens)?;
+
+    let event = Event::new("apollo/vaults/osmosis/tranche").add_attributes(vec![
+        attr("action", "execute_callback_unlock"),
+        attr("tranche_id", tranche_id.to_string()),
+        attr("owner", owner),
+        attr("vault_token_amount", vault_token_amount),
+        attr("lp_tokens_to_unlock", base_tokens),
+    ]);
+
+    Ok(merge_responses(vec![burn_res, unlock_res]).add_event(event))
+}
+
+/// Save the lock ID returned by the lockup module to the tranche that is
+/// locking, or to the primary tranche if no tranche is marked.
+pub fn save_lock_id(deps: DepsMut, lock_id: u64) -> StdResult<()> {
+    match PENDING_TRANCHE_LOCK.may_load(deps.storage)? {
+        Some(tranche_id) => {
+            PENDING_TRANCHE_LOCK.remove(deps.storage);
+            TRANCHES.update(deps.storage, tranche_id, |tranche| {
+                let mut tranche = tranche.ok_or_else(|| StdError::not_found("Tranche"))?;
+                tranche.staking.lock_id = Some(lock_id);
+                Ok(tranche)
+            })?;
+        }
+        None => {
+            let contract = OsmosisVaultContract::default();
+            let mut staking = contract.staking.load(deps.storage)?;
+            staking.lock_id = Some(lock_id);
+            contract.staking.save(deps.storage, &staking)?;
+        }
+    }
+    Ok(())
+}
+
+fn tranche_response(deps: Deps, id: u64, tranche: Tranche) -> StdResult<TrancheResponse> {
+    Ok(TrancheResponse {
+        id,
+        lockup_duration: tranche.staking.get_lockup_duration(deps)?,
+        vault_token: tranche.vault_token.to_string(),
+        total_staked_base_tokens: tranche.total_staked_base_tokens,
+        vault_token_supply: tranche.vault_token.query_total_supply(deps)?,
+        reward_weight: tranche.reward_weight,
+    })
+}
+
+fn primary_tranche(deps: Deps) -> StdResult<Tranche> {
+    let contract = OsmosisVaultContract::default();
+    Ok(Tranche {
+        staking: contract.staking.load(deps.storage)?,
+        vault_token: contract.base_vault.vault_token.load(deps.storage)?,
+        total_staked_base_tokens: contract
+            .base_vault
+            .total_staked_base_tokens
+            .load(deps.storage)?,
+        reward_weight: primary_reward_weight(deps.storage)?,
+    })
+}
+
+/// Returns all tranches, starting with the primary tranche.
+pub fn query_tranches(deps: Deps) -> StdResult<Vec<TrancheResponse>> {
+    let mut tranches = vec![tranche_response(deps, PRIMARY_TRANCHE_ID, primary_tranche(deps)?)?];
+    for (id, tranche) in load_tranches(deps.storage)? {
+        tranches.push(tranche_response(deps, id, tranche)?);
+    }
+    Ok(tranches)
+}
+
+/// Returns the tranche `tranche_id`.
+pub fn query_tranche(deps: Deps, tranche_id: u64) -> StdResult<TrancheResponse> {
+    let tranche = if tranche_id == PRIMARY_TRANCHE_ID {
+        primary_tranche(deps)?
+    } else {
+        load_tranche(deps, tranche_id)?
+    };
+    tranche_response(deps, tranche_id, tranche)
+}
+
+/// Returns the amount of base tokens that `amount` vault tokens of the tranche
+/// `tranche_id` represent.
+pub fn query_convert_to_assets(deps: Deps, tranche_id: u64, amount: Uint128) -> StdResult<Uint128> {
+    let tranche = if tranche_id == PRIMARY_TRANCHE_ID {
+        primary_tranche(deps)?
+    } else {
+        load_tranche(deps, tranche_id)?
+    };
+    let vault_token_supply = tranche.vault_token.query_total_supply(deps)?;
+    OsmosisVaultContract::default().base_vault.calculate_base_tokens(
+        amount,
+        tranche.total_staked_base_tokens,
+        vault_token_supply,
+    )
+}
@@ -45,6 +45,23 @@ pub mod osmosis {
 
     impl Setup {
         pub fn new() -> Self {
+            Self::with_vault(OSMOSIS_VAULT_CONTRACT_NAME, |admin, pool_id, config| InstantiateMsg {
+                admin,
+                pool_id,
+                lockup_duration: 86400u64,
+                config,
+                vault_token_subdenom: "osmosis-vault".to_string(),
+            })
+        }
+
+        /// Sets up the pools, router and liquidity helper and instantiates
+        /// `contract_name` with the message returned by `instantiate_msg`,
+        /// which is given the admin address, the base pool id and the config.
+        pub fn with_vault<M, F>(contract_name: &str, instantiate_msg: F) -> Self
+        where
+            M: Serialize,
+            F: FnOnce(String, u64, ConfigUnchecked) -> M,
+        {
             let app = OsmosisTestApp::new();
             let wasm = Wasm::new(&app);
             let gamm = Gamm::new(&app);
@@ -147,6 +164,14 @@ pub mod osmosis {
                 treasury: treasury.address(),
                 liquidity_helper: lh,
                 router: router_address.clone().into(),
+                referral_fee_share: Decimal::zero(),
+                epoch_duration: 0,
+                buffer_ratio: Decimal::zero(),
+                exit_fee: Decimal::zero(),
+                max_sell_per_compound: vec![],
+                max_share_price_increase: Decimal::zero(),
+                ibc_withdraw_channel: String::new(),
+                ibc_transfer_timeout: 0,
             };
 
             // Update path on the router
@@ -171,14 +196,8 @@ pub mod osmosis {
             let vault_address = instantiate_contract(
                 &wasm,
                 &signer,
-                OSMOSIS_VAULT_CONTRACT_NAME,
-                &InstantiateMsg {
-                    admin: admin.address(),
-                    pool_id: base_pool_id,
-                    lockup_duration: 86400u64,
-                    config,
-                    vault_token_subdenom: "osmosis-vault".to_string(),
-                },
+                contract_name,
+                &instantiate_msg(admin.address(), base_pool_id, config),
             );
 
             Self {
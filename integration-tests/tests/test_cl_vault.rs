@@ -0,0 +1,399 @@
+mod helpers;
+use std::str::FromStr;
+
+use apollo_cw_asset::AssetInfoUnchecked;
+use cosmrs::proto::cosmos::bank::v1beta1::QueryBalanceRequest;
+use cosmwasm_std::{Coin, Decimal, Decimal256, Event, Uint128};
+use cw_vault_standard::msg::VaultInfoResponse;
+use osmosis_cl_vault::msg::{
+    ClExecuteMsg, ClQueryMsg, ExecuteMsg, ExtensionExecuteMsg, ExtensionQueryMsg, InstantiateMsg,
+    PositionResponse, QueryMsg, RangeResponse,
+};
+use osmosis_cl_vault::state::{ConfigUnchecked, Range};
+use osmosis_std::types::cosmos::bank::v1beta1::MsgSend;
+use osmosis_std::types::cosmwasm::wasm::v1::MsgExecuteContractResponse;
+use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::{
+    CreateConcentratedLiquidityPoolsProposal, MsgCreatePosition, Pool, PoolRecord, PoolsRequest,
+};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{
+    MsgSwapExactAmountIn, MsgSwapExactAmountInResponse, SwapAmountInRoute,
+};
+use osmosis_test_tube::{
+    Account, Bank, ConcentratedLiquidity, ExecuteResponse, GovWithAppAccess, Module,
+    OsmosisTestApp, Runner, SigningAccount, Wasm,
+};
+use prost::Message;
+
+use crate::helpers::osmosis::{assert_err, instantiate_contract};
+
+const OSMOSIS_CL_VAULT_CONTRACT_NAME: &str = "osmosis_cl_vault";
+const TWAP_WINDOW: u64 = 600;
+const DENOM0: &str = "uatom";
+const DENOM1: &str = "uosmo";
+
+struct ClSetup {
+    app: OsmosisTestApp,
+    admin: SigningAccount,
+    user: SigningAccount,
+    pool_id: u64,
+    vault_address: String,
+    vault_token: String,
+}
+
+/// Creates an uatom/uosmo concentrated liquidity pool at a price of one with a
+/// spread factor, so that swaps accrue spread rewards, and instantiates the
+/// vault with `reward_assets` sold through the admin account as router.
+fn setup(reward_assets: Vec<AssetInfoUnchecked>) -> ClSetup {
+    setup_at_price(reward_assets, 1)
+}
+
+/// Same as [`setup`], but the pool is created at a price of `price` uosmo per
+/// uatom.
+fn setup_at_price(reward_assets: Vec<AssetInfoUnchecked>, price: u128) -> ClSetup {
+    let app = OsmosisTestApp::new();
+    let wasm = Wasm::new(&app);
+    let cl = ConcentratedLiquidity::new(&app);
+
+    let balances = [
+        Coin::new(1_000_000_000_000, DENOM0),
+        Coin::new(1_000_000_000_000, DENOM1),
+        Coin::new(1_000_000_000_000, "pica"),
+    ];
+    let admin = app.init_account(&balances).unwrap();
+    let user = app.init_account(&balances).unwrap();
+
+    GovWithAppAccess::new(&app)
+        .propose_and_execute(
+            CreateConcentratedLiquidityPoolsProposal::TYPE_URL.to_string(),
+            CreateConcentratedLiquidityPoolsProposal {
+                title: "Create uatom/uosmo pool".to_string(),
+                description: "Concentrated liquidity pool for the vault".to_string(),
+                pool_records: vec![PoolRecord {
+                    denom0: DENOM0.to_string(),
+                    denom1: DENOM1.to_string(),
+                    tick_spacing: 100,
+                    spread_factor: Decimal::permille(3).atomics().to_string(),
+                    ..Default::default()
+                }],
+            },
+            admin.address(),
+            &admin,
+        )
+        .unwrap();
+    let pools = cl.query_pools(&PoolsRequest::default()).unwrap().pools;
+    let pool_id = Pool::decode(pools[0].value.as_slice()).unwrap().id;
+
+    // Full range liquidity setting the price
+    cl.create_position(
+        MsgCreatePosition {
+            pool_id,
+            sender: admin.address(),
+            lower_tick: Range::full().lower_tick,
+            upper_tick: Range::full().upper_tick,
+            tokens_provided: vec![
+                Coin::new(100_000_000_000, DENOM0).into(),
+                Coin::new(100_000_000_000 * price, DENOM1).into(),
+            ],
+            token_min_amount0: "1".to_string(),
+            token_min_amount1: "1".to_string(),
+        },
+        &admin,
+    )
+    .unwrap();
+
+    let vault_address = instantiate_contract(
+        &wasm,
+        &admin,
+        OSMOSIS_CL_VAULT_CONTRACT_NAME,
+        &InstantiateMsg {
+            admin: admin.address(),
+            pool_id,
+            range: None,
+            config: ConfigUnchecked {
+                performance_fee: Decimal::percent(10),
+                treasury: admin.address(),
+                router: admin.address().into(),
+                reward_assets,
+                max_slippage: Decimal::percent(5),
+                twap_window: TWAP_WINDOW,
+            },
+            vault_token_subdenom: "cl-vault".to_string(),
+        },
+    );
+    let info: VaultInfoResponse = wasm.query(&vault_address, &QueryMsg::Info {}).unwrap();
+
+    // The minimum amounts are derived from the TWAP over the window
+    app.increase_time(TWAP_WINDOW);
+
+    ClSetup {
+        app,
+        admin,
+        user,
+        pool_id,
+        vault_address,
+        vault_token: info.vault_token,
+    }
+}
+
+fn query_balance(app: &OsmosisTestApp, address: &str, denom: &str) -> Uint128 {
+    let balance = Bank::new(app)
+        .query_balance(&QueryBalanceRequest {
+            address: address.to_string(),
+            denom: denom.to_string(),
+        })
+        .unwrap()
+        .balance
+        .unwrap_or_default()
+        .amount;
+    Uint128::from_str(&balance).unwrap()
+}
+
+fn query_position(s: &ClSetup) -> PositionResponse {
+    Wasm::new(&s.app)
+        .query(
+            &s.vault_address,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::ConcentratedLiquidity(
+                ClQueryMsg::Position {},
+            )),
+        )
+        .unwrap()
+}
+
+fn query_supply(s: &ClSetup) -> Uint128 {
+    Wasm::new(&s.app).query(&s.vault_address, &QueryMsg::TotalVaultTokenSupply {}).unwrap()
+}
+
+fn deposit(
+    s: &ClSetup,
+    signer: &SigningAccount,
+    amount0: u128,
+    amount1: u128,
+) -> ExecuteResponse<MsgExecuteContractResponse> {
+    Wasm::new(&s.app)
+        .execute(
+            &s.vault_address,
+            &ExecuteMsg::Deposit {
+                amount: Uint128::new(amount0),
+                recipient: None,
+            },
+            &[Coin::new(amount0, DENOM0), Coin::new(amount1, DENOM1)],
+            signer,
+        )
+        .unwrap()
+}
+
+fn swap(s: &ClSetup, coin_in: Coin, denom_out: &str) {
+    s.app
+        .execute::<_, MsgSwapExactAmountInResponse>(
+            MsgSwapExactAmountIn {
+                sender: s.admin.address(),
+                routes: vec![SwapAmountInRoute {
+                    pool_id: s.pool_id,
+                    token_out_denom: denom_out.to_string(),
+                }],
+                token_in: Some(coin_in.into()),
+                token_out_min_amount: "1".to_string(),
+            },
+            MsgSwapExactAmountIn::TYPE_URL,
+            &s.admin,
+        )
+        .unwrap();
+}
+
+fn has_action(events: &[Event], action: &str) -> bool {
+    events.iter().any(|event| {
+        event.attributes.iter().any(|attr| attr.key == "action" && attr.value == action)
+    })
+}
+
+#[test]
+fn deposit_mints_liquidity_and_refunds_the_rest() {
+    let s = setup(vec![]);
+
+    // Only the amounts in the ratio of the position are used at a price of one
+    let balance0_before = query_balance(&s.app, &s.user.address(), DENOM0);
+    deposit(&s, &s.user, 300_000_000, 100_000_000);
+    let used0 = balance0_before - query_balance(&s.app, &s.user.address(), DENOM0);
+    assert!(used0 > Uint128::new(99_000_000) && used0 <= Uint128::new(100_000_000));
+
+    // The first deposit mints the liquidity of the position
+    let position = query_position(&s);
+    assert!(position.position_id.is_some());
+    let total_assets: Uint128 =
+        Wasm::new(&s.app).query(&s.vault_address, &QueryMsg::TotalAssets {}).unwrap();
+    assert_eq!(query_supply(&s), total_assets);
+    assert_eq!(query_balance(&s.app, &s.user.address(), &s.vault_token), total_assets);
+
+    // Later deposits mint proportional to the liquidity they add
+    deposit(&s, &s.admin, 100_000_000, 100_000_000);
+    let admin_vault_tokens = query_balance(&s.app, &s.admin.address(), &s.vault_token);
+    let user_vault_tokens = query_balance(&s.app, &s.user.address(), &s.vault_token);
+    let diff = if admin_vault_tokens > user_vault_tokens {
+        admin_vault_tokens - user_vault_tokens
+    } else {
+        user_vault_tokens - admin_vault_tokens
+    };
+    assert!(diff <= user_vault_tokens / Uint128::new(100));
+}
+
+#[test]
+fn deposit_uses_twap_price_of_unbalanced_pool() {
+    let s = setup_at_price(vec![], 4);
+
+    // At a price of four uosmo per uatom a full range position holds four
+    // times more uosmo than uatom, so only a quarter of the uatom is used. The
+    // minimum amounts at the TWAP price must allow that.
+    let balance0_before = query_balance(&s.app, &s.user.address(), DENOM0);
+    deposit(&s, &s.user, 100_000_000, 100_000_000);
+    let used0 = balance0_before - query_balance(&s.app, &s.user.address(), DENOM0);
+    assert!(used0 > Uint128::new(24_000_000) && used0 <= Uint128::new(25_000_000));
+    assert!(query_balance(&s.app, &s.user.address(), &s.vault_token) > Uint128::zero());
+}
+
+#[test]
+fn redeem_withdraws_share_of_position() {
+    let s = setup(vec![]);
+    let wasm = Wasm::new(&s.app);
+
+    deposit(&s, &s.user, 100_000_000, 100_000_000);
+    let vault_tokens = query_balance(&s.app, &s.user.address(), &s.vault_token);
+    let liquidity_before = query_position(&s).liquidity;
+
+    let balance0_before = query_balance(&s.app, &s.user.address(), DENOM0);
+    let half = vault_tokens / Uint128::new(2);
+    let redeem = |amount: Uint128| {
+        wasm.execute(
+            &s.vault_address,
+            &ExecuteMsg::Redeem {
+                recipient: None,
+                amount,
+            },
+            &[Coin::new(amount.u128(), &s.vault_token)],
+            &s.user,
+        )
+        .unwrap()
+    };
+    redeem(half);
+
+    // Both pool assets are sent to the redeemer
+    let received0 = query_balance(&s.app, &s.user.address(), DENOM0) - balance0_before;
+    assert!(received0 > Uint128::new(49_000_000) && received0 <= Uint128::new(50_000_000));
+    assert_eq!(query_supply(&s), vault_tokens - half);
+    let liquidity_after = query_position(&s).liquidity;
+    assert!(liquidity_after * Decimal256::from_ratio(2u128, 1u128) >= liquidity_before);
+
+    // Redeeming the remaining vault tokens removes the position
+    redeem(vault_tokens - half);
+    assert_eq!(query_supply(&s), Uint128::zero());
+    assert_eq!(query_position(&s).position_id, None);
+}
+
+#[test]
+fn compound_adds_spread_rewards_to_position() {
+    let s = setup(vec![]);
+    let wasm = Wasm::new(&s.app);
+
+    deposit(&s, &s.user, 100_000_000, 100_000_000);
+    let supply = query_supply(&s);
+    let liquidity_before = query_position(&s).liquidity;
+
+    // Swaps back and forth accrue spread rewards in both assets
+    swap(&s, Coin::new(1_000_000_000, DENOM0), DENOM1);
+    swap(&s, Coin::new(1_000_000_000, DENOM1), DENOM0);
+
+    let res = wasm
+        .execute(
+            &s.vault_address,
+            &ExecuteMsg::VaultExtension(ExtensionExecuteMsg::ConcentratedLiquidity(
+                ClExecuteMsg::Compound {},
+            )),
+            &[],
+            &s.user,
+        )
+        .unwrap();
+    assert!(has_action(&res.events, "reply_compound"));
+
+    // The rewards increase the liquidity backing the same vault tokens
+    assert_eq!(query_supply(&s), supply);
+    assert!(query_position(&s).liquidity > liquidity_before);
+}
+
+#[test]
+fn update_range_moves_liquidity_to_new_position() {
+    let s = setup(vec![]);
+    let wasm = Wasm::new(&s.app);
+
+    deposit(&s, &s.user, 100_000_000, 100_000_000);
+    let supply = query_supply(&s);
+    let position_before = query_position(&s);
+
+    let range = Range {
+        lower_tick: -900_000,
+        upper_tick: 900_000,
+    };
+    let update_range = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::ConcentratedLiquidity(
+        ClExecuteMsg::UpdateRange {
+            range: Some(range.clone()),
+        },
+    ));
+
+    // Only the admin can move the position
+    let err = wasm.execute(&s.vault_address, &update_range, &[], &s.user).unwrap_err();
+    assert_err(err, "Caller is not admin");
+
+    wasm.execute(&s.vault_address, &update_range, &[], &s.admin).unwrap();
+
+    let res: RangeResponse = wasm
+        .query(
+            &s.vault_address,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::ConcentratedLiquidity(
+                ClQueryMsg::Range {},
+            )),
+        )
+        .unwrap();
+    assert_eq!((res.lower_tick, res.upper_tick, res.full_range), (-900_000, 900_000, false));
+
+    // The assets are swapped to the ratio of the narrower range, which gives
+    // more liquidity for the same vault tokens
+    let position = query_position(&s);
+    assert_ne!(position.position_id, position_before.position_id);
+    assert!(position.liquidity > position_before.liquidity);
+    assert_eq!(query_supply(&s), supply);
+}
+
+#[test]
+fn failed_compound_does_not_fail_deposit() {
+    let s = setup(vec![AssetInfoUnchecked::Native("pica".to_string())]);
+    let wasm = Wasm::new(&s.app);
+
+    deposit(&s, &s.user, 100_000_000, 100_000_000);
+
+    // The router is not a contract, so selling the reward fails
+    Bank::new(&s.app)
+        .send(
+            MsgSend {
+                from_address: s.admin.address(),
+                to_address: s.vault_address.clone(),
+                amount: vec![Coin::new(1_000_000, "pica").into()],
+            },
+            &s.admin,
+        )
+        .unwrap();
+
+    let vault_tokens_before = query_balance(&s.app, &s.user.address(), &s.vault_token);
+    let res = deposit(&s, &s.user, 100_000_000, 100_000_000);
+    assert!(has_action(&res.events, "reply_compound_failure"));
+    assert!(query_balance(&s.app, &s.user.address(), &s.vault_token) > vault_tokens_before);
+    assert_eq!(query_balance(&s.app, &s.vault_address, "pica"), Uint128::new(1_000_000));
+
+    // A compound on its own still fails
+    wasm.execute(
+        &s.vault_address,
+        &ExecuteMsg::VaultExtension(ExtensionExecuteMsg::ConcentratedLiquidity(
+            ClExecuteMsg::Compound {},
+        )),
+        &[],
+        &s.user,
+    )
+    .unwrap_err();
+}
@@ -0,0 +1,302 @@
+mod helpers;
+use std::str::FromStr;
+
+use apollo_cw_asset::{AssetInfo, AssetInfoUnchecked, AssetList};
+use cosmrs::proto::cosmos::{
+    bank::v1beta1::{MsgSend, QueryBalanceRequest},
+    base::v1beta1::Coin as ProtoCoin,
+};
+use cosmwasm_std::{Coin, Decimal, Uint128};
+use cw_dex::osmosis::{OsmosisPool, OsmosisStaking};
+use cw_dex_router::operations::{SwapOperation, SwapOperationsList};
+use cw_vault_token::osmosis::OsmosisDenom;
+use liquidity_helper::helper::LiquidityHelperBase;
+use osmosis_test_tube::{Account, Bank, Module, OsmosisTestApp, SigningAccount, Wasm};
+use osmosis_vault::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use simple_vault::msg::{ExtensionQueryMsg, SimpleExtensionQueryMsg, StateResponse};
+use simple_vault::state::{CompoundFailure, ConfigUnchecked, MaxSell, SellLimitUnchecked};
+
+use crate::helpers::osmosis::{assert_err, Setup};
+
+const OSMOSIS_VAULT_CONTRACT_NAME: &str = "osmosis_vault";
+
+fn setup_with_config(update_config: impl FnOnce(&mut ConfigUnchecked)) -> Setup {
+    Setup::with_vault(OSMOSIS_VAULT_CONTRACT_NAME, |admin, pool_id, mut config| {
+        update_config(&mut config);
+        InstantiateMsg {
+            admin,
+            pool_id,
+            lockup_duration: 86400u64,
+            config,
+            vault_token_subdenom: "osmosis-vault".to_string(),
+        }
+    })
+}
+
+fn query_vault_state(
+    app: &OsmosisTestApp,
+    vault_addr: &str,
+) -> StateResponse<OsmosisStaking, OsmosisPool, OsmosisDenom> {
+    Wasm::new(app)
+        .query(
+            vault_addr,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::Simple(SimpleExtensionQueryMsg::State {})),
+        )
+        .unwrap()
+}
+
+fn query_last_compound_failure(app: &OsmosisTestApp, vault_addr: &str) -> Option<CompoundFailure> {
+    Wasm::new(app)
+        .query(
+            vault_addr,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::Simple(
+                SimpleExtensionQueryMsg::LastCompoundFailure {},
+            )),
+        )
+        .unwrap()
+}
+
+fn query_unsold_rewards(app: &OsmosisTestApp, vault_addr: &str) -> AssetList {
+    Wasm::new(app)
+        .query(
+            vault_addr,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::Simple(
+                SimpleExtensionQueryMsg::UnsoldRewards {},
+            )),
+        )
+        .unwrap()
+}
+
+fn query_token_balance(app: &OsmosisTestApp, address: &str, denom: &str) -> Uint128 {
+    let balance = Bank::new(app)
+        .query_balance(&QueryBalanceRequest {
+            address: address.to_string(),
+            denom: denom.to_string(),
+        })
+        .unwrap()
+        .balance
+        .unwrap_or_default()
+        .amount;
+    Uint128::from_str(&balance).unwrap()
+}
+
+fn send_native_coins(
+    app: &OsmosisTestApp,
+    from: &SigningAccount,
+    to: &str,
+    denom: &str,
+    amount: Uint128,
+) {
+    Bank::new(app)
+        .send(
+            MsgSend {
+                amount: vec![ProtoCoin {
+                    denom: denom.to_string(),
+                    amount: amount.to_string(),
+                }],
+                from_address: from.address(),
+                to_address: to.to_string(),
+            },
+            from,
+        )
+        .unwrap();
+}
+
+fn deposit(app: &OsmosisTestApp, vault_addr: &str, signer: &SigningAccount, denom: &str) {
+    let amount = Uint128::new(200_000_000u128);
+    Wasm::new(app)
+        .execute(
+            vault_addr,
+            &ExecuteMsg::Deposit {
+                amount,
+                recipient: None,
+            },
+            &[Coin::new(amount.u128(), denom)],
+            signer,
+        )
+        .unwrap();
+}
+
+#[test]
+fn failed_sell_rewards_does_not_revert_deposit() {
+    let Setup {
+        app,
+        signer,
+        admin,
+        vault_address,
+        base_token,
+        ..
+    } = Setup::new();
+
+    deposit(&app, &vault_address, &signer, &base_token.to_string());
+
+    // Route the reward token through the base pool, which does not contain it, so
+    // selling the rewards fails
+    let state = query_vault_state(&app, &vault_address);
+    let pica = AssetInfo::Native("pica".to_string());
+    let uatom = AssetInfo::Native("uatom".to_string());
+    Wasm::new(&app)
+        .execute(
+            state.config.router.0.as_str(),
+            &cw_dex_router::msg::ExecuteMsg::SetPath {
+                offer_asset: pica.clone().into(),
+                ask_asset: uatom.clone().into(),
+                path: SwapOperationsList::new(vec![SwapOperation {
+                    offer_asset_info: pica,
+                    ask_asset_info: uatom,
+                    pool: cw_dex::Pool::Osmosis(state.pool),
+                }])
+                .into(),
+                bidirectional: false,
+            },
+            &[],
+            &admin,
+        )
+        .unwrap();
+    assert_eq!(query_last_compound_failure(&app, &vault_address), None);
+
+    let reward_amount = Uint128::new(100_000_000u128);
+    send_native_coins(&app, &signer, &vault_address, "pica", reward_amount);
+
+    // The deposit that compounds the rewards still succeeds
+    deposit(&app, &vault_address, &signer, &base_token.to_string());
+
+    let failure = query_last_compound_failure(&app, &vault_address).unwrap();
+    assert_eq!(failure.step, "sell_rewards");
+    assert_eq!(
+        failure.assets.find(&AssetInfo::Native("pica".to_string())).unwrap().amount,
+        reward_amount
+    );
+
+    // The rewards were left in the contract, without charging the performance fee
+    assert_eq!(query_token_balance(&app, &vault_address, "pica"), reward_amount);
+}
+
+#[test]
+fn failed_provide_liquidity_does_not_revert_deposit() {
+    // The router does not implement the liquidity helper API, so providing
+    // liquidity fails
+    let Setup {
+        app,
+        signer,
+        vault_address,
+        base_token,
+        ..
+    } = setup_with_config(|config| {
+        config.liquidity_helper = LiquidityHelperBase(config.router.0.clone())
+    });
+
+    deposit(&app, &vault_address, &signer, &base_token.to_string());
+
+    let pool_asset_amount = Uint128::new(1_000u128);
+    send_native_coins(&app, &signer, &vault_address, "uatom", pool_asset_amount);
+
+    // The deposit that compounds the pool asset still succeeds
+    deposit(&app, &vault_address, &signer, &base_token.to_string());
+
+    let failure = query_last_compound_failure(&app, &vault_address).unwrap();
+    assert_eq!(failure.step, "provide_liquidity");
+    assert_eq!(failure.assets.len(), 1);
+    assert_eq!(
+        failure.assets.find(&AssetInfo::Native("uatom".to_string())).unwrap().amount,
+        pool_asset_amount
+    );
+
+    // The pool asset was left in the contract
+    assert_eq!(query_token_balance(&app, &vault_address, "uatom"), pool_asset_amount);
+}
+
+#[test]
+fn sell_limit_does_not_hold_back_pool_asset_rewards() {
+    // The pool asset uatom is also a reward asset with a sell limit far below the
+    // rewards
+    let Setup {
+        app,
+        signer,
+        vault_address,
+        base_token,
+        ..
+    } = setup_with_config(|config| {
+        let uatom = AssetInfoUnchecked::Native("uatom".to_string());
+        config.reward_assets.push(uatom.clone());
+        config.max_sell_per_compound = vec![SellLimitUnchecked {
+            asset: uatom,
+            max: MaxSell::Amount(Uint128::new(100u128)),
+        }];
+    });
+
+    deposit(&app, &vault_address, &signer, &base_token.to_string());
+
+    // Compound twice. The pool asset is provided as liquidity in full, so none of
+    // it is recorded as unsold, and the second compound does not subtract unsold
+    // rewards that were already provided.
+    for _ in 0..2 {
+        send_native_coins(&app, &signer, &vault_address, "uatom", Uint128::new(1_000u128));
+        deposit(&app, &vault_address, &signer, &base_token.to_string());
+
+        assert_eq!(query_unsold_rewards(&app, &vault_address).len(), 0);
+        assert_eq!(query_last_compound_failure(&app, &vault_address), None);
+    }
+}
+
+#[test]
+fn share_price_increase_above_limit_fails_compound() {
+    let Setup {
+        app,
+        signer,
+        vault_address,
+        base_token,
+        ..
+    } = setup_with_config(|config| config.max_share_price_increase = Decimal::percent(1));
+
+    deposit(&app, &vault_address, &signer, &base_token.to_string());
+    let state_before = query_vault_state(&app, &vault_address);
+
+    // The pools are shallow, so compounding the rewards raises the share price by
+    // far more than the limit
+    send_native_coins(&app, &signer, &vault_address, "pica", Uint128::new(100_000_000u128));
+
+    let amount = Uint128::new(200_000_000u128);
+    let res = Wasm::new(&app)
+        .execute(
+            &vault_address,
+            &ExecuteMsg::Deposit {
+                amount,
+                recipient: None,
+            },
+            &[Coin::new(amount.u128(), base_token.to_string())],
+            &signer,
+        )
+        .unwrap_err();
+    assert_err(res, "during compound, outside of the allowed bounds");
+
+    // The deposit was reverted
+    let state_after = query_vault_state(&app, &vault_address);
+    assert_eq!(state_after.total_staked_base_tokens, state_before.total_staked_base_tokens);
+    assert_eq!(state_after.vault_token_supply, state_before.vault_token_supply);
+}
+
+#[test]
+fn zero_max_share_price_increase_does_not_limit_compound() {
+    let Setup {
+        app,
+        signer,
+        vault_address,
+        base_token,
+        ..
+    } = setup_with_config(|config| config.max_share_price_increase = Decimal::zero());
+
+    deposit(&app, &vault_address, &signer, &base_token.to_string());
+    let state_before = query_vault_state(&app, &vault_address);
+
+    send_native_coins(&app, &signer, &vault_address, "pica", Uint128::new(100_000_000u128));
+    deposit(&app, &vault_address, &signer, &base_token.to_string());
+
+    // The share price more than doubled
+    let state_after = query_vault_state(&app, &vault_address);
+    let price_before =
+        Decimal::from_ratio(state_before.total_staked_base_tokens, state_before.vault_token_supply);
+    let price_after =
+        Decimal::from_ratio(state_after.total_staked_base_tokens, state_after.vault_token_supply);
+    assert!(price_after > price_before * Decimal::percent(200));
+}
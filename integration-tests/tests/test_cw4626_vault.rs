@@ -0,0 +1,550 @@
+mod helpers;
+use std::str::FromStr;
+
+use base_vault::DEFAULT_VAULT_TOKENS_PER_STAKED_BASE_TOKEN;
+use cosmrs::{proto::cosmos::bank::v1beta1::QueryBalanceRequest, Any};
+use cosmwasm_std::{to_binary, Coin, Decimal, Uint128};
+use cw20::{BalanceResponse, Cw20ReceiveMsg};
+use cw_vault_standard::extensions::lockup::{LockupExecuteMsg, LockupQueryMsg, UnlockingPosition};
+use osmosis_cw4626_vault::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, ReceiveMsg};
+use osmosis_std::types::osmosis::lockup::Params as LockupParams;
+use osmosis_test_tube::{Account, Bank, Module, OsmosisTestApp, SigningAccount, Wasm};
+use prost::Message;
+use simple_vault::msg::{
+    BufferResponse, ExtensionExecuteMsg, ExtensionQueryMsg, SimpleExtensionExecuteMsg,
+    SimpleExtensionQueryMsg,
+};
+use simple_vault::state::ReferralInfo;
+
+use crate::helpers::osmosis::{assert_err, Setup};
+
+const OSMOSIS_CW4626_VAULT_CONTRACT_NAME: &str = "osmosis_cw4626_vault";
+
+fn setup() -> Setup {
+    Setup::with_vault(OSMOSIS_CW4626_VAULT_CONTRACT_NAME, |admin, pool_id, config| InstantiateMsg {
+        admin,
+        pool_id,
+        lockup_duration: 86400u64,
+        config,
+        vault_token_name: "Osmosis Vault".to_string(),
+        vault_token_symbol: "OSMOVAULT".to_string(),
+    })
+}
+
+/// Sets up the vault with half of the base tokens kept unstaked in the
+/// instant redemption buffer.
+fn setup_with_buffer() -> Setup {
+    Setup::with_vault(OSMOSIS_CW4626_VAULT_CONTRACT_NAME, |admin, pool_id, mut config| {
+        config.buffer_ratio = Decimal::percent(50);
+        InstantiateMsg {
+            admin,
+            pool_id,
+            lockup_duration: 86400u64,
+            config,
+            vault_token_name: "Osmosis Vault".to_string(),
+            vault_token_symbol: "OSMOVAULT".to_string(),
+        }
+    })
+}
+
+fn query_buffer(app: &OsmosisTestApp, vault_addr: &str) -> BufferResponse {
+    let query = SimpleExtensionQueryMsg::Buffer {};
+    Wasm::new(app)
+        .query(vault_addr, &QueryMsg::VaultExtension(ExtensionQueryMsg::Simple(query)))
+        .unwrap()
+}
+
+fn query_vault_token_balance(app: &OsmosisTestApp, vault_addr: &str, address: &str) -> Uint128 {
+    let wasm = Wasm::new(app);
+    let res: BalanceResponse = wasm
+        .query(
+            vault_addr,
+            &QueryMsg::Balance {
+                address: address.to_string(),
+            },
+        )
+        .unwrap();
+    res.balance
+}
+
+fn query_token_balance(app: &OsmosisTestApp, address: &str, denom: &str) -> Uint128 {
+    let bank = Bank::new(app);
+    let balance = bank
+        .query_balance(&QueryBalanceRequest {
+            address: address.to_string(),
+            denom: denom.to_string(),
+        })
+        .unwrap()
+        .balance
+        .unwrap_or_default()
+        .amount;
+    Uint128::from_str(&balance).unwrap()
+}
+
+fn deposit(app: &OsmosisTestApp, vault_addr: &str, base_token: &str, signer: &SigningAccount) {
+    let wasm = Wasm::new(app);
+    let deposit_amount = Uint128::new(200_000_000u128);
+    wasm.execute(
+        vault_addr,
+        &ExecuteMsg::Deposit {
+            amount: deposit_amount,
+            recipient: None,
+        },
+        &[Coin {
+            amount: deposit_amount,
+            denom: base_token.to_string(),
+        }],
+        signer,
+    )
+    .unwrap();
+}
+
+#[test]
+fn receive_only_accepts_vault_tokens() {
+    let Setup {
+        app,
+        signer,
+        vault_address,
+        ..
+    } = setup();
+
+    let wasm = Wasm::new(&app);
+
+    // Calling the hook directly is the same as receiving a foreign cw20 token
+    let receive_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: signer.address(),
+        amount: Uint128::new(1_000_000u128),
+        msg: to_binary(&ReceiveMsg::Unlock {}).unwrap(),
+    });
+    let res = wasm.execute(&vault_address, &receive_msg, &[], &signer).unwrap_err();
+    assert_err(res, "Invalid vault token deposited.");
+}
+
+#[test]
+fn unlock_with_send() {
+    let Setup {
+        app,
+        signer,
+        vault_address,
+        base_token,
+        ..
+    } = setup();
+
+    let wasm = Wasm::new(&app);
+
+    deposit(&app, &vault_address, &base_token.to_string(), &signer);
+    let vault_token_balance = query_vault_token_balance(&app, &vault_address, &signer.address());
+    assert_eq!(
+        vault_token_balance,
+        Uint128::new(200_000_000u128) * DEFAULT_VAULT_TOKENS_PER_STAKED_BASE_TOKEN
+    );
+
+    // Send half of the vault tokens back to the vault to unlock them
+    let unlock_amount = vault_token_balance / Uint128::new(2);
+    wasm.execute(
+        &vault_address,
+        &ExecuteMsg::Send {
+            contract: vault_address.clone(),
+            amount: unlock_amount,
+            msg: to_binary(&ReceiveMsg::Unlock {}).unwrap(),
+        },
+        &[],
+        &signer,
+    )
+    .unwrap();
+
+    // The sent vault tokens are burned
+    assert_eq!(
+        query_vault_token_balance(&app, &vault_address, &signer.address()),
+        vault_token_balance - unlock_amount
+    );
+    let vault_token_supply: Uint128 =
+        wasm.query(&vault_address, &QueryMsg::TotalVaultTokenSupply {}).unwrap();
+    assert_eq!(vault_token_supply, vault_token_balance - unlock_amount);
+
+    // The unlocking position is owned by the sender of the vault tokens
+    let unlocking_positions: Vec<UnlockingPosition> = wasm
+        .query(
+            &vault_address,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::Lockup(
+                LockupQueryMsg::UnlockingPositions {
+                    owner: signer.address(),
+                    limit: None,
+                    start_after: None,
+                },
+            )),
+        )
+        .unwrap();
+    assert_eq!(unlocking_positions.len(), 1);
+    let position = unlocking_positions[0].clone();
+    assert_eq!(position.base_token_amount, Uint128::new(100_000_000u128));
+
+    let withdraw_msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(
+        LockupExecuteMsg::WithdrawUnlocked {
+            lockup_id: position.id,
+            recipient: None,
+        },
+    ));
+    let res = wasm.execute(&vault_address, &withdraw_msg, &[], &signer).unwrap_err();
+    assert_err(res, "Claim has not yet matured");
+
+    app.increase_time(86400);
+
+    let base_token_balance_before =
+        query_token_balance(&app, &signer.address(), &base_token.to_string());
+    wasm.execute(&vault_address, &withdraw_msg, &[], &signer).unwrap();
+    let base_token_balance_after =
+        query_token_balance(&app, &signer.address(), &base_token.to_string());
+    assert_eq!(base_token_balance_after - base_token_balance_before, position.base_token_amount);
+}
+
+#[test]
+fn unlock_with_extension_msg() {
+    let Setup {
+        app,
+        signer,
+        vault_address,
+        base_token,
+        ..
+    } = setup();
+
+    let wasm = Wasm::new(&app);
+
+    deposit(&app, &vault_address, &base_token.to_string(), &signer);
+    let vault_token_balance = query_vault_token_balance(&app, &vault_address, &signer.address());
+
+    // The cw20 vault tokens are taken from the sender's balance, no funds are
+    // attached
+    wasm.execute(
+        &vault_address,
+        &ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(LockupExecuteMsg::Unlock {
+            amount: vault_token_balance,
+        })),
+        &[],
+        &signer,
+    )
+    .unwrap();
+
+    assert_eq!(query_vault_token_balance(&app, &vault_address, &signer.address()), Uint128::zero());
+    let unlocking_positions: Vec<UnlockingPosition> = wasm
+        .query(
+            &vault_address,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::Lockup(
+                LockupQueryMsg::UnlockingPositions {
+                    owner: signer.address(),
+                    limit: None,
+                    start_after: None,
+                },
+            )),
+        )
+        .unwrap();
+    assert_eq!(unlocking_positions.len(), 1);
+    assert_eq!(unlocking_positions[0].base_token_amount, Uint128::new(200_000_000u128));
+}
+
+#[test]
+fn force_redeem_with_send() {
+    let Setup {
+        app,
+        signer,
+        force_withdraw_admin,
+        vault_address,
+        base_token,
+        ..
+    } = setup();
+
+    let wasm = Wasm::new(&app);
+
+    deposit(&app, &vault_address, &base_token.to_string(), &signer);
+    let force_redeem_amount = Uint128::new(100_000_000u128);
+    let force_redeem_msg = ExecuteMsg::Send {
+        contract: vault_address.clone(),
+        amount: force_redeem_amount,
+        msg: to_binary(&ReceiveMsg::ForceRedeem {
+            recipient: None,
+        })
+        .unwrap(),
+    };
+
+    // The sender of the vault tokens must be whitelisted
+    let res = wasm.execute(&vault_address, &force_redeem_msg, &[], &signer).unwrap_err();
+    assert_err(res, "Unauthorized");
+
+    // Move vault tokens to the whitelisted account
+    wasm.execute(
+        &vault_address,
+        &ExecuteMsg::Transfer {
+            recipient: force_withdraw_admin.address(),
+            amount: force_redeem_amount,
+        },
+        &[],
+        &signer,
+    )
+    .unwrap();
+
+    // Allow the contract to force unlock on the lockup module via governance
+    app.set_param_set(
+        "lockup",
+        Any {
+            type_url: LockupParams::TYPE_URL.to_string(),
+            value: LockupParams {
+                force_unlock_allowed_addresses: vec![vault_address.clone()],
+            }
+            .encode_to_vec(),
+        },
+    )
+    .unwrap();
+
+    wasm.execute(&vault_address, &force_redeem_msg, &[], &force_withdraw_admin).unwrap();
+
+    assert_eq!(
+        query_vault_token_balance(&app, &vault_address, &force_withdraw_admin.address()),
+        Uint128::zero()
+    );
+    assert_eq!(
+        query_token_balance(&app, &force_withdraw_admin.address(), &base_token.to_string()),
+        force_redeem_amount / DEFAULT_VAULT_TOKENS_PER_STAKED_BASE_TOKEN
+    );
+}
+
+#[test]
+fn unlock_with_buffer() {
+    let Setup {
+        app,
+        signer,
+        vault_address,
+        base_token,
+        ..
+    } = setup_with_buffer();
+
+    let wasm = Wasm::new(&app);
+
+    // Half of the deposit is kept in the buffer, the other half is staked
+    deposit(&app, &vault_address, &base_token.to_string(), &signer);
+    assert_eq!(query_buffer(&app, &vault_address).amount, Uint128::new(100_000_000u128));
+
+    // Unlock more base tokens than are staked. The rest is taken from the buffer.
+    let vault_token_balance = query_vault_token_balance(&app, &vault_address, &signer.address());
+    wasm.execute(
+        &vault_address,
+        &ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(LockupExecuteMsg::Unlock {
+            amount: vault_token_balance.multiply_ratio(3u128, 4u128),
+        })),
+        &[],
+        &signer,
+    )
+    .unwrap();
+    assert_eq!(query_buffer(&app, &vault_address).amount, Uint128::new(50_000_000u128));
+
+    let unlocking_positions: Vec<UnlockingPosition> = wasm
+        .query(
+            &vault_address,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::Lockup(
+                LockupQueryMsg::UnlockingPositions {
+                    owner: signer.address(),
+                    limit: None,
+                    start_after: None,
+                },
+            )),
+        )
+        .unwrap();
+    assert_eq!(unlocking_positions.len(), 1);
+    let position = unlocking_positions[0].clone();
+    assert_eq!(position.base_token_amount, Uint128::new(150_000_000u128));
+
+    app.increase_time(86400);
+
+    // The whole claim is paid out, both the unlocked and the buffered part
+    let base_token_balance_before =
+        query_token_balance(&app, &signer.address(), &base_token.to_string());
+    wasm.execute(
+        &vault_address,
+        &ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(
+            LockupExecuteMsg::WithdrawUnlocked {
+                lockup_id: position.id,
+                recipient: None,
+            },
+        )),
+        &[],
+        &signer,
+    )
+    .unwrap();
+    let base_token_balance_after =
+        query_token_balance(&app, &signer.address(), &base_token.to_string());
+    assert_eq!(base_token_balance_after - base_token_balance_before, position.base_token_amount);
+
+    // The rest of the buffer is still held by the vault
+    assert_eq!(
+        query_token_balance(&app, &vault_address, &base_token.to_string()),
+        Uint128::new(50_000_000u128)
+    );
+}
+
+#[test]
+fn force_redeem_with_buffer() {
+    let Setup {
+        app,
+        signer,
+        force_withdraw_admin,
+        vault_address,
+        base_token,
+        ..
+    } = setup_with_buffer();
+
+    let wasm = Wasm::new(&app);
+
+    deposit(&app, &vault_address, &base_token.to_string(), &signer);
+
+    // Move three quarters of the vault tokens to the whitelisted account
+    let force_redeem_amount = query_vault_token_balance(&app, &vault_address, &signer.address())
+        .multiply_ratio(3u128, 4u128);
+    wasm.execute(
+        &vault_address,
+        &ExecuteMsg::Transfer {
+            recipient: force_withdraw_admin.address(),
+            amount: force_redeem_amount,
+        },
+        &[],
+        &signer,
+    )
+    .unwrap();
+
+    app.set_param_set(
+        "lockup",
+        Any {
+            type_url: LockupParams::TYPE_URL.to_string(),
+            value: LockupParams {
+                force_unlock_allowed_addresses: vec![vault_address.clone()],
+            }
+            .encode_to_vec(),
+        },
+    )
+    .unwrap();
+
+    // Only the staked base tokens are force unlocked, the rest is paid from the
+    // buffer
+    wasm.execute(
+        &vault_address,
+        &ExecuteMsg::Send {
+            contract: vault_address.clone(),
+            amount: force_redeem_amount,
+            msg: to_binary(&ReceiveMsg::ForceRedeem {
+                recipient: None,
+            })
+            .unwrap(),
+        },
+        &[],
+        &force_withdraw_admin,
+    )
+    .unwrap();
+
+    assert_eq!(
+        query_token_balance(&app, &force_withdraw_admin.address(), &base_token.to_string()),
+        Uint128::new(150_000_000u128)
+    );
+    assert_eq!(query_buffer(&app, &vault_address).amount, Uint128::new(50_000_000u128));
+    assert_eq!(
+        query_token_balance(&app, &vault_address, &base_token.to_string()),
+        Uint128::new(50_000_000u128)
+    );
+}
+
+#[test]
+fn transfers_sync_referred_vault_tokens() {
+    let Setup {
+        app,
+        signer,
+        admin,
+        vault_address,
+        base_token,
+        ..
+    } = setup();
+
+    let wasm = Wasm::new(&app);
+    let referrer = app.init_account(&[Coin::new(1_000_000_000_000, "uosmo")]).unwrap();
+
+    // Deposit with a referrer
+    let deposit_amount = Uint128::new(200_000_000u128);
+    wasm.execute(
+        &vault_address,
+        &ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Simple(
+            SimpleExtensionExecuteMsg::Deposit {
+                amount: deposit_amount,
+                recipient: None,
+                referrer: Some(referrer.address()),
+            },
+        )),
+        &[Coin::new(deposit_amount.u128(), base_token.to_string())],
+        &signer,
+    )
+    .unwrap();
+
+    let balance = query_vault_token_balance(&app, &vault_address, &signer.address());
+    let referred = |app: &OsmosisTestApp| -> Uint128 {
+        let info: ReferralInfo = Wasm::new(app)
+            .query(
+                &vault_address,
+                &QueryMsg::VaultExtension(ExtensionQueryMsg::Simple(
+                    SimpleExtensionQueryMsg::Referral {
+                        address: referrer.address(),
+                    },
+                )),
+            )
+            .unwrap();
+        info.referred_vault_tokens
+    };
+    assert_eq!(referred(&app), balance);
+
+    // A transfer stops attributing the transferred vault tokens to the referrer
+    wasm.execute(
+        &vault_address,
+        &ExecuteMsg::Transfer {
+            recipient: admin.address(),
+            amount: balance / Uint128::new(2),
+        },
+        &[],
+        &signer,
+    )
+    .unwrap();
+    let balance = query_vault_token_balance(&app, &vault_address, &signer.address());
+    assert_eq!(referred(&app), balance);
+
+    // So does a transfer from the depositor using an allowance
+    wasm.execute(
+        &vault_address,
+        &ExecuteMsg::IncreaseAllowance {
+            spender: admin.address(),
+            amount: balance,
+            expires: None,
+        },
+        &[],
+        &signer,
+    )
+    .unwrap();
+    wasm.execute(
+        &vault_address,
+        &ExecuteMsg::TransferFrom {
+            owner: signer.address(),
+            recipient: admin.address(),
+            amount: balance / Uint128::new(2),
+        },
+        &[],
+        &admin,
+    )
+    .unwrap();
+    let balance = query_vault_token_balance(&app, &vault_address, &signer.address());
+    assert_eq!(referred(&app), balance);
+
+    // Vault tokens received by the depositor are not attributed to the referrer
+    wasm.execute(
+        &vault_address,
+        &ExecuteMsg::Transfer {
+            recipient: signer.address(),
+            amount: balance,
+        },
+        &[],
+        &admin,
+    )
+    .unwrap();
+    assert_eq!(referred(&app), balance);
+}
@@ -0,0 +1,256 @@
+mod helpers;
+use std::str::FromStr;
+
+use base_vault::DEFAULT_VAULT_TOKENS_PER_STAKED_BASE_TOKEN;
+use cosmrs::proto::cosmos::{
+    bank::v1beta1::{MsgSend, QueryBalanceRequest},
+    base::v1beta1::Coin as ProtoCoin,
+};
+use cosmwasm_std::{Coin, Uint128};
+use cw_vault_standard::extensions::lockup::LockupExecuteMsg;
+use cw_vault_standard::msg::VaultInfoResponse;
+use osmosis_test_tube::{Account, Bank, Module, OsmosisTestApp, SigningAccount, Wasm};
+use osmosis_vault::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use simple_vault::msg::{
+    ExtensionExecuteMsg, ExtensionQueryMsg, SimpleExtensionExecuteMsg, SimpleExtensionQueryMsg,
+};
+use simple_vault::state::SettledEpoch;
+
+use crate::helpers::osmosis::{assert_err, Setup};
+
+const OSMOSIS_VAULT_CONTRACT_NAME: &str = "osmosis_vault";
+const LOCKUP_DURATION: u64 = 86400;
+const EPOCH_DURATION: u64 = 3600;
+
+fn setup() -> Setup {
+    Setup::with_vault(OSMOSIS_VAULT_CONTRACT_NAME, |admin, pool_id, mut config| {
+        config.epoch_duration = EPOCH_DURATION;
+        InstantiateMsg {
+            admin,
+            pool_id,
+            lockup_duration: LOCKUP_DURATION,
+            config,
+            vault_token_subdenom: "osmosis-vault".to_string(),
+        }
+    })
+}
+
+fn query_token_balance(app: &OsmosisTestApp, address: &str, denom: &str) -> Uint128 {
+    let balance = Bank::new(app)
+        .query_balance(&QueryBalanceRequest {
+            address: address.to_string(),
+            denom: denom.to_string(),
+        })
+        .unwrap()
+        .balance
+        .unwrap_or_default()
+        .amount;
+    Uint128::from_str(&balance).unwrap()
+}
+
+fn send_native_coins(
+    app: &OsmosisTestApp,
+    from: &SigningAccount,
+    to: &str,
+    denom: &str,
+    amount: Uint128,
+) {
+    Bank::new(app)
+        .send(
+            MsgSend {
+                amount: vec![ProtoCoin {
+                    denom: denom.to_string(),
+                    amount: amount.to_string(),
+                }],
+                from_address: from.address(),
+                to_address: to.to_string(),
+            },
+            from,
+        )
+        .unwrap();
+}
+
+fn query_settled_epoch(app: &OsmosisTestApp, vault_addr: &str, epoch_id: u64) -> SettledEpoch {
+    Wasm::new(app)
+        .query(
+            vault_addr,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::Simple(
+                SimpleExtensionQueryMsg::SettledEpoch {
+                    epoch_id,
+                },
+            )),
+        )
+        .unwrap()
+}
+
+fn deposit(
+    app: &OsmosisTestApp,
+    vault_addr: &str,
+    base_token: &str,
+    amount: Uint128,
+    signer: &SigningAccount,
+) {
+    Wasm::new(app)
+        .execute(
+            vault_addr,
+            &ExecuteMsg::Deposit {
+                amount,
+                recipient: None,
+            },
+            &[Coin::new(amount.u128(), base_token)],
+            signer,
+        )
+        .unwrap();
+}
+
+fn unlock(
+    app: &OsmosisTestApp,
+    vault_addr: &str,
+    vault_token: &str,
+    amount: Uint128,
+    signer: &SigningAccount,
+) {
+    Wasm::new(app)
+        .execute(
+            vault_addr,
+            &ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(LockupExecuteMsg::Unlock {
+                amount,
+            })),
+            &[Coin::new(amount.u128(), vault_token)],
+            signer,
+        )
+        .unwrap();
+}
+
+fn simple_msg(msg: SimpleExtensionExecuteMsg) -> ExecuteMsg {
+    ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Simple(msg))
+}
+
+#[test]
+fn settled_epochs_mint_shares_and_unlock_base_tokens() {
+    let Setup {
+        app,
+        signer,
+        vault_address,
+        base_token,
+        ..
+    } = setup();
+    let wasm = Wasm::new(&app);
+    let base_token = base_token.to_string();
+    let info: VaultInfoResponse = wasm.query(&vault_address, &QueryMsg::Info {}).unwrap();
+    let vault_token = info.vault_token;
+
+    // The signer created the pool and holds all LP tokens
+    let user = app.init_account(&[Coin::new(1_000_000_000_000, "uosmo")]).unwrap();
+    send_native_coins(&app, &signer, &user.address(), &base_token, Uint128::new(1_000_000_000));
+
+    // Epoch 0: both users queue a deposit
+    deposit(&app, &vault_address, &base_token, Uint128::new(200_000_000), &signer);
+    deposit(&app, &vault_address, &base_token, Uint128::new(100_000_000), &user);
+    assert_eq!(query_token_balance(&app, &vault_address, &vault_token), Uint128::zero());
+
+    let settle_epoch = simple_msg(SimpleExtensionExecuteMsg::SettleEpoch {});
+    let res = wasm.execute(&vault_address, &settle_epoch, &[], &user).unwrap_err();
+    assert_err(res, "Epoch 0 cannot be settled before");
+
+    app.increase_time(EPOCH_DURATION);
+    wasm.execute(&vault_address, &settle_epoch, &[], &user).unwrap();
+
+    // The first deposits mint at the default rate
+    let settled = query_settled_epoch(&app, &vault_address, 0);
+    assert_eq!(settled.deposited_base_tokens, Uint128::new(300_000_000));
+    assert_eq!(
+        settled.minted_vault_tokens,
+        Uint128::new(300_000_000) * DEFAULT_VAULT_TOKENS_PER_STAKED_BASE_TOKEN
+    );
+    assert_eq!(settled.claim, None);
+
+    // Each user claims the shares of their deposit
+    let claim_shares = |epoch_id: u64| {
+        simple_msg(SimpleExtensionExecuteMsg::ClaimEpochShares {
+            epoch_id,
+            recipient: None,
+        })
+    };
+    wasm.execute(&vault_address, &claim_shares(0), &[], &signer).unwrap();
+    wasm.execute(&vault_address, &claim_shares(0), &[], &user).unwrap();
+    let signer_shares = query_token_balance(&app, &signer.address(), &vault_token);
+    let user_shares = query_token_balance(&app, &user.address(), &vault_token);
+    assert_eq!(
+        signer_shares,
+        Uint128::new(200_000_000) * DEFAULT_VAULT_TOKENS_PER_STAKED_BASE_TOKEN
+    );
+    assert_eq!(user_shares, Uint128::new(100_000_000) * DEFAULT_VAULT_TOKENS_PER_STAKED_BASE_TOKEN);
+
+    let res = wasm.execute(&vault_address, &claim_shares(0), &[], &user).unwrap_err();
+    assert_err(res, "No deposit to claim in epoch 0");
+
+    // Epoch 1: both users queue an unlock and a new deposit, and the vault earns
+    // rewards before the epoch is settled
+    let signer_unlocked = signer_shares / Uint128::new(2);
+    unlock(&app, &vault_address, &vault_token, signer_unlocked, &signer);
+    unlock(&app, &vault_address, &vault_token, user_shares, &user);
+    deposit(&app, &vault_address, &base_token, Uint128::new(100_000_000), &signer);
+    deposit(&app, &vault_address, &base_token, Uint128::new(200_000_000), &user);
+    send_native_coins(&app, &signer, &vault_address, "pica", Uint128::new(100_000_000));
+
+    app.increase_time(EPOCH_DURATION);
+    wasm.execute(&vault_address, &settle_epoch, &[], &user).unwrap();
+
+    let settled = query_settled_epoch(&app, &vault_address, 1);
+    assert_eq!(settled.deposited_base_tokens, Uint128::new(300_000_000));
+    assert_eq!(settled.unlocked_vault_tokens, signer_unlocked + user_shares);
+
+    // The queued deposits are minted after the rewards are compounded, so
+    // the minted shares are worth the deposits and no more
+    let minted_value: Uint128 = wasm
+        .query(
+            &vault_address,
+            &QueryMsg::ConvertToAssets {
+                amount: settled.minted_vault_tokens,
+            },
+        )
+        .unwrap();
+    assert!(minted_value <= Uint128::new(300_000_000));
+    assert!(minted_value + Uint128::one() >= Uint128::new(300_000_000));
+
+    // The unlocked shares were held during the epoch, so they earned the
+    // rewards
+    let claim = settled.claim.unwrap();
+    assert!(claim.base_token_amount > Uint128::new(200_000_000));
+
+    // The shares of the deposits are split pro rata
+    wasm.execute(&vault_address, &claim_shares(1), &[], &signer).unwrap();
+    wasm.execute(&vault_address, &claim_shares(1), &[], &user).unwrap();
+    assert_eq!(
+        query_token_balance(&app, &signer.address(), &vault_token),
+        signer_shares - signer_unlocked + settled.minted_vault_tokens.multiply_ratio(1u128, 3u128)
+    );
+    assert_eq!(
+        query_token_balance(&app, &user.address(), &vault_token),
+        settled.minted_vault_tokens.multiply_ratio(2u128, 3u128)
+    );
+
+    // The unlocked base tokens can be withdrawn once the lockup has passed
+    let withdraw_epoch_unlocked = simple_msg(SimpleExtensionExecuteMsg::WithdrawEpochUnlocked {
+        epoch_id: 1,
+        recipient: None,
+    });
+    let res = wasm.execute(&vault_address, &withdraw_epoch_unlocked, &[], &user).unwrap_err();
+    assert_err(res, "Claim has not yet matured");
+
+    app.increase_time(LOCKUP_DURATION);
+
+    let total_unlocked = settled.unlocked_vault_tokens;
+    for (account, unlocked) in [(&signer, signer_unlocked), (&user, user_shares)] {
+        let balance_before = query_token_balance(&app, &account.address(), &base_token);
+        wasm.execute(&vault_address, &withdraw_epoch_unlocked, &[], account).unwrap();
+        assert_eq!(
+            query_token_balance(&app, &account.address(), &base_token) - balance_before,
+            claim.base_token_amount.multiply_ratio(unlocked, total_unlocked)
+        );
+    }
+
+    let res = wasm.execute(&vault_address, &withdraw_epoch_unlocked, &[], &user).unwrap_err();
+    assert_err(res, "No unlock to withdraw in epoch 1");
+}
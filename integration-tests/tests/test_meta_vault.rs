@@ -0,0 +1,498 @@
+mod helpers;
+use std::str::FromStr;
+
+use base_vault::DEFAULT_VAULT_TOKENS_PER_STAKED_BASE_TOKEN;
+use cosmrs::proto::cosmos::bank::v1beta1::QueryBalanceRequest;
+use cosmwasm_std::{Coin, Decimal, Uint128};
+use cw_vault_standard::msg::VaultInfoResponse;
+use meta_vault::msg::{
+    AllocationResponse, ExecuteMsg, ExtensionExecuteMsg, ExtensionQueryMsg, InstantiateMsg,
+    MetaExecuteMsg, MetaQueryMsg, QueryMsg, VaultWeight,
+};
+use meta_vault::state::ConfigUnchecked;
+use osmosis_std::types::osmosis::gamm::v1beta1::{MsgJoinPool, MsgJoinPoolResponse};
+use osmosis_test_tube::{Account, Bank, Module, OsmosisTestApp, Runner, SigningAccount, Wasm};
+
+use crate::helpers::osmosis::{assert_err, instantiate_contract, Setup};
+use crate::helpers::swap;
+
+const META_VAULT_CONTRACT_NAME: &str = "meta_vault";
+const OSMOSIS_VAULT_CONTRACT_NAME: &str = "osmosis_vault";
+const LOCKUP_DURATION: u64 = 86400;
+const TWAP_WINDOW: u64 = 600;
+const DEPOSIT_AMOUNT: u128 = 1_000_000;
+
+struct MetaSetup {
+    app: OsmosisTestApp,
+    signer: SigningAccount,
+    admin: SigningAccount,
+    /// The uatom/uosmo pool of the underlying vaults.
+    pool_id: u64,
+    /// The underlying vaults, both on the uatom/uosmo pool, in the order of
+    /// the allocation query.
+    vaults: Vec<String>,
+    meta_vault: String,
+}
+
+fn osmosis_vault_msg(
+    admin: String,
+    pool_id: u64,
+    config: simple_vault::state::ConfigUnchecked,
+    vault_token_subdenom: &str,
+) -> osmosis_vault::msg::InstantiateMsg {
+    osmosis_vault::msg::InstantiateMsg {
+        admin,
+        pool_id,
+        lockup_duration: LOCKUP_DURATION,
+        config,
+        vault_token_subdenom: vault_token_subdenom.to_string(),
+    }
+}
+
+// setup instantiates two osmosis vaults on the uatom/uosmo pool and a
+// meta-vault depositing uosmo into them with equal weights
+fn setup() -> MetaSetup {
+    let mut instantiated = None;
+    let Setup {
+        app,
+        signer,
+        admin,
+        vault_address,
+        ..
+    } = Setup::with_vault(OSMOSIS_VAULT_CONTRACT_NAME, |admin, pool_id, config| {
+        instantiated = Some((pool_id, config.clone()));
+        osmosis_vault_msg(admin, pool_id, config, "osmosis-vault")
+    });
+    let (pool_id, config) = instantiated.unwrap();
+    let wasm = Wasm::new(&app);
+
+    let second_vault = instantiate_contract(
+        &wasm,
+        &signer,
+        OSMOSIS_VAULT_CONTRACT_NAME,
+        &osmosis_vault_msg(admin.address(), pool_id, config.clone(), "osmosis-vault-2"),
+    );
+
+    // Deepen the pool so that deposits barely move the price
+    app.execute::<_, MsgJoinPoolResponse>(
+        MsgJoinPool {
+            sender: signer.address(),
+            pool_id,
+            share_out_amount: "10000000000000000000000000000".to_string(),
+            token_in_maxs: vec![
+                Coin::new(200_000_000_000, "uatom").into(),
+                Coin::new(200_000_000_000, "uosmo").into(),
+            ],
+        },
+        MsgJoinPool::TYPE_URL,
+        &signer,
+    )
+    .unwrap();
+
+    let meta_vault = instantiate_contract(
+        &wasm,
+        &signer,
+        META_VAULT_CONTRACT_NAME,
+        &InstantiateMsg {
+            admin: admin.address(),
+            deposit_denom: "uosmo".to_string(),
+            vaults: vec![
+                VaultWeight {
+                    address: vault_address.clone(),
+                    weight: Decimal::percent(50),
+                },
+                VaultWeight {
+                    address: second_vault.clone(),
+                    weight: Decimal::percent(50),
+                },
+            ],
+            config: ConfigUnchecked {
+                keepers: vec![],
+                liquidity_helper: config.liquidity_helper,
+                rebalance_threshold: Decimal::percent(5),
+                max_slippage: Decimal::percent(5),
+                twap_window: TWAP_WINDOW,
+            },
+            vault_token_subdenom: "meta-vault".to_string(),
+        },
+    );
+
+    // Let the TWAP window pass since the creation of the pool
+    app.increase_time(TWAP_WINDOW);
+
+    // The meta-vault lists the vaults in the order of their addresses
+    let mut vaults = vec![vault_address, second_vault];
+    vaults.sort();
+
+    MetaSetup {
+        app,
+        signer,
+        admin,
+        pool_id,
+        vaults,
+        meta_vault,
+    }
+}
+
+fn query_token_balance(app: &OsmosisTestApp, address: &str, denom: &str) -> Uint128 {
+    let bank = Bank::new(app);
+    let balance = bank
+        .query_balance(&QueryBalanceRequest {
+            address: address.to_string(),
+            denom: denom.to_string(),
+        })
+        .unwrap()
+        .balance
+        .unwrap_or_default()
+        .amount;
+    Uint128::from_str(&balance).unwrap()
+}
+
+fn query_vault_token(app: &OsmosisTestApp, vault: &str) -> String {
+    let wasm = Wasm::new(app);
+    let info: VaultInfoResponse = wasm.query(vault, &QueryMsg::Info {}).unwrap();
+    info.vault_token
+}
+
+fn query_allocation(app: &OsmosisTestApp, meta_vault: &str) -> AllocationResponse {
+    let wasm = Wasm::new(app);
+    wasm.query(
+        meta_vault,
+        &QueryMsg::VaultExtension(ExtensionQueryMsg::Meta(MetaQueryMsg::Allocation {})),
+    )
+    .unwrap()
+}
+
+fn deposit(app: &OsmosisTestApp, meta_vault: &str, signer: &SigningAccount) {
+    let wasm = Wasm::new(app);
+    wasm.execute(
+        meta_vault,
+        &ExecuteMsg::Deposit {
+            amount: Uint128::new(DEPOSIT_AMOUNT),
+            recipient: None,
+        },
+        &[Coin::new(DEPOSIT_AMOUNT, "uosmo")],
+        signer,
+    )
+    .unwrap();
+}
+
+fn rebalance(app: &OsmosisTestApp, meta_vault: &str, signer: &SigningAccount) {
+    let wasm = Wasm::new(app);
+    wasm.execute(
+        meta_vault,
+        &ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Meta(MetaExecuteMsg::Rebalance {})),
+        &[],
+        signer,
+    )
+    .unwrap();
+}
+
+fn update_vaults(
+    app: &OsmosisTestApp,
+    meta_vault: &str,
+    vaults: Vec<VaultWeight>,
+    signer: &SigningAccount,
+) -> Result<(), osmosis_test_tube::RunnerError> {
+    let wasm = Wasm::new(app);
+    wasm.execute(
+        meta_vault,
+        &ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Meta(MetaExecuteMsg::UpdateVaults {
+            vaults,
+        })),
+        &[],
+        signer,
+    )
+    .map(|_| ())
+}
+
+#[test]
+fn deposit_allocates_to_vaults_and_mints_shares() {
+    let MetaSetup {
+        app,
+        signer,
+        vaults,
+        meta_vault,
+        ..
+    } = setup();
+
+    deposit(&app, &meta_vault, &signer);
+
+    // The deposit is split between the vaults, losing only the swap fee of
+    // providing liquidity with uosmo alone
+    let allocation = query_allocation(&app, &meta_vault);
+    assert_eq!(allocation.vaults.len(), 2);
+    assert!(allocation.idle_amount.is_zero());
+    assert!(allocation.total_value >= Uint128::new(DEPOSIT_AMOUNT * 95 / 100));
+    assert!(allocation.total_value <= Uint128::new(DEPOSIT_AMOUNT));
+    for (vault, address) in allocation.vaults.iter().zip(&vaults) {
+        assert_eq!(&vault.address, address);
+        assert!(!vault.vault_token_amount.is_zero());
+        assert_eq!(
+            vault.vault_token_amount,
+            query_token_balance(&app, &meta_vault, &query_vault_token(&app, address))
+        );
+    }
+
+    // Shares are minted for the value added
+    let shares =
+        query_token_balance(&app, &signer.address(), &query_vault_token(&app, &meta_vault));
+    assert_eq!(shares, allocation.total_value * DEFAULT_VAULT_TOKENS_PER_STAKED_BASE_TOKEN);
+}
+
+#[test]
+fn deposit_values_unbalanced_pool_at_twap_price() {
+    let MetaSetup {
+        app,
+        signer,
+        pool_id,
+        meta_vault,
+        ..
+    } = setup();
+
+    // Selling uosmo into the pool raises the price to four uosmo per uatom.
+    // The minimum LP tokens of the deposit value the uatom of the pool at that
+    // price once it is the TWAP.
+    swap(&app, &signer, pool_id, Coin::new(200_000_000_000, "uosmo"), "uatom");
+    app.increase_time(TWAP_WINDOW);
+
+    deposit(&app, &meta_vault, &signer);
+
+    let allocation = query_allocation(&app, &meta_vault);
+    assert!(allocation.idle_amount.is_zero());
+    assert!(allocation.total_value >= Uint128::new(DEPOSIT_AMOUNT * 95 / 100));
+    assert!(allocation.total_value <= Uint128::new(DEPOSIT_AMOUNT));
+}
+
+#[test]
+fn deposit_fails_below_min_shares() {
+    let MetaSetup {
+        app,
+        signer,
+        meta_vault,
+        ..
+    } = setup();
+
+    let wasm = Wasm::new(&app);
+    let deposit_msg = |min_shares: Uint128| {
+        ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Meta(MetaExecuteMsg::Deposit {
+            amount: Uint128::new(DEPOSIT_AMOUNT),
+            min_shares,
+            recipient: None,
+        }))
+    };
+    let funds = [Coin::new(DEPOSIT_AMOUNT, "uosmo")];
+
+    // The deposit loses the swap fee, so it cannot mint shares for its full
+    // amount
+    let min_shares = Uint128::new(DEPOSIT_AMOUNT) * DEFAULT_VAULT_TOKENS_PER_STAKED_BASE_TOKEN;
+    let res = wasm.execute(&meta_vault, &deposit_msg(min_shares), &funds, &signer).unwrap_err();
+    assert_err(res, "less than the minimum");
+
+    let min_shares = min_shares * Decimal::percent(95);
+    wasm.execute(&meta_vault, &deposit_msg(min_shares), &funds, &signer).unwrap();
+    let shares =
+        query_token_balance(&app, &signer.address(), &query_vault_token(&app, &meta_vault));
+    assert!(shares >= min_shares);
+}
+
+#[test]
+fn deposit_fails_below_min_lp_tokens() {
+    let MetaSetup {
+        app,
+        signer,
+        admin,
+        meta_vault,
+        ..
+    } = setup();
+
+    let wasm = Wasm::new(&app);
+    let mut config: ConfigUnchecked = wasm
+        .query(
+            &meta_vault,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::Meta(MetaQueryMsg::Config {})),
+        )
+        .unwrap();
+
+    // Without slippage, providing liquidity with uosmo alone mints fewer LP
+    // tokens than the deposit is worth at the TWAP price
+    config.max_slippage = Decimal::zero();
+    wasm.execute(
+        &meta_vault,
+        &ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Meta(MetaExecuteMsg::UpdateConfig {
+            config,
+        })),
+        &[],
+        &admin,
+    )
+    .unwrap();
+
+    wasm.execute(
+        &meta_vault,
+        &ExecuteMsg::Deposit {
+            amount: Uint128::new(DEPOSIT_AMOUNT),
+            recipient: None,
+        },
+        &[Coin::new(DEPOSIT_AMOUNT, "uosmo")],
+        &signer,
+    )
+    .unwrap_err();
+}
+
+#[test]
+fn redeem_sends_share_of_vault_tokens() {
+    let MetaSetup {
+        app,
+        signer,
+        vaults,
+        meta_vault,
+        ..
+    } = setup();
+
+    let wasm = Wasm::new(&app);
+    deposit(&app, &meta_vault, &signer);
+
+    let meta_token = query_vault_token(&app, &meta_vault);
+    let shares = query_token_balance(&app, &signer.address(), &meta_token);
+    let vault_tokens = vaults.iter().map(|x| query_vault_token(&app, x)).collect::<Vec<_>>();
+    let held_before =
+        vault_tokens.iter().map(|x| query_token_balance(&app, &meta_vault, x)).collect::<Vec<_>>();
+
+    let redeem_amount = shares / Uint128::new(2);
+    wasm.execute(
+        &meta_vault,
+        &ExecuteMsg::Redeem {
+            amount: redeem_amount,
+            recipient: None,
+        },
+        &[Coin::new(redeem_amount.u128(), &meta_token)],
+        &signer,
+    )
+    .unwrap();
+
+    // The shares are burned and half of each underlying vault token is sent
+    // to the redeemer
+    assert_eq!(query_token_balance(&app, &signer.address(), &meta_token), shares - redeem_amount);
+    for (vault_token, before) in vault_tokens.iter().zip(held_before) {
+        let received = query_token_balance(&app, &signer.address(), vault_token);
+        let expected = before / Uint128::new(2);
+        assert!(received <= expected && received + Uint128::one() >= expected);
+        assert_eq!(query_token_balance(&app, &meta_vault, vault_token), before - received);
+    }
+}
+
+#[test]
+fn rebalance_moves_value_out_of_vault_without_weight() {
+    let MetaSetup {
+        app,
+        signer,
+        admin,
+        vaults,
+        meta_vault,
+    } = setup();
+
+    let wasm = Wasm::new(&app);
+    deposit(&app, &meta_vault, &signer);
+
+    // Only the admin and keepers can rebalance
+    let res = wasm
+        .execute(
+            &meta_vault,
+            &ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Meta(MetaExecuteMsg::Rebalance {})),
+            &[],
+            &signer,
+        )
+        .unwrap_err();
+    assert_err(res, "Unauthorized");
+
+    let weights = |first: u64, second: u64| {
+        vec![
+            VaultWeight {
+                address: vaults[0].clone(),
+                weight: Decimal::percent(first),
+            },
+            VaultWeight {
+                address: vaults[1].clone(),
+                weight: Decimal::percent(second),
+            },
+        ]
+    };
+    update_vaults(&app, &meta_vault, weights(100, 0), &admin).unwrap();
+    let total_value = query_allocation(&app, &meta_vault).total_value;
+
+    // The first rebalance unlocks everything held in the second vault
+    rebalance(&app, &meta_vault, &admin);
+    let allocation = query_allocation(&app, &meta_vault);
+    assert!(allocation.vaults[1].vault_token_amount.is_zero());
+    assert!(!allocation.unlocking_value.is_zero());
+    assert!(allocation.total_value + Uint128::one() >= total_value);
+
+    // The second rebalance after the lockup withdraws the liquidity, sells
+    // the uatom and allocates everything to the first vault
+    app.increase_time(LOCKUP_DURATION);
+    rebalance(&app, &meta_vault, &admin);
+    let allocation = query_allocation(&app, &meta_vault);
+    assert!(allocation.unlocking_value.is_zero());
+    assert!(allocation.vaults[1].value.is_zero());
+    assert!(allocation.idle_amount.is_zero());
+    assert!(allocation.vaults[0].value >= total_value * Decimal::percent(95));
+    assert!(query_token_balance(&app, &meta_vault, "uatom").is_zero());
+}
+
+#[test]
+fn update_vaults_removes_only_empty_vaults() {
+    let MetaSetup {
+        app,
+        signer,
+        admin,
+        vaults,
+        meta_vault,
+    } = setup();
+
+    deposit(&app, &meta_vault, &signer);
+    let first_only = vec![VaultWeight {
+        address: vaults[0].clone(),
+        weight: Decimal::one(),
+    }];
+
+    // Only the admin can update the vaults
+    let res = update_vaults(&app, &meta_vault, first_only.clone(), &signer).unwrap_err();
+    assert_err(res, "Caller is not admin");
+
+    // The weights cannot all be zero
+    let zero_weights = vaults
+        .iter()
+        .map(|address| VaultWeight {
+            address: address.clone(),
+            weight: Decimal::zero(),
+        })
+        .collect();
+    let res = update_vaults(&app, &meta_vault, zero_weights, &admin).unwrap_err();
+    assert_err(res, "Sum of vault weights must be greater than zero");
+
+    // The second vault still holds part of the deposit
+    let res = update_vaults(&app, &meta_vault, first_only.clone(), &admin).unwrap_err();
+    assert_err(res, format!("Vault {} still holds assets of the meta-vault", vaults[1]));
+
+    // Move everything out of the second vault, then remove it
+    let mut weights = first_only.clone();
+    weights.push(VaultWeight {
+        address: vaults[1].clone(),
+        weight: Decimal::zero(),
+    });
+    update_vaults(&app, &meta_vault, weights, &admin).unwrap();
+    rebalance(&app, &meta_vault, &admin);
+
+    // Unlocking positions also count as assets held in the vault
+    let res = update_vaults(&app, &meta_vault, first_only.clone(), &admin).unwrap_err();
+    assert_err(res, format!("Vault {} still holds assets of the meta-vault", vaults[1]));
+
+    app.increase_time(LOCKUP_DURATION);
+    rebalance(&app, &meta_vault, &admin);
+    update_vaults(&app, &meta_vault, first_only, &admin).unwrap();
+
+    let allocation = query_allocation(&app, &meta_vault);
+    assert_eq!(allocation.vaults.len(), 1);
+    assert_eq!(allocation.vaults[0].address, vaults[0]);
+    assert_eq!(allocation.vaults[0].weight, Decimal::one());
+}
@@ -0,0 +1,160 @@
+mod helpers;
+use std::str::FromStr;
+
+use apollo_cw_asset::AssetInfo;
+use cosmrs::proto::cosmos::{
+    bank::v1beta1::{MsgSend, QueryBalanceRequest},
+    base::v1beta1::Coin as ProtoCoin,
+};
+use cosmwasm_std::{Addr, Coin, Decimal, Uint128};
+use osmosis_test_tube::{Account, Bank, Module, OsmosisTestApp, SigningAccount, Wasm};
+use osmosis_vault::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use simple_vault::msg::{
+    ExtensionExecuteMsg, ExtensionQueryMsg, SimpleExtensionExecuteMsg, SimpleExtensionQueryMsg,
+};
+use simple_vault::state::ReferralInfo;
+
+use crate::helpers::osmosis::{assert_err, Setup};
+
+const OSMOSIS_VAULT_CONTRACT_NAME: &str = "osmosis_vault";
+
+fn setup() -> Setup {
+    Setup::with_vault(OSMOSIS_VAULT_CONTRACT_NAME, |admin, pool_id, mut config| {
+        config.referral_fee_share = Decimal::percent(50);
+        InstantiateMsg {
+            admin,
+            pool_id,
+            lockup_duration: 86400u64,
+            config,
+            vault_token_subdenom: "osmosis-vault".to_string(),
+        }
+    })
+}
+
+fn query_referral(app: &OsmosisTestApp, vault_addr: &str, address: &str) -> ReferralInfo {
+    Wasm::new(app)
+        .query(
+            vault_addr,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::Simple(
+                SimpleExtensionQueryMsg::Referral {
+                    address: address.to_string(),
+                },
+            )),
+        )
+        .unwrap()
+}
+
+fn query_token_balance(app: &OsmosisTestApp, address: &str, denom: &str) -> Uint128 {
+    let balance = Bank::new(app)
+        .query_balance(&QueryBalanceRequest {
+            address: address.to_string(),
+            denom: denom.to_string(),
+        })
+        .unwrap()
+        .balance
+        .unwrap_or_default()
+        .amount;
+    Uint128::from_str(&balance).unwrap()
+}
+
+fn deposit_msg(amount: Uint128, recipient: Option<String>, referrer: Option<String>) -> ExecuteMsg {
+    ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Simple(SimpleExtensionExecuteMsg::Deposit {
+        amount,
+        recipient,
+        referrer,
+    }))
+}
+
+#[test]
+fn referrer_earns_share_of_performance_fee() {
+    let Setup {
+        app,
+        signer,
+        vault_address,
+        base_token,
+        ..
+    } = setup();
+    let wasm = Wasm::new(&app);
+    let referrer = app.init_account(&[Coin::new(1_000_000_000_000, "uosmo")]).unwrap();
+
+    // A referrer can not be set for another recipient
+    let amount = Uint128::new(200_000_000u128);
+    let res = wasm
+        .execute(
+            &vault_address,
+            &deposit_msg(amount, Some(referrer.address()), Some(referrer.address())),
+            &[Coin::new(amount.u128(), base_token.to_string())],
+            &signer,
+        )
+        .unwrap_err();
+    assert_err(res, "A referrer can only be set by the recipient of the vault tokens");
+
+    // Deposit with a referrer
+    wasm.execute(
+        &vault_address,
+        &deposit_msg(amount, None, Some(referrer.address())),
+        &[Coin::new(amount.u128(), base_token.to_string())],
+        &signer,
+    )
+    .unwrap();
+
+    let depositor_info = query_referral(&app, &vault_address, &signer.address());
+    assert_eq!(depositor_info.referrer, Some(Addr::unchecked(referrer.address())));
+    let referrer_info = query_referral(&app, &vault_address, &referrer.address());
+    assert!(!referrer_info.referred_vault_tokens.is_zero());
+    assert_eq!(referrer_info.claimable.len(), 0);
+
+    // Send rewards to the vault and compound them with another deposit
+    let reward_amount = Uint128::new(100_000_000u128);
+    Bank::new(&app)
+        .send(
+            MsgSend {
+                amount: vec![ProtoCoin {
+                    denom: "pica".to_string(),
+                    amount: reward_amount.to_string(),
+                }],
+                from_address: signer.address(),
+                to_address: vault_address.clone(),
+            },
+            &signer,
+        )
+        .unwrap();
+    wasm.execute(
+        &vault_address,
+        &deposit_msg(amount, None, None),
+        &[Coin::new(amount.u128(), base_token.to_string())],
+        &signer,
+    )
+    .unwrap();
+
+    // The referred depositor held all vault tokens during the compound, so the
+    // referrer earns half of the 12.5% performance fee, up to rounding
+    let pica = AssetInfo::Native("pica".to_string());
+    let referrer_info = query_referral(&app, &vault_address, &referrer.address());
+    let claimable = referrer_info.claimable.find(&pica).unwrap().amount;
+    assert!(Uint128::new(6_250_000u128) - claimable <= Uint128::one());
+
+    // The referrer claims the fee
+    claim_referral_fees(&wasm, &vault_address, &referrer);
+    assert_eq!(query_token_balance(&app, &referrer.address(), "pica"), claimable);
+    let referrer_info = query_referral(&app, &vault_address, &referrer.address());
+    assert_eq!(referrer_info.claimable.len(), 0);
+
+    // Claiming again sends nothing
+    claim_referral_fees(&wasm, &vault_address, &referrer);
+    assert_eq!(query_token_balance(&app, &referrer.address(), "pica"), claimable);
+}
+
+fn claim_referral_fees(wasm: &Wasm<OsmosisTestApp>, vault_addr: &str, referrer: &SigningAccount) {
+    wasm.execute(
+        vault_addr,
+        &ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Simple(
+            SimpleExtensionExecuteMsg::ClaimReferralFees {
+                recipient: None,
+            },
+        )),
+        &[],
+        referrer,
+    )
+    .unwrap();
+}
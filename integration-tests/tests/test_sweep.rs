@@ -0,0 +1,115 @@
+mod helpers;
+
+use apollo_cw_asset::{AssetInfoUnchecked, AssetList};
+use cosmrs::proto::cosmos::{bank::v1beta1::MsgSend, base::v1beta1::Coin as ProtoCoin};
+use cosmwasm_std::{Coin, Uint128};
+use cw_dex::osmosis::{OsmosisPool, OsmosisStaking};
+use cw_vault_token::osmosis::OsmosisDenom;
+use osmosis_test_tube::{Account, Bank, Module, OsmosisTestApp, SigningAccount, Wasm};
+use osmosis_vault::msg::{ExecuteMsg, QueryMsg};
+use simple_vault::msg::{
+    ExtensionExecuteMsg, ExtensionQueryMsg, SimpleExtensionExecuteMsg, SimpleExtensionQueryMsg,
+    StateResponse,
+};
+
+use crate::helpers::osmosis::{assert_err, Setup};
+
+fn send_native_coins(
+    app: &OsmosisTestApp,
+    from: &SigningAccount,
+    to: &str,
+    denom: &str,
+    amount: Uint128,
+) {
+    Bank::new(app)
+        .send(
+            MsgSend {
+                amount: vec![ProtoCoin {
+                    denom: denom.to_string(),
+                    amount: amount.to_string(),
+                }],
+                from_address: from.address(),
+                to_address: to.to_string(),
+            },
+            from,
+        )
+        .unwrap();
+}
+
+#[test]
+fn sweep_rejects_accounted_assets() {
+    let Setup {
+        app,
+        signer,
+        admin,
+        vault_address,
+        base_token,
+        ..
+    } = Setup::new();
+
+    let wasm = Wasm::new(&app);
+
+    let deposit_amount = Uint128::new(200_000_000u128);
+    wasm.execute(
+        &vault_address,
+        &ExecuteMsg::Deposit {
+            amount: deposit_amount,
+            recipient: None,
+        },
+        &[Coin::new(deposit_amount.u128(), base_token.to_string())],
+        &signer,
+    )
+    .unwrap();
+
+    let state: StateResponse<OsmosisStaking, OsmosisPool, OsmosisDenom> = wasm
+        .query(
+            &vault_address,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::Simple(SimpleExtensionQueryMsg::State {})),
+        )
+        .unwrap();
+    let vault_token_denom = state.vault_token.to_string();
+
+    // Send some of the base token, the pool assets, the reward asset and the vault
+    // token to the vault
+    let accounted = vec![
+        base_token.to_string(),
+        "uatom".to_string(),
+        "uosmo".to_string(),
+        "pica".to_string(),
+        vault_token_denom,
+    ];
+    for denom in &accounted {
+        send_native_coins(&app, &signer, &vault_address, denom, Uint128::new(1_000u128));
+    }
+
+    // None of them are listed as unaccounted
+    let unaccounted: AssetList = wasm
+        .query(
+            &vault_address,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::Simple(
+                SimpleExtensionQueryMsg::UnaccountedBalances {
+                    cw20s: vec![],
+                },
+            )),
+        )
+        .unwrap();
+    assert_eq!(unaccounted.len(), 0);
+
+    // And none of them can be swept
+    for denom in accounted {
+        let res = wasm
+            .execute(
+                &vault_address,
+                &ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Simple(
+                    SimpleExtensionExecuteMsg::Sweep {
+                        asset: AssetInfoUnchecked::Native(denom),
+                        recipient: admin.address(),
+                    },
+                )),
+                &[],
+                &admin,
+            )
+            .unwrap_err();
+        assert_err(res, "which is accounted for by the vault");
+    }
+}
@@ -0,0 +1,464 @@
+mod helpers;
+use std::str::FromStr;
+
+use base_vault::DEFAULT_VAULT_TOKENS_PER_STAKED_BASE_TOKEN;
+use cosmrs::{
+    proto::cosmos::{
+        bank::v1beta1::{MsgSend, QueryBalanceRequest},
+        base::v1beta1::Coin as ProtoCoin,
+    },
+    Any,
+};
+use cosmwasm_std::{Coin, Decimal, Uint128};
+use cw_utils::Duration;
+use cw_vault_standard::extensions::lockup::{LockupExecuteMsg, LockupQueryMsg, UnlockingPosition};
+use osmosis_std::types::osmosis::lockup::Params as LockupParams;
+use osmosis_test_tube::{Account, Bank, Module, OsmosisTestApp, SigningAccount, Wasm};
+use osmosis_vault::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use prost::Message;
+use simple_vault::msg::{
+    ExtensionExecuteMsg, ExtensionQueryMsg, TrancheExecuteMsg, TrancheQueryMsg, TrancheResponse,
+};
+use simple_vault::state::ConfigUnchecked;
+
+use crate::helpers::osmosis::{assert_err, Setup};
+
+const OSMOSIS_VAULT_CONTRACT_NAME: &str = "osmosis_vault";
+const TRANCHE_LOCKUP_DURATION: u64 = 3600;
+
+fn setup_with_config(update_config: impl FnOnce(&mut ConfigUnchecked)) -> Setup {
+    Setup::with_vault(OSMOSIS_VAULT_CONTRACT_NAME, |admin, pool_id, mut config| {
+        update_config(&mut config);
+        InstantiateMsg {
+            admin,
+            pool_id,
+            lockup_duration: 86400u64,
+            config,
+            vault_token_subdenom: "osmosis-vault".to_string(),
+        }
+    })
+}
+
+fn add_tranche_msg(lockup_duration: u64, reward_weight: Decimal) -> ExecuteMsg {
+    ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Tranche(TrancheExecuteMsg::AddTranche {
+        lockup_duration,
+        vault_token_subdenom: format!("tranche-{}", lockup_duration),
+        reward_weight,
+    }))
+}
+
+/// Adds a tranche with `TRANCHE_LOCKUP_DURATION` and returns it.
+fn add_tranche(
+    app: &OsmosisTestApp,
+    vault_addr: &str,
+    admin: &SigningAccount,
+    reward_weight: Decimal,
+) -> TrancheResponse {
+    let wasm = Wasm::new(app);
+    wasm.execute(
+        vault_addr,
+        &add_tranche_msg(TRANCHE_LOCKUP_DURATION, reward_weight),
+        &[Coin::new(10_000_000, "uosmo")],
+        admin,
+    )
+    .unwrap();
+    query_tranche(app, vault_addr, 1)
+}
+
+fn query_tranche(app: &OsmosisTestApp, vault_addr: &str, tranche_id: u64) -> TrancheResponse {
+    let query = TrancheQueryMsg::Tranche {
+        tranche_id,
+    };
+    Wasm::new(app)
+        .query(vault_addr, &QueryMsg::VaultExtension(ExtensionQueryMsg::Tranche(query)))
+        .unwrap()
+}
+
+fn tranche_deposit_msg(tranche_id: u64, amount: Uint128) -> ExecuteMsg {
+    ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Tranche(TrancheExecuteMsg::Deposit {
+        tranche_id,
+        amount,
+        recipient: None,
+    }))
+}
+
+fn query_token_balance(app: &OsmosisTestApp, address: &str, denom: &str) -> Uint128 {
+    let balance = Bank::new(app)
+        .query_balance(&QueryBalanceRequest {
+            address: address.to_string(),
+            denom: denom.to_string(),
+        })
+        .unwrap()
+        .balance
+        .unwrap_or_default()
+        .amount;
+    Uint128::from_str(&balance).unwrap()
+}
+
+fn send_native_coins(
+    app: &OsmosisTestApp,
+    from: &SigningAccount,
+    to: &str,
+    denom: &str,
+    amount: Uint128,
+) {
+    Bank::new(app)
+        .send(
+            MsgSend {
+                amount: vec![ProtoCoin {
+                    denom: denom.to_string(),
+                    amount: amount.to_string(),
+                }],
+                from_address: from.address(),
+                to_address: to.to_string(),
+            },
+            from,
+        )
+        .unwrap();
+}
+
+#[test]
+fn add_tranche_and_deposit() {
+    let Setup {
+        app,
+        signer,
+        admin,
+        vault_address,
+        base_token,
+        ..
+    } = Setup::new();
+
+    let wasm = Wasm::new(&app);
+
+    // Only the admin can add tranches
+    let res = wasm
+        .execute(
+            &vault_address,
+            &add_tranche_msg(TRANCHE_LOCKUP_DURATION, Decimal::one()),
+            &[Coin::new(10_000_000, "uosmo")],
+            &signer,
+        )
+        .unwrap_err();
+    assert_err(res, "Caller is not admin");
+
+    // The lockup duration of the primary tranche is taken
+    let res = wasm
+        .execute(
+            &vault_address,
+            &add_tranche_msg(86400, Decimal::one()),
+            &[Coin::new(10_000_000, "uosmo")],
+            &admin,
+        )
+        .unwrap_err();
+    assert_err(res, "A tranche with lockup duration 86400 already exists");
+
+    let tranche = add_tranche(&app, &vault_address, &admin, Decimal::percent(200));
+    assert_eq!(tranche.id, 1);
+    assert_eq!(tranche.lockup_duration, Duration::Time(TRANCHE_LOCKUP_DURATION));
+    assert_eq!(tranche.reward_weight, Decimal::percent(200));
+    assert_eq!(tranche.total_staked_base_tokens, Uint128::zero());
+
+    let tranches: Vec<TrancheResponse> = wasm
+        .query(
+            &vault_address,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::Tranche(TrancheQueryMsg::Tranches {})),
+        )
+        .unwrap();
+    assert_eq!(tranches.iter().map(|t| t.id).collect::<Vec<_>>(), vec![0, 1]);
+
+    // Deposit into the tranche
+    let deposit_amount = Uint128::new(200_000_000u128);
+    wasm.execute(
+        &vault_address,
+        &tranche_deposit_msg(1, deposit_amount),
+        &[Coin::new(deposit_amount.u128(), base_token.to_string())],
+        &signer,
+    )
+    .unwrap();
+
+    let tranche = query_tranche(&app, &vault_address, 1);
+    assert_eq!(tranche.total_staked_base_tokens, deposit_amount);
+    assert_eq!(
+        query_token_balance(&app, &signer.address(), &tranche.vault_token),
+        deposit_amount * DEFAULT_VAULT_TOKENS_PER_STAKED_BASE_TOKEN
+    );
+    assert_eq!(
+        tranche.vault_token_supply,
+        deposit_amount * DEFAULT_VAULT_TOKENS_PER_STAKED_BASE_TOKEN
+    );
+
+    // The primary tranche is untouched
+    assert_eq!(query_tranche(&app, &vault_address, 0).total_staked_base_tokens, Uint128::zero());
+}
+
+#[test]
+fn tranche_unlock_and_withdraw() {
+    let Setup {
+        app,
+        signer,
+        admin,
+        vault_address,
+        base_token,
+        ..
+    } = Setup::new();
+
+    let wasm = Wasm::new(&app);
+
+    let tranche = add_tranche(&app, &vault_address, &admin, Decimal::one());
+    let deposit_amount = Uint128::new(200_000_000u128);
+    wasm.execute(
+        &vault_address,
+        &tranche_deposit_msg(1, deposit_amount),
+        &[Coin::new(deposit_amount.u128(), base_token.to_string())],
+        &signer,
+    )
+    .unwrap();
+
+    let vault_token_balance = query_token_balance(&app, &signer.address(), &tranche.vault_token);
+    wasm.execute(
+        &vault_address,
+        &ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Tranche(TrancheExecuteMsg::Unlock {
+            tranche_id: 1,
+            amount: vault_token_balance,
+        })),
+        &[Coin::new(vault_token_balance.u128(), &tranche.vault_token)],
+        &signer,
+    )
+    .unwrap();
+    assert_eq!(query_tranche(&app, &vault_address, 1).total_staked_base_tokens, Uint128::zero());
+
+    // The unlocking position is shared with the lockup extension
+    let unlocking_positions: Vec<UnlockingPosition> = wasm
+        .query(
+            &vault_address,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::Lockup(
+                LockupQueryMsg::UnlockingPositions {
+                    owner: signer.address(),
+                    start_after: None,
+                    limit: None,
+                },
+            )),
+        )
+        .unwrap();
+    assert_eq!(unlocking_positions.len(), 1);
+    let position = unlocking_positions[0].clone();
+    assert_eq!(position.base_token_amount, deposit_amount);
+
+    // The position matures after the lockup duration of the tranche
+    let withdraw_msg = ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(
+        LockupExecuteMsg::WithdrawUnlocked {
+            lockup_id: position.id,
+            recipient: None,
+        },
+    ));
+    let res = wasm.execute(&vault_address, &withdraw_msg, &[], &signer).unwrap_err();
+    assert_err(res, "Claim has not yet matured");
+
+    app.increase_time(TRANCHE_LOCKUP_DURATION);
+
+    let base_token_balance_before =
+        query_token_balance(&app, &signer.address(), &base_token.to_string());
+    wasm.execute(&vault_address, &withdraw_msg, &[], &signer).unwrap();
+    assert_eq!(
+        query_token_balance(&app, &signer.address(), &base_token.to_string()),
+        base_token_balance_before + deposit_amount
+    );
+}
+
+#[test]
+fn tranche_force_redeem() {
+    let Setup {
+        app,
+        signer,
+        admin,
+        force_withdraw_admin,
+        vault_address,
+        base_token,
+        ..
+    } = Setup::new();
+
+    let wasm = Wasm::new(&app);
+
+    let tranche = add_tranche(&app, &vault_address, &admin, Decimal::one());
+    let deposit_amount = Uint128::new(200_000_000u128);
+    wasm.execute(
+        &vault_address,
+        &tranche_deposit_msg(1, deposit_amount),
+        &[Coin::new(deposit_amount.u128(), base_token.to_string())],
+        &signer,
+    )
+    .unwrap();
+
+    let redeem_amount =
+        query_token_balance(&app, &signer.address(), &tranche.vault_token) / Uint128::new(2);
+    let force_redeem_msg =
+        ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Tranche(TrancheExecuteMsg::ForceRedeem {
+            tranche_id: 1,
+            amount: redeem_amount,
+            recipient: None,
+        }));
+
+    // The sender must be whitelisted
+    let res = wasm
+        .execute(
+            &vault_address,
+            &force_redeem_msg,
+            &[Coin::new(redeem_amount.u128(), &tranche.vault_token)],
+            &signer,
+        )
+        .unwrap_err();
+    assert_err(res, "Unauthorized");
+
+    send_native_coins(
+        &app,
+        &signer,
+        &force_withdraw_admin.address(),
+        &tranche.vault_token,
+        redeem_amount,
+    );
+    app.set_param_set(
+        "lockup",
+        Any {
+            type_url: LockupParams::TYPE_URL.to_string(),
+            value: LockupParams {
+                force_unlock_allowed_addresses: vec![vault_address.clone()],
+            }
+            .encode_to_vec(),
+        },
+    )
+    .unwrap();
+
+    wasm.execute(
+        &vault_address,
+        &force_redeem_msg,
+        &[Coin::new(redeem_amount.u128(), &tranche.vault_token)],
+        &force_withdraw_admin,
+    )
+    .unwrap();
+
+    let half_deposit = deposit_amount / Uint128::new(2);
+    assert_eq!(
+        query_token_balance(&app, &force_withdraw_admin.address(), &base_token.to_string()),
+        half_deposit
+    );
+    assert_eq!(
+        query_tranche(&app, &vault_address, 1).total_staked_base_tokens,
+        deposit_amount - half_deposit
+    );
+}
+
+#[test]
+fn rewards_are_split_between_tranches() {
+    let Setup {
+        app,
+        signer,
+        admin,
+        vault_address,
+        base_token,
+        ..
+    } = Setup::new();
+
+    let wasm = Wasm::new(&app);
+
+    // The tranche has twice the reward weight of the primary tranche
+    add_tranche(&app, &vault_address, &admin, Decimal::percent(200));
+    let deposit_amount = Uint128::new(200_000_000u128);
+    wasm.execute(
+        &vault_address,
+        &ExecuteMsg::Deposit {
+            amount: deposit_amount,
+            recipient: None,
+        },
+        &[Coin::new(deposit_amount.u128(), base_token.to_string())],
+        &signer,
+    )
+    .unwrap();
+    wasm.execute(
+        &vault_address,
+        &tranche_deposit_msg(1, deposit_amount),
+        &[Coin::new(deposit_amount.u128(), base_token.to_string())],
+        &signer,
+    )
+    .unwrap();
+
+    // Send reward tokens to the vault and compound them with a deposit into the
+    // tranche
+    send_native_coins(&app, &signer, &vault_address, "pica", Uint128::new(100_000_000u128));
+    wasm.execute(
+        &vault_address,
+        &tranche_deposit_msg(1, deposit_amount),
+        &[Coin::new(deposit_amount.u128(), base_token.to_string())],
+        &signer,
+    )
+    .unwrap();
+
+    let primary_rewards =
+        query_tranche(&app, &vault_address, 0).total_staked_base_tokens - deposit_amount;
+    let tranche_rewards = query_tranche(&app, &vault_address, 1).total_staked_base_tokens
+        - deposit_amount
+        - deposit_amount;
+    assert!(!primary_rewards.is_zero());
+    assert!(tranche_rewards.abs_diff(primary_rewards * Uint128::new(2)) <= Uint128::new(2));
+}
+
+#[test]
+fn tranche_share_price_increase_is_limited() {
+    let Setup {
+        app,
+        signer,
+        admin,
+        vault_address,
+        base_token,
+        ..
+    } = setup_with_config(|config| config.max_share_price_increase = Decimal::percent(1));
+
+    let wasm = Wasm::new(&app);
+
+    // Only the tranche holds base tokens, so it receives all the rewards
+    add_tranche(&app, &vault_address, &admin, Decimal::one());
+    let deposit_amount = Uint128::new(200_000_000u128);
+    wasm.execute(
+        &vault_address,
+        &tranche_deposit_msg(1, deposit_amount),
+        &[Coin::new(deposit_amount.u128(), base_token.to_string())],
+        &signer,
+    )
+    .unwrap();
+
+    send_native_coins(&app, &signer, &vault_address, "pica", Uint128::new(100_000_000u128));
+    let res = wasm
+        .execute(
+            &vault_address,
+            &tranche_deposit_msg(1, deposit_amount),
+            &[Coin::new(deposit_amount.u128(), base_token.to_string())],
+            &signer,
+        )
+        .unwrap_err();
+    assert_err(res, "during compound, outside of the allowed bounds");
+}
+
+#[test]
+fn tranche_deposits_are_rejected_in_batched_mode() {
+    let Setup {
+        app,
+        signer,
+        admin,
+        vault_address,
+        base_token,
+        ..
+    } = setup_with_config(|config| config.epoch_duration = 3600);
+
+    let wasm = Wasm::new(&app);
+
+    add_tranche(&app, &vault_address, &admin, Decimal::one());
+    let deposit_amount = Uint128::new(200_000_000u128);
+    let res = wasm
+        .execute(
+            &vault_address,
+            &tranche_deposit_msg(1, deposit_amount),
+            &[Coin::new(deposit_amount.u128(), base_token.to_string())],
+            &signer,
+        )
+        .unwrap_err();
+    assert_err(res, "Tranche deposits and unlocks are not supported in batched mode");
+}
@@ -0,0 +1,150 @@
+mod helpers;
+
+use cosmwasm_std::{Coin, Uint128};
+use cw_dex::osmosis::{OsmosisPool, OsmosisStaking};
+use cw_vault_standard::extensions::lockup::{LockupExecuteMsg, LockupQueryMsg, UnlockingPosition};
+use cw_vault_token::osmosis::OsmosisDenom;
+use osmosis_test_tube::{Account, Module, OsmosisTestApp, SigningAccount, Wasm};
+use osmosis_vault::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use simple_vault::msg::{
+    ExtensionExecuteMsg, ExtensionQueryMsg, SimpleExtensionExecuteMsg, SimpleExtensionQueryMsg,
+    StateResponse,
+};
+
+use crate::helpers::osmosis::{assert_err, Setup};
+
+const OSMOSIS_VAULT_CONTRACT_NAME: &str = "osmosis_vault";
+
+/// Deposits and unlocks base tokens and returns the unlocking position.
+fn deposit_and_unlock(
+    app: &OsmosisTestApp,
+    vault_addr: &str,
+    signer: &SigningAccount,
+    base_token: &str,
+) -> UnlockingPosition {
+    let wasm = Wasm::new(app);
+
+    let amount = Uint128::new(200_000_000u128);
+    wasm.execute(
+        vault_addr,
+        &ExecuteMsg::Deposit {
+            amount,
+            recipient: None,
+        },
+        &[Coin::new(amount.u128(), base_token)],
+        signer,
+    )
+    .unwrap();
+
+    let state: StateResponse<OsmosisStaking, OsmosisPool, OsmosisDenom> = wasm
+        .query(
+            vault_addr,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::Simple(SimpleExtensionQueryMsg::State {})),
+        )
+        .unwrap();
+    wasm.execute(
+        vault_addr,
+        &ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(LockupExecuteMsg::Unlock {
+            amount: state.vault_token_supply,
+        })),
+        &[Coin::new(state.vault_token_supply.u128(), state.vault_token.to_string())],
+        signer,
+    )
+    .unwrap();
+
+    let positions: Vec<UnlockingPosition> = wasm
+        .query(
+            vault_addr,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::Lockup(
+                LockupQueryMsg::UnlockingPositions {
+                    owner: signer.address(),
+                    start_after: None,
+                    limit: None,
+                },
+            )),
+        )
+        .unwrap();
+    assert_eq!(positions.len(), 1);
+    positions[0].clone()
+}
+
+fn withdraw_unlocked_remote_msg(lockup_id: u64) -> ExecuteMsg {
+    ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Simple(
+        SimpleExtensionExecuteMsg::WithdrawUnlockedRemote {
+            lockup_id,
+            remote_recipient: "cosmos1remote".to_string(),
+        },
+    ))
+}
+
+#[test]
+fn withdraw_unlocked_remote_is_disabled_without_channel() {
+    let Setup {
+        app,
+        signer,
+        vault_address,
+        base_token,
+        ..
+    } = Setup::new();
+
+    let position = deposit_and_unlock(&app, &vault_address, &signer, &base_token.to_string());
+    app.increase_time(86400);
+
+    let res = Wasm::new(&app)
+        .execute(&vault_address, &withdraw_unlocked_remote_msg(position.id), &[], &signer)
+        .unwrap_err();
+    assert_err(res, "Withdrawals over IBC are disabled");
+}
+
+#[test]
+fn failed_withdraw_unlocked_remote_keeps_the_position() {
+    let Setup {
+        app,
+        signer,
+        vault_address,
+        base_token,
+        ..
+    } = Setup::with_vault(OSMOSIS_VAULT_CONTRACT_NAME, |admin, pool_id, mut config| {
+        config.ibc_withdraw_channel = "channel-0".to_string();
+        config.ibc_transfer_timeout = 600;
+        InstantiateMsg {
+            admin,
+            pool_id,
+            lockup_duration: 86400u64,
+            config,
+            vault_token_subdenom: "osmosis-vault".to_string(),
+        }
+    });
+    let wasm = Wasm::new(&app);
+
+    let position = deposit_and_unlock(&app, &vault_address, &signer, &base_token.to_string());
+
+    // The position must have matured
+    let res = wasm
+        .execute(&vault_address, &withdraw_unlocked_remote_msg(position.id), &[], &signer)
+        .unwrap_err();
+    assert_err(res, "Claim has not yet matured");
+
+    app.increase_time(86400);
+
+    // The test chain has no IBC channels, so the transfer fails and the whole
+    // withdrawal is reverted
+    let res = wasm
+        .execute(&vault_address, &withdraw_unlocked_remote_msg(position.id), &[], &signer)
+        .unwrap_err();
+    assert_err(res, "channel not found");
+
+    // The position can still be withdrawn locally
+    wasm.execute(
+        &vault_address,
+        &ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Lockup(
+            LockupExecuteMsg::WithdrawUnlocked {
+                lockup_id: position.id,
+                recipient: None,
+            },
+        )),
+        &[],
+        &signer,
+    )
+    .unwrap();
+}
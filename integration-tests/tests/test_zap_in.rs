@@ -0,0 +1,150 @@
+mod helpers;
+use std::str::FromStr;
+
+use cosmrs::proto::cosmos::bank::v1beta1::QueryBalanceRequest;
+use cosmwasm_std::{Addr, Coin, Uint128};
+use cw_dex::osmosis::{OsmosisPool, OsmosisStaking};
+use cw_vault_token::osmosis::OsmosisDenom;
+use osmosis_test_tube::{Account, Bank, Module, OsmosisTestApp, Wasm};
+use osmosis_vault::msg::{ExecuteMsg, QueryMsg};
+use simple_vault::msg::{
+    CallbackMsg, ExtensionExecuteMsg, ExtensionQueryMsg, SimpleExtensionExecuteMsg,
+    SimpleExtensionQueryMsg, StateResponse,
+};
+
+use crate::helpers::osmosis::{assert_err, Setup};
+
+fn query_vault_state(
+    app: &OsmosisTestApp,
+    vault_addr: &str,
+) -> StateResponse<OsmosisStaking, OsmosisPool, OsmosisDenom> {
+    Wasm::new(app)
+        .query(
+            vault_addr,
+            &QueryMsg::VaultExtension(ExtensionQueryMsg::Simple(SimpleExtensionQueryMsg::State {})),
+        )
+        .unwrap()
+}
+
+fn query_token_balance(app: &OsmosisTestApp, address: &str, denom: &str) -> Uint128 {
+    let balance = Bank::new(app)
+        .query_balance(&QueryBalanceRequest {
+            address: address.to_string(),
+            denom: denom.to_string(),
+        })
+        .unwrap()
+        .balance
+        .unwrap_or_default()
+        .amount;
+    Uint128::from_str(&balance).unwrap()
+}
+
+fn zap_in_msg(min_base_tokens: Uint128, recipient: Option<String>) -> ExecuteMsg {
+    ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Simple(SimpleExtensionExecuteMsg::ZapIn {
+        min_base_tokens,
+        recipient,
+    }))
+}
+
+#[test]
+fn zap_in_deposits_pool_assets() {
+    let Setup {
+        app,
+        signer,
+        admin,
+        vault_address,
+        ..
+    } = Setup::new();
+
+    let state_before = query_vault_state(&app, &vault_address);
+    let vault_token_denom = state_before.vault_token.to_string();
+
+    // Zap in with a single pool asset, minting the vault tokens to the admin
+    Wasm::new(&app)
+        .execute(
+            &vault_address,
+            &zap_in_msg(Uint128::one(), Some(admin.address())),
+            &[Coin::new(100, "uatom")],
+            &signer,
+        )
+        .unwrap();
+
+    let state_after = query_vault_state(&app, &vault_address);
+    let deposited = state_after.total_staked_base_tokens - state_before.total_staked_base_tokens;
+    assert!(!deposited.is_zero());
+    assert_eq!(
+        state_after.vault_token_supply - state_before.vault_token_supply,
+        query_token_balance(&app, &admin.address(), &vault_token_denom)
+    );
+    assert!(query_token_balance(&app, &signer.address(), &vault_token_denom).is_zero());
+}
+
+#[test]
+fn zap_in_below_min_base_tokens_fails() {
+    let Setup {
+        app,
+        signer,
+        vault_address,
+        ..
+    } = Setup::new();
+
+    let res = Wasm::new(&app)
+        .execute(
+            &vault_address,
+            &zap_in_msg(Uint128::MAX, None),
+            &[Coin::new(100, "uatom"), Coin::new(100, "uosmo")],
+            &signer,
+        )
+        .unwrap_err();
+    assert_err(res, "base tokens, less than the minimum of");
+}
+
+#[test]
+fn zap_in_rejects_invalid_funds() {
+    let Setup {
+        app,
+        signer,
+        vault_address,
+        ..
+    } = Setup::new();
+    let wasm = Wasm::new(&app);
+
+    let res = wasm.execute(&vault_address, &zap_in_msg(Uint128::one(), None), &[], &signer);
+    assert_err(res.unwrap_err(), "No funds sent to zap in");
+
+    let res = wasm.execute(
+        &vault_address,
+        &zap_in_msg(Uint128::one(), None),
+        &[Coin::new(100, "pica")],
+        &signer,
+    );
+    assert_err(res.unwrap_err(), "Cannot zap in with pica, which is not a pool asset");
+}
+
+#[test]
+fn zap_in_deposit_can_only_be_called_by_the_vault() {
+    let Setup {
+        app,
+        signer,
+        vault_address,
+        base_token,
+        ..
+    } = Setup::new();
+
+    // Send base tokens to the vault and try to deposit them as if they came from
+    // a zap-in
+    let amount = Uint128::new(1_000_000u128);
+    let res = Wasm::new(&app)
+        .execute(
+            &vault_address,
+            &ExecuteMsg::VaultExtension(ExtensionExecuteMsg::Callback(CallbackMsg::ZapInDeposit {
+                base_token_balance_before: Uint128::zero(),
+                min_base_tokens: Uint128::zero(),
+                recipient: Addr::unchecked(signer.address()),
+            })),
+            &[Coin::new(amount.u128(), base_token.to_string())],
+            &signer,
+        )
+        .unwrap_err();
+    assert_err(res, "Unauthorized");
+}
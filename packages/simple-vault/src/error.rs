@@ -1,5 +1,5 @@
 use apollo_cw_asset::AssetInfo;
-use cosmwasm_std::{Coin, DivideByZeroError, OverflowError, StdError};
+use cosmwasm_std::{Coin, Decimal, DivideByZeroError, OverflowError, StdError, Uint128};
 use cw_controllers::AdminError;
 use cw_dex::CwDexError;
 use cw_dex_router::ContractError as CwDexRouterError;
@@ -73,6 +73,18 @@ pub enum ContractError {
         actual: Vec<Coin>,
     },
 
+    #[error("Insufficient buffer for instant redemption. Available: {available}, requested: {requested}")]
+    InsufficientBuffer {
+        available: Uint128,
+        requested: Uint128,
+    },
+
+    #[error("Share price moved from {before} to {after} base tokens per vault token during compound, outside of the allowed bounds")]
+    SharePriceOutOfBounds { before: Decimal, after: Decimal },
+
+    #[error("A referrer can only be set by the recipient of the vault tokens")]
+    ReferrerNotRecipient {},
+
     #[error("No data in SubMsgResponse")]
     NoDataInSubMsgResponse {},
 
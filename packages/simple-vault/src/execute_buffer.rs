@@ -0,0 +1,199 @@
+use apollo_utils::responses::merge_responses;
+use cosmwasm_std::{
+    attr, Addr, Deps, DepsMut, Env, Event, MessageInfo, Response, StdError, StdResult, Storage,
+    Uint128,
+};
+use cw_dex::traits::{Pool, Stake};
+use cw_vault_token::VaultToken;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::ContractError;
+use crate::msg::{BufferResponse, CallbackMsg};
+use crate::SimpleVault;
+
+/// ExecuteMsg handlers for instant redemptions from the buffer of unstaked
+/// base tokens. This has a trait bound Stake on the S generic.
+impl<S, P, V> SimpleVault<'_, S, P, V>
+where
+    S: Stake + Serialize + DeserializeOwned,
+    P: Pool + Serialize + DeserializeOwned,
+    V: VaultToken + Serialize + DeserializeOwned,
+{
+    /// Redeem vault tokens instantly from the buffer. This will first compound
+    /// the pending rewards, then the vault tokens will be burned and their
+    /// value in base tokens, minus the exit fee, will be sent to the
+    /// recipient. If the vault token is a native token, the tokens must be
+    /// sent in the `info.funds` field.
+    ///
+    /// ## Arguments
+    /// - `vault_token_amount`: Amount of vault tokens to redeem.
+    /// - `recipient`: Optional address to receive the base tokens. If None, the
+    ///   `info.sender` will be used instead.
+    pub fn execute_instant_redeem(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        info: &MessageInfo,
+        vault_token_amount: Uint128,
+        recipient: Option<String>,
+    ) -> Result<Response, ContractError> {
+        let vault_token = self.base_vault.vault_token.load(deps.storage)?;
+
+        // Receive the vault token to the contract's balance, or validate that it was
+        // already received
+        vault_token.receive(deps.branch(), &env, info, vault_token_amount)?;
+
+        // The burned vault tokens no longer accrue referral fees
+        self.sync_referral(deps.branch(), &info.sender)?;
+
+        // Unwrap recipient or use caller's address
+        let recipient =
+            recipient.map_or(Ok(info.sender.clone()), |x| deps.api.addr_validate(&x))?;
+
+        let event = Event::new("apollo/vaults/execute_buffer").add_attributes(vec![
+            attr("action", "instant_redeem"),
+            attr("recipient", recipient.clone()),
+            attr("amount", vault_token_amount),
+        ]);
+
+        // Compound then redeem
+        Ok(self
+            .compound(deps, &env, Uint128::zero())?
+            .add_message(
+                CallbackMsg::InstantRedeem {
+                    amount: vault_token_amount,
+                    recipient,
+                }
+                .into_cosmos_msg(&env)?,
+            )
+            .add_event(event))
+    }
+
+    /// Callback function to redeem `amount` of vault tokens from the buffer
+    /// and send the base tokens minus the exit fee to `recipient`. Called
+    /// from the `execute_instant_redeem` function.
+    pub fn execute_callback_instant_redeem(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        vault_token_amount: Uint128,
+        recipient: Addr,
+    ) -> Result<Response, ContractError> {
+        let exit_fee = self.config.load(deps.storage)?.exit_fee;
+
+        // Burn vault tokens and get the amount of base tokens they represent
+        let (base_tokens, burn_res) = self.base_vault.burn_vault_tokens_for_base_tokens(
+            deps.branch(),
+            &env,
+            vault_token_amount,
+        )?;
+
+        let fee = base_tokens * exit_fee;
+        let payout = base_tokens.checked_sub(fee)?;
+
+        let buffer = self.buffer.may_load(deps.storage)?.unwrap_or_default();
+        if payout > buffer {
+            return Err(ContractError::InsufficientBuffer {
+                available: buffer,
+                requested: payout,
+            });
+        }
+        self.buffer
+            .save(deps.storage, &buffer.checked_sub(payout)?)?;
+
+        // The fee stays in the buffer and accrues to the remaining vault token holders
+        self.base_vault
+            .total_staked_base_tokens
+            .update(deps.storage, |old_value| {
+                old_value.checked_add(fee).map_err(StdError::overflow)
+            })?;
+
+        let send_res = self.base_vault.send_base_tokens(deps, &recipient, payout)?;
+
+        let event = Event::new("apollo/vaults/execute_buffer").add_attributes(vec![
+            attr("action", "execute_callback_instant_redeem"),
+            attr("recipient", recipient),
+            attr("vault_token_amount", vault_token_amount),
+            attr("base_token_amount", payout),
+            attr("exit_fee", fee),
+        ]);
+
+        Ok(merge_responses(vec![burn_res, send_res]).add_event(event))
+    }
+}
+
+impl<S, P, V> SimpleVault<'_, S, P, V>
+where
+    S: Serialize + DeserializeOwned,
+    P: Serialize + DeserializeOwned,
+    V: VaultToken + Serialize + DeserializeOwned,
+{
+    /// Splits `base_tokens` that were just burned from `total_staked_base_tokens`
+    /// into the part that is staked and the part that is held in the buffer.
+    /// The staked base tokens are used first, and the rest is taken from the
+    /// buffer. Returns `(to_unstake, from_buffer)`.
+    pub(crate) fn take_from_buffer(
+        &self,
+        storage: &mut dyn Storage,
+        base_tokens: Uint128,
+    ) -> StdResult<(Uint128, Uint128)> {
+        let buffer = self.buffer.may_load(storage)?.unwrap_or_default();
+        let total_staked_base_tokens = self.base_vault.total_staked_base_tokens.load(storage)?;
+
+        // Base tokens that were staked before the burn
+        let staked = total_staked_base_tokens
+            .checked_add(base_tokens)?
+            .saturating_sub(buffer);
+
+        let to_unstake = base_tokens.min(staked);
+        let from_buffer = base_tokens.checked_sub(to_unstake)?;
+        if !from_buffer.is_zero() {
+            self.buffer
+                .save(storage, &buffer.checked_sub(from_buffer)?)?;
+        }
+
+        Ok((to_unstake, from_buffer))
+    }
+
+    /// Takes up to `amount` of the base tokens of unlocking position
+    /// `lockup_id` that are held in the contract rather than unlocking.
+    /// Returns the amount taken.
+    pub(crate) fn take_unlocking_buffer(
+        &self,
+        storage: &mut dyn Storage,
+        lockup_id: u64,
+        amount: Uint128,
+    ) -> StdResult<Uint128> {
+        let unlocking_buffer = self
+            .unlocking_buffer
+            .may_load(storage, lockup_id)?
+            .unwrap_or_default();
+
+        let from_buffer = amount.min(unlocking_buffer);
+        let remaining = unlocking_buffer.checked_sub(from_buffer)?;
+        if remaining.is_zero() {
+            self.unlocking_buffer.remove(storage, lockup_id);
+        } else {
+            self.unlocking_buffer.save(storage, lockup_id, &remaining)?;
+        }
+
+        Ok(from_buffer)
+    }
+
+    /// Returns the level of the buffer for instant redemptions.
+    pub fn query_buffer(&self, deps: Deps) -> StdResult<BufferResponse> {
+        let config = self.config.load(deps.storage)?;
+        let total_staked_base_tokens = self
+            .base_vault
+            .total_staked_base_tokens
+            .load(deps.storage)?;
+
+        Ok(BufferResponse {
+            amount: self.buffer.may_load(deps.storage)?.unwrap_or_default(),
+            target_amount: total_staked_base_tokens * config.buffer_ratio,
+            buffer_ratio: config.buffer_ratio,
+            exit_fee: config.exit_fee,
+        })
+    }
+}
@@ -1,7 +1,7 @@
-use apollo_cw_asset::{Asset, AssetList};
+use apollo_cw_asset::{Asset, AssetInfo, AssetList};
 use cosmwasm_std::{
-    attr, to_binary, Decimal, DepsMut, Env, Event, MessageInfo, Response, StdError, StdResult,
-    Uint128,
+    attr, to_binary, Decimal, Deps, DepsMut, Env, Event, MessageInfo, Reply, Response, StdError,
+    StdResult, SubMsg, SubMsgResult, Uint128,
 };
 use cw_dex::traits::{Pool, Stake};
 use cw_vault_token::VaultToken;
@@ -10,8 +10,15 @@ use serde::Serialize;
 
 use crate::error::ContractError;
 use crate::msg::CallbackMsg;
+use crate::state::{CompoundFailure, Config, MaxSell, SellLimit};
 use crate::SimpleVault;
 
+/// Reply ID of the `SellRewards` step of a compound. Only replies on error.
+pub const SELL_REWARDS_REPLY_ID: u64 = 5001;
+/// Reply ID of the `ProvideLiquidity` step of a compound. Only replies on
+/// error.
+pub const PROVIDE_LIQUIDITY_REPLY_ID: u64 = 5002;
+
 impl<S, P, V> SimpleVault<'_, S, P, V>
 where
     S: Stake + Serialize + DeserializeOwned,
@@ -31,17 +38,44 @@ where
         deps: DepsMut,
         env: &Env,
         user_deposit_amount: Uint128,
+    ) -> Result<Response, ContractError> {
+        self.compound_with_pending_mint(deps, env, user_deposit_amount, user_deposit_amount)
+    }
+
+    /// Same as `compound`, but with the amount of deposited base tokens that
+    /// will be staked during the compound given separately from
+    /// `user_deposit_amount`. This is needed for cw20 deposits, which are not
+    /// yet part of the contract balance when compounding.
+    ///
+    /// # Arguments
+    /// - `user_deposit_amount` - Amount of base tokens in the contract that
+    ///   come from the user deposit.
+    /// - `pending_mint_amount` - Amount of base tokens deposited for which
+    ///   vault tokens are minted after the compound. Excluded from the share
+    ///   price check of the compound.
+    pub fn compound_with_pending_mint(
+        &self,
+        deps: DepsMut,
+        env: &Env,
+        user_deposit_amount: Uint128,
+        pending_mint_amount: Uint128,
     ) -> Result<Response, ContractError> {
         let staking = self.staking.load(deps.storage)?;
 
         // Claim pending rewards
         let claim_rewards_res = staking.claim_rewards(deps.as_ref(), env)?;
 
-        // Sell rewards
-        let sell_rewards = CallbackMsg::SellRewards {}.into_cosmos_msg(env)?;
-
-        // Provide liquidity
-        let provide_liquidity = CallbackMsg::ProvideLiquidity {}.into_cosmos_msg(env)?;
+        // Sell rewards and provide liquidity. A failure of either step is recorded
+        // in the reply instead of reverting the deposit or unlock that compounds,
+        // and the rewards are left in the contract until the next compound.
+        let sell_rewards = SubMsg::reply_on_error(
+            CallbackMsg::SellRewards {}.into_cosmos_msg(env)?,
+            SELL_REWARDS_REPLY_ID,
+        );
+        let provide_liquidity = SubMsg::reply_on_error(
+            CallbackMsg::ProvideLiquidity {}.into_cosmos_msg(env)?,
+            PROVIDE_LIQUIDITY_REPLY_ID,
+        );
 
         // Get the base token balance
         let base_token_balance = self
@@ -54,6 +88,22 @@ where
         // user deposit.
         let stake = CallbackMsg::Stake {
             base_token_balance_before: base_token_balance.checked_sub(user_deposit_amount)?,
+            user_deposit_amount,
+        }
+        .into_cosmos_msg(env)?;
+
+        // Check the share price once the compounded rewards are staked
+        let check_share_price = CallbackMsg::CheckSharePrice {
+            total_staked_base_tokens_before: self
+                .base_vault
+                .total_staked_base_tokens
+                .load(deps.storage)?,
+            vault_token_supply_before: self
+                .base_vault
+                .vault_token
+                .load(deps.storage)?
+                .query_total_supply(deps.as_ref())?,
+            pending_mint_amount,
         }
         .into_cosmos_msg(env)?;
 
@@ -64,14 +114,74 @@ where
         ]);
 
         Ok(claim_rewards_res
-            .add_message(sell_rewards)
-            .add_message(provide_liquidity)
+            .add_submessage(sell_rewards)
+            .add_submessage(provide_liquidity)
             .add_message(stake)
+            .add_message(check_share_price)
             .add_event(event))
     }
 
+    /// Handle the reply of a failed `SellRewards` or `ProvideLiquidity` step of
+    /// a compound. The failure is recorded and the rest of the transaction
+    /// proceeds with the rewards left in the contract.
+    pub fn reply_compound_failure(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        reply: Reply,
+    ) -> Result<Response, ContractError> {
+        let reason = match reply.result {
+            SubMsgResult::Err(reason) => reason,
+            SubMsgResult::Ok(_) => return Ok(Response::default()),
+        };
+
+        // The failed step was reverted, so the balances are those the step was
+        // called with
+        let (step, assets) = match reply.id {
+            SELL_REWARDS_REPLY_ID => {
+                let pool_assets = self.pool.load(deps.storage)?.pool_assets(deps.as_ref())?;
+                let base_token = self.base_vault.base_token.load(deps.storage)?;
+                let mut assets = AssetList::new();
+                for x in self.config.load(deps.storage)?.reward_assets {
+                    if pool_assets.contains(&x) || x == base_token {
+                        continue;
+                    }
+                    let amount = self.compoundable_balance(deps.as_ref(), &env, &x)?;
+                    if !amount.is_zero() {
+                        assets.add(&Asset::new(x, amount))?;
+                    }
+                }
+                ("sell_rewards", assets)
+            }
+            PROVIDE_LIQUIDITY_REPLY_ID => {
+                ("provide_liquidity", self.provide_liquidity_assets(deps.as_ref(), &env)?)
+            }
+            id => return Err(ContractError::UnknownReplyId(id)),
+        };
+
+        let failure = CompoundFailure {
+            step: step.to_string(),
+            assets,
+            reason,
+            height: env.block.height,
+        };
+        self.last_compound_failure.save(deps.storage, &failure)?;
+
+        let event = Event::new("apollo/vaults/execute_compound").add_attributes(vec![
+            attr("action", "compound_failure"),
+            attr("step", failure.step),
+            attr("assets", failure.assets.to_string()),
+            attr("reason", failure.reason),
+            attr("height", failure.height.to_string()),
+        ]);
+
+        Ok(Response::new().add_event(event))
+    }
+
     /// Sells all the reward tokens in the contract for the underlying tokens of
-    /// the pool in proportion to the current balance of the pool.
+    /// the pool in proportion to the current balance of the pool. Reward
+    /// tokens above the sell limit of their asset are left in the contract and
+    /// sold in later compounds.
     pub fn execute_callback_sell_rewards(
         &self,
         deps: DepsMut,
@@ -84,22 +194,74 @@ where
         let treasury = cfg.treasury;
         let performance_fee = cfg.performance_fee;
         let base_token = &self.base_vault.base_token.load(deps.storage)?;
+        let vault_token_supply =
+            self.base_vault.vault_token.load(deps.storage)?.query_total_supply(deps.as_ref())?;
 
         // AssetList of reward tokens collected from performance fees
         let mut reward_asset_balances_to_treasury = AssetList::new();
+        // AssetList of reward tokens reserved for referrers
+        let mut reward_asset_balances_to_referrers = AssetList::new();
+        // Rewards left unsold by previous compounds because of a sell limit
+        let unsold_rewards = self
+            .unsold_rewards
+            .may_load(deps.storage)?
+            .unwrap_or_else(AssetList::new);
+        let mut new_unsold_rewards = AssetList::new();
 
-        let reward_assets_to_sell: AssetList = reward_assets
-            .into_iter()
-            .map(|x| {
-                // Take performance fee from each reward asset
-                let balance = x.query_balance(&deps.querier, env.contract.address.clone())?;
-                let balance_after_fee = balance * (Decimal::one() - performance_fee);
-                let balance_sent_to_treasury = balance.checked_sub(balance_after_fee)?;
-                reward_asset_balances_to_treasury
-                    .add(&Asset::new(x.clone(), balance_sent_to_treasury))?;
-                Ok(Asset::new(x, balance_after_fee))
-            })
-            .collect::<StdResult<Vec<_>>>()?
+        let mut reward_assets_to_sell = vec![];
+        for x in reward_assets {
+            // Unsold rewards were already charged the performance fee
+            let unsold = unsold_rewards
+                .find(&x)
+                .map(|asset| asset.amount)
+                .unwrap_or_default();
+
+            let balance = self
+                .compoundable_balance(deps.as_ref(), &env, &x)?
+                .checked_sub(unsold)?;
+
+            // Take performance fee from each reward asset
+            let balance_after_fee = balance * (Decimal::one() - performance_fee);
+            let fee = balance.checked_sub(balance_after_fee)?;
+
+            // Reserve the referrers' share of the performance fee
+            let balance_to_referrers = self.referrals.distribute_fee(
+                deps.storage,
+                &Asset::new(x.clone(), fee),
+                cfg.referral_fee_share,
+                vault_token_supply,
+            )?;
+            if !balance_to_referrers.is_zero() {
+                reward_asset_balances_to_referrers
+                    .add(&Asset::new(x.clone(), balance_to_referrers))?;
+            }
+            reward_asset_balances_to_treasury
+                .add(&Asset::new(x.clone(), fee.checked_sub(balance_to_referrers)?))?;
+
+            // Sell at most the limit of the asset and keep the rest for later
+            // compounds. Pool assets and the base token are not sold, but
+            // provided as liquidity or staked in full, so they are never held
+            // back as unsold rewards.
+            let mut amount_to_sell = balance_after_fee.checked_add(unsold)?;
+            let is_sold = !pool_assets.contains(&x) && &x != base_token;
+            let limit = cfg.max_sell_per_compound.iter().find(|l| l.asset == x);
+            if let Some(limit) = limit.filter(|_| is_sold) {
+                let max_sell = self.max_sell_amount(deps.as_ref(), &cfg, limit)?;
+                if amount_to_sell > max_sell {
+                    new_unsold_rewards.add(&Asset::new(
+                        x.clone(),
+                        amount_to_sell.checked_sub(max_sell)?,
+                    ))?;
+                    amount_to_sell = max_sell;
+                }
+            }
+
+            reward_assets_to_sell.push(Asset::new(x, amount_to_sell));
+        }
+        self.unsold_rewards
+            .save(deps.storage, &new_unsold_rewards)?;
+
+        let reward_assets_to_sell: AssetList = reward_assets_to_sell
             .into_iter()
             .filter(|x| x.amount != Uint128::zero()) // Filter out assets with 0 balance
             //We only want to swap the reward assets that are not in the pair
@@ -123,6 +285,12 @@ where
                 reward_asset_balances_to_treasury.to_string(),
             );
         }
+        if reward_asset_balances_to_referrers.len() > 0 {
+            event = event.add_attribute(
+                "reward_asset_balances_to_referrers",
+                reward_asset_balances_to_referrers.to_string(),
+            );
+        }
 
         // Swap all other reward assets
         if reward_assets_to_sell.len() > 0 {
@@ -135,35 +303,92 @@ where
             msgs.append(&mut swap_msgs);
             event = event.add_attribute("reward_assets_to_sell", reward_assets_to_sell.to_string());
         }
+        if new_unsold_rewards.len() > 0 {
+            event = event.add_attribute("unsold_rewards", new_unsold_rewards.to_string());
+        }
 
         Ok(Response::new().add_messages(msgs).add_event(event))
     }
 
-    /// Provides liquidity to the pool with all the underlying tokens in the
-    /// contract.
-    pub fn execute_callback_provide_liquidity(
+    /// Returns the maximum amount of the asset of `limit` to sell in a single
+    /// compound.
+    fn max_sell_amount(
         &self,
-        deps: DepsMut,
-        env: Env,
-        _info: MessageInfo,
-    ) -> Result<Response, ContractError> {
-        let cfg = self.config.load(deps.storage)?;
-        let pool = self.pool.load(deps.storage)?;
+        deps: Deps,
+        cfg: &Config,
+        limit: &SellLimit,
+    ) -> Result<Uint128, ContractError> {
+        match limit.max {
+            MaxSell::Amount(amount) => Ok(amount),
+            MaxSell::PoolDepthRatio(ratio) => {
+                // The depth of the first pool the reward asset is sold into
+                let path = cfg.router.query_path_for_pair(
+                    &deps.querier,
+                    &limit.asset,
+                    &cfg.reward_liquidation_target,
+                )?;
+                let depth = match path.0.first() {
+                    Some(operation) => operation
+                        .pool
+                        .as_trait()
+                        .get_pool_liquidity(deps)?
+                        .find(&limit.asset)
+                        .map(|asset| asset.amount)
+                        .unwrap_or_default(),
+                    None => Uint128::zero(),
+                };
+                Ok(depth * ratio)
+            }
+        }
+    }
 
-        let contract_assets: AssetList = pool
-            .pool_assets(deps.as_ref())?
+    /// Returns the balance of `asset` in the contract that can be compounded.
+    /// Unclaimed referral fees are held in the contract and must not be
+    /// compounded.
+    fn compoundable_balance(
+        &self,
+        deps: Deps,
+        env: &Env,
+        asset: &AssetInfo,
+    ) -> StdResult<Uint128> {
+        Ok(asset
+            .query_balance(&deps.querier, env.contract.address.clone())?
+            .checked_sub(self.referrals.unclaimed_amount(deps.storage, asset)?)?)
+    }
+
+    /// Returns the underlying tokens of the pool in the contract that are
+    /// provided as liquidity by `execute_callback_provide_liquidity`.
+    fn provide_liquidity_assets(&self, deps: Deps, env: &Env) -> StdResult<AssetList> {
+        Ok(self
+            .pool
+            .load(deps.storage)?
+            .pool_assets(deps)?
             .into_iter()
             .map(|a| {
                 Ok(Asset {
-                    info: a.clone(),
-                    amount: a.query_balance(&deps.querier, env.contract.address.clone())?,
+                    amount: self.compoundable_balance(deps, env, &a)?,
+                    info: a,
                 })
             })
             .collect::<StdResult<Vec<_>>>()?
             .into_iter()
             .filter(|x| x.amount != Uint128::zero()) // Filter out assets with 0 balance
             .collect::<Vec<_>>()
-            .into();
+            .into())
+    }
+
+    /// Provides liquidity to the pool with all the underlying tokens in the
+    /// contract.
+    pub fn execute_callback_provide_liquidity(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        _info: MessageInfo,
+    ) -> Result<Response, ContractError> {
+        let cfg = self.config.load(deps.storage)?;
+        let pool = self.pool.load(deps.storage)?;
+
+        let contract_assets = self.provide_liquidity_assets(deps.as_ref(), &env)?;
 
         // No assets to provide liquidity with
         if contract_assets.len() == 0 {
@@ -188,7 +413,8 @@ where
     }
 
     /// Callback function to stake the LP tokens in the contract. Stakes the
-    /// entire balance of base tokens in the contract.
+    /// new base tokens in the contract, minus what is kept to refill the
+    /// buffer, plus the part of the buffer above its target.
     ///
     /// This is called after compounding. Since we do not know how many base
     /// tokens we receive from the liquidity provision we call this as a
@@ -210,13 +436,9 @@ where
             .checked_sub(base_token_balance_before)
             .unwrap_or_default();
 
-        // No base tokens to stake
-        if amount_to_stake.is_zero() {
-            return Ok(Response::default());
-        }
-
         // Update total_staked_base_tokens with amount from compound
-        self.base_vault
+        let total_staked_base_tokens = self
+            .base_vault
             .total_staked_base_tokens
             .update(deps.storage, |old_value| {
                 old_value
@@ -224,16 +446,40 @@ where
                     .map_err(StdError::overflow)
             })?;
 
-        // We stake the entire base_token_balance, which means we don't have to
-        // issue this call again in execute_callback_deposit.
-        let res = self
-            .staking
-            .load(deps.storage)?
-            .stake(deps.as_ref(), &env, amount_to_stake)?;
+        // Keep base tokens unstaked until the instant redemption buffer reaches
+        // its target share of the total base tokens, and stake the part of the
+        // buffer above its target, e.g. after the buffer ratio was lowered
+        let buffer_ratio = self.config.load(deps.storage)?.buffer_ratio;
+        let buffer = self.buffer.may_load(deps.storage)?.unwrap_or_default();
+        let buffer_target = total_staked_base_tokens * buffer_ratio;
+        let buffer_refill = buffer_target.saturating_sub(buffer).min(amount_to_stake);
+        let buffer_excess = buffer.saturating_sub(buffer_target);
+        if !buffer_refill.is_zero() || !buffer_excess.is_zero() {
+            self.buffer.save(
+                deps.storage,
+                &buffer.checked_add(buffer_refill)?.checked_sub(buffer_excess)?,
+            )?;
+        }
+        let amount_to_stake = amount_to_stake
+            .checked_sub(buffer_refill)?
+            .checked_add(buffer_excess)?;
+
+        // We stake the entire base_token_balance except for the buffer refill,
+        // which means we don't have to issue this call again in
+        // execute_callback_deposit.
+        let res = if amount_to_stake.is_zero() {
+            Response::new()
+        } else {
+            self.staking
+                .load(deps.storage)?
+                .stake(deps.as_ref(), &env, amount_to_stake)?
+        };
 
         let event = Event::new("apollo/vaults/execute_compound").add_attributes(vec![
             attr("action", "execute_callback_stake"),
             attr("amount_to_stake", amount_to_stake.to_string()),
+            attr("buffer_refill", buffer_refill.to_string()),
+            attr("buffer_excess", buffer_excess.to_string()),
             attr("base_token_balance", base_token_balance.to_string()),
             attr(
                 "base_token_balance_before",
@@ -243,4 +489,53 @@ where
 
         Ok(res.add_event(event))
     }
+
+    /// Callback function to check that the base tokens per vault token did not
+    /// decrease, or increase by more than `max_share_price_increase`, during
+    /// a compound. Called after `execute_callback_stake`.
+    pub fn execute_callback_check_share_price(
+        &self,
+        deps: DepsMut,
+        total_staked_base_tokens_before: Uint128,
+        vault_token_supply_before: Uint128,
+        pending_mint_amount: Uint128,
+    ) -> Result<Response, ContractError> {
+        let vault_token_supply = self
+            .base_vault
+            .vault_token
+            .load(deps.storage)?
+            .query_total_supply(deps.as_ref())?;
+
+        // The share price is undefined while there are no vault tokens
+        if vault_token_supply_before.is_zero() || vault_token_supply.is_zero() {
+            return Ok(Response::default());
+        }
+
+        // The deposited base tokens were staked, but their vault tokens are only
+        // minted after the compound
+        let total_staked_base_tokens = self
+            .base_vault
+            .total_staked_base_tokens
+            .load(deps.storage)?
+            .checked_sub(pending_mint_amount)?;
+
+        let before =
+            Decimal::from_ratio(total_staked_base_tokens_before, vault_token_supply_before);
+        let after = Decimal::from_ratio(total_staked_base_tokens, vault_token_supply);
+
+        let max_increase = self.config.load(deps.storage)?.max_share_price_increase;
+        if after < before
+            || (!max_increase.is_zero() && after > before * (Decimal::one() + max_increase))
+        {
+            return Err(ContractError::SharePriceOutOfBounds { before, after });
+        }
+
+        let event = Event::new("apollo/vaults/execute_compound").add_attributes(vec![
+            attr("action", "execute_callback_check_share_price"),
+            attr("share_price_before", before.to_string()),
+            attr("share_price_after", after.to_string()),
+        ]);
+
+        Ok(Response::new().add_event(event))
+    }
 }
@@ -0,0 +1,183 @@
+use apollo_utils::responses::merge_responses;
+use cosmwasm_std::{attr, Addr, Deps, DepsMut, Env, Event, MessageInfo, Response, StdResult};
+use cw_dex::traits::{Pool, Stake};
+use cw_vault_token::VaultToken;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::ContractError;
+use crate::msg::CallbackMsg;
+use crate::state::{Epoch, EpochPosition, SettledEpoch};
+use crate::SimpleVault;
+
+/// ExecuteMsg handlers for settling epochs of queued deposits and unlocks.
+/// This has a trait bound Stake on the S generic.
+impl<S, P, V> SimpleVault<'_, S, P, V>
+where
+    S: Stake + Serialize + DeserializeOwned,
+    P: Pool + Serialize + DeserializeOwned,
+    V: VaultToken + Serialize + DeserializeOwned,
+{
+    /// Settle the current epoch. This compounds the vault once, staking all
+    /// base tokens deposited in the epoch, and starts a single unlock for all
+    /// vault tokens unlocked in the epoch. Users can then claim their share
+    /// of the minted vault tokens and of the unlocked base tokens.
+    pub fn execute_settle_epoch(&self, deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+        let epoch_duration = self.config.load(deps.storage)?.epoch_duration;
+        let epoch = self
+            .epochs
+            .settle(deps.storage, &env.block, epoch_duration)?;
+
+        // The queued deposits are already held by the contract, both for native and
+        // cw20 base tokens, and are staked by the compound.
+        let compound_res = self.compound(deps, &env, epoch.deposited_base_tokens)?;
+
+        let mut msgs = vec![];
+        if !epoch.deposited_base_tokens.is_zero() {
+            msgs.push(
+                CallbackMsg::SettleEpochDeposits { epoch_id: epoch.id }.into_cosmos_msg(&env)?,
+            );
+        }
+        #[cfg(feature = "lockup")]
+        if !epoch.unlocked_vault_tokens.is_zero() {
+            // The unlocked vault tokens are held by the contract, so the contract owns
+            // the claim of the epoch.
+            msgs.push(
+                CallbackMsg::Unlock {
+                    owner: env.contract.address.clone(),
+                    vault_token_amount: epoch.unlocked_vault_tokens,
+                }
+                .into_cosmos_msg(&env)?,
+            );
+            msgs.push(
+                CallbackMsg::SettleEpochUnlocks { epoch_id: epoch.id }.into_cosmos_msg(&env)?,
+            );
+            msgs.push(CallbackMsg::SaveClaim {}.into_cosmos_msg(&env)?);
+        }
+
+        let event = Event::new("apollo/vaults/execute_epoch").add_attributes(vec![
+            attr("action", "execute_settle_epoch"),
+            attr("epoch_id", epoch.id.to_string()),
+            attr("deposited_base_tokens", epoch.deposited_base_tokens),
+            attr("unlocked_vault_tokens", epoch.unlocked_vault_tokens),
+        ]);
+
+        Ok(compound_res.add_messages(msgs).add_event(event))
+    }
+
+    /// Callback function to mint the vault tokens for the deposits of the
+    /// settled epoch `epoch_id` to the contract. Called from
+    /// `execute_settle_epoch` after the deposits have been staked.
+    pub fn execute_callback_settle_epoch_deposits(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        epoch_id: u64,
+    ) -> Result<Response, ContractError> {
+        let settled = self.epochs.query_settled_epoch(deps.as_ref(), epoch_id)?;
+        let amount = settled.deposited_base_tokens;
+
+        let vault_token = self.base_vault.vault_token.load(deps.storage)?;
+        let total_staked_amount = self
+            .base_vault
+            .total_staked_base_tokens
+            .load(deps.storage)?;
+        let vault_token_supply = vault_token.query_total_supply(deps.as_ref())?;
+
+        // The deposits were already added to `total_staked_amount` during the
+        // compound, see `execute_callback_mint_vault_token`.
+        let vault_tokens = self.base_vault.calculate_vault_tokens(
+            amount,
+            total_staked_amount.checked_sub(amount)?,
+            vault_token_supply,
+        )?;
+        self.epochs
+            .set_minted_vault_tokens(deps.storage, epoch_id, vault_tokens)?;
+
+        let event = Event::new("apollo/vaults/execute_epoch").add_attributes(vec![
+            attr("action", "execute_callback_settle_epoch_deposits"),
+            attr("epoch_id", epoch_id.to_string()),
+            attr("mint_amount", vault_tokens),
+        ]);
+
+        Ok(vault_token
+            .mint(deps, &env, &env.contract.address, vault_tokens)?
+            .add_event(event))
+    }
+
+    /// Claim the vault tokens for the deposits of `info.sender` in the settled
+    /// epoch `epoch_id`.
+    ///
+    /// ## Arguments
+    /// - epoch_id: The ID of the settled epoch.
+    /// - recipient: Optional address to receive the vault tokens. If `None`,
+    ///   `info.sender` will be used instead.
+    pub fn execute_claim_epoch_shares(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        epoch_id: u64,
+        recipient: Option<String>,
+    ) -> Result<Response, ContractError> {
+        // Unwrap recipient or use caller's address
+        let recipient =
+            recipient.map_or(Ok(info.sender.clone()), |x| deps.api.addr_validate(&x))?;
+
+        let vault_tokens = self
+            .epochs
+            .claim_vault_tokens(deps.storage, epoch_id, &info.sender)?;
+
+        // Attribute the claimed vault tokens to the recipient's referrer, as is done
+        // when minting vault tokens for a regular deposit.
+        let balance = self
+            .base_vault
+            .query_vault_token_balance(deps.as_ref(), recipient.to_string())?;
+        self.referrals
+            .add_vault_tokens(deps.storage, &recipient, balance, vault_tokens)?;
+
+        // The vault tokens are held by the contract. Burn them and mint the same
+        // amount to the recipient so that this works for all vault token types.
+        let vault_token = self.base_vault.vault_token.load(deps.storage)?;
+        let burn_res = vault_token.burn(deps.branch(), &env, vault_tokens)?;
+        let mint_res = vault_token.mint(deps, &env, &recipient, vault_tokens)?;
+
+        let event = Event::new("apollo/vaults/execute_epoch").add_attributes(vec![
+            attr("action", "execute_claim_epoch_shares"),
+            attr("epoch_id", epoch_id.to_string()),
+            attr("owner", info.sender),
+            attr("recipient", recipient),
+            attr("amount", vault_tokens),
+        ]);
+
+        Ok(merge_responses(vec![burn_res, mint_res]).add_event(event))
+    }
+}
+
+impl<S, P, V> SimpleVault<'_, S, P, V>
+where
+    S: Serialize + DeserializeOwned,
+    P: Serialize + DeserializeOwned,
+    V: VaultToken + Serialize + DeserializeOwned,
+{
+    /// Returns the epoch that deposits and unlocks are currently queued in.
+    pub fn query_current_epoch(&self, deps: Deps, env: Env) -> StdResult<Epoch> {
+        self.epochs.current_epoch(deps.storage, &env.block)
+    }
+
+    /// Returns the settled epoch `epoch_id`.
+    pub fn query_settled_epoch(&self, deps: Deps, epoch_id: u64) -> StdResult<SettledEpoch> {
+        self.epochs.query_settled_epoch(deps, epoch_id)
+    }
+
+    /// Returns the position of `address` in the epoch `epoch_id`.
+    pub fn query_epoch_position(
+        &self,
+        deps: Deps,
+        epoch_id: u64,
+        address: String,
+    ) -> StdResult<EpochPosition> {
+        let address: Addr = deps.api.addr_validate(&address)?;
+        self.epochs.query_position(deps, epoch_id, &address)
+    }
+}
@@ -26,45 +26,69 @@ where
         vault_token_amount: Uint128,
         recipient: Option<String>,
     ) -> Result<Response, ContractError> {
-        let cfg = self.config.load(deps.storage)?;
         let vault_token = self.base_vault.vault_token.load(deps.storage)?;
 
         // Receive the vault token to the contract's balance, or validate that it was
         // already received
         vault_token.receive(deps.branch(), &env, &info, vault_token_amount)?;
 
+        self.execute_force_redeem_received(deps, env, info.sender, vault_token_amount, recipient)
+    }
+
+    /// Force withdrawal of a locked position for `vault_token_amount` vault
+    /// tokens that are already held by the contract, e.g. vault tokens received
+    /// through a cw20 `Send` hook. `sender` must be whitelisted.
+    pub fn execute_force_redeem_received(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        sender: Addr,
+        vault_token_amount: Uint128,
+        recipient: Option<String>,
+    ) -> Result<Response, ContractError> {
+        let cfg = self.config.load(deps.storage)?;
+
         // Unwrap recipient or use caller's address
-        let recipient =
-            recipient.map_or(Ok(info.sender.clone()), |x| deps.api.addr_validate(&x))?;
+        let recipient = recipient.map_or(Ok(sender.clone()), |x| deps.api.addr_validate(&x))?;
 
         // Check ForceWithdraw whitelist
         let whitelist = cfg.force_withdraw_whitelist;
-        if !whitelist.contains(&info.sender) {
+        if !whitelist.contains(&sender) {
             return Err(ContractError::Unauthorized {});
         }
 
+        // The burned vault tokens no longer accrue referral fees
+        self.sync_referral(deps.branch(), &sender)?;
+
         // Burn vault tokens and get the amount of base tokens to withdraw
-        let (lp_tokens_to_unlock, burn_res) = self.base_vault.burn_vault_tokens_for_base_tokens(
+        let (base_tokens, burn_res) = self.base_vault.burn_vault_tokens_for_base_tokens(
             deps.branch(),
             &env,
             vault_token_amount,
         )?;
 
-        // Call force withdraw on staked LP
-        let staking = self.staking.load(deps.storage)?;
-        let force_withdraw_res =
-            staking.force_unlock(deps.as_ref(), &env, None, lp_tokens_to_unlock)?;
+        // Call force withdraw on the staked part of the base tokens, the rest is
+        // paid from the buffer
+        let (lp_tokens_to_unlock, from_buffer) =
+            self.take_from_buffer(deps.storage, base_tokens)?;
+        let force_withdraw_res = if lp_tokens_to_unlock.is_zero() {
+            Response::default()
+        } else {
+            let staking = self.staking.load(deps.storage)?;
+            staking.force_unlock(deps.as_ref(), &env, None, lp_tokens_to_unlock)?
+        };
 
         // Send the unstaked tokens to the recipient
         let send_res = self
             .base_vault
-            .send_base_tokens(deps, &recipient, lp_tokens_to_unlock)?;
+            .send_base_tokens(deps, &recipient, base_tokens)?;
 
         let event = Event::new("apollo/vaults/execute_force_unlock").add_attributes(vec![
             attr("action", "execute_force_redeem"),
             attr("recipient", recipient),
             attr("vault_token_amount", vault_token_amount),
-            attr("redeem_amount", lp_tokens_to_unlock),
+            attr("redeem_amount", base_tokens),
+            attr("from_buffer", from_buffer),
         ]);
 
         Ok(merge_responses(vec![burn_res, force_withdraw_res, send_res]).add_event(event))
@@ -107,13 +131,18 @@ where
             .claims
             .force_claim(deps.storage, &info, lockup_id, amount)?;
 
+        // The part of the claim that was taken from the buffer is already held
+        // in this contract
+        let from_buffer = self.take_unlocking_buffer(deps.storage, lockup_id, claimed_amount)?;
+        let to_unlock = claimed_amount.checked_sub(from_buffer)?;
+
         // If the lockup is not expired, call force withdraw to retrieve the
         // locked tokens.
         // If the lockup is already expired the tokens are already unlocked and
         // already sent to this contract.
-        let force_withdraw_res = if !is_expired {
+        let force_withdraw_res = if !is_expired && !to_unlock.is_zero() {
             let staking = self.staking.load(deps.storage)?;
-            staking.force_unlock(deps.as_ref(), &env, Some(lockup_id), claimed_amount)?
+            staking.force_unlock(deps.as_ref(), &env, Some(lockup_id), to_unlock)?
         } else {
             Response::default()
         };
@@ -43,6 +43,9 @@ where
         // already received
         vault_token.receive(deps.branch(), &env, info, vault_token_amount)?;
 
+        // The burned vault tokens no longer accrue referral fees
+        self.sync_referral(deps.branch(), &info.sender)?;
+
         // Unwrap recipient or use caller's address
         let recipient =
             recipient.map_or(Ok(info.sender.clone()), |x| deps.api.addr_validate(&x))?;
@@ -79,25 +82,32 @@ where
         let staking = self.staking.load(deps.storage)?;
 
         // Burn vault tokens and get the amount of base tokens to withdraw
-        let (lp_tokens_to_unstake, burn_res) = self.base_vault.burn_vault_tokens_for_base_tokens(
+        let (base_tokens, burn_res) = self.base_vault.burn_vault_tokens_for_base_tokens(
             deps.branch(),
             &env,
             vault_token_amount,
         )?;
 
-        // Unstakes base tokens
-        let unstake_res = staking.unstake(deps.as_ref(), &env, lp_tokens_to_unstake)?;
+        // Unstake the staked part of the base tokens, the rest is paid from the buffer
+        let (lp_tokens_to_unstake, from_buffer) =
+            self.take_from_buffer(deps.storage, base_tokens)?;
+        let unstake_res = if lp_tokens_to_unstake.is_zero() {
+            Response::new()
+        } else {
+            staking.unstake(deps.as_ref(), &env, lp_tokens_to_unstake)?
+        };
 
         // Send unstaked base tokes to recipient
         let send_res = self
             .base_vault
-            .send_base_tokens(deps, &recipient, lp_tokens_to_unstake)?;
+            .send_base_tokens(deps, &recipient, base_tokens)?;
 
         let event = Event::new("apollo/vaults/execute_redeem").add_attributes(vec![
             attr("action", "execute_callback_redeem"),
             attr("recipient", recipient),
             attr("vault_token_amount", vault_token_amount),
             attr("lp_tokens_to_unstake", lp_tokens_to_unstake),
+            attr("from_buffer", from_buffer),
         ]);
 
         Ok(merge_responses(vec![burn_res, unstake_res, send_res]).add_event(event))
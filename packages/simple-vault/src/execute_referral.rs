@@ -0,0 +1,84 @@
+use cosmwasm_std::{attr, Addr, Deps, DepsMut, Event, MessageInfo, Response, StdResult};
+use cw_vault_token::VaultToken;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::ContractError;
+use crate::state::ReferralInfo;
+use crate::SimpleVault;
+
+impl<S, P, V> SimpleVault<'_, S, P, V>
+where
+    S: Serialize + DeserializeOwned,
+    P: Serialize + DeserializeOwned,
+    V: VaultToken + Serialize + DeserializeOwned,
+{
+    /// Claim all referral fees accrued by `info.sender` and send them to
+    /// `recipient`.
+    ///
+    /// ## Arguments
+    /// - recipient: Optional address to receive the fees. If `None`,
+    ///   `info.sender` will be used instead.
+    pub fn execute_claim_referral_fees(
+        &self,
+        deps: DepsMut,
+        info: MessageInfo,
+        recipient: Option<String>,
+    ) -> Result<Response, ContractError> {
+        // Unwrap recipient or use caller's address
+        let recipient =
+            recipient.map_or(Ok(info.sender.clone()), |x| deps.api.addr_validate(&x))?;
+
+        let claimed = self.referrals.claim(deps.storage, &info.sender)?;
+
+        // Send the claimed fees to the recipient
+        let msgs = claimed
+            .to_vec()
+            .into_iter()
+            .map(|x| x.transfer_msg(&recipient))
+            .collect::<StdResult<Vec<_>>>()?;
+
+        let event = Event::new("apollo/vaults/execute_referral").add_attributes(vec![
+            attr("action", "execute_claim_referral_fees"),
+            attr("referrer", info.sender),
+            attr("recipient", recipient),
+            attr("claimed", claimed.to_string()),
+        ]);
+
+        Ok(Response::new().add_messages(msgs).add_event(event))
+    }
+
+    /// Caps the vault tokens of `address` that are attributed to its referrer
+    /// at its current vault token balance. Referral fees follow the vault
+    /// tokens a referred depositor holds, so anyone can call this after the
+    /// depositor transferred vault tokens away.
+    pub fn execute_sync_referral(
+        &self,
+        deps: DepsMut,
+        address: String,
+    ) -> Result<Response, ContractError> {
+        let address = deps.api.addr_validate(&address)?;
+        self.sync_referral(deps, &address)?;
+
+        let event = Event::new("apollo/vaults/execute_referral").add_attributes(vec![
+            attr("action", "execute_sync_referral"),
+            attr("address", address),
+        ]);
+
+        Ok(Response::new().add_event(event))
+    }
+
+    /// Caps the vault tokens of `address` that are attributed to its referrer
+    /// at its current vault token balance.
+    pub fn sync_referral(&self, deps: DepsMut, address: &Addr) -> StdResult<()> {
+        let balance =
+            self.base_vault.query_vault_token_balance(deps.as_ref(), address.to_string())?;
+        self.referrals.sync_vault_tokens(deps.storage, address, balance)
+    }
+
+    /// Returns the referral info of `address`.
+    pub fn query_referral(&self, deps: Deps, address: String) -> StdResult<ReferralInfo> {
+        let address = deps.api.addr_validate(&address)?;
+        self.referrals.query_referral(deps, &address)
+    }
+}
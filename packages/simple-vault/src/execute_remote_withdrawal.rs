@@ -0,0 +1,176 @@
+use apollo_cw_asset::AssetInfo;
+use cosmwasm_std::{
+    attr, BankMsg, Coin, DepsMut, Env, Event, MessageInfo, Reply, Response, StdError, SubMsg,
+};
+use cw_dex::traits::{LockedStaking, Pool};
+use cw_vault_token::VaultToken;
+use osmosis_std::types::ibc::applications::transfer::v1::{MsgTransfer, MsgTransferResponse};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::ContractError;
+use crate::msg::IbcLifecycleComplete;
+use crate::state::RemoteWithdrawalStatus;
+use crate::SimpleVault;
+
+/// Offset of the reply IDs of the IBC transfers of remote withdrawals. The
+/// reply ID of a transfer is the offset plus the ID of the withdrawal.
+pub const WITHDRAW_UNLOCKED_REMOTE_REPLY_ID_OFFSET: u64 = 1 << 32;
+
+/// ExecuteMsg handlers for withdrawing unlocked base tokens to another chain
+/// with the IBC-hooks module of Osmosis.
+impl<S, P, V> SimpleVault<'_, S, P, V>
+where
+    S: LockedStaking + Serialize + DeserializeOwned,
+    P: Pool + Serialize + DeserializeOwned,
+    V: VaultToken + Serialize + DeserializeOwned,
+{
+    /// Withdraw the base tokens from a locked position that has finished
+    /// unlocking to an address on another chain, using an ICS-20 transfer over
+    /// the `ibc_withdraw_channel` of the config. Only supported for native
+    /// base tokens.
+    ///
+    /// The withdrawal is recorded, and the transfer asks the IBC-hooks module
+    /// for a callback once it is acknowledged or times out, so that the base
+    /// tokens can be refunded to the owner if it fails. See
+    /// [`Self::sudo_ibc_lifecycle_complete`].
+    ///
+    /// ## Arguments
+    /// - lockup_id: ID of the lockup position to withdraw from.
+    /// - remote_recipient: The address on the remote chain to receive the
+    ///   withdrawn base tokens.
+    pub fn execute_withdraw_unlocked_remote(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        info: &MessageInfo,
+        lockup_id: u64,
+        remote_recipient: String,
+    ) -> Result<Response, ContractError> {
+        let config = self.config.load(deps.storage)?;
+        if config.ibc_withdraw_channel.is_empty() {
+            return Err(ContractError::from("Withdrawals over IBC are disabled"));
+        }
+        let denom = match self.base_vault.base_token.load(deps.storage)? {
+            AssetInfo::Native(denom) => denom,
+            AssetInfo::Cw20(_) => {
+                return Err(ContractError::from(
+                    "Only native base tokens can be withdrawn over IBC",
+                ))
+            }
+        };
+
+        let sum_to_claim = self.claims.claim_tokens(deps.storage, &env.block, info, lockup_id)?;
+
+        let res = self.withdraw_unlocked(deps.branch(), &env, lockup_id, sum_to_claim)?;
+
+        let amount = Coin {
+            denom,
+            amount: sum_to_claim,
+        };
+        let withdrawal_id = self.remote_withdrawals.record(
+            deps.storage,
+            &info.sender,
+            remote_recipient.clone(),
+            config.ibc_withdraw_channel.clone(),
+            amount.clone(),
+        )?;
+
+        let transfer = MsgTransfer {
+            source_port: "transfer".to_string(),
+            source_channel: config.ibc_withdraw_channel.clone(),
+            token: Some(amount.into()),
+            sender: env.contract.address.to_string(),
+            receiver: remote_recipient.clone(),
+            timeout_height: None,
+            timeout_timestamp: env.block.time.plus_seconds(config.ibc_transfer_timeout).nanos(),
+            memo: format!(r#"{{"ibc_callback":"{}"}}"#, env.contract.address),
+        };
+
+        let event = Event::new("apollo/vaults/execute_unlock").add_attributes(vec![
+            attr("action", "execute_withdraw_unlocked_remote"),
+            attr("remote_recipient", remote_recipient),
+            attr("channel_id", config.ibc_withdraw_channel),
+            attr("lockup_id", lockup_id.to_string()),
+            attr("amount", sum_to_claim),
+            attr("withdrawal_id", withdrawal_id.to_string()),
+        ]);
+
+        Ok(res
+            .add_submessage(SubMsg::reply_on_success(
+                transfer,
+                WITHDRAW_UNLOCKED_REMOTE_REPLY_ID_OFFSET + withdrawal_id,
+            ))
+            .add_event(event))
+    }
+
+    /// Records the packet sequence of the transfer of a remote withdrawal, so
+    /// that the IBC-hooks callback can be matched to the withdrawal. The ID of
+    /// the withdrawal is the reply ID minus
+    /// [`WITHDRAW_UNLOCKED_REMOTE_REPLY_ID_OFFSET`].
+    pub fn reply_withdraw_unlocked_remote(
+        &self,
+        deps: DepsMut,
+        reply: Reply,
+    ) -> Result<Response, ContractError> {
+        let withdrawal_id = reply.id - WITHDRAW_UNLOCKED_REMOTE_REPLY_ID_OFFSET;
+        let data = reply
+            .result
+            .into_result()
+            .map_err(StdError::generic_err)?
+            .data
+            .ok_or_else(|| StdError::generic_err("No data in IBC transfer reply"))?;
+        let sequence = MsgTransferResponse::try_from(data)?.sequence;
+
+        let withdrawal =
+            self.remote_withdrawals.set_sequence(deps.storage, withdrawal_id, sequence)?;
+
+        let event = Event::new("apollo/vaults/execute_unlock").add_attributes(vec![
+            attr("action", "reply_withdraw_unlocked_remote"),
+            attr("withdrawal_id", withdrawal.id.to_string()),
+            attr("sequence", sequence.to_string()),
+        ]);
+        Ok(Response::new().add_event(event))
+    }
+
+    /// Completes a remote withdrawal when the IBC-hooks module reports that its
+    /// transfer was acknowledged or timed out. If the transfer did not succeed
+    /// the base tokens were returned to the contract, and are sent back to the
+    /// owner of the withdrawn lockup position.
+    pub fn sudo_ibc_lifecycle_complete(
+        &self,
+        deps: DepsMut,
+        msg: IbcLifecycleComplete,
+    ) -> Result<Response, ContractError> {
+        let (channel, sequence, success) = match msg {
+            IbcLifecycleComplete::IbcAck {
+                channel,
+                sequence,
+                success,
+                ..
+            } => (channel, sequence, success),
+            IbcLifecycleComplete::IbcTimeout {
+                channel,
+                sequence,
+            } => (channel, sequence, false),
+        };
+
+        let withdrawal =
+            self.remote_withdrawals.complete(deps.storage, &channel, sequence, success)?;
+
+        let event = Event::new("apollo/vaults/execute_unlock").add_attributes(vec![
+            attr("action", "sudo_ibc_lifecycle_complete"),
+            attr("withdrawal_id", withdrawal.id.to_string()),
+            attr("success", success.to_string()),
+        ]);
+
+        let mut res = Response::new().add_event(event);
+        if withdrawal.status == RemoteWithdrawalStatus::Refunded {
+            res = res.add_message(BankMsg::Send {
+                to_address: withdrawal.owner.to_string(),
+                amount: vec![withdrawal.amount],
+            });
+        }
+        Ok(res)
+    }
+}
@@ -1,10 +1,12 @@
 use apollo_utils::assets::receive_asset;
 use apollo_utils::responses::merge_responses;
-use cosmwasm_std::{attr, Addr, Coin, DepsMut, Env, Event, MessageInfo, Response, Uint128};
+use cosmwasm_std::{
+    attr, to_binary, Addr, Coin, DepsMut, Env, Event, MessageInfo, Response, Uint128,
+};
 
 use cw_dex::traits::{Pool, Stake};
 
-use apollo_cw_asset::{Asset, AssetInfo};
+use apollo_cw_asset::{Asset, AssetInfo, AssetList};
 use cw_vault_token::VaultToken;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
@@ -24,12 +26,17 @@ where
 {
     /// Deposit base tokens into the vault. This will first compound the pending
     /// rewards, then the deposited tokens will be staked and vault tokens
-    /// will be minted to the `info.sender`.
+    /// will be minted to the `info.sender`. If the vault is in batched mode the
+    /// deposit is instead queued in the current epoch, and the vault tokens
+    /// can be claimed once the epoch is settled.
     ///
     /// ## Arguments
     /// - amount: Amount of base tokens to deposit.
     /// - recipient: Optional address to receive the minted vault tokens. If
     ///   None, the `info.sender` will be used instead.
+    /// - referrer: Optional referrer of the recipient. Only recorded if the
+    ///   recipient does not have a referrer yet. Errors if the recipient is not
+    ///   the `info.sender`.
     pub fn execute_deposit(
         &self,
         deps: DepsMut,
@@ -37,11 +44,24 @@ where
         info: &MessageInfo,
         amount: Uint128,
         recipient: Option<String>,
+        referrer: Option<String>,
     ) -> Result<Response, ContractError> {
         // Unwrap recipient or use caller's address
         let recipient =
             recipient.map_or(Ok(info.sender.clone()), |x| deps.api.addr_validate(&x))?;
 
+        // Record the referrer of the recipient. Depositors can only choose their
+        // own referrer, since the referrer is permanent.
+        let referrer = referrer
+            .map(|x| -> Result<_, ContractError> {
+                if recipient != info.sender {
+                    return Err(ContractError::ReferrerNotRecipient {});
+                }
+                let referrer_addr = deps.api.addr_validate(&x)?;
+                Ok(self.referrals.register(deps.storage, &recipient, &referrer_addr)?)
+            })
+            .transpose()?;
+
         // Receive the assets to the contract
         let receive_res = receive_asset(
             info,
@@ -60,6 +80,23 @@ where
             });
         }
 
+        // In batched mode the deposit is queued until the epoch is settled
+        if self.config.load(deps.storage)?.epoch_duration > 0 {
+            let epoch_id =
+                self.epochs.queue(deps.storage, &env.block, &recipient, amount, Uint128::zero())?;
+
+            let mut event = Event::new("apollo/vaults/execute_staking").add_attributes(vec![
+                attr("action", "queue_deposit"),
+                attr("recipient", recipient),
+                attr("amount", amount),
+                attr("epoch_id", epoch_id.to_string()),
+            ]);
+            if let Some(referrer) = referrer {
+                event = event.add_attribute("referrer", referrer);
+            }
+            return Ok(receive_res.add_event(event));
+        }
+
         // If base token is a native token it was sent in the `info.funds` and is
         // already part of the contract balance. That is not the case for a cw20 token,
         // which will be received when the above `receive_res` is handled.
@@ -69,7 +106,8 @@ where
         };
 
         // Compound. Also stakes the users deposit
-        let compound_res = self.compound(deps, &env, user_deposit_amount)?;
+        let compound_res =
+            self.compound_with_pending_mint(deps, &env, user_deposit_amount, amount)?;
 
         // Mint vault tokens to recipient
         let mint_res = Response::new().add_message(
@@ -80,16 +118,153 @@ where
             .into_cosmos_msg(&env)?,
         );
 
-        let event = Event::new("apollo/vaults/execute_staking").add_attributes(vec![
+        let mut event = Event::new("apollo/vaults/execute_staking").add_attributes(vec![
             attr("action", "deposit"),
             attr("recipient", recipient),
             attr("amount", amount),
         ]);
+        if let Some(referrer) = referrer {
+            event = event.add_attribute("referrer", referrer);
+        }
 
         // Merge responses and add message to mint vault token
         Ok(merge_responses(vec![receive_res, compound_res, mint_res]).add_event(event))
     }
 
+    /// Deposit pool assets into the vault. The assets sent in `info.funds` are
+    /// provided as liquidity through the liquidity helper, and the received
+    /// base tokens are deposited in the `ZapInDeposit` callback.
+    ///
+    /// ## Arguments
+    /// - min_base_tokens: The minimum amount of base tokens the provided
+    ///   liquidity must produce.
+    /// - recipient: Optional address to receive the minted vault tokens. If
+    ///   None, the `info.sender` will be used instead.
+    pub fn execute_zap_in(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: &MessageInfo,
+        min_base_tokens: Uint128,
+        recipient: Option<String>,
+    ) -> Result<Response, ContractError> {
+        // Unwrap recipient or use caller's address
+        let recipient =
+            recipient.map_or(Ok(info.sender.clone()), |x| deps.api.addr_validate(&x))?;
+
+        let pool = self.pool.load(deps.storage)?;
+        let pool_assets = pool.pool_assets(deps.as_ref())?;
+
+        // Only pool assets can be provided as liquidity
+        if info.funds.is_empty() {
+            return Err(ContractError::from("No funds sent to zap in"));
+        }
+        for coin in &info.funds {
+            if !pool_assets.contains(&AssetInfo::Native(coin.denom.clone())) {
+                return Err(ContractError::from(format!(
+                    "Cannot zap in with {}, which is not a pool asset",
+                    coin.denom
+                )));
+            }
+        }
+        let assets: AssetList = info
+            .funds
+            .iter()
+            .map(|coin| Asset::new(AssetInfo::Native(coin.denom.clone()), coin.amount))
+            .collect::<Vec<_>>()
+            .into();
+
+        let base_token_balance_before = self
+            .base_vault
+            .base_token
+            .load(deps.storage)?
+            .query_balance(&deps.querier, &env.contract.address)?;
+
+        let provide_liquidity_msgs = self
+            .config
+            .load(deps.storage)?
+            .liquidity_helper
+            .balancing_provide_liquidity(assets.clone(), Uint128::zero(), to_binary(&pool)?, None)?;
+
+        let deposit = CallbackMsg::ZapInDeposit {
+            base_token_balance_before,
+            min_base_tokens,
+            recipient: recipient.clone(),
+        }
+        .into_cosmos_msg(&env)?;
+
+        let event = Event::new("apollo/vaults/execute_staking").add_attributes(vec![
+            attr("action", "zap_in"),
+            attr("recipient", recipient),
+            attr("assets", assets.to_string()),
+        ]);
+
+        Ok(Response::new()
+            .add_messages(provide_liquidity_msgs)
+            .add_message(deposit)
+            .add_event(event))
+    }
+
+    /// Callback function to deposit the base tokens received from providing
+    /// liquidity in `execute_zap_in`. Deposits the increase of the base token
+    /// balance of the contract since `base_token_balance_before`.
+    pub fn execute_callback_zap_in_deposit(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        base_token_balance_before: Uint128,
+        min_base_tokens: Uint128,
+        recipient: Addr,
+    ) -> Result<Response, ContractError> {
+        let amount = self
+            .base_vault
+            .base_token
+            .load(deps.storage)?
+            .query_balance(&deps.querier, &env.contract.address)?
+            .checked_sub(base_token_balance_before)?;
+
+        if amount < min_base_tokens {
+            return Err(ContractError::from(format!(
+                "Zap in produced {} base tokens, less than the minimum of {}",
+                amount, min_base_tokens
+            )));
+        }
+
+        // In batched mode the deposit is queued until the epoch is settled
+        if self.config.load(deps.storage)?.epoch_duration > 0 {
+            let epoch_id =
+                self.epochs.queue(deps.storage, &env.block, &recipient, amount, Uint128::zero())?;
+
+            let event = Event::new("apollo/vaults/execute_staking").add_attributes(vec![
+                attr("action", "queue_deposit"),
+                attr("recipient", recipient),
+                attr("amount", amount),
+                attr("epoch_id", epoch_id.to_string()),
+            ]);
+            return Ok(Response::new().add_event(event));
+        }
+
+        // The base tokens are already part of the contract balance, also for cw20
+        // base tokens, since they were received from the liquidity provision.
+        let compound_res = self.compound_with_pending_mint(deps, &env, amount, amount)?;
+
+        let mint_res = Response::new().add_message(
+            CallbackMsg::MintVaultToken {
+                amount,
+                recipient: recipient.clone(),
+            }
+            .into_cosmos_msg(&env)?,
+        );
+
+        let event = Event::new("apollo/vaults/execute_staking").add_attributes(vec![
+            attr("action", "execute_callback_zap_in_deposit"),
+            attr("recipient", recipient),
+            attr("amount", amount),
+        ]);
+
+        Ok(merge_responses(vec![compound_res, mint_res]).add_event(event))
+    }
+
     /// Callback function to mint `amount` of vault tokens to
     /// `vault_token_recipient`. Called from the `execute_deposit` function.
     pub fn execute_callback_mint_vault_token(
@@ -116,6 +291,17 @@ where
             vault_token_supply,
         )?;
 
+        // Attribute the minted vault tokens to the recipient's referrer
+        let balance = self
+            .base_vault
+            .query_vault_token_balance(deps.as_ref(), vault_token_recipient.to_string())?;
+        self.referrals.add_vault_tokens(
+            deps.storage,
+            &vault_token_recipient,
+            balance,
+            vault_tokens,
+        )?;
+
         let event = Event::new("apollo/vaults/execute_staking").add_attributes(vec![
             attr("action", "execute_callback_mint_vault_token"),
             attr("recipient", vault_token_recipient.to_string()),
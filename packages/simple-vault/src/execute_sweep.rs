@@ -0,0 +1,106 @@
+use apollo_cw_asset::{Asset, AssetInfo, AssetInfoUnchecked, AssetList};
+use cosmwasm_std::{attr, Deps, DepsMut, Env, Event, MessageInfo, Response, StdResult};
+use cw_dex::traits::Pool;
+use cw_vault_token::VaultToken;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::ContractError;
+use crate::SimpleVault;
+
+impl<S, P, V> SimpleVault<'_, S, P, V>
+where
+    S: Serialize + DeserializeOwned,
+    P: Pool + Serialize + DeserializeOwned,
+    V: VaultToken + Serialize + DeserializeOwned,
+{
+    /// Send the full balance of `asset` held by the contract to `recipient`.
+    /// Can only be called by the admin. Refuses to sweep any asset that is
+    /// accounted for by the vault.
+    pub fn execute_sweep(
+        &self,
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        asset: AssetInfoUnchecked,
+        recipient: String,
+    ) -> Result<Response, ContractError> {
+        self.admin.assert_admin(deps.as_ref(), &info.sender)?;
+
+        let asset = asset.check(deps.api)?;
+        let recipient = deps.api.addr_validate(&recipient)?;
+
+        if self.is_accounted_asset(deps.as_ref(), &asset)? {
+            return Err(ContractError::from(format!(
+                "Cannot sweep {}, which is accounted for by the vault",
+                asset
+            )));
+        }
+
+        let amount = asset.query_balance(&deps.querier, env.contract.address)?;
+        if amount.is_zero() {
+            return Err(ContractError::from(format!(
+                "No balance of {} to sweep",
+                asset
+            )));
+        }
+
+        let event = Event::new("apollo/vaults/execute_sweep").add_attributes(vec![
+            attr("action", "execute_sweep"),
+            attr("asset", asset.to_string()),
+            attr("recipient", recipient.to_string()),
+            attr("amount", amount),
+        ]);
+
+        Ok(Response::new()
+            .add_message(Asset::new(asset, amount).transfer_msg(&recipient)?)
+            .add_event(event))
+    }
+
+    /// Returns the balances of the contract that are not accounted for by the
+    /// vault. All native tokens are included, but cw20 balances cannot be
+    /// enumerated, so only the cw20 tokens in `cw20s` are included.
+    pub fn query_unaccounted_balances(
+        &self,
+        deps: Deps,
+        env: Env,
+        cw20s: Vec<String>,
+    ) -> StdResult<AssetList> {
+        let mut unaccounted = AssetList::new();
+        for coin in deps.querier.query_all_balances(env.contract.address.clone())? {
+            let info = AssetInfo::Native(coin.denom);
+            if !self.is_accounted_asset(deps, &info)? {
+                unaccounted.add(&Asset::new(info, coin.amount))?;
+            }
+        }
+        for addr in cw20s {
+            let info = AssetInfo::Cw20(deps.api.addr_validate(&addr)?);
+            if !self.is_accounted_asset(deps, &info)? {
+                let amount = info.query_balance(&deps.querier, env.contract.address.clone())?;
+                if !amount.is_zero() {
+                    unaccounted.add(&Asset::new(info, amount))?;
+                }
+            }
+        }
+        Ok(unaccounted)
+    }
+
+    /// Returns true if `asset` is the base token, a pool asset, a reward asset
+    /// or the vault token.
+    fn is_accounted_asset(&self, deps: Deps, asset: &AssetInfo) -> StdResult<bool> {
+        let config = self.config.load(deps.storage)?;
+        let base_token = self.base_vault.base_token.load(deps.storage)?;
+        let pool_assets = self.pool.load(deps.storage)?.pool_assets(deps)?;
+        let vault_token = self.base_vault.vault_token.load(deps.storage)?.to_string();
+
+        let denom_or_addr = match asset {
+            AssetInfo::Native(denom) => denom.clone(),
+            AssetInfo::Cw20(addr) => addr.to_string(),
+        };
+
+        Ok(asset == &base_token
+            || pool_assets.contains(asset)
+            || config.reward_assets.contains(asset)
+            || denom_or_addr == vault_token)
+    }
+}
@@ -30,7 +30,7 @@ where
     ///   `None` is provided `info.sender` will be used instead.
     pub fn execute_withdraw_unlocked(
         &self,
-        deps: DepsMut,
+        mut deps: DepsMut,
         env: Env,
         info: &MessageInfo,
         lockup_id: u64,
@@ -44,11 +44,7 @@ where
             .claims
             .claim_tokens(deps.storage, &env.block, info, lockup_id)?;
 
-        let res = self.staking.load(deps.storage)?.withdraw_unlocked(
-            deps.as_ref(),
-            &env,
-            sum_to_claim,
-        )?;
+        let res = self.withdraw_unlocked(deps.branch(), &env, lockup_id, sum_to_claim)?;
 
         let event = Event::new("apollo/vaults/execute_unlock").add_attributes(vec![
             attr("action", "execute_withdraw_unlocked"),
@@ -81,12 +77,49 @@ where
         // already received
         vault_token.receive(deps.branch(), &env, info, vault_token_amount)?;
 
+        self.execute_unlock_received(deps, env, info.sender.clone(), vault_token_amount)
+    }
+
+    /// Start the unlocking process for `vault_token_amount` vault tokens that
+    /// are already held by the contract, e.g. vault tokens received through a
+    /// cw20 `Send` hook. The unlocking position will be owned by `owner`. If
+    /// the vault is in batched mode the unlock is instead queued in the
+    /// current epoch.
+    pub fn execute_unlock_received(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        owner: Addr,
+        vault_token_amount: Uint128,
+    ) -> Result<Response, ContractError> {
+        // The burned vault tokens no longer accrue referral fees
+        self.sync_referral(deps.branch(), &owner)?;
+
+        // In batched mode the unlock is queued until the epoch is settled
+        if self.config.load(deps.storage)?.epoch_duration > 0 {
+            let epoch_id = self.epochs.queue(
+                deps.storage,
+                &env.block,
+                &owner,
+                Uint128::zero(),
+                vault_token_amount,
+            )?;
+
+            let event = Event::new("apollo/vaults/execute_unlock").add_attributes(vec![
+                attr("action", "queue_unlock"),
+                attr("owner", owner.to_string()),
+                attr("amount", vault_token_amount),
+                attr("epoch_id", epoch_id.to_string()),
+            ]);
+            return Ok(Response::new().add_event(event));
+        }
+
         // First compound the vault
         let compound_res = self.compound(deps, &env, Uint128::zero())?;
 
         // Continue with the unlock after compounding
         let unlock_msg = CallbackMsg::Unlock {
-            owner: info.sender.clone(),
+            owner: owner.clone(),
             vault_token_amount,
         }
         .into_cosmos_msg(&env)?;
@@ -96,7 +129,7 @@ where
 
         let event = Event::new("apollo/vaults/execute_unlock").add_attributes(vec![
             attr("action", "execute_unlock"),
-            attr("owner", info.sender.to_string()),
+            attr("owner", owner.to_string()),
             attr("amount", vault_token_amount),
         ]);
 
@@ -118,12 +151,19 @@ where
         let staking = self.staking.load(deps.storage)?;
 
         // Burn vault tokens and get the amount of base tokens to withdraw
-        let (lp_tokens_to_unlock, burn_res) = self.base_vault.burn_vault_tokens_for_base_tokens(
+        let (base_tokens, burn_res) = self.base_vault.burn_vault_tokens_for_base_tokens(
             deps.branch(),
             &env,
             vault_token_amount,
         )?;
 
+        // Unlock the staked part of the base tokens. The rest is taken from the
+        // buffer and held in the contract until the claim is withdrawn.
+        let (lp_tokens_to_unlock, from_buffer) =
+            self.take_from_buffer(deps.storage, base_tokens)?;
+        self.pending_unlocking_buffer
+            .save(deps.storage, &from_buffer)?;
+
         let expiration = self
             .staking
             .load(deps.storage)?
@@ -134,13 +174,17 @@ where
         self.claims.create_pending_claim(
             deps.storage,
             &owner,
-            lp_tokens_to_unlock,
+            base_tokens,
             expiration,
             None,
         )?;
 
         // Unstake response
-        let unlock_res = staking.unlock(deps.as_ref(), &env, lp_tokens_to_unlock)?;
+        let unlock_res = if lp_tokens_to_unlock.is_zero() {
+            Response::new()
+        } else {
+            staking.unlock(deps.as_ref(), &env, lp_tokens_to_unlock)?
+        };
 
         // Event containing the lockup id and claim
         let event = Event::new("apollo/vaults/execute_unlock").add_attributes(vec![
@@ -149,6 +193,7 @@ where
             ("owner", owner.as_ref()),
             ("vault_token_amount", &vault_token_amount.to_string()),
             ("lp_tokens_to_unlock", &lp_tokens_to_unlock.to_string()),
+            ("from_buffer", &from_buffer.to_string()),
         ]);
 
         // Create response.
@@ -164,6 +209,17 @@ where
         // Commit the pending claim
         self.claims.commit_pending_claim(deps.storage)?;
 
+        // Record the part of the claim that was taken from the buffer
+        let from_buffer = self
+            .pending_unlocking_buffer
+            .may_load(deps.storage)?
+            .unwrap_or_default();
+        if !from_buffer.is_zero() {
+            self.unlocking_buffer
+                .save(deps.storage, claim.id, &from_buffer)?;
+        }
+        self.pending_unlocking_buffer.remove(deps.storage);
+
         let event = Event::new(UNLOCKING_POSITION_CREATED_EVENT_TYPE)
             .add_attribute("action", "execute_callback_save_claim")
             .add_attribute("unlock_amount", claim.base_token_amount.to_string())
@@ -174,6 +230,98 @@ where
         Ok(Response::default().add_event(event))
     }
 
+    /// Callback function to record the pending claim as the claim of the
+    /// settled epoch `epoch_id`. Called from `execute_settle_epoch` after
+    /// the unlock, and before the pending claim is saved.
+    pub fn execute_callback_settle_epoch_unlocks(
+        &self,
+        deps: DepsMut,
+        epoch_id: u64,
+    ) -> Result<Response, ContractError> {
+        let claim = self.claims.get_pending_claim(deps.storage)?;
+        self.epochs.set_claim(deps.storage, epoch_id, claim.clone())?;
+
+        let event = Event::new("apollo/vaults/execute_unlock").add_attributes(vec![
+            attr("action", "execute_callback_settle_epoch_unlocks"),
+            attr("epoch_id", epoch_id.to_string()),
+            attr("lockup_id", claim.id.to_string()),
+            attr("base_token_amount", claim.base_token_amount),
+        ]);
+
+        Ok(Response::new().add_event(event))
+    }
+
+    /// Withdraw the base tokens for the unlocks of `info.sender` in the
+    /// settled epoch `epoch_id`. The unlock of the epoch must have
+    /// matured.
+    ///
+    /// ## Arguments
+    /// - epoch_id: The ID of the settled epoch.
+    /// - recipient: Optional address to receive the base tokens. If
+    ///   `None`, `info.sender` will be used instead.
+    pub fn execute_withdraw_epoch_unlocked(
+        &self,
+        mut deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        epoch_id: u64,
+        recipient: Option<String>,
+    ) -> Result<Response, ContractError> {
+        // Unwrap recipient or use caller's address
+        let recipient =
+            recipient.map_or(Ok(info.sender.clone()), |x| deps.api.addr_validate(&x))?;
+
+        let (claim, amount) =
+            self.epochs.claim_base_tokens(deps.storage, epoch_id, &info.sender)?;
+        self.claims.claim_partial(
+            deps.storage,
+            &env.block,
+            &env.contract.address,
+            claim.id,
+            amount,
+        )?;
+
+        let res = self.withdraw_unlocked(deps.branch(), &env, claim.id, amount)?;
+
+        let event = Event::new("apollo/vaults/execute_unlock").add_attributes(vec![
+            attr("action", "execute_withdraw_epoch_unlocked"),
+            attr("epoch_id", epoch_id.to_string()),
+            attr("owner", info.sender),
+            attr("recipient", recipient.clone()),
+            attr("lockup_id", claim.id.to_string()),
+            attr("amount", amount),
+        ]);
+
+        Ok(merge_responses(vec![
+            res,
+            self.base_vault.send_base_tokens(deps, &recipient, amount)?,
+        ])
+        .add_event(event))
+    }
+
+    /// Withdraw `amount` base tokens of unlocking position `lockup_id` that
+    /// has finished unlocking. The part of the position that was taken from
+    /// the buffer is already held in the contract, so only the rest is
+    /// withdrawn from the staking.
+    pub(crate) fn withdraw_unlocked(
+        &self,
+        deps: DepsMut,
+        env: &Env,
+        lockup_id: u64,
+        amount: Uint128,
+    ) -> Result<Response, ContractError> {
+        let from_buffer = self.take_unlocking_buffer(deps.storage, lockup_id, amount)?;
+        let to_withdraw = amount.checked_sub(from_buffer)?;
+        if to_withdraw.is_zero() {
+            return Ok(Response::new());
+        }
+
+        Ok(self
+            .staking
+            .load(deps.storage)?
+            .withdraw_unlocked(deps.as_ref(), env, to_withdraw)?)
+    }
+
     /// Query unlocking positions for `owner`. Optional arguments `start_after`
     /// and `limit` can be used for pagination.
     ///
@@ -23,17 +23,29 @@ extern crate derive_builder;
 
 /// Error types
 pub mod error;
+/// Logic related to instant redemptions from the buffer.
+pub mod execute_buffer;
 /// Logic related to compounding.
 pub mod execute_compound;
+/// Logic related to queueing deposits and unlocks per epoch.
+pub mod execute_epoch;
 /// Logic related to force unlocking.
 #[cfg(feature = "force-unlock")]
 pub mod execute_force_unlock;
+/// Logic related to referrals.
+pub mod execute_referral;
+/// Logic related to withdrawing unlocked base tokens to other chains with
+/// IBC-hooks.
+#[cfg(feature = "ibc-hooks")]
+pub mod execute_remote_withdrawal;
 /// Implementations related to redeeming and withdrawing
 /// for non-lockup vaults.
 #[cfg(feature = "redeem")]
 pub mod execute_redeem;
 /// Logic related to staking.
 pub mod execute_staking;
+/// Logic related to sweeping tokens not accounted for by the vault.
+pub mod execute_sweep;
 /// Logic related to unlocking of locked positions.
 #[cfg(feature = "lockup")]
 pub mod execute_unlock;
@@ -1,5 +1,8 @@
+use apollo_cw_asset::AssetInfoUnchecked;
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{to_binary, Addr, CosmosMsg, Env, StdResult, Uint128, WasmMsg};
+use cosmwasm_std::{to_binary, Addr, CosmosMsg, Decimal, Env, StdResult, Uint128, WasmMsg};
+#[cfg(feature = "tranche")]
+use cw_utils::Duration;
 #[cfg(feature = "force-unlock")]
 use cw_vault_standard::extensions::force_unlock::ForceUnlockExecuteMsg;
 #[cfg(feature = "lockup")]
@@ -27,6 +30,9 @@ pub enum ExtensionExecuteMsg {
     /// Execute a message from the force unlock extension.
     #[cfg(feature = "force-unlock")]
     ForceUnlock(ForceUnlockExecuteMsg),
+    /// Execute a message related to lockup tranches.
+    #[cfg(feature = "tranche")]
+    Tranche(TrancheExecuteMsg),
 }
 
 /// Callback messages for the autocompounding vault `Callback` extension
@@ -45,6 +51,31 @@ pub enum CallbackMsg {
         /// received as cw20s in a previous message they must be deducted from
         /// the current contract balance.
         base_token_balance_before: Uint128,
+        /// The part of the base tokens to stake that comes from a user deposit
+        /// rather than from compounded rewards.
+        #[serde(default)]
+        user_deposit_amount: Uint128,
+    },
+    /// Check that the base tokens per vault token did not decrease, or
+    /// increase by more than `max_share_price_increase`, during a compound.
+    CheckSharePrice {
+        /// Total staked base tokens before the compound.
+        total_staked_base_tokens_before: Uint128,
+        /// Total supply of vault tokens before the compound.
+        vault_token_supply_before: Uint128,
+        /// Base tokens deposited and staked during the compound for which
+        /// vault tokens have not yet been minted.
+        pending_mint_amount: Uint128,
+    },
+    /// Deposit the base tokens received from providing liquidity with the
+    /// assets of a zap-in.
+    ZapInDeposit {
+        /// Contract base token balance before liquidity was provided.
+        base_token_balance_before: Uint128,
+        /// The minimum amount of base tokens the zap-in must produce.
+        min_base_tokens: Uint128,
+        /// The recipient of the vault tokens.
+        recipient: Addr,
     },
     /// Mint vault tokens
     MintVaultToken {
@@ -77,6 +108,27 @@ pub enum CallbackMsg {
     /// Save the currently pending claim to the `claims` storage.
     #[cfg(feature = "lockup")]
     SaveClaim {},
+    /// Burn vault tokens and send their value in base tokens from the buffer,
+    /// minus the exit fee.
+    InstantRedeem {
+        /// The address which should receive the base tokens.
+        recipient: Addr,
+        /// The amount of vault tokens sent to the contract.
+        amount: Uint128,
+    },
+    /// Mint the vault tokens for the deposits of a settled epoch to the
+    /// contract, where they are held until claimed by the depositors.
+    SettleEpochDeposits {
+        /// The ID of the settled epoch.
+        epoch_id: u64,
+    },
+    /// Record the currently pending claim as the claim of the unlocks of a
+    /// settled epoch.
+    #[cfg(feature = "lockup")]
+    SettleEpochUnlocks {
+        /// The ID of the settled epoch.
+        epoch_id: u64,
+    },
 }
 
 impl CallbackMsg {
@@ -115,6 +167,96 @@ pub enum SimpleExtensionExecuteMsg {
     /// Removes the initiated admin transfer. This can only be called by the
     /// admin who initiated the admin transfer.
     DropAdminTransfer {},
+    /// Deposit base tokens into the vault, optionally recording a referrer for
+    /// the recipient of the vault tokens.
+    Deposit {
+        /// The amount of base tokens to deposit.
+        amount: Uint128,
+        /// Optional address to receive the vault tokens. Defaults to the
+        /// sender.
+        recipient: Option<String>,
+        /// Optional referrer of the recipient. Only the first referrer
+        /// recorded for a recipient is kept. Can only be set when the
+        /// recipient is the sender.
+        referrer: Option<String>,
+    },
+    /// Deposit pool assets into the vault. The assets sent in the funds are
+    /// provided as liquidity, and the received base tokens are deposited. Any
+    /// subset of the native pool assets may be sent.
+    ZapIn {
+        /// The minimum amount of base tokens the provided liquidity must
+        /// produce.
+        min_base_tokens: Uint128,
+        /// Optional address to receive the vault tokens. Defaults to the
+        /// sender.
+        recipient: Option<String>,
+    },
+    /// Withdraw the base tokens from a locked position that has finished
+    /// unlocking to an address on another chain. The base tokens are sent
+    /// with an ICS-20 transfer over the `ibc_withdraw_channel` of the config,
+    /// and refunded if the transfer fails. Requires the IBC-hooks module of
+    /// Osmosis.
+    #[cfg(feature = "ibc-hooks")]
+    WithdrawUnlockedRemote {
+        /// ID of the lockup position to withdraw from.
+        lockup_id: u64,
+        /// The address on the remote chain to receive the base tokens.
+        remote_recipient: String,
+    },
+    /// Claim the referral fees accrued by the sender.
+    ClaimReferralFees {
+        /// Optional address to receive the fees. Defaults to the sender.
+        recipient: Option<String>,
+    },
+    /// Stop attributing the vault tokens a referred depositor no longer holds
+    /// to their referrer, e.g. after a transfer. Can be called by anyone.
+    SyncReferral {
+        /// The referred depositor.
+        address: String,
+    },
+    /// Redeem vault tokens instantly from the buffer of unstaked base tokens,
+    /// paying the exit fee. If the vault token is a native token it must be
+    /// sent in the funds.
+    InstantRedeem {
+        /// The amount of vault tokens to redeem.
+        amount: Uint128,
+        /// Optional address to receive the base tokens. Defaults to the
+        /// sender.
+        recipient: Option<String>,
+    },
+    /// Settle the current epoch, processing all deposits and unlocks queued in
+    /// it with a single compound and a single unlock. Can be called by anyone
+    /// once `epoch_duration` seconds have passed since the epoch started.
+    SettleEpoch {},
+    /// Claim the vault tokens for the deposits of the sender in a settled
+    /// epoch.
+    ClaimEpochShares {
+        /// The ID of the settled epoch.
+        epoch_id: u64,
+        /// Optional address to receive the vault tokens. Defaults to the
+        /// sender.
+        recipient: Option<String>,
+    },
+    /// Withdraw the base tokens for the unlocks of the sender in a settled
+    /// epoch, once the unlock of the epoch has matured.
+    #[cfg(feature = "lockup")]
+    WithdrawEpochUnlocked {
+        /// The ID of the settled epoch.
+        epoch_id: u64,
+        /// Optional address to receive the base tokens. Defaults to the
+        /// sender.
+        recipient: Option<String>,
+    },
+    /// Send the full balance of an asset that is not accounted for by the
+    /// vault, e.g. an airdrop or a mistaken transfer, to `recipient`. Can only
+    /// be called by the admin. The base token, the pool assets, the reward
+    /// assets and the vault token cannot be swept.
+    Sweep {
+        /// The asset to sweep.
+        asset: AssetInfoUnchecked,
+        /// The address to receive the swept tokens.
+        recipient: String,
+    },
 }
 
 /// Apollo extension queries define functionality that is part of all apollo
@@ -123,6 +265,43 @@ pub enum SimpleExtensionExecuteMsg {
 pub enum SimpleExtensionQueryMsg {
     /// Query the current state of the vault.
     State {},
+    /// Query the referral info of an address, both as a depositor and as a
+    /// referrer.
+    Referral {
+        /// The address to query.
+        address: String,
+    },
+    /// Query the level of the buffer for instant redemptions.
+    Buffer {},
+    /// Query the epoch that deposits and unlocks are currently queued in.
+    CurrentEpoch {},
+    /// Query a settled epoch.
+    SettledEpoch {
+        /// The ID of the epoch.
+        epoch_id: u64,
+    },
+    /// Query the deposits and unlocks queued by an address in an epoch that
+    /// have not yet been claimed.
+    EpochPosition {
+        /// The ID of the epoch.
+        epoch_id: u64,
+        /// The address to query.
+        address: String,
+    },
+    /// Query the last step of a compound that failed, if any. Returns an
+    /// `Option<CompoundFailure>`.
+    LastCompoundFailure {},
+    /// Query the reward tokens held by the vault that were not sold because of
+    /// the sell limit of their asset. Returns an `AssetList`.
+    UnsoldRewards {},
+    /// Query the balances of the vault that are not accounted for by the vault
+    /// and can be swept. Returns an `AssetList` of all such native tokens and
+    /// of the cw20 tokens in `cw20s`, as cw20 balances cannot be enumerated.
+    UnaccountedBalances {
+        /// The addresses of the cw20 tokens to include.
+        #[serde(default)]
+        cw20s: Vec<String>,
+    },
 }
 
 /// Extension query messages for an apollo autocompounding vault
@@ -133,6 +312,198 @@ pub enum ExtensionQueryMsg {
     Lockup(LockupQueryMsg),
     /// Apollo extension queries.
     Simple(SimpleExtensionQueryMsg),
+    /// Queries related to lockup tranches.
+    #[cfg(feature = "tranche")]
+    Tranche(TrancheQueryMsg),
+}
+
+/// Sudo messages for an apollo autocompounding vault.
+#[cfg(feature = "ibc-hooks")]
+#[cw_serde]
+pub enum SudoMsg {
+    /// Callback from the IBC-hooks module for a transfer sent with an
+    /// `ibc_callback` memo, i.e. the transfer of a remote withdrawal.
+    IbcLifecycleComplete(IbcLifecycleComplete),
+}
+
+/// The outcome of an IBC transfer, as reported by the IBC-hooks module.
+#[cfg(feature = "ibc-hooks")]
+#[cw_serde]
+pub enum IbcLifecycleComplete {
+    /// The transfer was acknowledged by the remote chain.
+    IbcAck {
+        /// The source channel of the transfer.
+        channel: String,
+        /// The packet sequence of the transfer.
+        sequence: u64,
+        /// The acknowledgement.
+        ack: String,
+        /// Whether the transfer succeeded.
+        success: bool,
+    },
+    /// The transfer timed out.
+    IbcTimeout {
+        /// The source channel of the transfer.
+        channel: String,
+        /// The packet sequence of the transfer.
+        sequence: u64,
+    },
+}
+
+/// Messages for the lockup tranches of the vault. Each tranche locks the base
+/// tokens with its own lockup duration and has its own vault token, while the
+/// rewards of all tranches are compounded together. Tranche 0 is the primary
+/// tranche, i.e. the lockup duration and vault token the vault was
+/// instantiated with.
+#[cfg(feature = "tranche")]
+#[cw_serde]
+pub enum TrancheExecuteMsg {
+    /// Add a tranche. Can only be called by the admin. The 10 OSMO required to
+    /// create the vault token of the tranche must be sent in the funds.
+    AddTranche {
+        /// The lockup duration in seconds of the tranche. Must differ from the
+        /// lockup duration of all existing tranches.
+        lockup_duration: u64,
+        /// The subdenom of the vault token of the tranche.
+        vault_token_subdenom: String,
+        /// The weight of the tranche when splitting compounded rewards, per
+        /// staked base token.
+        reward_weight: Decimal,
+    },
+    /// Update the reward weight of a tranche. Can only be called by the admin.
+    UpdateRewardWeight {
+        /// The ID of the tranche.
+        tranche_id: u64,
+        /// The new reward weight.
+        reward_weight: Decimal,
+    },
+    /// Deposit base tokens into a tranche. The base tokens must be sent in the
+    /// funds. Not supported while the vault is in batched mode. Referral fees
+    /// and instant redemptions from the buffer only apply to tranche 0.
+    Deposit {
+        /// The ID of the tranche.
+        tranche_id: u64,
+        /// The amount of base tokens to deposit.
+        amount: Uint128,
+        /// Optional address to receive the vault tokens of the tranche.
+        /// Defaults to the sender.
+        recipient: Option<String>,
+    },
+    /// Burn vault tokens of a tranche and start unlocking the base tokens with
+    /// the lockup duration of the tranche. The vault tokens must be sent in
+    /// the funds. Not supported while the vault is in batched mode. The
+    /// unlocking position is withdrawn with
+    /// `LockupExecuteMsg::WithdrawUnlocked`, or force withdrawn with
+    /// `ForceUnlockExecuteMsg::ForceWithdrawUnlocking`.
+    Unlock {
+        /// The ID of the tranche.
+        tranche_id: u64,
+        /// The amount of vault tokens to unlock.
+        amount: Uint128,
+    },
+    /// Burn vault tokens of a tranche and force unlock the base tokens they
+    /// represent, skipping the lockup duration. Can only be called by
+    /// addresses in the force withdraw whitelist. The vault tokens must be
+    /// sent in the funds.
+    ForceRedeem {
+        /// The ID of the tranche.
+        tranche_id: u64,
+        /// The amount of vault tokens to redeem.
+        amount: Uint128,
+        /// Optional address to receive the base tokens. Defaults to the
+        /// sender.
+        recipient: Option<String>,
+    },
+    /// Execute a tranche callback message.
+    Callback(TrancheCallbackMsg),
+}
+
+/// Callback messages for the tranche extension. Tranche 0 is handled by the
+/// callbacks of [`CallbackMsg`] and never appears here.
+#[cfg(feature = "tranche")]
+#[cw_serde]
+pub enum TrancheCallbackMsg {
+    /// Lock base tokens held by the contract in a tranche and add them to the
+    /// base tokens of the tranche.
+    Stake {
+        /// The ID of the tranche.
+        tranche_id: u64,
+        /// The amount of base tokens to lock.
+        amount: Uint128,
+    },
+    /// Mint vault tokens of a tranche for deposited base tokens.
+    MintVaultToken {
+        /// The ID of the tranche.
+        tranche_id: u64,
+        /// The amount of base tokens deposited.
+        amount: Uint128,
+        /// The recipient of the vault tokens.
+        recipient: Addr,
+    },
+    /// Burn vault tokens of a tranche and start the unlocking process.
+    Unlock {
+        /// The ID of the tranche.
+        tranche_id: u64,
+        /// The address that will be the owner of the unlocking position.
+        owner: Addr,
+        /// The amount of vault tokens to burn.
+        vault_token_amount: Uint128,
+    },
+}
+
+#[cfg(feature = "tranche")]
+impl TrancheCallbackMsg {
+    /// Convert the callback message to a [`CosmosMsg`] calling the contract
+    /// itself.
+    pub fn into_cosmos_msg(&self, env: &Env) -> StdResult<CosmosMsg> {
+        Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: env.contract.address.to_string(),
+            msg: to_binary(&VaultStandardExecuteMsg::VaultExtension(
+                ExtensionExecuteMsg::Tranche(TrancheExecuteMsg::Callback(self.clone())),
+            ))?,
+            funds: vec![],
+        }))
+    }
+}
+
+/// Queries for the lockup tranches of the vault.
+#[cfg(feature = "tranche")]
+#[cw_serde]
+pub enum TrancheQueryMsg {
+    /// Returns all tranches, including the primary tranche, as a
+    /// `Vec<TrancheResponse>`.
+    Tranches {},
+    /// Returns the [`TrancheResponse`] of a tranche.
+    Tranche {
+        /// The ID of the tranche.
+        tranche_id: u64,
+    },
+    /// Returns the amount of base tokens that `amount` vault tokens of a
+    /// tranche represent.
+    ConvertToAssets {
+        /// The ID of the tranche.
+        tranche_id: u64,
+        /// The amount of vault tokens.
+        amount: Uint128,
+    },
+}
+
+/// Information about a lockup tranche.
+#[cfg(feature = "tranche")]
+#[cw_serde]
+pub struct TrancheResponse {
+    /// The ID of the tranche.
+    pub id: u64,
+    /// The lockup duration of the tranche.
+    pub lockup_duration: Duration,
+    /// The denom of the vault token of the tranche.
+    pub vault_token: String,
+    /// The base tokens locked in the tranche.
+    pub total_staked_base_tokens: Uint128,
+    /// The total supply of the vault token of the tranche.
+    pub vault_token_supply: Uint128,
+    /// The weight of the tranche when splitting compounded rewards.
+    pub reward_weight: Decimal,
 }
 
 /// Response struct containing information about the current state of the vault.
@@ -155,3 +526,18 @@ pub struct StateResponse<S, P, V> {
     /// The total supply of the vault token.
     pub vault_token_supply: Uint128,
 }
+
+/// Response struct containing the level of the buffer for instant redemptions.
+/// Returned by `SimpleVault::query_buffer`.
+#[cw_serde]
+pub struct BufferResponse {
+    /// The amount of base tokens currently in the buffer.
+    pub amount: Uint128,
+    /// The amount of base tokens the buffer is refilled to on deposits and
+    /// compounds.
+    pub target_amount: Uint128,
+    /// The target share of the total base tokens kept in the buffer.
+    pub buffer_ratio: Decimal,
+    /// The share of the redeemed base tokens charged on instant redemptions.
+    pub exit_fee: Decimal,
+}
@@ -1,10 +1,12 @@
 use crate::SimpleVault;
+use apollo_cw_asset::AssetList;
 use cosmwasm_std::Env;
 use cw_vault_token::VaultToken;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use crate::msg::StateResponse;
+use crate::state::CompoundFailure;
 use cosmwasm_std::{Deps, StdResult};
 
 impl<'a, S, P, V> SimpleVault<'a, S, P, V>
@@ -38,4 +40,18 @@ where
             pool,
         })
     }
+
+    /// Returns the last step of a compound that failed, if any.
+    pub fn query_last_compound_failure(&self, deps: Deps) -> StdResult<Option<CompoundFailure>> {
+        self.last_compound_failure.may_load(deps.storage)
+    }
+
+    /// Returns the reward tokens that were not sold because of the sell limit
+    /// of their asset and are carried over to later compounds.
+    pub fn query_unsold_rewards(&self, deps: Deps) -> StdResult<AssetList> {
+        Ok(self
+            .unsold_rewards
+            .may_load(deps.storage)?
+            .unwrap_or_else(AssetList::new))
+    }
 }
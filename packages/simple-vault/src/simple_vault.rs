@@ -1,14 +1,18 @@
+use apollo_cw_asset::AssetList;
 use base_vault::BaseVault;
-use cosmwasm_std::{Addr, Binary, DepsMut, Event, MessageInfo, Response};
+use cosmwasm_std::{Addr, Binary, DepsMut, Env, Event, MessageInfo, Response, Uint128};
 use cw_controllers::Admin;
 use cw_dex::traits::Pool;
-use cw_storage_plus::Item;
+use cw_storage_plus::{Item, Map};
 use cw_vault_token::VaultToken;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use crate::error::ContractError;
-use crate::state::{Claims, Config, ConfigUpdates};
+use crate::msg::CallbackMsg;
+use crate::state::{
+    Claims, CompoundFailure, Config, ConfigUpdates, Epochs, Referrals, RemoteWithdrawals,
+};
 
 /// SimpleVault is a wrapper around BaseVault that implements
 /// autocompounding functionality.
@@ -35,6 +39,39 @@ pub struct SimpleVault<'a, S, P, V> {
     /// Stores claims of base_tokens for users who have burned their vault
     /// tokens via ExecuteMsg::Unlock.
     pub claims: Claims<'a>,
+
+    /// Stores the referrer of each referred depositor and the referral fees
+    /// accrued by each referrer.
+    pub referrals: Referrals<'a>,
+
+    /// Stores the deposits and unlocks queued per epoch while the vault is in
+    /// batched mode.
+    pub epochs: Epochs<'a>,
+
+    /// The amount of base tokens held unstaked in the contract as a buffer for
+    /// instant redemptions. These are included in `total_staked_base_tokens`.
+    pub buffer: Item<'a, Uint128>,
+
+    /// Base tokens of unlocking positions, keyed by lockup ID, that were
+    /// taken from the buffer because the unlock exceeded the staked base
+    /// tokens. These are held in the contract rather than unlocking.
+    pub unlocking_buffer: Map<'a, u64, Uint128>,
+
+    /// The part of the pending claim that was taken from the buffer. Moved to
+    /// `unlocking_buffer` when the claim is saved.
+    pub pending_unlocking_buffer: Item<'a, Uint128>,
+
+    /// The last step of a compound that failed, if any.
+    pub last_compound_failure: Item<'a, CompoundFailure>,
+
+    /// Reward tokens held by the contract that were not sold because of the
+    /// sell limit of their asset. These were already charged the performance
+    /// fee.
+    pub unsold_rewards: Item<'a, AssetList>,
+
+    /// Stores the withdrawals sent to other chains over IBC until their
+    /// transfer is acknowledged or times out.
+    pub remote_withdrawals: RemoteWithdrawals<'a>,
 }
 
 impl<'a, S, P, V> Default for SimpleVault<'a, S, P, V> {
@@ -47,6 +84,26 @@ impl<'a, S, P, V> Default for SimpleVault<'a, S, P, V> {
             claims: Claims::new("claims", "claims_index", "pending_claim", "num_claims"),
             admin: Admin::new("admin"),
             admin_transfer: Item::new("admin_transfer"),
+            referrals: Referrals::new(
+                "referrers",
+                "referred_vault_tokens",
+                "referrer_info",
+                "total_referred_vault_tokens",
+                "referral_fee_per_vault_token",
+                "unclaimed_referral_fees",
+                "unclaimed_referral_fee_remainder",
+            ),
+            epochs: Epochs::new("current_epoch", "settled_epochs", "epoch_positions"),
+            buffer: Item::new("buffer"),
+            unlocking_buffer: Map::new("unlocking_buffer"),
+            pending_unlocking_buffer: Item::new("pending_unlocking_buffer"),
+            last_compound_failure: Item::new("last_compound_failure"),
+            unsold_rewards: Item::new("unsold_rewards"),
+            remote_withdrawals: RemoteWithdrawals::new(
+                "remote_withdrawals",
+                "remote_withdrawal_sequences",
+                "next_remote_withdrawal_id",
+            ),
         }
     }
 }
@@ -154,19 +211,19 @@ where
         Ok(Response::new().add_event(event))
     }
 
-    /// Update the config.
+    /// Update the config. If the buffer ratio is lowered, the part of the
+    /// buffer above the new target is staked.
     pub fn execute_update_config(
         &self,
         deps: DepsMut,
+        env: Env,
         info: MessageInfo,
         updates: ConfigUpdates,
     ) -> Result<Response, ContractError> {
         self.admin.assert_admin(deps.as_ref(), &info.sender)?;
 
-        let new_config = self
-            .config
-            .load(deps.storage)?
-            .update(deps.as_ref(), updates.clone())?;
+        let old_config = self.config.load(deps.storage)?;
+        let new_config = old_config.clone().update(deps.as_ref(), updates.clone())?;
         self.config.save(deps.storage, &new_config)?;
 
         let event = Event::new("apollo/vaults/autocompounding_vault").add_attributes(vec![
@@ -174,6 +231,22 @@ where
             ("updates", &format!("{:?}", updates)),
         ]);
 
-        Ok(Response::default().add_event(event))
+        let mut res = Response::default().add_event(event);
+        if new_config.buffer_ratio < old_config.buffer_ratio {
+            // Staking nothing new stakes the part of the buffer above its target
+            let base_token_balance = self
+                .base_vault
+                .base_token
+                .load(deps.storage)?
+                .query_balance(&deps.querier, &env.contract.address)?;
+            res = res.add_message(
+                CallbackMsg::Stake {
+                    base_token_balance_before: base_token_balance,
+                    user_deposit_amount: Uint128::zero(),
+                }
+                .into_cosmos_msg(&env)?,
+            );
+        }
+        Ok(res)
     }
 }
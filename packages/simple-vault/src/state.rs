@@ -1,11 +1,12 @@
-use apollo_cw_asset::{AssetInfo, AssetInfoBase};
+use apollo_cw_asset::{Asset, AssetInfo, AssetInfoBase, AssetList};
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
-    Addr, BlockInfo, Decimal, Deps, MessageInfo, Order, StdError, StdResult, Storage, Uint128,
+    Addr, Api, BlockInfo, Coin, Decimal, Decimal256, Deps, MessageInfo, Order, StdError, StdResult,
+    Storage, Uint128, Uint256,
 };
 use cw20::Expiration;
 use cw_dex_router::helpers::CwDexRouterBase;
-use cw_storage_plus::{Bound, Index, IndexList, IndexedMap, Item, MultiIndex};
+use cw_storage_plus::{Bound, Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 use cw_vault_standard::extensions::lockup::UnlockingPosition;
 use liquidity_helper::LiquidityHelperBase;
 use schemars::JsonSchema;
@@ -37,6 +38,95 @@ pub struct ConfigBase<T> {
     pub force_withdraw_whitelist: Vec<T>,
     /// Helper for providing liquidity with unbalanced assets.
     pub liquidity_helper: LiquidityHelperBase<T>,
+    /// Share of the performance fee that is paid out to the referrer of a
+    /// depositor, pro rata to the vault tokens of the referred depositor.
+    #[serde(default)]
+    pub referral_fee_share: Decimal,
+    /// Duration in seconds of a deposit and unlock epoch. If zero, deposits
+    /// and unlocks are processed immediately. Otherwise they are queued and
+    /// processed together when the epoch is settled.
+    #[serde(default)]
+    pub epoch_duration: u64,
+    /// Target share of the total base tokens that is kept unstaked in the
+    /// contract as a buffer for instant redemptions. If zero, no buffer is
+    /// kept.
+    #[serde(default)]
+    pub buffer_ratio: Decimal,
+    /// Share of the redeemed base tokens that is charged on instant
+    /// redemptions from the buffer. The fee stays in the vault and accrues to
+    /// the remaining vault token holders.
+    #[serde(default)]
+    pub exit_fee: Decimal,
+    /// Limits on the amount of a reward asset that is sold in a single
+    /// compound. Any amount above the limit stays in the contract and is sold
+    /// in later compounds. Reward assets without a limit are sold in full.
+    /// Limits on pool assets or the base token have no effect, as those are
+    /// not sold.
+    #[serde(default)]
+    pub max_sell_per_compound: Vec<SellLimitBase<T>>,
+    /// Maximum relative increase of the base tokens per vault token in a
+    /// single compound. If zero, increases are not bounded. A compound that
+    /// decreases the base tokens per vault token always fails.
+    #[serde(default)]
+    pub max_share_price_increase: Decimal,
+    /// The IBC channel over which unlocked base tokens are sent to remote
+    /// recipients. If empty, withdrawals to remote recipients are disabled.
+    #[serde(default)]
+    pub ibc_withdraw_channel: String,
+    /// Timeout in seconds of the IBC transfers to remote recipients.
+    #[serde(default)]
+    pub ibc_transfer_timeout: u64,
+}
+
+/// Limit on the amount of a reward asset that is sold in a single compound.
+#[cw_serde]
+pub struct SellLimitBase<T> {
+    /// The reward asset to limit.
+    pub asset: AssetInfoBase<T>,
+    /// The maximum amount of the reward asset to sell per compound.
+    pub max: MaxSell,
+}
+
+/// Sell limit with non-validated addresses.
+pub type SellLimitUnchecked = SellLimitBase<String>;
+/// Sell limit with validated addresses.
+pub type SellLimit = SellLimitBase<Addr>;
+
+impl SellLimitUnchecked {
+    /// Validate the addresses of the sell limit.
+    pub fn check(&self, api: &dyn Api) -> StdResult<SellLimit> {
+        if let MaxSell::PoolDepthRatio(ratio) = self.max {
+            if ratio > Decimal::one() {
+                return Err(StdError::generic_err(
+                    "Sell limit pool depth ratio cannot be greater than 100%",
+                ));
+            }
+        }
+        Ok(SellLimit {
+            asset: self.asset.check(api)?,
+            max: self.max.clone(),
+        })
+    }
+}
+
+impl From<SellLimit> for SellLimitUnchecked {
+    fn from(limit: SellLimit) -> Self {
+        Self {
+            asset: limit.asset.into(),
+            max: limit.max,
+        }
+    }
+}
+
+/// The maximum amount of a reward asset to sell per compound.
+#[cw_serde]
+pub enum MaxSell {
+    /// An absolute amount of the reward asset.
+    Amount(Uint128),
+    /// A fraction of the liquidity of the reward asset in the first pool of
+    /// the router path from the reward asset to the reward liquidation
+    /// target.
+    PoolDepthRatio(Decimal),
 }
 
 /// Config with non-validated addresses.
@@ -72,6 +162,25 @@ impl Config {
             liquidity_helper: updates
                 .liquidity_helper
                 .unwrap_or_else(|| self.liquidity_helper.into()),
+            referral_fee_share: updates.referral_fee_share.unwrap_or(self.referral_fee_share),
+            epoch_duration: updates.epoch_duration.unwrap_or(self.epoch_duration),
+            buffer_ratio: updates.buffer_ratio.unwrap_or(self.buffer_ratio),
+            exit_fee: updates.exit_fee.unwrap_or(self.exit_fee),
+            max_sell_per_compound: updates.max_sell_per_compound.unwrap_or_else(|| {
+                self.max_sell_per_compound
+                    .into_iter()
+                    .map(Into::into)
+                    .collect()
+            }),
+            max_share_price_increase: updates
+                .max_share_price_increase
+                .unwrap_or(self.max_share_price_increase),
+            ibc_withdraw_channel: updates
+                .ibc_withdraw_channel
+                .unwrap_or(self.ibc_withdraw_channel),
+            ibc_transfer_timeout: updates
+                .ibc_transfer_timeout
+                .unwrap_or(self.ibc_transfer_timeout),
         }
         .check(deps)
     }
@@ -86,6 +195,28 @@ impl ConfigUnchecked {
             ));
         }
 
+        if self.referral_fee_share > Decimal::one() {
+            return Err(StdError::generic_err(
+                "Referral fee share cannot be greater than 100%",
+            ));
+        }
+
+        if self.buffer_ratio > Decimal::one() {
+            return Err(StdError::generic_err(
+                "Buffer ratio cannot be greater than 100%",
+            ));
+        }
+
+        if self.exit_fee > Decimal::one() {
+            return Err(StdError::generic_err("Exit fee cannot be greater than 100%"));
+        }
+
+        if !self.ibc_withdraw_channel.is_empty() && self.ibc_transfer_timeout == 0 {
+            return Err(StdError::generic_err(
+                "IBC transfer timeout must be set when an IBC withdraw channel is set",
+            ));
+        }
+
         let reward_assets: Vec<AssetInfo> = self
             .reward_assets
             .iter()
@@ -94,6 +225,20 @@ impl ConfigUnchecked {
         let router = self.router.check(deps.api)?;
         let reward_liquidation_target = self.reward_liquidation_target.check(deps.api)?;
 
+        let max_sell_per_compound: Vec<SellLimit> = self
+            .max_sell_per_compound
+            .iter()
+            .map(|x| x.check(deps.api))
+            .collect::<StdResult<_>>()?;
+        for limit in &max_sell_per_compound {
+            if !reward_assets.contains(&limit.asset) {
+                return Err(StdError::generic_err(format!(
+                    "Sell limit asset {} is not a reward asset",
+                    limit.asset
+                )));
+            }
+        }
+
         // Check that the router can route between all reward assets and the
         // reward liquidation target. We discard the actual path because we
         // don't need it here. We just need to make sure the paths exist.
@@ -128,6 +273,14 @@ impl ConfigUnchecked {
                 .map(|x| deps.api.addr_validate(x))
                 .collect::<StdResult<_>>()?,
             liquidity_helper: self.liquidity_helper.check(deps.api)?,
+            referral_fee_share: self.referral_fee_share,
+            epoch_duration: self.epoch_duration,
+            buffer_ratio: self.buffer_ratio,
+            exit_fee: self.exit_fee,
+            max_sell_per_compound,
+            max_share_price_increase: self.max_share_price_increase,
+            ibc_withdraw_channel: self.ibc_withdraw_channel.clone(),
+            ibc_transfer_timeout: self.ibc_transfer_timeout,
         })
     }
 }
@@ -314,6 +467,38 @@ impl<'a> Claims<'a> {
         Ok(claim.base_token_amount)
     }
 
+    /// Claim `amount` of a matured claim owned by `owner`, leaving the rest of
+    /// the claim in place. Used for claims that are shared by several users,
+    /// e.g. the claim of a settled epoch which is owned by the contract.
+    ///
+    /// ## Returns
+    /// Returns an error if the claim is not owned by `owner`, has not yet
+    /// matured, or holds less than `amount`.
+    pub fn claim_partial(
+        &self,
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+        owner: &Addr,
+        lock_id: u64,
+        amount: Uint128,
+    ) -> StdResult<()> {
+        let mut claim = self.claims.load(storage, lock_id)?;
+
+        if &claim.owner != owner {
+            return Err(StdError::generic_err("Claim not owned by sender"));
+        }
+        if !claim.release_at.is_expired(block) {
+            return Err(StdError::generic_err("Claim has not yet matured."));
+        }
+
+        claim.base_token_amount = claim.base_token_amount.checked_sub(amount)?;
+        if claim.base_token_amount.is_zero() {
+            self.claims.remove(storage, lock_id)
+        } else {
+            self.claims.save(storage, lock_id, &claim)
+        }
+    }
+
     /// Bypass expiration and claim `claim_amount`. Should only be called if the
     /// caller is whitelisted. Will return an error if the claim does not exist
     /// or if the caller is not the owner of the claim.
@@ -389,6 +574,804 @@ impl<'a> Claims<'a> {
     }
 }
 
+//--------------------------------------------------------------------------------------------------
+// Referrals
+//--------------------------------------------------------------------------------------------------
+
+/// Accumulated referral fee per referred vault token, for each reward asset.
+pub type FeePerVaultToken = Vec<(AssetInfo, Decimal)>;
+
+/// Referral fee accounting for a single referrer.
+#[cw_serde]
+pub struct ReferrerInfo {
+    /// The amount of vault tokens of referred depositors that are currently
+    /// attributed to the referrer.
+    pub referred_vault_tokens: Uint128,
+    /// The value of the global fee per vault token index when the referrer was
+    /// last settled.
+    pub fee_per_vault_token: FeePerVaultToken,
+    /// Referral fees that have been accrued but not yet claimed.
+    pub accrued: AssetList,
+    /// Fractions of a token of each reward asset accrued but not yet added to
+    /// `accrued`. Carried over to the next settlement so that rounding does
+    /// not lose any fees.
+    #[serde(default)]
+    pub remainder: FeePerVaultToken,
+}
+
+/// Returns the value of `asset_info` in `list`, or zero if it is missing.
+fn find_decimal(list: &FeePerVaultToken, asset_info: &AssetInfo) -> Decimal {
+    list.iter().find(|(x, _)| x == asset_info).map(|(_, x)| *x).unwrap_or_default()
+}
+
+/// Sets the value of `asset_info` in `list` to `value`.
+fn set_decimal(list: &mut FeePerVaultToken, asset_info: &AssetInfo, value: Decimal) {
+    match list.iter_mut().find(|(x, _)| x == asset_info) {
+        Some((_, x)) => *x = value,
+        None => list.push((asset_info.clone(), value)),
+    }
+}
+
+/// Splits `value` into its integer part and its fractional part.
+fn split_decimal(value: Decimal256) -> StdResult<(Uint128, Decimal)> {
+    let one = Decimal256::one().atomics();
+    let integer = Uint128::try_from(value.atomics().checked_div(one)?)?;
+    let fraction = Uint128::try_from(value.atomics().checked_rem(one)?)?;
+    Ok((integer, Decimal::new(fraction)))
+}
+
+/// Returns `amount * ratio + remainder` without rounding.
+fn mul_exact(amount: Uint128, ratio: Decimal, remainder: Decimal) -> StdResult<Decimal256> {
+    Ok(Decimal256::new(Uint256::from(amount).checked_mul(Decimal256::from(ratio).atomics())?)
+        .checked_add(Decimal256::from(remainder))?)
+}
+
+/// A struct for recording the referrer of each depositor and for accruing the
+/// referrers' share of the performance fee.
+///
+/// Fees are tracked with a global index of fee per referred vault token for
+/// each reward asset, so that distributing the fee during a compound does not
+/// need to iterate over all referrers.
+pub struct Referrals<'a> {
+    /// The referrer of each referred depositor.
+    referrers: Map<'a, &'a Addr, Addr>,
+    /// Vault tokens minted to each referred depositor that are still
+    /// attributed to their referrer.
+    referred_vault_tokens: Map<'a, &'a Addr, Uint128>,
+    /// Accounting for each referrer.
+    referrer_info: Map<'a, &'a Addr, ReferrerInfo>,
+    /// The sum of all vault tokens attributed to referrers.
+    total_referred_vault_tokens: Item<'a, Uint128>,
+    /// Global index of accrued referral fee per referred vault token.
+    fee_per_vault_token: Item<'a, FeePerVaultToken>,
+    /// Referral fees held by the contract that have not yet been claimed.
+    /// These must not be compounded.
+    unclaimed: Item<'a, AssetList>,
+    /// Fractions of a token of each reward asset that were allocated to
+    /// referrers but not yet added to `unclaimed`.
+    unclaimed_remainder: Item<'a, FeePerVaultToken>,
+}
+
+impl<'a> Referrals<'a> {
+    /// Create a new Referrals instance
+    pub fn new(
+        referrers_namespace: &'a str,
+        referred_vault_tokens_namespace: &'a str,
+        referrer_info_namespace: &'a str,
+        total_referred_vault_tokens_key: &'a str,
+        fee_per_vault_token_key: &'a str,
+        unclaimed_key: &'a str,
+        unclaimed_remainder_key: &'a str,
+    ) -> Self {
+        Self {
+            referrers: Map::new(referrers_namespace),
+            referred_vault_tokens: Map::new(referred_vault_tokens_namespace),
+            referrer_info: Map::new(referrer_info_namespace),
+            total_referred_vault_tokens: Item::new(total_referred_vault_tokens_key),
+            fee_per_vault_token: Item::new(fee_per_vault_token_key),
+            unclaimed: Item::new(unclaimed_key),
+            unclaimed_remainder: Item::new(unclaimed_remainder_key),
+        }
+    }
+
+    /// Records `referrer` as the referrer of `depositor`. A depositor keeps the
+    /// first referrer that was recorded for them, later referrers are ignored.
+    ///
+    /// ## Returns
+    /// Returns the referrer of `depositor` after the call.
+    pub fn register(
+        &self,
+        storage: &mut dyn Storage,
+        depositor: &Addr,
+        referrer: &Addr,
+    ) -> StdResult<Addr> {
+        if depositor == referrer {
+            return Err(StdError::generic_err("Depositor cannot refer themselves"));
+        }
+
+        match self.referrers.may_load(storage, depositor)? {
+            Some(existing) => Ok(existing),
+            None => {
+                self.referrers.save(storage, depositor, referrer)?;
+                Ok(referrer.clone())
+            }
+        }
+    }
+
+    /// Returns the referrer of `depositor`, if any.
+    pub fn referrer_of(&self, storage: &dyn Storage, depositor: &Addr) -> StdResult<Option<Addr>> {
+        self.referrers.may_load(storage, depositor)
+    }
+
+    /// Loads the info of `referrer` and adds the fees accrued since it was
+    /// last settled. The returned info is not saved.
+    fn settle(&self, storage: &dyn Storage, referrer: &Addr) -> StdResult<ReferrerInfo> {
+        let index = self.fee_per_vault_token.may_load(storage)?.unwrap_or_default();
+        let mut info =
+            self.referrer_info.may_load(storage, referrer)?.unwrap_or_else(|| ReferrerInfo {
+                referred_vault_tokens: Uint128::zero(),
+                fee_per_vault_token: vec![],
+                accrued: AssetList::new(),
+                remainder: vec![],
+            });
+
+        for (asset_info, fee_per_vault_token) in &index {
+            let last = find_decimal(&info.fee_per_vault_token, asset_info);
+            let (accrued, remainder) = split_decimal(mul_exact(
+                info.referred_vault_tokens,
+                fee_per_vault_token.checked_sub(last)?,
+                find_decimal(&info.remainder, asset_info),
+            )?)?;
+            if !accrued.is_zero() {
+                info.accrued.add(&Asset::new(asset_info.clone(), accrued))?;
+            }
+            set_decimal(&mut info.remainder, asset_info, remainder);
+        }
+        info.fee_per_vault_token = index;
+
+        Ok(info)
+    }
+
+    /// Sets the vault tokens of `depositor` that are attributed to their
+    /// referrer to `amount`. Does nothing if the depositor was not referred.
+    fn set_vault_tokens(
+        &self,
+        storage: &mut dyn Storage,
+        depositor: &Addr,
+        amount: Uint128,
+    ) -> StdResult<()> {
+        let referrer = match self.referrers.may_load(storage, depositor)? {
+            Some(referrer) => referrer,
+            None => return Ok(()),
+        };
+        let referred = self.referred_vault_tokens.may_load(storage, depositor)?.unwrap_or_default();
+        if referred == amount {
+            return Ok(());
+        }
+
+        let mut info = self.settle(storage, &referrer)?;
+        let total = self.total_referred_vault_tokens.may_load(storage)?.unwrap_or_default();
+        let total = if amount > referred {
+            let added = amount.checked_sub(referred)?;
+            info.referred_vault_tokens = info.referred_vault_tokens.checked_add(added)?;
+            total.checked_add(added)?
+        } else {
+            let removed = referred.checked_sub(amount)?;
+            info.referred_vault_tokens = info.referred_vault_tokens.checked_sub(removed)?;
+            total.checked_sub(removed)?
+        };
+        self.referrer_info.save(storage, &referrer, &info)?;
+        self.total_referred_vault_tokens.save(storage, &total)?;
+
+        if amount.is_zero() {
+            self.referred_vault_tokens.remove(storage, depositor);
+            Ok(())
+        } else {
+            self.referred_vault_tokens.save(storage, depositor, &amount)
+        }
+    }
+
+    /// Attributes `vault_tokens` that are minted to `depositor` to the
+    /// depositor's referrer. `balance` is the vault token balance of the
+    /// depositor before the mint, any previously attributed vault tokens
+    /// above it were transferred away and are no longer attributed. Does
+    /// nothing if the depositor was not referred.
+    pub fn add_vault_tokens(
+        &self,
+        storage: &mut dyn Storage,
+        depositor: &Addr,
+        balance: Uint128,
+        vault_tokens: Uint128,
+    ) -> StdResult<()> {
+        let referred = self.referred_vault_tokens.may_load(storage, depositor)?.unwrap_or_default();
+        self.set_vault_tokens(storage, depositor, referred.min(balance).checked_add(vault_tokens)?)
+    }
+
+    /// Caps the vault tokens of `depositor` that are attributed to their
+    /// referrer at `balance`, the current vault token balance of the
+    /// depositor. Called when `depositor` burns vault tokens, and can be
+    /// called by anyone after vault tokens were transferred away from a
+    /// referred depositor.
+    pub fn sync_vault_tokens(
+        &self,
+        storage: &mut dyn Storage,
+        depositor: &Addr,
+        balance: Uint128,
+    ) -> StdResult<()> {
+        let referred = self.referred_vault_tokens.may_load(storage, depositor)?.unwrap_or_default();
+        self.set_vault_tokens(storage, depositor, referred.min(balance))
+    }
+
+    /// Reserves the referrers' share of a performance fee. The share is the
+    /// `referral_fee_share` of the part of `fee` that is attributable to
+    /// referred vault tokens.
+    ///
+    /// Only the amount that is allocated to the referred vault tokens by the
+    /// index is reserved. Fractions of a token are carried over to the next
+    /// call, so that the reserved amount always covers the accrued fees but
+    /// no rounding dust is left in the contract.
+    ///
+    /// ## Returns
+    /// Returns the amount of `fee` that was reserved for referrers. This amount
+    /// must be kept in the contract until it is claimed.
+    pub fn distribute_fee(
+        &self,
+        storage: &mut dyn Storage,
+        fee: &Asset,
+        referral_fee_share: Decimal,
+        vault_token_supply: Uint128,
+    ) -> StdResult<Uint128> {
+        let total = self.total_referred_vault_tokens.may_load(storage)?.unwrap_or_default();
+        if total.is_zero() || vault_token_supply.is_zero() || referral_fee_share.is_zero() {
+            return Ok(Uint128::zero());
+        }
+
+        let referral_amount =
+            (fee.amount * referral_fee_share).multiply_ratio(total, vault_token_supply);
+        if referral_amount.is_zero() {
+            return Ok(Uint128::zero());
+        }
+
+        let increment = Decimal::from_ratio(referral_amount, total);
+        let mut index = self.fee_per_vault_token.may_load(storage)?.unwrap_or_default();
+        let fee_per_vault_token = find_decimal(&index, &fee.info).checked_add(increment)?;
+        set_decimal(&mut index, &fee.info, fee_per_vault_token);
+        self.fee_per_vault_token.save(storage, &index)?;
+
+        // Reserve the whole tokens of what the index allocated to referrers
+        let mut remainders = self.unclaimed_remainder.may_load(storage)?.unwrap_or_default();
+        let (reserved, remainder) =
+            split_decimal(mul_exact(total, increment, find_decimal(&remainders, &fee.info))?)?;
+        set_decimal(&mut remainders, &fee.info, remainder);
+        self.unclaimed_remainder.save(storage, &remainders)?;
+
+        if !reserved.is_zero() {
+            let mut unclaimed = self.unclaimed.may_load(storage)?.unwrap_or_else(AssetList::new);
+            unclaimed.add(&Asset::new(fee.info.clone(), reserved))?;
+            self.unclaimed.save(storage, &unclaimed)?;
+        }
+
+        Ok(reserved)
+    }
+
+    /// Claims all referral fees accrued by `referrer`.
+    ///
+    /// ## Returns
+    /// Returns the claimed assets, which must be sent to the referrer.
+    pub fn claim(&self, storage: &mut dyn Storage, referrer: &Addr) -> StdResult<AssetList> {
+        let mut info = self.settle(storage, referrer)?;
+        let claimed = std::mem::replace(&mut info.accrued, AssetList::new());
+        self.referrer_info.save(storage, referrer, &info)?;
+
+        let mut unclaimed = self.unclaimed.may_load(storage)?.unwrap_or_else(AssetList::new);
+        for asset in claimed.to_vec() {
+            unclaimed.deduct(&asset)?;
+        }
+        self.unclaimed.save(storage, &unclaimed)?;
+
+        Ok(claimed)
+    }
+
+    /// Returns the amount of `asset_info` held by the contract for referral
+    /// fees that have not yet been claimed.
+    pub fn unclaimed_amount(
+        &self,
+        storage: &dyn Storage,
+        asset_info: &AssetInfo,
+    ) -> StdResult<Uint128> {
+        Ok(self
+            .unclaimed
+            .may_load(storage)?
+            .and_then(|x| x.find(asset_info).map(|asset| asset.amount))
+            .unwrap_or_default())
+    }
+
+    // ========== Query functions ==========
+
+    /// Query the referral info of `address`, both as a depositor and as a
+    /// referrer.
+    pub fn query_referral(&self, deps: Deps, address: &Addr) -> StdResult<ReferralInfo> {
+        let info = self.settle(deps.storage, address)?;
+        Ok(ReferralInfo {
+            referrer: self.referrers.may_load(deps.storage, address)?,
+            referred_vault_tokens: info.referred_vault_tokens,
+            claimable: info.accrued,
+        })
+    }
+}
+
+/// Referral info of an address. Returned by [`Referrals::query_referral`].
+#[cw_serde]
+pub struct ReferralInfo {
+    /// The referrer of the address, if it was referred.
+    pub referrer: Option<Addr>,
+    /// The vault tokens of depositors referred by the address.
+    pub referred_vault_tokens: Uint128,
+    /// Referral fees the address can claim.
+    pub claimable: AssetList,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Epochs
+//--------------------------------------------------------------------------------------------------
+
+/// An epoch in which deposits and unlocks are queued while the vault is in
+/// batched mode, i.e. `epoch_duration` in the config is non-zero.
+#[cw_serde]
+pub struct Epoch {
+    /// The ID of the epoch. Starts at zero and is incremented every time an
+    /// epoch is settled.
+    pub id: u64,
+    /// Block time in seconds at which the epoch started.
+    pub started_at: u64,
+    /// The base tokens queued for deposit in the epoch.
+    pub deposited_base_tokens: Uint128,
+    /// The vault tokens queued for unlocking in the epoch.
+    pub unlocked_vault_tokens: Uint128,
+}
+
+/// The result of settling an epoch, used to compute the share of each user
+/// that queued a deposit or unlock in the epoch.
+#[cw_serde]
+pub struct SettledEpoch {
+    /// The base tokens that were deposited in the epoch.
+    pub deposited_base_tokens: Uint128,
+    /// The vault tokens minted for the deposits of the epoch. These are held
+    /// by the contract until claimed by the depositors.
+    pub minted_vault_tokens: Uint128,
+    /// The vault tokens that were unlocked in the epoch.
+    pub unlocked_vault_tokens: Uint128,
+    /// The claim holding the base tokens of the unlocks of the epoch. The
+    /// claim is owned by the contract. `None` if nothing was unlocked.
+    pub claim: Option<Claim>,
+}
+
+/// The deposits and unlocks queued by a single user in an epoch.
+#[cw_serde]
+#[derive(Default)]
+pub struct EpochPosition {
+    /// The base tokens queued for deposit that have not yet been claimed as
+    /// vault tokens.
+    pub deposited_base_tokens: Uint128,
+    /// The vault tokens queued for unlocking whose base tokens have not yet
+    /// been withdrawn.
+    pub unlocked_vault_tokens: Uint128,
+}
+
+impl EpochPosition {
+    fn is_empty(&self) -> bool {
+        self.deposited_base_tokens.is_zero() && self.unlocked_vault_tokens.is_zero()
+    }
+}
+
+/// A struct for queueing deposits and unlocks per epoch, so that they can be
+/// settled together with a single compound and a single unlock.
+pub struct Epochs<'a> {
+    /// The epoch that deposits and unlocks are currently queued in.
+    current_epoch: Item<'a, Epoch>,
+    /// Epochs that have been settled, by epoch ID.
+    settled_epochs: Map<'a, u64, SettledEpoch>,
+    /// The positions of users, by epoch ID and owner.
+    positions: Map<'a, (u64, &'a Addr), EpochPosition>,
+}
+
+impl<'a> Epochs<'a> {
+    /// Create a new Epochs instance
+    pub fn new(
+        current_epoch_key: &'a str,
+        settled_epochs_namespace: &'a str,
+        positions_namespace: &'a str,
+    ) -> Self {
+        Self {
+            current_epoch: Item::new(current_epoch_key),
+            settled_epochs: Map::new(settled_epochs_namespace),
+            positions: Map::new(positions_namespace),
+        }
+    }
+
+    /// Returns the current epoch. If no epoch has been saved yet the first
+    /// epoch is returned, starting at the current block time.
+    pub fn current_epoch(&self, storage: &dyn Storage, block: &BlockInfo) -> StdResult<Epoch> {
+        Ok(self.current_epoch.may_load(storage)?.unwrap_or_else(|| Epoch {
+            id: 0,
+            started_at: block.time.seconds(),
+            deposited_base_tokens: Uint128::zero(),
+            unlocked_vault_tokens: Uint128::zero(),
+        }))
+    }
+
+    /// Queues a deposit of `base_tokens` and an unlock of `vault_tokens` for
+    /// `owner` in the current epoch.
+    ///
+    /// ## Returns
+    /// Returns the ID of the current epoch.
+    pub fn queue(
+        &self,
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+        owner: &Addr,
+        base_tokens: Uint128,
+        vault_tokens: Uint128,
+    ) -> StdResult<u64> {
+        let mut epoch = self.current_epoch(storage, block)?;
+        epoch.deposited_base_tokens = epoch.deposited_base_tokens.checked_add(base_tokens)?;
+        epoch.unlocked_vault_tokens = epoch.unlocked_vault_tokens.checked_add(vault_tokens)?;
+        self.current_epoch.save(storage, &epoch)?;
+
+        let mut position = self.positions.may_load(storage, (epoch.id, owner))?.unwrap_or_default();
+        position.deposited_base_tokens = position.deposited_base_tokens.checked_add(base_tokens)?;
+        position.unlocked_vault_tokens =
+            position.unlocked_vault_tokens.checked_add(vault_tokens)?;
+        self.positions.save(storage, (epoch.id, owner), &position)?;
+
+        Ok(epoch.id)
+    }
+
+    /// Closes the current epoch and starts the next one. The vault tokens
+    /// minted and the claim created for the closed epoch must be recorded
+    /// with [`Self::set_minted_vault_tokens`] and [`Self::set_claim`].
+    ///
+    /// ## Returns
+    /// Returns the closed epoch, or an error if `epoch_duration` seconds have
+    /// not yet passed since it started.
+    pub fn settle(
+        &self,
+        storage: &mut dyn Storage,
+        block: &BlockInfo,
+        epoch_duration: u64,
+    ) -> StdResult<Epoch> {
+        let epoch = self.current_epoch(storage, block)?;
+        let ends_at = epoch.started_at.saturating_add(epoch_duration);
+        if block.time.seconds() < ends_at {
+            return Err(StdError::generic_err(format!(
+                "Epoch {} cannot be settled before {}",
+                epoch.id, ends_at
+            )));
+        }
+
+        self.settled_epochs.save(
+            storage,
+            epoch.id,
+            &SettledEpoch {
+                deposited_base_tokens: epoch.deposited_base_tokens,
+                minted_vault_tokens: Uint128::zero(),
+                unlocked_vault_tokens: epoch.unlocked_vault_tokens,
+                claim: None,
+            },
+        )?;
+        self.current_epoch.save(
+            storage,
+            &Epoch {
+                id: epoch.id + 1,
+                started_at: block.time.seconds(),
+                deposited_base_tokens: Uint128::zero(),
+                unlocked_vault_tokens: Uint128::zero(),
+            },
+        )?;
+
+        Ok(epoch)
+    }
+
+    /// Records the vault tokens minted for the deposits of the settled epoch
+    /// `epoch_id`.
+    pub fn set_minted_vault_tokens(
+        &self,
+        storage: &mut dyn Storage,
+        epoch_id: u64,
+        vault_tokens: Uint128,
+    ) -> StdResult<()> {
+        let mut settled = self.settled_epochs.load(storage, epoch_id)?;
+        settled.minted_vault_tokens = vault_tokens;
+        self.settled_epochs.save(storage, epoch_id, &settled)
+    }
+
+    /// Records the claim created for the unlocks of the settled epoch
+    /// `epoch_id`.
+    pub fn set_claim(
+        &self,
+        storage: &mut dyn Storage,
+        epoch_id: u64,
+        claim: Claim,
+    ) -> StdResult<()> {
+        let mut settled = self.settled_epochs.load(storage, epoch_id)?;
+        settled.claim = Some(claim);
+        self.settled_epochs.save(storage, epoch_id, &settled)
+    }
+
+    /// Loads the position of `owner` in the settled epoch `epoch_id`.
+    fn load_settled_position(
+        &self,
+        storage: &dyn Storage,
+        epoch_id: u64,
+        owner: &Addr,
+    ) -> StdResult<(SettledEpoch, EpochPosition)> {
+        let settled = self
+            .settled_epochs
+            .may_load(storage, epoch_id)?
+            .ok_or_else(|| StdError::generic_err(format!("Epoch {} is not settled", epoch_id)))?;
+        let position = self.positions.may_load(storage, (epoch_id, owner))?.unwrap_or_default();
+        Ok((settled, position))
+    }
+
+    /// Saves `position`, removing it if it is empty.
+    fn save_position(
+        &self,
+        storage: &mut dyn Storage,
+        epoch_id: u64,
+        owner: &Addr,
+        position: &EpochPosition,
+    ) -> StdResult<()> {
+        if position.is_empty() {
+            self.positions.remove(storage, (epoch_id, owner));
+            Ok(())
+        } else {
+            self.positions.save(storage, (epoch_id, owner), position)
+        }
+    }
+
+    /// Claims the vault tokens minted for the deposits of `owner` in the
+    /// settled epoch `epoch_id`.
+    ///
+    /// ## Returns
+    /// Returns the amount of vault tokens that must be transferred from the
+    /// contract to the owner.
+    pub fn claim_vault_tokens(
+        &self,
+        storage: &mut dyn Storage,
+        epoch_id: u64,
+        owner: &Addr,
+    ) -> StdResult<Uint128> {
+        let (settled, mut position) = self.load_settled_position(storage, epoch_id, owner)?;
+        if position.deposited_base_tokens.is_zero() {
+            return Err(StdError::generic_err(format!(
+                "No deposit to claim in epoch {}",
+                epoch_id
+            )));
+        }
+
+        let vault_tokens = settled
+            .minted_vault_tokens
+            .multiply_ratio(position.deposited_base_tokens, settled.deposited_base_tokens);
+        position.deposited_base_tokens = Uint128::zero();
+        self.save_position(storage, epoch_id, owner, &position)?;
+
+        Ok(vault_tokens)
+    }
+
+    /// Claims the base tokens of the unlocks of `owner` in the settled epoch
+    /// `epoch_id`.
+    ///
+    /// ## Returns
+    /// Returns the claim of the epoch and the amount of its base tokens that
+    /// belong to the owner. The amount must still be deducted from the claim.
+    pub fn claim_base_tokens(
+        &self,
+        storage: &mut dyn Storage,
+        epoch_id: u64,
+        owner: &Addr,
+    ) -> StdResult<(Claim, Uint128)> {
+        let (settled, mut position) = self.load_settled_position(storage, epoch_id, owner)?;
+        let claim = match settled.claim {
+            Some(claim) if !position.unlocked_vault_tokens.is_zero() => claim,
+            _ => {
+                return Err(StdError::generic_err(format!(
+                    "No unlock to withdraw in epoch {}",
+                    epoch_id
+                )))
+            }
+        };
+
+        let base_tokens = claim.base_token_amount.multiply_ratio(
+            position.unlocked_vault_tokens,
+            settled.unlocked_vault_tokens,
+        );
+        position.unlocked_vault_tokens = Uint128::zero();
+        self.save_position(storage, epoch_id, owner, &position)?;
+
+        Ok((claim, base_tokens))
+    }
+
+    // ========== Query functions ==========
+
+    /// Query the settled epoch `epoch_id`.
+    pub fn query_settled_epoch(&self, deps: Deps, epoch_id: u64) -> StdResult<SettledEpoch> {
+        self.settled_epochs.load(deps.storage, epoch_id)
+    }
+
+    /// Query the position of `owner` in the epoch `epoch_id`. Returns an empty
+    /// position if the owner has nothing queued or left to claim.
+    pub fn query_position(
+        &self,
+        deps: Deps,
+        epoch_id: u64,
+        owner: &Addr,
+    ) -> StdResult<EpochPosition> {
+        Ok(self.positions.may_load(deps.storage, (epoch_id, owner))?.unwrap_or_default())
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Compound failures
+//--------------------------------------------------------------------------------------------------
+
+/// A step of a compound that failed. The step was reverted and the assets it
+/// handles were left in the contract until the next compound.
+#[cw_serde]
+pub struct CompoundFailure {
+    /// The step of the compound that failed, either `sell_rewards` or
+    /// `provide_liquidity`.
+    pub step: String,
+    /// The assets the step was called with, i.e. the rewards to sell or the
+    /// pool assets to provide as liquidity, which were left in the contract.
+    pub assets: AssetList,
+    /// The error returned by the step.
+    pub reason: String,
+    /// The block height of the failure.
+    pub height: u64,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Remote withdrawals
+//--------------------------------------------------------------------------------------------------
+
+/// The status of a withdrawal sent to another chain over IBC.
+#[cw_serde]
+pub enum RemoteWithdrawalStatus {
+    /// The transfer was sent and has not been acknowledged yet.
+    Pending,
+    /// The transfer was acknowledged as successful.
+    Succeeded,
+    /// The transfer failed or timed out, and the base tokens were sent back to
+    /// the owner.
+    Refunded,
+}
+
+/// A withdrawal of unlocked base tokens to an address on another chain.
+#[cw_serde]
+pub struct RemoteWithdrawal {
+    /// The ID of the withdrawal.
+    pub id: u64,
+    /// The owner of the withdrawn lockup position, who is refunded if the
+    /// transfer fails.
+    pub owner: Addr,
+    /// The address on the remote chain receiving the base tokens.
+    pub remote_recipient: String,
+    /// The channel the transfer was sent over.
+    pub channel_id: String,
+    /// The withdrawn base tokens.
+    pub amount: Coin,
+    /// The packet sequence of the transfer. `None` until the transfer was
+    /// sent.
+    pub sequence: Option<u64>,
+    /// The status of the transfer.
+    pub status: RemoteWithdrawalStatus,
+}
+
+/// A struct for recording the withdrawals sent over IBC, so that the base
+/// tokens can be refunded to the owner when the transfer fails or times out.
+pub struct RemoteWithdrawals<'a> {
+    /// The withdrawals by ID.
+    pub withdrawals: Map<'a, u64, RemoteWithdrawal>,
+    /// The ID of the withdrawal sent with each (channel, packet sequence).
+    pub sequences: Map<'a, (&'a str, u64), u64>,
+    /// The ID of the next withdrawal.
+    pub next_id: Item<'a, u64>,
+}
+
+impl<'a> RemoteWithdrawals<'a> {
+    /// Create a new RemoteWithdrawals instance
+    pub fn new(
+        withdrawals_namespace: &'a str,
+        sequences_namespace: &'a str,
+        next_id_key: &'a str,
+    ) -> Self {
+        Self {
+            withdrawals: Map::new(withdrawals_namespace),
+            sequences: Map::new(sequences_namespace),
+            next_id: Item::new(next_id_key),
+        }
+    }
+
+    /// Records a pending withdrawal and returns its ID.
+    pub fn record(
+        &self,
+        storage: &mut dyn Storage,
+        owner: &Addr,
+        remote_recipient: String,
+        channel_id: String,
+        amount: Coin,
+    ) -> StdResult<u64> {
+        let id = self.next_id.may_load(storage)?.unwrap_or_default();
+        self.next_id.save(storage, &(id + 1))?;
+
+        self.withdrawals.save(
+            storage,
+            id,
+            &RemoteWithdrawal {
+                id,
+                owner: owner.clone(),
+                remote_recipient,
+                channel_id,
+                amount,
+                sequence: None,
+                status: RemoteWithdrawalStatus::Pending,
+            },
+        )?;
+        Ok(id)
+    }
+
+    /// Sets the packet sequence of the transfer of the withdrawal with ID `id`.
+    pub fn set_sequence(
+        &self,
+        storage: &mut dyn Storage,
+        id: u64,
+        sequence: u64,
+    ) -> StdResult<RemoteWithdrawal> {
+        let mut withdrawal = self.withdrawals.load(storage, id)?;
+        withdrawal.sequence = Some(sequence);
+        self.withdrawals.save(storage, id, &withdrawal)?;
+        self.sequences.save(storage, (&withdrawal.channel_id, sequence), &id)?;
+        Ok(withdrawal)
+    }
+
+    /// Completes the pending withdrawal sent with `sequence` over `channel`.
+    /// If the transfer did not succeed the withdrawal is marked as refunded,
+    /// and the caller MUST send `amount` back to `owner`.
+    ///
+    /// ## Returns
+    /// Returns the updated withdrawal, or an error if there is no pending
+    /// withdrawal with the sequence.
+    pub fn complete(
+        &self,
+        storage: &mut dyn Storage,
+        channel: &str,
+        sequence: u64,
+        success: bool,
+    ) -> StdResult<RemoteWithdrawal> {
+        let id = self.sequences.may_load(storage, (channel, sequence))?.ok_or_else(|| {
+            StdError::generic_err(format!(
+                "No remote withdrawal with sequence {} on {}",
+                sequence, channel
+            ))
+        })?;
+
+        let mut withdrawal = self.withdrawals.load(storage, id)?;
+        if withdrawal.status != RemoteWithdrawalStatus::Pending {
+            return Err(StdError::generic_err(format!(
+                "Remote withdrawal {} was already completed",
+                id
+            )));
+        }
+        withdrawal.status = if success {
+            RemoteWithdrawalStatus::Succeeded
+        } else {
+            RemoteWithdrawalStatus::Refunded
+        };
+        self.withdrawals.save(storage, id, &withdrawal)?;
+        Ok(withdrawal)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use cosmwasm_std::testing::{
@@ -707,4 +1690,381 @@ mod tests {
             .query_claims_for_owner(deps.as_ref(), &owner, start_after, limit)
             .map(|claims| claims.iter().map(|c| c.1.clone()).collect())
     }
+
+    fn referrals() -> Referrals<'static> {
+        Referrals::new(
+            "referrers",
+            "referred_vault_tokens",
+            "referrer_info",
+            "total_referred_vault_tokens",
+            "fee_per_vault_token",
+            "unclaimed_referral_fees",
+            "unclaimed_referral_fee_remainder",
+        )
+    }
+
+    #[test]
+    fn test_register_referrer() {
+        let mut deps = mock_dependencies();
+        let referrals = referrals();
+        let depositor = Addr::unchecked(OWNER);
+
+        // Depositor cannot refer themselves
+        let err = referrals.register(&mut deps.storage, &depositor, &depositor).unwrap_err();
+        assert_eq!(err, StdError::generic_err("Depositor cannot refer themselves"));
+
+        // The first referrer is kept
+        let referrer = referrals
+            .register(&mut deps.storage, &depositor, &Addr::unchecked("referrer"))
+            .unwrap();
+        assert_eq!(referrer, Addr::unchecked("referrer"));
+        let referrer = referrals
+            .register(&mut deps.storage, &depositor, &Addr::unchecked("other_referrer"))
+            .unwrap();
+        assert_eq!(referrer, Addr::unchecked("referrer"));
+    }
+
+    #[test]
+    fn test_distribute_and_claim_referral_fees() {
+        let mut deps = mock_dependencies();
+        let referrals = referrals();
+        let depositor = Addr::unchecked(OWNER);
+        let referrer = Addr::unchecked("referrer");
+        let reward = AssetInfo::Native("pica".to_string());
+
+        // Fees are not reserved while nobody is referred
+        let reserved = referrals
+            .distribute_fee(
+                &mut deps.storage,
+                &Asset::new(reward.clone(), 1000u128),
+                Decimal::percent(50),
+                Uint128::new(1000),
+            )
+            .unwrap();
+        assert_eq!(reserved, Uint128::zero());
+
+        // Referred depositor holds 250 of 1000 vault tokens
+        referrals.register(&mut deps.storage, &depositor, &referrer).unwrap();
+        referrals
+            .add_vault_tokens(&mut deps.storage, &depositor, Uint128::zero(), Uint128::new(250))
+            .unwrap();
+
+        // 50% of the fee attributable to 25% of the supply is reserved
+        let reserved = referrals
+            .distribute_fee(
+                &mut deps.storage,
+                &Asset::new(reward.clone(), 1000u128),
+                Decimal::percent(50),
+                Uint128::new(1000),
+            )
+            .unwrap();
+        assert_eq!(reserved, Uint128::new(125));
+        assert_eq!(
+            referrals.unclaimed_amount(&deps.storage, &reward).unwrap(),
+            Uint128::new(125)
+        );
+
+        // Burning vault tokens stops further accrual but keeps accrued fees
+        referrals.sync_vault_tokens(&mut deps.storage, &depositor, Uint128::zero()).unwrap();
+        let info = referrals.query_referral(deps.as_ref(), &referrer).unwrap();
+        assert_eq!(info.referred_vault_tokens, Uint128::zero());
+        assert_eq!(info.claimable.to_vec(), vec![Asset::new(reward.clone(), 125u128)]);
+
+        // Claiming empties the accrued and unclaimed fees
+        let claimed = referrals.claim(&mut deps.storage, &referrer).unwrap();
+        assert_eq!(claimed.to_vec(), vec![Asset::new(reward.clone(), 125u128)]);
+        assert_eq!(referrals.unclaimed_amount(&deps.storage, &reward).unwrap(), Uint128::zero());
+        let claimed = referrals.claim(&mut deps.storage, &referrer).unwrap();
+        assert_eq!(claimed.len(), 0);
+    }
+
+    #[test]
+    fn test_referred_vault_tokens_follow_balance() {
+        let mut deps = mock_dependencies();
+        let referrals = referrals();
+        let depositor = Addr::unchecked(OWNER);
+        let referrer = Addr::unchecked("referrer");
+
+        referrals.register(&mut deps.storage, &depositor, &referrer).unwrap();
+        referrals
+            .add_vault_tokens(&mut deps.storage, &depositor, Uint128::zero(), Uint128::new(250))
+            .unwrap();
+
+        // The depositor transferred away 150 vault tokens
+        referrals.sync_vault_tokens(&mut deps.storage, &depositor, Uint128::new(100)).unwrap();
+        let info = referrals.query_referral(deps.as_ref(), &referrer).unwrap();
+        assert_eq!(info.referred_vault_tokens, Uint128::new(100));
+
+        // Syncing never increases the attributed vault tokens
+        referrals.sync_vault_tokens(&mut deps.storage, &depositor, Uint128::new(500)).unwrap();
+        let info = referrals.query_referral(deps.as_ref(), &referrer).unwrap();
+        assert_eq!(info.referred_vault_tokens, Uint128::new(100));
+
+        // Minting caps the attributed vault tokens at the balance before the mint
+        referrals
+            .add_vault_tokens(&mut deps.storage, &depositor, Uint128::new(40), Uint128::new(60))
+            .unwrap();
+        let info = referrals.query_referral(deps.as_ref(), &referrer).unwrap();
+        assert_eq!(info.referred_vault_tokens, Uint128::new(100));
+    }
+
+    #[test]
+    fn test_referral_fees_leave_no_dust() {
+        let mut deps = mock_dependencies();
+        let referrals = referrals();
+        let reward = AssetInfo::Native("pica".to_string());
+        let referrer_a = Addr::unchecked("referrer_a");
+        let referrer_b = Addr::unchecked("referrer_b");
+
+        // Referrers of 1 and 2 of the 3 vault tokens
+        for (depositor, referrer, amount) in
+            [("depositor_a", &referrer_a, 1u128), ("depositor_b", &referrer_b, 2u128)]
+        {
+            let depositor = Addr::unchecked(depositor);
+            referrals.register(&mut deps.storage, &depositor, referrer).unwrap();
+            referrals
+                .add_vault_tokens(&mut deps.storage, &depositor, Uint128::zero(), amount.into())
+                .unwrap();
+        }
+
+        // 10 / 3 per vault token does not divide evenly. Fractions are carried
+        // over, so everything reserved is eventually claimed.
+        for (reserved, claimed_a, claimed_b) in [(9u128, 3u128, 6u128), (10, 3, 7)] {
+            let res = referrals
+                .distribute_fee(
+                    &mut deps.storage,
+                    &Asset::new(reward.clone(), 10u128),
+                    Decimal::one(),
+                    Uint128::new(3),
+                )
+                .unwrap();
+            assert_eq!(res, Uint128::new(reserved));
+
+            let claimed = referrals.claim(&mut deps.storage, &referrer_a).unwrap();
+            assert_eq!(claimed.to_vec(), vec![Asset::new(reward.clone(), claimed_a)]);
+            let claimed = referrals.claim(&mut deps.storage, &referrer_b).unwrap();
+            assert_eq!(claimed.to_vec(), vec![Asset::new(reward.clone(), claimed_b)]);
+            assert_eq!(
+                referrals.unclaimed_amount(&deps.storage, &reward).unwrap(),
+                Uint128::zero()
+            );
+        }
+    }
+
+    #[test_case(100, OWNER, 40 => Ok(Some(60)); "partial claim")]
+    #[test_case(100, OWNER, 100 => Ok(None); "claim entire amount")]
+    #[test_case(100, NOT_OWNER, 40 => Err(StdError::generic_err("Claim not owned by sender")); "claim not owned by owner")]
+    #[test_case(99, OWNER, 40 => Err(StdError::generic_err("Claim has not yet matured.")); "claim not yet matured")]
+    #[test_case(100, OWNER, 101 => matches Err(_); "amount greater than claim")]
+    fn test_claim_partial(
+        block_height: u64,
+        owner: &str,
+        amount: u128,
+    ) -> StdResult<Option<u128>> {
+        let mut env = mock_env();
+        env.block.height = block_height;
+
+        let (mut deps, claims) = setup_pending_claim(None);
+        claims.commit_pending_claim(&mut deps.storage).unwrap();
+
+        claims.claim_partial(
+            &mut deps.storage,
+            &env.block,
+            &Addr::unchecked(owner),
+            0,
+            Uint128::new(amount),
+        )?;
+
+        // Return what is left of the claim
+        Ok(claims
+            .claims
+            .may_load(&deps.storage, 0)
+            .unwrap()
+            .map(|claim| claim.base_token_amount.u128()))
+    }
+
+    fn epochs() -> Epochs<'static> {
+        Epochs::new("current_epoch", "settled_epochs", "epoch_positions")
+    }
+
+    #[test]
+    fn test_settle_epoch() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let epochs = epochs();
+        let started_at = env.block.time.seconds();
+
+        epochs
+            .queue(
+                &mut deps.storage,
+                &env.block,
+                &Addr::unchecked(OWNER),
+                Uint128::new(100),
+                Uint128::zero(),
+            )
+            .unwrap();
+
+        // The epoch cannot be settled before the epoch duration has passed
+        let err = epochs.settle(&mut deps.storage, &env.block, 60).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err(format!("Epoch 0 cannot be settled before {}", started_at + 60))
+        );
+
+        env.block.time = env.block.time.plus_seconds(60);
+        let epoch = epochs.settle(&mut deps.storage, &env.block, 60).unwrap();
+        assert_eq!(epoch.id, 0);
+        assert_eq!(epoch.deposited_base_tokens, Uint128::new(100));
+
+        // The next epoch starts empty
+        let next = epochs.current_epoch(&deps.storage, &env.block).unwrap();
+        assert_eq!(
+            next,
+            Epoch {
+                id: 1,
+                started_at: started_at + 60,
+                deposited_base_tokens: Uint128::zero(),
+                unlocked_vault_tokens: Uint128::zero(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_claim_epoch() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let epochs = epochs();
+        let owner = Addr::unchecked(OWNER);
+        let not_owner = Addr::unchecked(NOT_OWNER);
+
+        // Owner deposits a quarter and unlocks a third of the epoch
+        epochs
+            .queue(&mut deps.storage, &env.block, &owner, Uint128::new(100), Uint128::new(10))
+            .unwrap();
+        epochs
+            .queue(&mut deps.storage, &env.block, &not_owner, Uint128::new(300), Uint128::new(20))
+            .unwrap();
+
+        // Nothing can be claimed before the epoch is settled
+        let err = epochs.claim_vault_tokens(&mut deps.storage, 0, &owner).unwrap_err();
+        assert_eq!(err, StdError::generic_err("Epoch 0 is not settled"));
+
+        epochs.settle(&mut deps.storage, &env.block, 0).unwrap();
+        epochs.set_minted_vault_tokens(&mut deps.storage, 0, Uint128::new(1000)).unwrap();
+        let claim = Claim {
+            id: 5,
+            owner: Addr::unchecked("contract"),
+            base_token_amount: Uint128::new(90),
+            release_at: EXPIRATION,
+        };
+        epochs.set_claim(&mut deps.storage, 0, claim.clone()).unwrap();
+
+        let vault_tokens = epochs.claim_vault_tokens(&mut deps.storage, 0, &owner).unwrap();
+        assert_eq!(vault_tokens, Uint128::new(250));
+        let err = epochs.claim_vault_tokens(&mut deps.storage, 0, &owner).unwrap_err();
+        assert_eq!(err, StdError::generic_err("No deposit to claim in epoch 0"));
+
+        let (epoch_claim, base_tokens) =
+            epochs.claim_base_tokens(&mut deps.storage, 0, &owner).unwrap();
+        assert_eq!(epoch_claim, claim);
+        assert_eq!(base_tokens, Uint128::new(30));
+
+        // The position is removed once everything is claimed
+        let position = epochs.query_position(deps.as_ref(), 0, &owner).unwrap();
+        assert_eq!(position, EpochPosition::default());
+        assert!(epochs.positions.may_load(&deps.storage, (0, &owner)).unwrap().is_none());
+    }
+
+    fn setup_remote_withdrawal() -> (
+        OwnedDeps<MockStorage, MockApi, MockQuerier>,
+        RemoteWithdrawals<'static>,
+    ) {
+        let mut deps = mock_dependencies();
+        let withdrawals = RemoteWithdrawals::new("withdrawals", "sequences", "next_id");
+
+        let id = withdrawals
+            .record(
+                &mut deps.storage,
+                &Addr::unchecked(OWNER),
+                "remote".to_string(),
+                "channel-0".to_string(),
+                Coin::new(100, "base"),
+            )
+            .unwrap();
+        assert_eq!(id, 0);
+        withdrawals.set_sequence(&mut deps.storage, id, 7).unwrap();
+
+        (deps, withdrawals)
+    }
+
+    #[test]
+    fn test_remote_withdrawal_sequence() {
+        let (mut deps, withdrawals) = setup_remote_withdrawal();
+
+        // The sequence is set on the given withdrawal, even when another
+        // withdrawal was recorded after it
+        let id = withdrawals
+            .record(
+                &mut deps.storage,
+                &Addr::unchecked(NOT_OWNER),
+                "remote".to_string(),
+                "channel-0".to_string(),
+                Coin::new(50, "base"),
+            )
+            .unwrap();
+        assert_eq!(id, 1);
+        let other_id = withdrawals
+            .record(
+                &mut deps.storage,
+                &Addr::unchecked(OWNER),
+                "remote".to_string(),
+                "channel-0".to_string(),
+                Coin::new(25, "base"),
+            )
+            .unwrap();
+        assert_eq!(other_id, 2);
+        let withdrawal = withdrawals.set_sequence(&mut deps.storage, id, 8).unwrap();
+        assert_eq!(withdrawal.id, 1);
+        assert_eq!(withdrawal.sequence, Some(8));
+        assert_eq!(withdrawals.withdrawals.load(&deps.storage, other_id).unwrap().sequence, None);
+
+        assert_eq!(withdrawals.sequences.load(&deps.storage, ("channel-0", 7)).unwrap(), 0);
+        assert_eq!(withdrawals.sequences.load(&deps.storage, ("channel-0", 8)).unwrap(), 1);
+
+        // The withdrawal must exist
+        withdrawals.set_sequence(&mut deps.storage, 3, 9).unwrap_err();
+    }
+
+    #[test_case(true, RemoteWithdrawalStatus::Succeeded ; "succeeded")]
+    #[test_case(false, RemoteWithdrawalStatus::Refunded ; "refunded")]
+    fn test_complete_remote_withdrawal(success: bool, expected: RemoteWithdrawalStatus) {
+        let (mut deps, withdrawals) = setup_remote_withdrawal();
+
+        let withdrawal = withdrawals.complete(&mut deps.storage, "channel-0", 7, success).unwrap();
+        assert_eq!(
+            withdrawal,
+            RemoteWithdrawal {
+                id: 0,
+                owner: Addr::unchecked(OWNER),
+                remote_recipient: "remote".to_string(),
+                channel_id: "channel-0".to_string(),
+                amount: Coin::new(100, "base"),
+                sequence: Some(7),
+                status: expected,
+            }
+        );
+        assert_eq!(withdrawals.withdrawals.load(&deps.storage, 0).unwrap(), withdrawal);
+
+        // A withdrawal can only be completed once, so it is never refunded twice
+        let err = withdrawals.complete(&mut deps.storage, "channel-0", 7, false).unwrap_err();
+        assert_eq!(err, StdError::generic_err("Remote withdrawal 0 was already completed"));
+    }
+
+    #[test]
+    fn test_complete_unknown_remote_withdrawal() {
+        let (mut deps, withdrawals) = setup_remote_withdrawal();
+
+        let err = withdrawals.complete(&mut deps.storage, "channel-1", 7, false).unwrap_err();
+        assert_eq!(err, StdError::generic_err("No remote withdrawal with sequence 7 on channel-1"));
+    }
 }
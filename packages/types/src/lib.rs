@@ -1,2 +1,4 @@
 pub mod error;
+#[cfg(feature = "osmosis")]
+pub mod twap;
 pub mod vault;
@@ -0,0 +1,35 @@
+use std::str::FromStr;
+
+use cosmwasm_std::{QuerierWrapper, StdError, StdResult, Timestamp};
+use osmosis_std::shim::Timestamp as OsmosisTimestamp;
+use osmosis_std::types::osmosis::twap::v1beta1::TwapQuerier;
+
+/// Queries the arithmetic TWAP price of `denom` in `quote_denom` in pool
+/// `pool_id` from `start_time` until now, i.e. the amount of `quote_denom` per
+/// unit of `denom`.
+///
+/// Osmosis returns the TWAP of the base asset quoted in the quote asset, so
+/// `denom` is passed as the base asset and `quote_denom` as the quote asset.
+pub fn query_arithmetic_twap_price<T>(
+    querier: &QuerierWrapper,
+    pool_id: u64,
+    denom: &str,
+    quote_denom: &str,
+    start_time: Timestamp,
+) -> StdResult<T>
+where
+    T: FromStr<Err = StdError>,
+{
+    let twap = TwapQuerier::new(querier)
+        .arithmetic_twap_to_now(
+            pool_id,
+            denom.to_string(),
+            quote_denom.to_string(),
+            Some(OsmosisTimestamp {
+                seconds: start_time.seconds() as i64,
+                nanos: 0,
+            }),
+        )?
+        .arithmetic_twap;
+    T::from_str(&twap)
+}